@@ -605,6 +605,12 @@ impl Action for RunAction {
             "allow_dep_file_cache_upload".to_owned() => self.inner.allow_dep_file_cache_upload.to_string(),
         }
     }
+
+    fn aquery_command_line(&self, fs: &ExecutorFs) -> anyhow::Result<Option<ExpandedCommandLine>> {
+        let (expanded, _worker) = self
+            .expand_command_line_and_worker(fs, &mut SimpleCommandLineArtifactVisitor::new())?;
+        Ok(Some(expanded))
+    }
 }
 
 #[async_trait]
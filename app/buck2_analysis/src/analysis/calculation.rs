@@ -278,12 +278,14 @@ async fn get_analysis_result(
                         MaybeCompatible::Compatible(result)
                     };
 
+                    let failed = result.is_err();
                     (
                         result,
                         buck2_data::AnalysisEnd {
                             target: Some(target.as_proto().into()),
                             rule: func.to_string(),
                             profile,
+                            failed,
                         },
                     )
                 })
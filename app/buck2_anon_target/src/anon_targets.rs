@@ -435,12 +435,14 @@ impl AnonTargetKey {
                 Ok(AnalysisResult::new(provider_collection, deferred, None))
             }
             .map(|res| {
+                let failed = res.is_err();
                 (
                     res,
                     buck2_data::AnalysisEnd {
                         target: Some(self.0.as_proto().into()),
                         rule: self.0.rule_type().to_string(),
                         profile: None, // Not implemented for anon targets
+                        failed,
                     },
                 )
             }),
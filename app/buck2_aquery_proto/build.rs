@@ -0,0 +1,23 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::io;
+
+fn main() -> io::Result<()> {
+    let proto_files = &["analysis_v2.proto"];
+
+    buck2_protoc_dev::configure()
+        .setup_protoc()
+        // Field names in Bazel's `--output=jsonproto` follow the proto3 JSON mapping.
+        .type_attribute(
+            ".",
+            "#[derive(::serde::Serialize)] #[serde(rename_all = \"camelCase\")]",
+        )
+        .compile(proto_files, &["."])
+}
@@ -0,0 +1,13 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Subset of Bazel's `analysis_v2.proto`, used by `aquery --output-format=proto|jsonproto`
+//! so that tooling written against `analysis.ActionGraphContainer` can consume buck2 output.
+
+tonic::include_proto!("analysis");
@@ -0,0 +1,43 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::io;
+
+fn main() -> io::Result<()> {
+    let proto_files = &["build_event_stream.proto", "publish_build_event.proto"];
+
+    buck2_protoc_dev::configure()
+        .setup_protoc()
+        // Field names in Bazel's `--build_event_json_file` follow the proto3 JSON mapping.
+        .type_attribute(
+            ".build_event_stream",
+            "#[derive(::serde::Serialize)] #[serde(rename_all = \"camelCase\")]",
+        )
+        // Oneofs are inlined in their message in the proto3 JSON mapping.
+        .field_attribute(".build_event_stream.BuildEventId.id", "#[serde(flatten)]")
+        .field_attribute(
+            ".build_event_stream.BuildEvent.payload",
+            "#[serde(flatten)]",
+        )
+        .field_attribute(".build_event_stream.File.file", "#[serde(flatten)]")
+        // Enums are written by name in the proto3 JSON mapping.
+        .field_attribute(
+            ".build_event_stream.TestResult.status",
+            "#[serde(serialize_with = \"crate::serialize_test_status\")]",
+        )
+        .field_attribute(
+            ".build_event_stream.TestSummary.overall_status",
+            "#[serde(serialize_with = \"crate::serialize_test_status\")]",
+        )
+        .field_attribute(
+            ".build_event_stream.TargetConfigured.test_size",
+            "#[serde(serialize_with = \"crate::serialize_test_size\")]",
+        )
+        .compile(proto_files, &["."])
+}
@@ -0,0 +1,40 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Subset of Bazel's Build Event Protocol (`build_event_stream.proto`) and of the
+//! `google.devtools.build.v1.PublishBuildEvent` service used to stream it, so that BEP
+//! consumers can read buck2 builds.
+
+pub mod build_event_stream {
+    tonic::include_proto!("build_event_stream");
+}
+
+pub mod google {
+    pub mod devtools {
+        pub mod build {
+            pub mod v1 {
+                tonic::include_proto!("google.devtools.build.v1");
+            }
+        }
+    }
+}
+
+fn serialize_test_status<S: serde::Serializer>(status: &i32, s: S) -> Result<S::Ok, S::Error> {
+    match build_event_stream::TestStatus::from_i32(*status) {
+        Some(status) => s.serialize_str(status.as_str_name()),
+        None => s.serialize_i32(*status),
+    }
+}
+
+fn serialize_test_size<S: serde::Serializer>(size: &i32, s: S) -> Result<S::Ok, S::Error> {
+    match build_event_stream::TestSize::from_i32(*size) {
+        Some(size) => s.serialize_str(size.as_str_name()),
+        None => s.serialize_i32(*size),
+    }
+}
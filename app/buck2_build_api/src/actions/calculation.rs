@@ -119,7 +119,7 @@ async fn build_action_no_redirect(
     let action = &action;
 
     let fut = async move {
-        let (execute_result, command_reports) = executor
+        let (execute_result, command_reports, digest_inputs) = executor
             .execute(materialized_inputs, action, cancellation)
             .await;
 
@@ -269,6 +269,7 @@ async fn build_action_no_redirect(
                 did_dep_file_cache_upload: did_dep_file_cache_upload.unwrap_or_default(),
                 dep_file_key,
                 eligible_for_full_hybrid,
+                digest_inputs,
                 buck2_revision,
                 buck2_build_time,
                 hostname,
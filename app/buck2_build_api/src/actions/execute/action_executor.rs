@@ -7,6 +7,7 @@
  * of this source tree.
  */
 
+use std::collections::HashMap;
 use std::fmt::Debug;
 use std::ops::ControlFlow;
 use std::sync::Arc;
@@ -19,6 +20,9 @@ use buck2_common::dice::data::HasIoProvider;
 use buck2_common::events::HasEvents;
 use buck2_common::io::IoProvider;
 use buck2_common::liveliness_observer::NoopLivelinessObserver;
+use buck2_core::directory::DirectoryEntry;
+use buck2_core::directory::DirectoryIterator;
+use buck2_core::directory::FingerprintedDirectory;
 use buck2_core::execution_types::executor_config::CommandExecutorConfig;
 use buck2_core::fs::artifact_path_resolver::ArtifactFs;
 use buck2_core::fs::buck_out_path::BuckOutPath;
@@ -27,6 +31,7 @@ use buck2_execute::artifact::fs::ExecutorFs;
 use buck2_execute::artifact_value::ArtifactValue;
 use buck2_execute::digest_config::DigestConfig;
 use buck2_execute::digest_config::HasDigestConfig;
+use buck2_execute::directory::ActionDirectoryMember;
 use buck2_execute::execute::action_digest::ActionDigest;
 use buck2_execute::execute::blobs::ActionBlobs;
 use buck2_execute::execute::blocking::BlockingExecutor;
@@ -54,6 +59,7 @@ use buck2_execute::materialize::materializer::HasMaterializer;
 use buck2_execute::materialize::materializer::Materializer;
 use buck2_execute::output_size::OutputCountAndBytes;
 use buck2_execute::output_size::OutputSize;
+use buck2_execute::re::convert::platform_to_proto;
 use buck2_execute::re::manager::ManagedRemoteExecutionClient;
 use buck2_file_watcher::mergebase::GetMergebase;
 use buck2_file_watcher::mergebase::Mergebase;
@@ -222,6 +228,7 @@ pub trait ActionExecutor: Send + Sync {
     ) -> (
         Result<(ActionOutputs, ActionExecutionMetadata), ExecuteError>,
         Vec<CommandExecutionReport>,
+        Option<buck2_data::ActionDigestInputs>,
     );
 }
 
@@ -326,6 +333,8 @@ struct BuckActionExecutionContext<'a> {
     inputs: IndexMap<ArtifactGroup, ArtifactGroupValues>,
     outputs: &'a [BuildArtifact],
     command_reports: &'a mut Vec<CommandExecutionReport>,
+    /// Set when `RunActionKnobs::log_action_digest_inputs` is enabled.
+    digest_inputs: &'a mut Option<buck2_data::ActionDigestInputs>,
     cancellations: &'a CancellationContext<'a>,
 }
 
@@ -391,9 +400,14 @@ impl ActionExecutionCtx for BuckActionExecutionContext<'_> {
         &mut self,
         request: &CommandExecutionRequest,
     ) -> anyhow::Result<PreparedAction> {
-        self.executor
+        let prepared_action = self
+            .executor
             .command_executor
-            .prepare_action(request, self.digest_config())
+            .prepare_action(request, self.digest_config())?;
+        if self.run_action_knobs().log_action_digest_inputs {
+            *self.digest_inputs = Some(action_digest_inputs(request, &prepared_action, self.fs()));
+        }
+        Ok(prepared_action)
     }
 
     async fn action_cache(
@@ -577,8 +591,10 @@ impl ActionExecutor for BuckActionExecutor {
     ) -> (
         Result<(ActionOutputs, ActionExecutionMetadata), ExecuteError>,
         Vec<CommandExecutionReport>,
+        Option<buck2_data::ActionDigestInputs>,
     ) {
         let mut command_reports = Vec::new();
+        let mut digest_inputs = None;
 
         let res = async {
             let outputs = action.outputs()?;
@@ -589,6 +605,7 @@ impl ActionExecutor for BuckActionExecutor {
                 inputs,
                 outputs: outputs.as_ref(),
                 command_reports: &mut command_reports,
+                digest_inputs: &mut digest_inputs,
                 cancellations,
             };
 
@@ -658,7 +675,86 @@ impl ActionExecutor for BuckActionExecutor {
         }
         .await;
 
-        (res, command_reports)
+        if let (Ok((outputs, _)), Some(digest_inputs)) = (&res, &mut digest_inputs) {
+            let mut digests: HashMap<String, Option<String>> = outputs
+                .iter()
+                .map(|(path, value)| {
+                    (
+                        self.command_executor.fs().resolve_build(path).to_string(),
+                        value.digest().map(|d| d.to_string()),
+                    )
+                })
+                .collect();
+            for output in &mut digest_inputs.outputs {
+                output.digest = digests.remove(&output.path).flatten();
+            }
+        }
+
+        (res, command_reports, digest_inputs)
+    }
+}
+
+/// Everything that goes into the action digest, so that the digests of two actions can be
+/// compared.
+fn action_digest_inputs(
+    request: &CommandExecutionRequest,
+    prepared_action: &PreparedAction,
+    fs: &ArtifactFs,
+) -> buck2_data::ActionDigestInputs {
+    let mut inputs = Vec::new();
+    let mut walk = request
+        .paths()
+        .input_directory()
+        .fingerprinted_ordered_walk();
+    while let Some((path, entry)) = walk.next() {
+        let (data, is_executable) = match entry {
+            DirectoryEntry::Dir(_) => continue,
+            DirectoryEntry::Leaf(ActionDirectoryMember::File(f)) => (
+                buck2_data::action_digest_inputs::input::Data::Digest(f.digest.to_string()),
+                f.is_executable,
+            ),
+            DirectoryEntry::Leaf(ActionDirectoryMember::Symlink(s)) => (
+                buck2_data::action_digest_inputs::input::Data::SymlinkTarget(s.to_string()),
+                false,
+            ),
+            DirectoryEntry::Leaf(ActionDirectoryMember::ExternalSymlink(s)) => (
+                buck2_data::action_digest_inputs::input::Data::SymlinkTarget(
+                    s.target_str().to_owned(),
+                ),
+                false,
+            ),
+        };
+        inputs.push(buck2_data::action_digest_inputs::Input {
+            path: path.get().to_string(),
+            data: Some(data),
+            is_executable,
+        });
+    }
+
+    buck2_data::ActionDigestInputs {
+        action_digest: prepared_action.action.to_string(),
+        argv: request.all_args_vec(),
+        env: request
+            .env()
+            .iter()
+            .map(|(key, value)| buck2_data::EnvironmentEntry {
+                key: key.clone(),
+                value: value.clone(),
+            })
+            .collect(),
+        working_directory: request
+            .working_directory()
+            .map(|p| p.as_str().to_owned())
+            .unwrap_or_default(),
+        inputs,
+        platform: Some(platform_to_proto(&prepared_action.platform)),
+        outputs: request
+            .outputs()
+            .map(|output| buck2_data::action_digest_inputs::Output {
+                path: output.resolve(fs).path.to_string(),
+                digest: None,
+            })
+            .collect(),
     }
 }
 
@@ -24,6 +24,10 @@ pub struct RunActionKnobs {
     /// for network actions (download_file, cas_artifact). Used to support offline
     /// builds.
     pub use_network_action_output_cache: bool,
+
+    /// Record what went into the action digests in the event log, so that cache misses can be
+    /// explained with `buck2 log diff-actions`.
+    pub log_action_digest_inputs: bool,
 }
 
 pub trait HasRunActionKnobs {
@@ -80,6 +80,7 @@ use thiserror::Error;
 use crate::actions::execute::action_execution_target::ActionExecutionTarget;
 use crate::actions::execute::action_executor::ActionExecutionMetadata;
 use crate::actions::execute::action_executor::ActionOutputs;
+use crate::actions::impls::expanded_command_line::ExpandedCommandLine;
 use crate::actions::impls::run_action_knobs::RunActionKnobs;
 use crate::artifact_groups::ArtifactGroup;
 use crate::artifact_groups::ArtifactGroupValues;
@@ -155,6 +156,13 @@ pub trait Action: Allocative + Debug + Send + Sync + 'static {
         indexmap! {}
     }
 
+    /// The command this action runs, for `aquery` output formats which need the arguments and
+    /// the environment separately rather than rendered as attributes.
+    /// `None` for actions which don't run a command.
+    fn aquery_command_line(&self, _fs: &ExecutorFs) -> anyhow::Result<Option<ExpandedCommandLine>> {
+        Ok(None)
+    }
+
     // TODO this probably wants more data for execution, like printing a short_name and the target
 }
 
@@ -18,6 +18,7 @@ use std::sync::Arc;
 
 use allocative::Allocative;
 use buck2_artifact::actions::key::ActionKey;
+use buck2_artifact::artifact::artifact_type::Artifact;
 use buck2_core::build_file_path::BuildFilePath;
 use buck2_core::cells::cell_path::CellPath;
 use buck2_core::cells::CellResolver;
@@ -74,7 +75,16 @@ impl ActionAttr {
 pub struct SetProjectionInputsData {
     key: TransitiveSetProjectionKey,
     pub direct: Vec<ActionQueryNodeRef>,
-    pub(crate) children: Vec<SetProjectionInputs>,
+    /// Artifacts directly contained in the projection, including source artifacts
+    /// (which have no action in `direct`).
+    pub artifacts: Vec<Artifact>,
+    pub children: Vec<SetProjectionInputs>,
+}
+
+impl SetProjectionInputsData {
+    pub fn key(&self) -> &TransitiveSetProjectionKey {
+        &self.key
+    }
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
@@ -89,12 +99,14 @@ impl SetProjectionInputs {
     pub fn new(
         key: TransitiveSetProjectionKey,
         direct: Vec<ActionQueryNodeRef>,
+        artifacts: Vec<Artifact>,
         children: Vec<SetProjectionInputs>,
     ) -> Self {
         Self {
             node: ArcIntern::new(SetProjectionInputsData {
                 key,
                 direct,
+                artifacts,
                 children,
             }),
         }
@@ -181,6 +193,13 @@ impl ActionQueryNode {
     pub fn key(&self) -> &ActionQueryNodeRef {
         &self.key
     }
+
+    pub fn action_data(&self) -> Option<&ActionData> {
+        match &self.data {
+            ActionQueryNodeData::Analysis(..) => None,
+            ActionQueryNodeData::Action(data) => Some(data),
+        }
+    }
 }
 
 impl LabeledNode for ActionQueryNode {
@@ -223,7 +242,19 @@ pub struct ActionData {
 }
 
 impl ActionData {
-    fn attrs(&self) -> IndexMap<String, String> {
+    pub fn action(&self) -> &Arc<RegisteredAction> {
+        &self.action
+    }
+
+    pub fn deps(&self) -> &[ActionInput] {
+        &self.deps
+    }
+
+    pub fn fs(&self) -> &Arc<ArtifactFs> {
+        &self.fs
+    }
+
+    pub fn attrs(&self) -> IndexMap<String, String> {
         let mut attrs = self.action.action().aquery_attributes(&ExecutorFs::new(
             &self.fs,
             self.action.execution_config().options.path_separator,
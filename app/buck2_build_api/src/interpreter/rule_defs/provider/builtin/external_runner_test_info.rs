@@ -9,6 +9,7 @@
 
 use std::iter::empty;
 use std::iter::once;
+use std::time::Duration;
 
 use allocative::Allocative;
 use anyhow::Context as _;
@@ -98,6 +99,17 @@ pub struct ExternalRunnerTestInfoGen<V> {
     /// should be ignored when executing tests even if those are passed as required from test runner.
     #[provider(field_type = DictType<String, Option<StarlarkConfiguredProvidersLabel>>)]
     local_resources: V,
+
+    /// Timeout of the test in milliseconds. The test runner decides how it relates to its own
+    /// timeouts.
+    #[provider(field_type = i32)]
+    timeout_ms: V,
+
+    /// Command converting the coverage data the test writes to `$COVERAGE_DIR` to LCOV, with
+    /// `buck2 test --coverage`. It receives the directory as its last argument and prints LCOV to
+    /// stdout.
+    #[provider(field_type = FrozenValue)]
+    coverage_merger: V,
 }
 
 // NOTE: All the methods here unwrap because we validate at freeze time.
@@ -152,6 +164,14 @@ impl FrozenExternalRunnerTestInfo {
         unwrap_all(iter_local_resources(self.local_resources.to_value())).collect()
     }
 
+    pub fn timeout(&self) -> Option<Duration> {
+        unpack_opt_timeout_ms(self.timeout_ms.to_value()).unwrap()
+    }
+
+    pub fn coverage_merger(&self) -> Option<&dyn CommandLineArgLike> {
+        unpack_opt_arglike(self.coverage_merger.to_value()).unwrap()
+    }
+
     pub fn visit_artifacts(
         &self,
         visitor: &mut dyn CommandLineArtifactVisitor,
@@ -169,6 +189,10 @@ impl FrozenExternalRunnerTestInfo {
             arglike.visit_artifacts(visitor)?;
         }
 
+        if let Some(coverage_merger) = self.coverage_merger() {
+            coverage_merger.visit_artifacts(visitor)?;
+        }
+
         // Ignoring local resources as those are built on-demand.
 
         Ok(())
@@ -397,6 +421,14 @@ fn unpack_opt_executor<'v>(
     Ok(Some(executor))
 }
 
+fn unpack_opt_arglike<'v>(value: Value<'v>) -> anyhow::Result<Option<&'v dyn CommandLineArgLike>> {
+    if value.is_none() {
+        return Ok(None);
+    }
+
+    Ok(Some(ValueAsCommandLineLike::unpack_value_err(value)?.0))
+}
+
 fn check_all<I, T>(it: I) -> anyhow::Result<()>
 where
     I: IntoIterator<Item = anyhow::Result<T>>,
@@ -414,6 +446,20 @@ where
     it.into_iter().map(|e| e.unwrap())
 }
 
+fn unpack_opt_timeout_ms(timeout_ms: Value) -> anyhow::Result<Option<Duration>> {
+    match NoneOr::<i32>::unpack_value(timeout_ms)
+        .context("`timeout_ms` must be an int if provided")?
+        .into_option()
+    {
+        None => Ok(None),
+        Some(ms) if ms > 0 => Ok(Some(Duration::from_millis(ms as u64))),
+        Some(ms) => Err(anyhow::anyhow!(
+            "`timeout_ms` must be positive, got: `{}`",
+            ms
+        )),
+    }
+}
+
 fn validate_external_runner_test_info<'v, V>(
     info: &ExternalRunnerTestInfoGen<V>,
 ) -> anyhow::Result<()>
@@ -431,6 +477,8 @@ where
     NoneOr::<bool>::unpack_value(info.run_from_project_root.to_value())
         .context("`run_from_project_root` must be a bool if provided")?;
     unpack_opt_executor(info.default_executor.to_value()).context("Invalid `default_executor`")?;
+    unpack_opt_timeout_ms(info.timeout_ms.to_value())?;
+    unpack_opt_arglike(info.coverage_merger.to_value()).context("Invalid `coverage_merger`")?;
     info.test_type
         .to_value()
         .unpack_str()
@@ -452,6 +500,8 @@ fn external_runner_test_info_creator(globals: &mut GlobalsBuilder) {
         #[starlark(default = NoneType)] default_executor: Value<'v>,
         #[starlark(default = NoneType)] executor_overrides: Value<'v>,
         #[starlark(default = NoneType)] local_resources: Value<'v>,
+        #[starlark(default = NoneType)] timeout_ms: Value<'v>,
+        #[starlark(default = NoneType)] coverage_merger: Value<'v>,
     ) -> anyhow::Result<ExternalRunnerTestInfo<'v>> {
         let res = ExternalRunnerTestInfo {
             test_type: r#type,
@@ -464,6 +514,8 @@ fn external_runner_test_info_creator(globals: &mut GlobalsBuilder) {
             default_executor,
             executor_overrides,
             local_resources,
+            timeout_ms,
+            coverage_merger,
         };
         validate_external_runner_test_info(&res)?;
         Ok(res)
@@ -13,6 +13,7 @@ use buck2_core::cells::name::CellName;
 use buck2_test_api::data::ConfiguredTarget;
 use buck2_test_api::data::ExternalRunnerSpec;
 use buck2_test_api::data::ExternalRunnerSpecValue;
+use buck2_test_api::data::LocalResourceType;
 use buck2_test_api::protocol::TestExecutor;
 use futures::future::BoxFuture;
 use futures::future::FutureExt;
@@ -87,6 +88,14 @@ impl TestProvider for FrozenExternalRunnerTestInfo {
             contacts: self.contacts().map(|l| l.to_owned()).collect(),
             oncall: self.contacts().exactly_one().ok().map(str::to_owned),
             working_dir_cell,
+            local_resources: self
+                .local_resources()
+                .into_keys()
+                .map(|name| LocalResourceType {
+                    name: name.to_owned(),
+                })
+                .collect(),
+            timeout: self.timeout(),
         };
 
         async move { executor.external_runner_spec(spec).await }.boxed()
@@ -7,12 +7,15 @@
  * of this source tree.
  */
 
+use std::sync::Arc;
+
 use async_trait::async_trait;
 use buck2_core::fs::project_rel_path::ProjectRelativePath;
 use buck2_core::target::label::TargetLabel;
 use buck2_node::configured_universe::CqueryUniverse;
 use buck2_node::nodes::configured::ConfiguredTargetNode;
 use buck2_node::nodes::unconfigured::TargetNode;
+use buck2_query::query::syntax::simple::eval::profile::QueryProfiler;
 use buck2_query::query::syntax::simple::eval::values::QueryEvaluationResult;
 use buck2_util::late_binding::LateBinding;
 use dice::DiceComputations;
@@ -36,6 +39,7 @@ pub trait QueryFrontend: Send + Sync + 'static {
         query: &str,
         query_args: &[String],
         global_target_platform: Option<TargetLabel>,
+        profiler: Option<Arc<QueryProfiler>>,
     ) -> anyhow::Result<QueryEvaluationResult<TargetNode>>;
 
     async fn eval_cquery(
@@ -47,6 +51,7 @@ pub trait QueryFrontend: Send + Sync + 'static {
         query_args: &[String],
         global_target_platform: Option<TargetLabel>,
         target_universe: Option<&[String]>,
+        profiler: Option<Arc<QueryProfiler>>,
     ) -> anyhow::Result<QueryEvaluationResult<ConfiguredTargetNode>>;
 
     async fn eval_aquery(
@@ -34,6 +34,8 @@ fn test_construction() -> anyhow::Result<()> {
             ExternalRunnerTestInfo(type = "foo", labels = ("foo",))
             ExternalRunnerTestInfo(type = "foo", use_project_relative_paths = True)
             ExternalRunnerTestInfo(type = "foo", run_from_project_root = True)
+            ExternalRunnerTestInfo(type = "foo", timeout_ms = 60000)
+            ExternalRunnerTestInfo(type = "foo", coverage_merger = cmd_args("merge"))
         "#
     );
     let mut tester = tester();
@@ -184,6 +186,36 @@ fn test_validation() -> anyhow::Result<()> {
         "`executor_overrides`",
     );
 
+    tester.run_starlark_bzl_test_expecting_error(
+        indoc!(
+            r#"
+        def test():
+            ExternalRunnerTestInfo(type = "foo", timeout_ms = "60s")
+        "#
+        ),
+        "`timeout_ms`",
+    );
+
+    tester.run_starlark_bzl_test_expecting_error(
+        indoc!(
+            r#"
+        def test():
+            ExternalRunnerTestInfo(type = "foo", timeout_ms = 0)
+        "#
+        ),
+        "`timeout_ms` must be positive",
+    );
+
+    tester.run_starlark_bzl_test_expecting_error(
+        indoc!(
+            r#"
+        def test():
+            ExternalRunnerTestInfo(type = "foo", coverage_merger = {})
+        "#
+        ),
+        "`coverage_merger`",
+    );
+
     Ok(())
 }
 
@@ -74,6 +74,8 @@ pub struct BuildSignalsContext {
     pub command_name: String,
     pub metadata: HashMap<String, String>,
     pub isolation_prefix: FileNameBuf,
+    /// Log the whole graph along with the critical path (`buck2.log_build_graph`).
+    pub log_build_graph: bool,
 }
 
 /// Created along with the BuildSignalsInstaller (ideally, BuildSignalsInstaller's definition would
@@ -135,8 +137,14 @@ pub trait HasCriticalPathBackend {
     fn set_critical_path_backend(&mut self, backend: CriticalPathBackendName);
 
     fn get_critical_path_backend(&self) -> CriticalPathBackendName;
+
+    fn set_log_build_graph(&mut self, log_build_graph: bool);
+
+    fn get_log_build_graph(&self) -> bool;
 }
 
+struct LogBuildGraph(bool);
+
 impl HasCriticalPathBackend for UserComputationData {
     fn set_critical_path_backend(&mut self, backend: CriticalPathBackendName) {
         self.data.set(backend);
@@ -148,4 +156,12 @@ impl HasCriticalPathBackend for UserComputationData {
             .get::<CriticalPathBackendName>()
             .expect("CriticalPathBackendName should be set")
     }
+
+    fn set_log_build_graph(&mut self, log_build_graph: bool) {
+        self.data.set(LogBuildGraph(log_build_graph));
+    }
+
+    fn get_log_build_graph(&self) -> bool {
+        self.data.get::<LogBuildGraph>().map_or(false, |l| l.0)
+    }
 }
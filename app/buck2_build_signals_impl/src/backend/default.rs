@@ -148,6 +148,7 @@ impl BuildListenerBackend for DefaultBackend {
             critical_path,
             num_nodes: self.num_nodes,
             num_edges: self.num_edges,
+            graph: None,
         })
     }
 
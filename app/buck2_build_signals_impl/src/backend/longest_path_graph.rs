@@ -16,14 +16,19 @@ use buck2_build_signals::CriticalPathBackendName;
 use buck2_build_signals::NodeDuration;
 use buck2_core::soft_error;
 use buck2_critical_path::compute_critical_path_potentials;
+use buck2_critical_path::Graph;
 use buck2_critical_path::GraphBuilder;
 use buck2_critical_path::OptionalVertexId;
 use buck2_critical_path::PushError;
+use buck2_critical_path::VertexData;
+use buck2_critical_path::VertexKeys;
 use buck2_events::span::SpanId;
 use dupe::Dupe;
 use smallvec::SmallVec;
 
 use crate::backend::backend::BuildListenerBackend;
+use crate::critical_path_entry;
+use crate::node_entry;
 use crate::BuildInfo;
 use crate::NodeData;
 use crate::NodeKey;
@@ -33,6 +38,7 @@ use crate::NodeKey;
 pub(crate) struct LongestPathGraphBackend {
     builder: anyhow::Result<GraphBuilder<NodeKey, NodeData>>,
     top_level_analysis: Vec<VisibilityEdge>,
+    log_build_graph: bool,
 }
 
 /// Represents nodes that block us "seeing" other parts of the graph until they finish evaluating.
@@ -42,10 +48,11 @@ struct VisibilityEdge {
 }
 
 impl LongestPathGraphBackend {
-    pub(crate) fn new() -> Self {
+    pub(crate) fn new(log_build_graph: bool) -> Self {
         Self {
             builder: Ok(GraphBuilder::new()),
             top_level_analysis: Vec::new(),
+            log_build_graph,
         }
     }
 }
@@ -177,6 +184,12 @@ impl BuildListenerBackend for LongestPathGraphBackend {
 
         drop(durations);
 
+        let build_graph = if self.log_build_graph {
+            Some(build_graph(&graph, &keys, &data)?)
+        } else {
+            None
+        };
+
         let critical_path = critical_path
             .iter()
             .map(|(cp_idx, vertex_idx)| {
@@ -204,6 +217,7 @@ impl BuildListenerBackend for LongestPathGraphBackend {
             critical_path,
             num_nodes: graph.vertices_count() as _,
             num_edges: graph.edges_count() as _,
+            graph: build_graph,
         })
     }
 
@@ -211,3 +225,26 @@ impl BuildListenerBackend for LongestPathGraphBackend {
         CriticalPathBackendName::LongestPathGraph
     }
 }
+
+/// Serialize the whole graph, including the edges to top level analyses.
+fn build_graph(
+    graph: &Graph,
+    keys: &VertexKeys<NodeKey>,
+    data: &VertexData<NodeData>,
+) -> anyhow::Result<buck2_data::BuildGraph> {
+    let nodes = graph
+        .iter_vertices()
+        .map(|idx| {
+            anyhow::Ok(buck2_data::build_graph::Node {
+                entry: Some(critical_path_entry(
+                    node_entry(&keys[idx], &data[idx]),
+                    &data[idx],
+                    None,
+                )?),
+                deps: graph.iter_edges(idx).map(|dep| dep.into_inner()).collect(),
+            })
+        })
+        .collect::<Result<_, _>>()?;
+
+    Ok(buck2_data::BuildGraph { nodes })
+}
@@ -319,9 +319,12 @@ impl DeferredBuildSignals for DeferredBuildSignalsImpl {
         ctx: BuildSignalsContext,
     ) -> Box<dyn FinishBuildSignals> {
         let handle = match backend {
-            CriticalPathBackendName::LongestPathGraph => {
-                start_backend(events, self.receiver, LongestPathGraphBackend::new(), ctx)
-            }
+            CriticalPathBackendName::LongestPathGraph => start_backend(
+                events,
+                self.receiver,
+                LongestPathGraphBackend::new(ctx.log_build_graph),
+                ctx,
+            ),
             CriticalPathBackendName::Default => {
                 start_backend(events, self.receiver, DefaultBackend::new(), ctx)
             }
@@ -403,6 +406,7 @@ where
             critical_path,
             num_nodes,
             num_edges,
+            graph,
         } = self.backend.finish()?;
 
         let compute_elapsed = now.elapsed();
@@ -425,70 +429,11 @@ where
         let critical_path2 = critical_path
             .iter()
             .filter_map(|(key, data, potential_improvement)| {
-                let entry: buck2_data::critical_path_entry2::Entry = match key {
-                    NodeKey::BuildKey(key) => {
-                        let owner = key.0.owner().to_proto().into();
-
-                        // If we have a NodeKey that's an ActionKey we'd expect to have an `action`
-                        // in our data (unless we didn't actually run it because of e.g. early
-                        // cutoff, in which case omitting it is what we want).
-                        let action = data.action.as_ref()?;
-
-                        buck2_data::critical_path_entry2::ActionExecution {
-                            owner: Some(owner),
-                            name: Some(buck2_data::ActionName {
-                                category: action.category().as_str().to_owned(),
-                                identifier: action.identifier().unwrap_or("").to_owned(),
-                            }),
-                        }
-                        .into()
-                    }
-                    NodeKey::AnalysisKey(key) => buck2_data::critical_path_entry2::Analysis {
-                        target: Some(key.0.as_proto().into()),
-                    }
-                    .into(),
-                    NodeKey::Materialization(key) => {
-                        let owner = key.key().owner().to_proto().into();
-
-                        buck2_data::critical_path_entry2::Materialization {
-                            owner: Some(owner),
-                            path: key.get_path().path().to_string(),
-                        }
-                        .into()
-                    }
-                    NodeKey::InterpreterResultsKey(key) => buck2_data::critical_path_entry2::Load {
-                        package: key.0.to_string(),
-                    }
-                    .into(),
-                    NodeKey::PackageListingKey(key) => buck2_data::critical_path_entry2::Listing {
-                        package: key.0.to_string(),
-                    }
-                    .into(),
-                    NodeKey::EnsureProjectedArtifactKey(..) => return None,
-                    NodeKey::EnsureTransitiveSetProjectionKey(..) => return None,
-                    NodeKey::DeferredCompute(..) => return None,
-                    NodeKey::DeferredResolve(..) => return None,
-                    NodeKey::ConfiguredTargetNodeKey(..) => return None,
-                };
-
-                Some((entry, data, potential_improvement))
+                Some((node_entry(key, data)?, data, potential_improvement))
             })
             .chain(std::iter::once(meta_entry))
             .map(|(entry, data, potential_improvement)| {
-                anyhow::Ok(buck2_data::CriticalPathEntry2 {
-                    span_ids: data
-                        .span_ids
-                        .iter()
-                        .map(|span_id| (*span_id).into())
-                        .collect(),
-                    duration: Some(data.duration.critical_path_duration().try_into()?),
-                    user_duration: Some(data.duration.user.try_into()?),
-                    total_duration: Some(data.duration.total.try_into()?),
-                    potential_improvement_duration: potential_improvement
-                        .map(|p| p.try_into())
-                        .transpose()?,
-                    entry: Some(entry),
-                })
+                critical_path_entry(Some(entry), data, *potential_improvement)
             })
             .collect::<Result<Vec<_>, _>>()?;
 
@@ -502,6 +447,7 @@ where
             num_edges,
             uses_total_duration: true,
             backend_name: Some(T::name().to_string()),
+            build_graph: graph,
         });
         Ok(())
     }
@@ -612,6 +558,78 @@ pub struct BuildInfo {
     critical_path: Vec<(NodeKey, NodeData, Option<Duration>)>,
     num_nodes: u64,
     num_edges: u64,
+    /// The whole graph, if requested and supported by the backend.
+    graph: Option<buck2_data::BuildGraph>,
+}
+
+/// What to show for a node in the critical path, if anything.
+fn node_entry(key: &NodeKey, data: &NodeData) -> Option<buck2_data::critical_path_entry2::Entry> {
+    let entry = match key {
+        NodeKey::BuildKey(key) => {
+            let owner = key.0.owner().to_proto().into();
+
+            // If we have a NodeKey that's an ActionKey we'd expect to have an `action`
+            // in our data (unless we didn't actually run it because of e.g. early
+            // cutoff, in which case omitting it is what we want).
+            let action = data.action.as_ref()?;
+
+            buck2_data::critical_path_entry2::ActionExecution {
+                owner: Some(owner),
+                name: Some(buck2_data::ActionName {
+                    category: action.category().as_str().to_owned(),
+                    identifier: action.identifier().unwrap_or("").to_owned(),
+                }),
+            }
+            .into()
+        }
+        NodeKey::AnalysisKey(key) => buck2_data::critical_path_entry2::Analysis {
+            target: Some(key.0.as_proto().into()),
+        }
+        .into(),
+        NodeKey::Materialization(key) => {
+            let owner = key.key().owner().to_proto().into();
+
+            buck2_data::critical_path_entry2::Materialization {
+                owner: Some(owner),
+                path: key.get_path().path().to_string(),
+            }
+            .into()
+        }
+        NodeKey::InterpreterResultsKey(key) => buck2_data::critical_path_entry2::Load {
+            package: key.0.to_string(),
+        }
+        .into(),
+        NodeKey::PackageListingKey(key) => buck2_data::critical_path_entry2::Listing {
+            package: key.0.to_string(),
+        }
+        .into(),
+        NodeKey::EnsureProjectedArtifactKey(..) => return None,
+        NodeKey::EnsureTransitiveSetProjectionKey(..) => return None,
+        NodeKey::DeferredCompute(..) => return None,
+        NodeKey::DeferredResolve(..) => return None,
+        NodeKey::ConfiguredTargetNodeKey(..) => return None,
+    };
+
+    Some(entry)
+}
+
+fn critical_path_entry(
+    entry: Option<buck2_data::critical_path_entry2::Entry>,
+    data: &NodeData,
+    potential_improvement: Option<Duration>,
+) -> anyhow::Result<buck2_data::CriticalPathEntry2> {
+    Ok(buck2_data::CriticalPathEntry2 {
+        span_ids: data
+            .span_ids
+            .iter()
+            .map(|span_id| (*span_id).into())
+            .collect(),
+        duration: Some(data.duration.critical_path_duration().try_into()?),
+        user_duration: Some(data.duration.user.try_into()?),
+        total_duration: Some(data.duration.total.try_into()?),
+        potential_improvement_duration: potential_improvement.map(|p| p.try_into()).transpose()?,
+        entry,
+    })
 }
 
 #[derive(Clone)]
@@ -663,6 +663,7 @@ fn cquery_methods(builder: &mut MethodsBuilder) {
                                 &query_args,
                                 this.target_platform.dupe(),
                                 target_universe.into_option().as_ref().map(|v| &v.items[..]),
+                                None,
                             )
                             .await?,
                         eval,
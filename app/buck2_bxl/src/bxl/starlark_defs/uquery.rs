@@ -498,7 +498,14 @@ fn uquery_methods(builder: &mut MethodsBuilder) {
                     parse_query_evaluation_result(
                         QUERY_FRONTEND
                             .get()?
-                            .eval_uquery(dice, &this.ctx.working_dir()?, query, &query_args, None)
+                            .eval_uquery(
+                                dice,
+                                &this.ctx.working_dir()?,
+                                query,
+                                &query_args,
+                                None,
+                                None,
+                            )
                             .await?,
                         eval,
                     )
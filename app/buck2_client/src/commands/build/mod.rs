@@ -38,8 +38,15 @@ use multimap::MultiMap;
 use serde::Serialize;
 
 use crate::commands::build::out::copy_to_out;
+use crate::commands::build::rerun_failed::previous_failures;
+use crate::commands::build::rerun_failed::RerunFailedOptions;
+use crate::commands::build::watch::watch;
+use crate::commands::build::watch::WatchOptions;
+use crate::commands::build::watch::WatchedCommand;
 
 mod out;
+pub(crate) mod rerun_failed;
+pub(crate) mod watch;
 
 #[derive(Debug, clap::Parser)]
 #[clap(name = "build", about = "Build the specified targets")]
@@ -50,6 +57,12 @@ pub struct BuildCommand {
     #[clap(flatten)]
     build_opts: CommonBuildOptions,
 
+    #[clap(flatten)]
+    watch_opts: WatchOptions,
+
+    #[clap(flatten)]
+    rerun_failed_opts: RerunFailedOptions,
+
     #[clap(
         long,
         short = 'u',
@@ -228,13 +241,18 @@ pub fn print_build_result(
     Ok(())
 }
 
-#[async_trait]
-impl StreamingCommand for BuildCommand {
-    const COMMAND_NAME: &'static str = "build";
+impl BuildCommand {
+    /// Takes the options which configure and select the targets from an earlier invocation, to
+    /// build again what failed in it.
+    fn with_options_of(mut self, earlier: BuildCommand) -> Self {
+        self.common_opts.config_opts = earlier.common_opts.config_opts;
+        self.target_universe = earlier.target_universe;
+        self
+    }
 
-    async fn exec_impl(
-        self,
-        buckd: &mut BuckdClientConnector,
+    async fn build(
+        &self,
+        buckd: &mut BuckdClientConnector<'_>,
         matches: &clap::ArgMatches,
         ctx: &mut ClientCommandContext<'_>,
     ) -> ExitResult {
@@ -266,9 +284,10 @@ impl StreamingCommand for BuildCommand {
                     }),
                     build_opts: Some(self.build_opts.to_proto()),
                     final_artifact_materializations: self.materializations.to_proto() as i32,
-                    target_universe: self.target_universe,
+                    target_universe: self.target_universe.clone(),
                     output_hashes_file: self
                         .output_hashes_file
+                        .as_ref()
                         .map(|p| {
                             p.resolve(&ctx.working_dir).into_string().with_context(|| {
                                 format!(
@@ -365,6 +384,30 @@ impl StreamingCommand for BuildCommand {
 
         res.with_stdout(stdout)
     }
+}
+
+#[async_trait]
+impl StreamingCommand for BuildCommand {
+    const COMMAND_NAME: &'static str = "build";
+
+    async fn exec_impl(
+        self,
+        buckd: &mut BuckdClientConnector,
+        matches: &clap::ArgMatches,
+        ctx: &mut ClientCommandContext<'_>,
+    ) -> ExitResult {
+        if self.watch_opts.watch {
+            let watch_opts = self.watch_opts.clone();
+            return watch(self, &watch_opts, buckd, matches, ctx).await;
+        }
+        if self.rerun_failed_opts.rerun_failed {
+            let rerun = previous_failures::<Self>(ctx, Self::COMMAND_NAME).await?;
+            let mut cmd = self.with_options_of(rerun.command);
+            cmd.patterns = rerun.targets;
+            return cmd.build(buckd, &rerun.matches, ctx).await;
+        }
+        self.build(buckd, matches, ctx).await
+    }
 
     fn console_opts(&self) -> &CommonConsoleOptions {
         &self.common_opts.console_opts
@@ -379,6 +422,24 @@ impl StreamingCommand for BuildCommand {
     }
 }
 
+#[async_trait]
+impl WatchedCommand for BuildCommand {
+    type Command = Self;
+
+    fn command(&self) -> &Self {
+        self
+    }
+
+    async fn run_once(
+        &mut self,
+        buckd: &mut BuckdClientConnector,
+        matches: &clap::ArgMatches,
+        ctx: &mut ClientCommandContext<'_>,
+    ) -> ExitResult {
+        self.build(buckd, matches, ctx).await
+    }
+}
+
 pub(crate) fn print_build_succeeded(
     console: &FinalConsole,
     ctx: &ClientCommandContext<'_>,
@@ -576,4 +637,12 @@ mod test {
 
         Ok(())
     }
+    #[test]
+    fn rerun_keeps_options() -> anyhow::Result<()> {
+        let earlier = parse(&["-c", "a.b=c", "--target-universe", "//:u", "//:t"])?;
+        let rerun = parse(&["--rerun-failed"])?.with_options_of(earlier);
+        assert_eq!(vec!["a.b=c"], rerun.common_opts.config_opts.config_values);
+        assert_eq!(vec!["//:u"], rerun.target_universe);
+        Ok(())
+    }
 }
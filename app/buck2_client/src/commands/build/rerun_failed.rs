@@ -0,0 +1,426 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! `--rerun-failed`: build or test only what failed in the last invocation of the command.
+//!
+//! The failures are read from the event log of the last invocation, like `buck2 log what-failed`
+//! does. The configuration flags, and the flags which select targets like `--target-universe` or
+//! the label filters of `buck2 test`, are parsed from the command line recorded in the event log
+//! of the last invocation which was not itself a rerun, so that repeated reruns keep them.
+
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+
+use anyhow::Context;
+use buck2_client_ctx::client_ctx::ClientCommandContext;
+use buck2_client_ctx::stream_value::StreamValue;
+use buck2_client_ctx::subscribers::event_log::file_names::retrieve_all_logs;
+use buck2_client_ctx::subscribers::event_log::utils::Invocation;
+use tokio_stream::StreamExt;
+
+#[derive(Debug, thiserror::Error)]
+enum RerunFailedError {
+    #[error("No earlier `buck2 {0}` found in the event logs")]
+    NoPreviousInvocation(&'static str),
+    #[error("Nothing failed in the last `buck2 {0}`: {1}")]
+    NoFailures(&'static str, String),
+}
+
+#[derive(Debug, Clone, clap::Parser)]
+pub(crate) struct RerunFailedOptions {
+    /// Only run again the targets which failed in the last invocation of this command, with the
+    /// same configuration and target selection flags. With `buck2 test`, if the test runner
+    /// reported which test cases failed and supports running single test cases, only those are
+    /// run again.
+    #[clap(long, conflicts_with_all = &["TARGET_PATTERNS", "watch"])]
+    pub(crate) rerun_failed: bool,
+}
+
+/// What to run again.
+pub(crate) struct Rerun<T> {
+    /// The last invocation which was not a rerun, to take configuration and target selection
+    /// flags from.
+    pub(crate) command: T,
+    /// The matches of `command`.
+    pub(crate) matches: clap::ArgMatches,
+    /// Unconfigured labels of the targets which failed to build or test, and `package:` patterns
+    /// of the packages which failed to load.
+    pub(crate) targets: Vec<String>,
+    /// Names of the test cases which failed by unconfigured target label, for the targets whose
+    /// failures the test runner all reported per test case.
+    pub(crate) test_cases: BTreeMap<String, BTreeSet<String>>,
+}
+
+pub(crate) async fn previous_failures<T: clap::Parser>(
+    ctx: &ClientCommandContext<'_>,
+    command_name: &'static str,
+) -> anyhow::Result<Rerun<T>> {
+    let mut logs = retrieve_all_logs(ctx)?;
+    logs.reverse(); // newest first
+
+    let mut failures = None;
+    for log in logs {
+        let (invocation, events) = match log.unpack_stream().await {
+            Ok(stream) => stream,
+            // Logs of commands still running, or written by other versions, can't be read.
+            Err(..) => continue,
+        };
+        if invocation.trace_id == ctx.trace_id {
+            continue;
+        }
+        let args = match command_args(&invocation, command_name) {
+            Some(args) => args,
+            None => continue,
+        };
+
+        let failures = match &mut failures {
+            Some(failures) => failures,
+            None => {
+                let read = Failures::read(events).await?;
+                if read.is_empty() {
+                    return Err(RerunFailedError::NoFailures(
+                        command_name,
+                        invocation.display_command_line(),
+                    )
+                    .into());
+                }
+                buck2_client_ctx::eprintln!(
+                    "Running again what failed in: {}",
+                    invocation.display_command_line()
+                )?;
+                failures.insert(read)
+            }
+        };
+        if args.iter().any(|a| a == "--rerun-failed") {
+            continue;
+        }
+
+        let matches = T::command().try_get_matches_from(args).with_context(|| {
+            format!(
+                "Error parsing the command line of an earlier invocation: {}",
+                invocation.display_command_line()
+            )
+        })?;
+        let command = T::from_arg_matches(&matches)?;
+        let Failures {
+            targets,
+            test_cases,
+            target_level_failures: _,
+        } = std::mem::take(failures).without_target_level_failures();
+        return Ok(Rerun {
+            command,
+            matches,
+            targets: targets.into_iter().collect(),
+            test_cases,
+        });
+    }
+
+    Err(RerunFailedError::NoPreviousInvocation(command_name).into())
+}
+
+/// The arguments of `command_name` in an invocation, starting with the command name, or `None`
+/// if this invocation is a different command.
+fn command_args(invocation: &Invocation, command_name: &str) -> Option<Vec<String>> {
+    // Logs written before `@` args were recorded expanded only have the raw args.
+    let args = if invocation.expanded_command_line_args.is_empty() {
+        &invocation.command_line_args
+    } else {
+        &invocation.expanded_command_line_args
+    };
+    // Skip the executable and the global options.
+    let position = args.iter().skip(1).position(|a| a == command_name)? + 1;
+    Some(args[position..].to_vec())
+}
+
+#[derive(Default)]
+struct Failures {
+    targets: BTreeSet<String>,
+    test_cases: BTreeMap<String, BTreeSet<String>>,
+    /// Targets which failed to load, analyze or build, or whose test failures were not all
+    /// reported per test case: all their tests run again.
+    target_level_failures: BTreeSet<String>,
+}
+
+impl Failures {
+    async fn read(
+        mut events: impl futures::Stream<Item = anyhow::Result<StreamValue>> + Unpin,
+    ) -> anyhow::Result<Self> {
+        let mut failures = Self::default();
+        while let Some(event) = events.try_next().await? {
+            if let StreamValue::Event(event) = event {
+                failures.add(&event)?;
+            }
+        }
+        Ok(failures)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.targets.is_empty()
+    }
+
+    /// Drops the failed test cases of the targets whose tests all run again.
+    fn without_target_level_failures(mut self) -> Self {
+        let target_level_failures = &self.target_level_failures;
+        self.test_cases
+            .retain(|target, _| !target_level_failures.contains(target));
+        self
+    }
+
+    fn add(&mut self, event: &buck2_data::BuckEvent) -> anyhow::Result<()> {
+        use buck2_data::buck_event::Data;
+
+        match &event.data {
+            Some(Data::SpanEnd(end)) => match &end.data {
+                Some(buck2_data::span_end_event::Data::ActionExecution(action))
+                    if action.failed =>
+                {
+                    use buck2_data::action_key::Owner;
+
+                    if let Some(
+                        Owner::TargetLabel(target)
+                        | Owner::TestTargetLabel(target)
+                        | Owner::LocalResourceSetup(target),
+                    ) = action.key.as_ref().and_then(|k| k.owner.as_ref())
+                    {
+                        self.add_target_level_failure(target)?;
+                    }
+                }
+                Some(buck2_data::span_end_event::Data::Analysis(analysis)) if analysis.failed => {
+                    if let Some(buck2_data::analysis_end::Target::StandardTarget(target)) =
+                        &analysis.target
+                    {
+                        self.add_target_level_failure(target)?;
+                    }
+                }
+                Some(buck2_data::span_end_event::Data::Load(load)) if load.error.is_some() => {
+                    // The module of a build file is `cell//package:BUCK`: run again all the
+                    // targets of the package, as the ones which failed are not known.
+                    if let Some((package, _build_file)) = load.module_id.rsplit_once(':') {
+                        let pattern = format!("{}:", package);
+                        self.targets.insert(pattern.clone());
+                        self.target_level_failures.insert(pattern);
+                    }
+                }
+                _ => {}
+            },
+            Some(Data::Instant(instant)) => match &instant.data {
+                Some(buck2_data::instant_event::Data::TestResult(result)) => {
+                    use buck2_data::TestStatus;
+
+                    let failed = matches!(
+                        TestStatus::from_i32(result.status),
+                        Some(
+                            TestStatus::Fail
+                                | TestStatus::Fatal
+                                | TestStatus::Timeout
+                                | TestStatus::ListingFailed
+                        )
+                    );
+                    if let (true, Some(target)) = (failed, &result.target_label) {
+                        let label = self.add_target(target)?;
+                        // Runners which don't report test cases name results after the target.
+                        if result.name == label {
+                            self.target_level_failures.insert(label);
+                        } else {
+                            self.test_cases
+                                .entry(label)
+                                .or_default()
+                                .insert(result.name.clone());
+                        }
+                    }
+                }
+                _ => {}
+            },
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Returns the unconfigured label of the target: the rerun uses the same configuration flags.
+    fn add_target(&mut self, target: &buck2_data::ConfiguredTargetLabel) -> anyhow::Result<String> {
+        let label = target.label.as_ref().context("Missing target label")?;
+        let label = format!("{}:{}", label.package, label.name);
+        self.targets.insert(label.clone());
+        Ok(label)
+    }
+
+    fn add_target_level_failure(
+        &mut self,
+        target: &buck2_data::ConfiguredTargetLabel,
+    ) -> anyhow::Result<()> {
+        let label = self.add_target(target)?;
+        self.target_level_failures.insert(label);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use buck2_wrapper_common::invocation_id::TraceId;
+
+    use super::*;
+
+    fn target(name: &str) -> buck2_data::ConfiguredTargetLabel {
+        buck2_data::ConfiguredTargetLabel {
+            label: Some(buck2_data::TargetLabel {
+                package: "root//foo".to_owned(),
+                name: name.to_owned(),
+            }),
+            configuration: Some(buck2_data::Configuration {
+                full_name: "cfg#123".to_owned(),
+            }),
+            execution_configuration: None,
+        }
+    }
+
+    fn test_result(
+        name: &str,
+        target_name: &str,
+        status: buck2_data::TestStatus,
+    ) -> buck2_data::BuckEvent {
+        buck2_data::BuckEvent {
+            data: Some(
+                buck2_data::InstantEvent {
+                    data: Some(
+                        buck2_data::TestResult {
+                            name: name.to_owned(),
+                            status: status as i32,
+                            target_label: Some(target(target_name)),
+                            ..Default::default()
+                        }
+                        .into(),
+                    ),
+                }
+                .into(),
+            ),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_failures() -> anyhow::Result<()> {
+        let mut failures = Failures::default();
+        failures.add(&test_result("a - Case", "a", buck2_data::TestStatus::Fail))?;
+        failures.add(&test_result("b - Case", "b", buck2_data::TestStatus::Pass))?;
+        assert_eq!(
+            vec!["root//foo:a"],
+            failures.targets.iter().collect::<Vec<_>>()
+        );
+        assert!(failures.target_level_failures.is_empty());
+
+        failures.add(&test_result(
+            "root//foo:c",
+            "c",
+            buck2_data::TestStatus::Timeout,
+        ))?;
+        failures.add(&test_result("c - Case", "c", buck2_data::TestStatus::Fail))?;
+        assert_eq!(
+            vec!["root//foo:a", "root//foo:c"],
+            failures.targets.iter().collect::<Vec<_>>()
+        );
+
+        // Only the test cases of targets whose failures were all reported per test case are kept.
+        let failures = failures.without_target_level_failures();
+        assert_eq!(
+            vec![("root//foo:a", vec!["a - Case"])],
+            failures
+                .test_cases
+                .iter()
+                .map(|(target, cases)| (
+                    target.as_str(),
+                    cases.iter().map(|c| c.as_str()).collect::<Vec<_>>()
+                ))
+                .collect::<Vec<_>>()
+        );
+        Ok(())
+    }
+
+    fn span_end(data: buck2_data::span_end_event::Data) -> buck2_data::BuckEvent {
+        buck2_data::BuckEvent {
+            data: Some(
+                buck2_data::SpanEndEvent {
+                    data: Some(data),
+                    ..Default::default()
+                }
+                .into(),
+            ),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_load_and_analysis_failures() -> anyhow::Result<()> {
+        let mut failures = Failures::default();
+        failures.add(&span_end(
+            buck2_data::AnalysisEnd {
+                target: Some(buck2_data::analysis_end::Target::StandardTarget(target(
+                    "a",
+                ))),
+                failed: true,
+                ..Default::default()
+            }
+            .into(),
+        ))?;
+        failures.add(&span_end(
+            buck2_data::AnalysisEnd {
+                target: Some(buck2_data::analysis_end::Target::StandardTarget(target(
+                    "b",
+                ))),
+                failed: false,
+                ..Default::default()
+            }
+            .into(),
+        ))?;
+        failures.add(&span_end(
+            buck2_data::LoadBuildFileEnd {
+                module_id: "root//bar:BUCK".to_owned(),
+                cell: "root".to_owned(),
+                error: Some("syntax error".to_owned()),
+                ..Default::default()
+            }
+            .into(),
+        ))?;
+        assert_eq!(
+            vec!["root//bar:", "root//foo:a"],
+            failures.targets.iter().collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec!["root//bar:", "root//foo:a"],
+            failures.target_level_failures.iter().collect::<Vec<_>>()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_command_args() {
+        let invocation = Invocation {
+            command_line_args: Vec::new(),
+            expanded_command_line_args: [
+                "buck2",
+                "--isolation-dir",
+                "x",
+                "build",
+                "-c",
+                "a.b=c",
+                "//:t",
+            ]
+            .map(|a| a.to_owned())
+            .to_vec(),
+            working_dir: String::new(),
+            trace_id: TraceId::null(),
+        };
+        assert_eq!(
+            Some(vec!["build", "-c", "a.b=c", "//:t"]),
+            command_args(&invocation, "build")
+                .as_ref()
+                .map(|args| args.iter().map(|a| a.as_str()).collect::<Vec<_>>())
+        );
+        assert_eq!(None, command_args(&invocation, "test"));
+    }
+}
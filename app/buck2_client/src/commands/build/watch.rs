@@ -0,0 +1,332 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! `--watch`: rerun a command whenever files change.
+//!
+//! We keep a subscription open with the daemon to be told about file changes. Each iteration of
+//! the command is run on a new connection, so that it gets its own trace id, console and event
+//! log, just like a fresh invocation would.
+
+use std::future::Future;
+use std::time::Duration;
+use std::time::Instant;
+
+use anyhow::Context;
+use async_trait::async_trait;
+use buck2_cli_proto::SubscriptionRequestWrapper;
+use buck2_client_ctx::client_ctx::ClientCommandContext;
+use buck2_client_ctx::command_outcome::CommandOutcome;
+use buck2_client_ctx::daemon::client::connect::BuckdConnectOptions;
+use buck2_client_ctx::daemon::client::BuckdClientConnector;
+use buck2_client_ctx::events_ctx::PartialResultCtx;
+use buck2_client_ctx::events_ctx::PartialResultHandler;
+use buck2_client_ctx::exit_result::ExitResult;
+use buck2_client_ctx::final_console::FinalConsole;
+use buck2_client_ctx::streaming::connect_for_command;
+use buck2_client_ctx::streaming::StreamingCommand;
+use buck2_subscription_proto::subscription_response::Response;
+use buck2_subscription_proto::SubscriptionRequest;
+use buck2_wrapper_common::invocation_id::TraceId;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::UnboundedReceiverStream;
+
+#[derive(Debug, thiserror::Error)]
+enum WatchError {
+    #[error("The daemon stopped sending file change notifications")]
+    SubscriptionClosed,
+}
+
+#[derive(Debug, Clone, clap::Parser)]
+pub(crate) struct WatchOptions {
+    /// Keep running, and run the command again whenever files change. If files change while
+    /// the command is still running, it is cancelled and started again.
+    #[clap(long)]
+    pub(crate) watch: bool,
+
+    /// With `--watch`, how long to wait for file changes to settle before running the command
+    /// again, in milliseconds.
+    #[clap(long, default_value = "200", value_name = "MILLISECONDS")]
+    watch_debounce: u64,
+}
+
+/// A command which can be run repeatedly with `--watch`.
+#[async_trait]
+pub(crate) trait WatchedCommand: Send {
+    type Command: StreamingCommand;
+
+    /// The command, used to set up the console and the logging of each iteration.
+    fn command(&self) -> &Self::Command;
+
+    /// Run a single iteration. The result is reported but doesn't end the watch.
+    async fn run_once(
+        &mut self,
+        buckd: &mut BuckdClientConnector,
+        matches: &clap::ArgMatches,
+        ctx: &mut ClientCommandContext<'_>,
+    ) -> ExitResult;
+}
+
+/// Run `cmd` until interrupted, starting a new iteration whenever files change.
+pub(crate) async fn watch(
+    mut cmd: impl WatchedCommand,
+    opts: &WatchOptions,
+    buckd: &mut BuckdClientConnector<'_>,
+    matches: &clap::ArgMatches,
+    ctx: &mut ClientCommandContext<'_>,
+) -> ExitResult {
+    let console = cmd.command().console_opts().final_console();
+
+    let mut subscription_client = ctx
+        .connect_buckd(BuckdConnectOptions::existing_only_no_console())
+        .await?;
+    let mut client_context = ctx.client_context(matches, cmd.command())?;
+    // The subscription is a command of its own, it shouldn't share a trace id with the first
+    // iteration.
+    client_context.trace_id = TraceId::new().to_string();
+
+    let (requests_tx, requests_rx) = mpsc::unbounded_channel();
+    let (changes_tx, mut changes_rx) = mpsc::unbounded_channel();
+    let mut handler = FilesChangedHandler { changes_tx };
+
+    let mut subscription = subscription_client.with_flushing();
+    let subscription = subscription.subscription(
+        client_context,
+        UnboundedReceiverStream::new(requests_rx),
+        &mut handler,
+    );
+
+    let iterations = async {
+        let wait_for_changes = || {
+            requests_tx
+                .send(wait_for_file_changes_request())
+                .ok()
+                .context(WatchError::SubscriptionClosed)
+        };
+
+        for iteration in 1.. {
+            // Changes made from here on may or may not be seen by this iteration, so we want to
+            // hear about them.
+            wait_for_changes()?;
+
+            let start = Instant::now();
+            let res = if iteration == 1 {
+                run_cancellable(cmd.run_once(buckd, matches, ctx), &mut changes_rx).await?
+            } else {
+                ctx.trace_id = TraceId::new();
+                let mut buckd = connect_for_command(cmd.command(), ctx).await?;
+                run_cancellable(cmd.run_once(&mut buckd, matches, ctx), &mut changes_rx).await?
+            };
+
+            match res {
+                Some(res) => {
+                    let success = res.report_without_exit()?;
+                    print_iteration_summary(&console, iteration, success, start.elapsed())?;
+                    changes_rx
+                        .recv()
+                        .await
+                        .context(WatchError::SubscriptionClosed)?;
+                }
+                None => {
+                    console.print_warning(&format!(
+                        "Watch #{}: files changed, cancelled after {:.1}s",
+                        iteration,
+                        start.elapsed().as_secs_f64()
+                    ))?;
+                }
+            }
+
+            debounce(
+                &mut changes_rx,
+                Duration::from_millis(opts.watch_debounce),
+                &wait_for_changes,
+            )
+            .await?;
+        }
+
+        anyhow::Ok(())
+    };
+
+    tokio::select! {
+        res = subscription => {
+            match res? {
+                CommandOutcome::Success(_) => ExitResult::err(WatchError::SubscriptionClosed.into()),
+                CommandOutcome::Failure(exit_result) => exit_result,
+            }
+        }
+        res = iterations => res.into(),
+    }
+}
+
+/// Run an iteration, unless files change first. Dropping the request cancels the command on
+/// the daemon.
+async fn run_cancellable<R>(
+    run: impl Future<Output = R>,
+    changes_rx: &mut mpsc::UnboundedReceiver<()>,
+) -> anyhow::Result<Option<R>> {
+    tokio::select! {
+        res = run => Ok(Some(res)),
+        changed = changes_rx.recv() => {
+            changed.context(WatchError::SubscriptionClosed)?;
+            Ok(None)
+        }
+    }
+}
+
+/// Let the changes settle: return once no change was reported for `debounce`. Each
+/// `wait_for_changes` call asks for a single notification.
+async fn debounce(
+    changes_rx: &mut mpsc::UnboundedReceiver<()>,
+    debounce: Duration,
+    wait_for_changes: impl Fn() -> anyhow::Result<()>,
+) -> anyhow::Result<()> {
+    loop {
+        wait_for_changes()?;
+        match tokio::time::timeout(debounce, changes_rx.recv()).await {
+            Ok(changed) => changed.context(WatchError::SubscriptionClosed)?,
+            Err(_) => return Ok(()),
+        }
+    }
+}
+
+fn print_iteration_summary(
+    console: &FinalConsole,
+    iteration: u64,
+    success: bool,
+    elapsed: Duration,
+) -> anyhow::Result<()> {
+    let message = format!(
+        "Watch #{}: {} in {:.1}s, waiting for changes...",
+        iteration,
+        if success { "succeeded" } else { "failed" },
+        elapsed.as_secs_f64()
+    );
+    if success {
+        console.print_success(&message)
+    } else {
+        console.print_error(&message)
+    }
+}
+
+fn wait_for_file_changes_request() -> SubscriptionRequestWrapper {
+    SubscriptionRequestWrapper {
+        request: Some(SubscriptionRequest {
+            request: Some(buck2_subscription_proto::WaitForFileChanges {}.into()),
+        }),
+    }
+}
+
+/// Forwards the `FilesChanged` notifications of the subscription.
+struct FilesChangedHandler {
+    changes_tx: mpsc::UnboundedSender<()>,
+}
+
+#[async_trait]
+impl PartialResultHandler for FilesChangedHandler {
+    type PartialResult = buck2_cli_proto::SubscriptionResponseWrapper;
+
+    async fn handle_partial_result(
+        &mut self,
+        _ctx: PartialResultCtx<'_, '_>,
+        partial_res: Self::PartialResult,
+    ) -> anyhow::Result<()> {
+        let response = partial_res
+            .response
+            .context("Empty `SubscriptionResponseWrapper`")?;
+        if let Some(Response::FilesChanged(_)) = response.response {
+            // The receiver only goes away once we stop watching.
+            let _ignored = self.changes_tx.send(());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use tokio::time::Instant;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_run_cancellable() -> anyhow::Result<()> {
+        let (changes_tx, mut changes_rx) = mpsc::unbounded_channel();
+
+        // Nothing changed: the iteration runs to completion.
+        assert_eq!(
+            Some(42),
+            run_cancellable(async { 42 }, &mut changes_rx).await?
+        );
+
+        // A change cancels the iteration.
+        changes_tx.send(())?;
+        assert_eq!(
+            None,
+            run_cancellable(std::future::pending::<()>(), &mut changes_rx).await?
+        );
+
+        // The subscription going away is an error.
+        drop(changes_tx);
+        assert!(
+            run_cancellable(std::future::pending::<()>(), &mut changes_rx)
+                .await
+                .is_err()
+        );
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_debounce() -> anyhow::Result<()> {
+        let (changes_tx, mut changes_rx) = mpsc::unbounded_channel();
+        let requests = Cell::new(0);
+        let wait_for_changes = || {
+            requests.set(requests.get() + 1);
+            anyhow::Ok(())
+        };
+
+        // No changes: we return once the debounce delay has elapsed.
+        let start = Instant::now();
+        debounce(
+            &mut changes_rx,
+            Duration::from_millis(200),
+            wait_for_changes,
+        )
+        .await?;
+        assert_eq!(Duration::from_millis(200), start.elapsed());
+        assert_eq!(1, requests.get());
+
+        // Each change restarts the delay, and asks for the next notification.
+        requests.set(0);
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            changes_tx.send(()).unwrap();
+            tokio::time::sleep(Duration::from_millis(150)).await;
+            changes_tx.send(()).unwrap();
+            // Keep the channel open until the debounce is over.
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        });
+        let start = Instant::now();
+        debounce(
+            &mut changes_rx,
+            Duration::from_millis(200),
+            wait_for_changes,
+        )
+        .await?;
+        assert_eq!(Duration::from_millis(450), start.elapsed());
+        assert_eq!(3, requests.get());
+
+        // The subscription going away is an error.
+        assert!(
+            debounce(&mut changes_rx, Duration::from_secs(10), wait_for_changes)
+                .await
+                .is_err()
+        );
+        Ok(())
+    }
+}
@@ -7,18 +7,42 @@
  * of this source tree.
  */
 
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::collections::VecDeque;
 use std::fmt;
 use std::time::Duration;
 
 use buck2_client_ctx::client_ctx::ClientCommandContext;
 use buck2_client_ctx::exit_result::ExitResult;
+use buck2_client_ctx::path_arg::PathArg;
 use buck2_client_ctx::stream_value::StreamValue;
+use buck2_client_ctx::subscribers::event_log::read::EventLogPathBuf;
+use buck2_common::convert::ProstDurationExt;
+use buck2_critical_path::compute_critical_path_potentials;
+use buck2_critical_path::Graph;
 use buck2_event_observer::display;
 use buck2_event_observer::display::TargetDisplayOptions;
 use tokio_stream::StreamExt;
 
 use crate::commands::log::options::EventLogOptions;
 
+#[derive(Debug, thiserror::Error)]
+enum CriticalPathError {
+    #[error(
+        "No potential improvements in the event log, they are only computed with \
+        `-c buck2.critical_path_backend2=longest-path-graph`"
+    )]
+    NoPotentials,
+    #[error(
+        "No build graph in the event log, rerun the build with \
+        `-c buck2.critical_path_backend2=longest-path-graph -c buck2.log_build_graph=true`"
+    )]
+    NoBuildGraph,
+    #[error("No critical path in the event log of `{0}`")]
+    NoCriticalPath(String),
+}
+
 /// Show the critical path for a selected build.
 ///
 /// This produces tab-delimited output listing every node on the critical path.
@@ -28,41 +52,93 @@ use crate::commands::log::options::EventLogOptions;
 /// before this node stops being on the critical path.
 ///
 /// All durations are in microseconds.
+///
+/// The `--what-if-*` options recompute the critical path from the build graph with different
+/// durations, which requires the build to have run with
+/// `-c buck2.critical_path_backend2=longest-path-graph -c buck2.log_build_graph=true`.
 #[derive(Debug, clap::Parser)]
 pub struct CriticalPathCommand {
     #[clap(flatten)]
     event_log: EventLogOptions,
+
+    /// Instead of the critical path, list the actions and analyses on it by potential
+    /// improvement, i.e. by how much the critical path would shrink if they took no time.
+    #[clap(long)]
+    potentials: bool,
+
+    /// Number of entries to list with `--potentials`.
+    #[clap(long, requires = "potentials", value_name = "N", default_value = "10")]
+    top: usize,
+
+    /// Simulate that the actions of this category were cache hits, so that no time was spent
+    /// running their commands.
+    #[clap(long, value_name = "CATEGORY")]
+    what_if_cache_hit: Vec<String>,
+
+    /// Simulate that commands executed remotely spent no time queued.
+    #[clap(long)]
+    what_if_no_queue: bool,
+
+    /// Compare the critical path with the one in this earlier event log: list how the duration
+    /// of every entry changed, and what the total change is attributed to.
+    #[clap(
+        long,
+        value_name = "PATH",
+        conflicts_with_all = &["potentials", "what-if-cache-hit", "what-if-no-queue"]
+    )]
+    compare: Option<PathArg>,
 }
 
 impl CriticalPathCommand {
     pub fn exec(self, _matches: &clap::ArgMatches, ctx: ClientCommandContext<'_>) -> ExitResult {
-        let Self { event_log } = self;
+        let Self {
+            event_log,
+            potentials,
+            top,
+            what_if_cache_hit,
+            what_if_no_queue,
+            compare,
+        } = self;
+        let what_if = WhatIf {
+            cache_hit_categories: what_if_cache_hit,
+            no_queue: what_if_no_queue,
+        };
 
         ctx.with_runtime(async move |ctx| {
             let log_path = event_log.get(&ctx).await?;
+            // The command times are only needed to change or attribute the durations.
+            let with_commands = compare.is_some() || !what_if.is_empty();
+            let build = read_build(&log_path, "Showing critical path from", with_commands).await?;
 
-            let (invocation, mut events) = log_path.unpack_stream().await?;
-            buck2_client_ctx::eprintln!(
-                "Showing critical path from: {}",
-                invocation.display_command_line()
-            )?;
-
-            while let Some(event) = events.try_next().await? {
-                match event {
-                    StreamValue::Event(event) => match event.data {
-                        Some(buck2_data::buck_event::Data::Instant(instant)) => {
-                            match instant.data {
-                                Some(buck2_data::instant_event::Data::BuildGraphInfo(
-                                    build_graph,
-                                )) => {
-                                    log_critical_path(&build_graph)?;
-                                }
-                                _ => {}
-                            }
-                        }
-                        _ => {}
-                    },
-                    _ => {}
+            if let Some(compare) = compare {
+                let base = EventLogPathBuf::infer(compare.resolve(&ctx.working_dir))?;
+                let base =
+                    read_build(&base, "Comparing with critical path from", with_commands).await?;
+                return compare_critical_paths(&base, &build);
+            }
+
+            for info in build.infos {
+                let critical_path = if what_if.is_empty() {
+                    info.critical_path2
+                } else {
+                    let graph = info
+                        .build_graph
+                        .as_ref()
+                        .ok_or(CriticalPathError::NoBuildGraph)?;
+                    let (before, _) = simulate(graph, &WhatIf::default(), &build.commands)?;
+                    let (after, critical_path) = simulate(graph, &what_if, &build.commands)?;
+                    buck2_client_ctx::eprintln!(
+                        "Estimated critical path duration: {}us (was {}us)",
+                        after,
+                        before
+                    )?;
+                    critical_path
+                };
+
+                if potentials {
+                    log_critical_path(&top_potentials(critical_path, top)?)?;
+                } else {
+                    log_critical_path(&critical_path)?;
                 }
             }
 
@@ -73,118 +149,734 @@ impl CriticalPathCommand {
     }
 }
 
-fn log_critical_path(critical_path: &buck2_data::BuildGraphExecutionInfo) -> anyhow::Result<()> {
-    let target_display_options = TargetDisplayOptions::for_log();
+/// What we need from the event log of a build.
+#[derive(Default)]
+struct Build {
+    command_line: String,
+    /// Usually, there is only one.
+    infos: Vec<buck2_data::BuildGraphExecutionInfo>,
+    /// Keyed by the span id of their action. Only collected if requested.
+    commands: HashMap<u64, CommandTimes>,
+}
 
-    for entry in &critical_path.critical_path2 {
-        use buck2_data::critical_path_entry2::Entry;
+async fn read_build(
+    log_path: &EventLogPathBuf,
+    description: &str,
+    with_commands: bool,
+) -> anyhow::Result<Build> {
+    let (invocation, mut events) = log_path.unpack_stream().await?;
+    let mut build = Build {
+        command_line: invocation.display_command_line(),
+        ..Default::default()
+    };
+    buck2_client_ctx::eprintln!("{}: {}", description, build.command_line)?;
+
+    while let Some(event) = events.try_next().await? {
+        match event {
+            StreamValue::Event(event) => match event.data {
+                Some(buck2_data::buck_event::Data::Instant(instant)) => match instant.data {
+                    Some(buck2_data::instant_event::Data::BuildGraphInfo(info)) => {
+                        build.infos.push(info);
+                    }
+                    _ => {}
+                },
+                Some(buck2_data::buck_event::Data::SpanEnd(end)) => match end.data {
+                    Some(buck2_data::span_end_event::Data::ActionExecution(action))
+                        if with_commands =>
+                    {
+                        if let Some(times) = CommandTimes::from_action(&action) {
+                            build.commands.insert(event.span_id, times);
+                        }
+                    }
+                    _ => {}
+                },
+                _ => {}
+            },
+            _ => {}
+        }
+    }
 
-        let kind;
-        let name;
-        let mut category = "";
-        let mut identifier = "";
+    Ok(build)
+}
 
-        match &entry.entry {
-            Some(Entry::Analysis(analysis)) => {
-                use buck2_data::critical_path_entry2::analysis::Target;
+/// Changes to the durations of the build graph.
+#[derive(Default)]
+struct WhatIf {
+    cache_hit_categories: Vec<String>,
+    no_queue: bool,
+}
 
-                kind = "analysis";
+impl WhatIf {
+    fn is_empty(&self) -> bool {
+        self.cache_hit_categories.is_empty() && !self.no_queue
+    }
 
-                name = match &analysis.target {
-                    Some(Target::StandardTarget(t)) => {
-                        display::display_configured_target_label(t, target_display_options)?
-                    }
-                    None => continue,
-                };
+    fn duration(
+        &self,
+        entry: &buck2_data::CriticalPathEntry2,
+        duration: Duration,
+        commands: &HashMap<u64, CommandTimes>,
+    ) -> Duration {
+        let category = match &entry.entry {
+            Some(buck2_data::critical_path_entry2::Entry::ActionExecution(action)) => {
+                action.name.as_ref().map_or("", |n| n.category.as_str())
             }
-            Some(Entry::ActionExecution(action_execution)) => {
-                use buck2_data::critical_path_entry2::action_execution::Owner;
+            _ => return duration,
+        };
+        let times = match entry.span_ids.iter().find_map(|s| commands.get(s)) {
+            Some(times) => times,
+            None => return duration,
+        };
+        if self.cache_hit_categories.iter().any(|c| c == category) {
+            // Cache hits don't queue either.
+            duration
+                .saturating_sub(times.execution)
+                .saturating_sub(times.queue)
+        } else if self.no_queue {
+            duration.saturating_sub(times.queue)
+        } else {
+            duration
+        }
+    }
+}
 
-                kind = "action";
+/// How long the command an action ended with ran for, and queued for.
+#[derive(Default)]
+struct CommandTimes {
+    execution: Duration,
+    queue: Duration,
+}
 
-                name = match &action_execution.owner {
-                    Some(Owner::TargetLabel(t)) => {
-                        display::display_configured_target_label(t, target_display_options)?
-                    }
-                    Some(Owner::BxlKey(t)) => display::display_bxl_key(t)?,
-                    Some(Owner::AnonTarget(t)) => display::display_anon_target(t)?,
-                    None => continue,
-                };
+impl CommandTimes {
+    fn from_action(action: &buck2_data::ActionExecutionEnd) -> Option<Self> {
+        let details = action.commands.last()?.details.as_ref()?;
+        let execution = details
+            .metadata
+            .as_ref()
+            .and_then(|m| m.wall_time.as_ref())
+            .and_then(|d| d.try_into_duration().ok())
+            .unwrap_or_default();
+        let queue = match details
+            .command_kind
+            .as_ref()
+            .and_then(|k| k.command.as_ref())
+        {
+            Some(buck2_data::command_execution_kind::Command::RemoteCommand(remote)) => remote
+                .queue_time
+                .as_ref()
+                .and_then(|d| d.try_into_duration().ok())
+                .unwrap_or_default(),
+            _ => Duration::ZERO,
+        };
+        Some(Self { execution, queue })
+    }
+}
 
-                match &action_execution.name {
-                    Some(name) => {
-                        category = &name.category;
-                        identifier = &name.identifier;
-                    }
-                    None => {}
+/// Recompute the critical path of `graph` with the durations changed by `what_if`. Returns its
+/// duration in microseconds, and the entries on it with their new durations and potentials.
+fn simulate(
+    graph: &buck2_data::BuildGraph,
+    what_if: &WhatIf,
+    commands: &HashMap<u64, CommandTimes>,
+) -> anyhow::Result<(u64, Vec<buck2_data::CriticalPathEntry2>)> {
+    let deps = Graph::from_deps(graph.nodes.iter().map(|n| n.deps.iter().copied()))?;
+    let mut weights = deps.allocate_vertex_data(0);
+    let mut entries = Vec::with_capacity(graph.nodes.len());
+
+    for (idx, node) in deps.iter_vertices().zip(&graph.nodes) {
+        let mut entry = node.entry.clone().unwrap_or_default();
+        let duration = entry
+            .total_duration
+            .as_ref()
+            .map(|d| d.try_into_duration())
+            .transpose()?
+            .unwrap_or_default();
+        let duration = what_if.duration(&entry, duration, commands);
+        weights[idx] = u64::try_from(duration.as_micros())?;
+        entry.duration = Some(duration.try_into()?);
+        entry.total_duration = Some(duration.try_into()?);
+        entries.push(entry);
+    }
+
+    let (critical_path, cost, replacement_costs) =
+        compute_critical_path_potentials(&deps, &weights)?;
+
+    let critical_path = critical_path
+        .iter()
+        .map(|(cp_idx, vertex)| {
+            let mut entry = std::mem::take(&mut entries[vertex.into_inner() as usize]);
+            let potential = cost.runtime - replacement_costs[cp_idx].runtime;
+            entry.potential_improvement_duration =
+                Some(Duration::from_micros(potential).try_into()?);
+            anyhow::Ok(entry)
+        })
+        .collect::<Result<_, _>>()?;
+
+    Ok((cost.runtime, critical_path))
+}
+
+/// The actions and analyses with the highest potential improvement.
+fn top_potentials(
+    critical_path: Vec<buck2_data::CriticalPathEntry2>,
+    top: usize,
+) -> anyhow::Result<Vec<buck2_data::CriticalPathEntry2>> {
+    use buck2_data::critical_path_entry2::Entry;
+
+    let mut entries = critical_path
+        .into_iter()
+        .filter(|e| {
+            matches!(
+                e.entry,
+                Some(Entry::ActionExecution(..)) | Some(Entry::Analysis(..))
+            )
+        })
+        .map(|e| {
+            let potential = e
+                .potential_improvement_duration
+                .as_ref()
+                .ok_or(CriticalPathError::NoPotentials)?
+                .try_into_duration()?;
+            anyhow::Ok((potential, e))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    entries.sort_by_key(|(potential, _)| Reverse(*potential));
+    Ok(entries.into_iter().take(top).map(|(_, e)| e).collect())
+}
+
+/// How an entry is shown, which also identifies it across builds.
+#[derive(PartialEq, Eq, Hash)]
+struct EntryIdentity<'a> {
+    kind: &'static str,
+    name: String,
+    category: &'a str,
+    identifier: &'a str,
+}
+
+fn entry_identity(
+    entry: &buck2_data::CriticalPathEntry2,
+) -> anyhow::Result<Option<EntryIdentity<'_>>> {
+    use buck2_data::critical_path_entry2::Entry;
+
+    let target_display_options = TargetDisplayOptions::for_log();
+
+    let kind;
+    let name;
+    let mut category = "";
+    let mut identifier = "";
+
+    match &entry.entry {
+        Some(Entry::Analysis(analysis)) => {
+            use buck2_data::critical_path_entry2::analysis::Target;
+
+            kind = "analysis";
+
+            name = match &analysis.target {
+                Some(Target::StandardTarget(t)) => {
+                    display::display_configured_target_label(t, target_display_options)?
                 }
-            }
-            Some(Entry::Materialization(materialization)) => {
-                use buck2_data::critical_path_entry2::materialization::Owner;
+                None => return Ok(None),
+            };
+        }
+        Some(Entry::ActionExecution(action_execution)) => {
+            use buck2_data::critical_path_entry2::action_execution::Owner;
 
-                kind = "materialization";
+            kind = "action";
 
-                name = match &materialization.owner {
-                    Some(Owner::TargetLabel(t)) => {
-                        display::display_configured_target_label(t, target_display_options)?
-                    }
-                    Some(Owner::BxlKey(t)) => display::display_bxl_key(t)?,
-                    Some(Owner::AnonTarget(t)) => display::display_anon_target(t)?,
-                    None => continue,
-                };
+            name = match &action_execution.owner {
+                Some(Owner::TargetLabel(t)) => {
+                    display::display_configured_target_label(t, target_display_options)?
+                }
+                Some(Owner::BxlKey(t)) => display::display_bxl_key(t)?,
+                Some(Owner::AnonTarget(t)) => display::display_anon_target(t)?,
+                None => return Ok(None),
+            };
 
-                identifier = &materialization.path;
-            }
-            Some(Entry::ComputeCriticalPath(..)) => {
-                kind = "compute-critical-path";
-                name = "".to_owned();
-            }
-            Some(Entry::Load(load)) => {
-                kind = "load";
-                name = load.package.clone();
-            }
-            Some(Entry::Listing(listing)) => {
-                kind = "listing";
-                name = listing.package.clone();
+            match &action_execution.name {
+                Some(name) => {
+                    category = &name.category;
+                    identifier = &name.identifier;
+                }
+                None => {}
             }
-            None => continue,
         }
+        Some(Entry::Materialization(materialization)) => {
+            use buck2_data::critical_path_entry2::materialization::Owner;
+
+            kind = "materialization";
+
+            name = match &materialization.owner {
+                Some(Owner::TargetLabel(t)) => {
+                    display::display_configured_target_label(t, target_display_options)?
+                }
+                Some(Owner::BxlKey(t)) => display::display_bxl_key(t)?,
+                Some(Owner::AnonTarget(t)) => display::display_anon_target(t)?,
+                None => return Ok(None),
+            };
 
-        struct OptionalDuration {
-            inner: Option<Duration>,
+            identifier = &materialization.path;
         }
+        Some(Entry::ComputeCriticalPath(..)) => {
+            kind = "compute-critical-path";
+            name = "".to_owned();
+        }
+        Some(Entry::Load(load)) => {
+            kind = "load";
+            name = load.package.clone();
+        }
+        Some(Entry::Listing(listing)) => {
+            kind = "listing";
+            name = listing.package.clone();
+        }
+        None => return Ok(None),
+    }
 
-        impl OptionalDuration {
-            fn new<T, E>(d: Option<T>) -> Result<Self, E>
-            where
-                T: TryInto<Duration, Error = E>,
-            {
-                Ok(Self {
-                    inner: d.map(|d| d.try_into()).transpose()?,
-                })
-            }
+    Ok(Some(EntryIdentity {
+        kind,
+        name,
+        category,
+        identifier,
+    }))
+}
+
+struct OptionalDuration {
+    inner: Option<Duration>,
+}
+
+impl OptionalDuration {
+    fn new<T, E>(d: Option<T>) -> Result<Self, E>
+    where
+        T: TryInto<Duration, Error = E>,
+    {
+        Ok(Self {
+            inner: d.map(|d| d.try_into()).transpose()?,
+        })
+    }
+}
+
+impl fmt::Display for OptionalDuration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(inner) = self.inner {
+            write!(f, "{}", inner.as_micros())?;
         }
+        Ok(())
+    }
+}
+
+fn log_critical_path(critical_path: &[buck2_data::CriticalPathEntry2]) -> anyhow::Result<()> {
+    for entry in critical_path {
+        let identity = match entry_identity(entry)? {
+            Some(identity) => identity,
+            None => continue,
+        };
+
+        buck2_client_ctx::println!(
+            "{}\t{}\t{}\t{}\t{}\t{}\t{}",
+            identity.kind,
+            identity.name,
+            identity.category,
+            identity.identifier,
+            OptionalDuration::new(entry.total_duration.clone())?,
+            OptionalDuration::new(entry.user_duration.clone())?,
+            OptionalDuration::new(entry.potential_improvement_duration.clone())?,
+        )?;
+    }
+
+    Ok(())
+}
+
+fn total_duration(entry: &buck2_data::CriticalPathEntry2) -> anyhow::Result<Duration> {
+    Ok(entry
+        .total_duration
+        .as_ref()
+        .map(|d| d.try_into_duration())
+        .transpose()?
+        .unwrap_or_default())
+}
 
-        impl fmt::Display for OptionalDuration {
-            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-                if let Some(inner) = self.inner {
-                    write!(f, "{}", inner.as_micros())?;
+/// What the duration of a critical path is spent on.
+#[derive(Default, Debug, PartialEq)]
+struct Attribution {
+    /// Analysis, loading and listing packages.
+    analysis: Duration,
+    /// Actions, except for remote execution queueing.
+    execution: Duration,
+    queue: Duration,
+    materialization: Duration,
+    other: Duration,
+}
+
+impl Attribution {
+    fn new(
+        critical_path: &[buck2_data::CriticalPathEntry2],
+        commands: &HashMap<u64, CommandTimes>,
+    ) -> anyhow::Result<Self> {
+        use buck2_data::critical_path_entry2::Entry;
+
+        let mut attribution = Self::default();
+        for entry in critical_path {
+            let duration = total_duration(entry)?;
+            match &entry.entry {
+                Some(Entry::Analysis(..) | Entry::Load(..) | Entry::Listing(..)) => {
+                    attribution.analysis += duration
+                }
+                Some(Entry::ActionExecution(..)) => {
+                    let queue = entry
+                        .span_ids
+                        .iter()
+                        .find_map(|s| commands.get(s))
+                        .map_or(Duration::ZERO, |t| t.queue.min(duration));
+                    attribution.queue += queue;
+                    attribution.execution += duration - queue;
                 }
-                Ok(())
+                Some(Entry::Materialization(..)) => attribution.materialization += duration,
+                Some(Entry::ComputeCriticalPath(..)) | None => attribution.other += duration,
+            }
+        }
+        Ok(attribution)
+    }
+
+    fn rows(&self) -> [(&'static str, Duration); 5] {
+        [
+            ("analysis", self.analysis),
+            ("execution", self.execution),
+            ("queue", self.queue),
+            ("materialization", self.materialization),
+            ("other", self.other),
+        ]
+    }
+}
+
+/// An entry of either critical path, with its duration in each build.
+struct ComparedEntry<'a> {
+    identity: EntryIdentity<'a>,
+    before: Option<Duration>,
+    after: Option<Duration>,
+}
+
+struct Comparison<'a> {
+    /// Entries of the new critical path in order, then those that disappeared from it.
+    entries: Vec<ComparedEntry<'a>>,
+    before: Attribution,
+    after: Attribution,
+}
+
+fn last_critical_path(build: &Build) -> anyhow::Result<&[buck2_data::CriticalPathEntry2]> {
+    build
+        .infos
+        .last()
+        .map(|info| info.critical_path2.as_slice())
+        .ok_or_else(|| CriticalPathError::NoCriticalPath(build.command_line.clone()).into())
+}
+
+/// Align the critical paths of two builds by the identity of their entries.
+fn compare<'a>(base: &'a Build, build: &'a Build) -> anyhow::Result<Comparison<'a>> {
+    let base_path = last_critical_path(base)?;
+    let path = last_critical_path(build)?;
+
+    // Identities aren't guaranteed to be unique on a critical path, so the occurrences of an
+    // identity are paired up in order.
+    let mut base_durations: HashMap<EntryIdentity, VecDeque<Duration>> = HashMap::new();
+    for entry in base_path {
+        if let Some(identity) = entry_identity(entry)? {
+            base_durations
+                .entry(identity)
+                .or_default()
+                .push_back(total_duration(entry)?);
+        }
+    }
+    let mut take_base_duration = |identity: &EntryIdentity<'a>| {
+        base_durations
+            .get_mut(identity)
+            .and_then(|durations| durations.pop_front())
+    };
+
+    let mut entries = Vec::new();
+    for entry in path {
+        if let Some(identity) = entry_identity(entry)? {
+            entries.push(ComparedEntry {
+                before: take_base_duration(&identity),
+                after: Some(total_duration(entry)?),
+                identity,
+            });
+        }
+    }
+    for entry in base_path {
+        if let Some(identity) = entry_identity(entry)? {
+            if let Some(before) = take_base_duration(&identity) {
+                entries.push(ComparedEntry {
+                    identity,
+                    before: Some(before),
+                    after: None,
+                });
             }
         }
+    }
+
+    Ok(Comparison {
+        entries,
+        before: Attribution::new(base_path, &base.commands)?,
+        after: Attribution::new(path, &build.commands)?,
+    })
+}
+
+/// The difference in microseconds, which may be negative.
+fn delta(before: Option<Duration>, after: Option<Duration>) -> i128 {
+    let micros = |d: Option<Duration>| d.map_or(0, |d| d.as_micros() as i128);
+    micros(after) - micros(before)
+}
+
+fn compare_critical_paths(base: &Build, build: &Build) -> anyhow::Result<()> {
+    let comparison = compare(base, build)?;
 
+    for entry in &comparison.entries {
         buck2_client_ctx::println!(
             "{}\t{}\t{}\t{}\t{}\t{}\t{}",
-            kind,
+            entry.identity.kind,
+            entry.identity.name,
+            entry.identity.category,
+            entry.identity.identifier,
+            OptionalDuration {
+                inner: entry.before
+            },
+            OptionalDuration { inner: entry.after },
+            delta(entry.before, entry.after),
+        )?;
+    }
+
+    let mut total = (Duration::ZERO, Duration::ZERO);
+    for ((name, before), (_, after)) in comparison
+        .before
+        .rows()
+        .into_iter()
+        .zip(comparison.after.rows())
+    {
+        buck2_client_ctx::println!(
+            "total\t{}\t\t\t{}\t{}\t{}",
             name,
-            category,
-            identifier,
-            OptionalDuration::new(entry.total_duration.clone())?,
-            OptionalDuration::new(entry.user_duration.clone())?,
-            OptionalDuration::new(entry.potential_improvement_duration.clone())?,
+            before.as_micros(),
+            after.as_micros(),
+            delta(Some(before), Some(after)),
         )?;
+        total.0 += before;
+        total.1 += after;
     }
+    buck2_client_ctx::println!(
+        "total\tall\t\t\t{}\t{}\t{}",
+        total.0.as_micros(),
+        total.1.as_micros(),
+        delta(Some(total.0), Some(total.1)),
+    )?;
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(
+        category: &str,
+        span_id: u64,
+        micros: u64,
+        deps: Vec<u32>,
+    ) -> buck2_data::build_graph::Node {
+        buck2_data::build_graph::Node {
+            entry: Some(buck2_data::CriticalPathEntry2 {
+                span_ids: vec![span_id],
+                total_duration: Some(Duration::from_micros(micros).try_into().unwrap()),
+                entry: Some(
+                    buck2_data::critical_path_entry2::ActionExecution {
+                        name: Some(buck2_data::ActionName {
+                            category: category.to_owned(),
+                            identifier: String::new(),
+                        }),
+                        owner: Some(
+                            buck2_data::critical_path_entry2::action_execution::Owner::TargetLabel(
+                                buck2_data::ConfiguredTargetLabel {
+                                    label: Some(buck2_data::TargetLabel {
+                                        package: "cell//pkg".to_owned(),
+                                        name: "foo".to_owned(),
+                                    }),
+                                    configuration: Some(buck2_data::Configuration {
+                                        full_name: "cfg".to_owned(),
+                                    }),
+                                    execution_configuration: None,
+                                },
+                            ),
+                        ),
+                    }
+                    .into(),
+                ),
+                ..Default::default()
+            }),
+            deps,
+        }
+    }
+
+    fn categories(critical_path: &[buck2_data::CriticalPathEntry2]) -> Vec<&str> {
+        critical_path
+            .iter()
+            .map(|e| match &e.entry {
+                Some(buck2_data::critical_path_entry2::Entry::ActionExecution(a)) => {
+                    a.name.as_ref().unwrap().category.as_str()
+                }
+                _ => "",
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_simulate() -> anyhow::Result<()> {
+        // `link` depends on `compile` and `codegen`, which is depended on before it's pushed.
+        let graph = buck2_data::BuildGraph {
+            nodes: vec![
+                node("compile", 1, 100, vec![]),
+                node("link", 2, 10, vec![0, 2]),
+                node("codegen", 3, 50, vec![]),
+            ],
+        };
+        let commands = HashMap::from_iter([
+            (
+                1,
+                CommandTimes {
+                    execution: Duration::from_micros(80),
+                    queue: Duration::from_micros(15),
+                },
+            ),
+            (3, CommandTimes::default()),
+        ]);
+
+        let (cost, critical_path) = simulate(&graph, &WhatIf::default(), &commands)?;
+        assert_eq!(110, cost);
+        assert_eq!(vec!["compile", "link"], categories(&critical_path));
+        // Without compile, the critical path would go through codegen.
+        assert_eq!(
+            Some(Duration::from_micros(50).try_into()?),
+            critical_path[0].potential_improvement_duration
+        );
+
+        let (cost, _) = simulate(
+            &graph,
+            &WhatIf {
+                cache_hit_categories: Vec::new(),
+                no_queue: true,
+            },
+            &commands,
+        )?;
+        assert_eq!(95, cost);
+
+        let (cost, critical_path) = simulate(
+            &graph,
+            &WhatIf {
+                cache_hit_categories: vec!["compile".to_owned()],
+                no_queue: false,
+            },
+            &commands,
+        )?;
+        assert_eq!(60, cost);
+        assert_eq!(vec!["codegen", "link"], categories(&critical_path));
+
+        assert_eq!(
+            vec!["compile"],
+            categories(&top_potentials(
+                simulate(&graph, &WhatIf::default(), &commands)?.1,
+                1
+            )?)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_compare() -> anyhow::Result<()> {
+        let build = |nodes: Vec<buck2_data::build_graph::Node>, queue: u64| Build {
+            infos: vec![buck2_data::BuildGraphExecutionInfo {
+                critical_path2: nodes.into_iter().filter_map(|n| n.entry).collect(),
+                ..Default::default()
+            }],
+            commands: HashMap::from_iter([(
+                1,
+                CommandTimes {
+                    execution: Duration::from_micros(50),
+                    queue: Duration::from_micros(queue),
+                },
+            )]),
+            ..Default::default()
+        };
+        let base = build(
+            vec![
+                node("codegen", 3, 20, vec![]),
+                node("compile", 1, 100, vec![]),
+            ],
+            30,
+        );
+        let new = build(
+            vec![node("compile", 1, 70, vec![]), node("link", 2, 10, vec![])],
+            5,
+        );
+
+        let entries = |comparison: &Comparison| {
+            comparison
+                .entries
+                .iter()
+                .map(|e| {
+                    (
+                        e.identity.category.to_owned(),
+                        e.before.map(|d| d.as_micros()),
+                        e.after.map(|d| d.as_micros()),
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let comparison = compare(&base, &new)?;
+        assert_eq!(
+            vec![
+                ("compile".to_owned(), Some(100), Some(70)),
+                ("link".to_owned(), None, Some(10)),
+                ("codegen".to_owned(), Some(20), None),
+            ],
+            entries(&comparison)
+        );
+        assert_eq!(
+            -30,
+            delta(
+                Some(Duration::from_micros(100)),
+                Some(Duration::from_micros(70))
+            )
+        );
+        assert_eq!(
+            Attribution {
+                execution: Duration::from_micros(90),
+                queue: Duration::from_micros(30),
+                ..Default::default()
+            },
+            comparison.before
+        );
+        assert_eq!(
+            Attribution {
+                execution: Duration::from_micros(75),
+                queue: Duration::from_micros(5),
+                ..Default::default()
+            },
+            comparison.after
+        );
+
+        // An identity which appears twice is paired up in order, and neither occurrence is lost.
+        let base = build(
+            vec![
+                node("compile", 1, 100, vec![]),
+                node("compile", 1, 40, vec![]),
+            ],
+            0,
+        );
+        let new = build(vec![node("compile", 1, 70, vec![])], 0);
+        assert_eq!(
+            vec![
+                ("compile".to_owned(), Some(100), Some(70)),
+                ("compile".to_owned(), Some(40), None),
+            ],
+            entries(&compare(&base, &new)?)
+        );
+        Ok(())
+    }
+}
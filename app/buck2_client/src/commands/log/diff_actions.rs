@@ -0,0 +1,288 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::fmt::Debug;
+
+use buck2_client_ctx::client_ctx::ClientCommandContext;
+use buck2_client_ctx::exit_result::ExitResult;
+use buck2_client_ctx::path_arg::PathArg;
+use buck2_client_ctx::stream_value::StreamValue;
+use buck2_client_ctx::subscribers::event_log::read::EventLogPathBuf;
+use buck2_data::action_digest_inputs::input::Data;
+use buck2_event_observer::display;
+use buck2_event_observer::display::TargetDisplayOptions;
+use tokio_stream::StreamExt;
+
+#[derive(Debug, thiserror::Error)]
+enum DiffActionsError {
+    #[error(
+        "No action digest inputs were recorded in `{0}`. Set `buck2.log_action_digest_inputs = true` \
+        in your buckconfig, restart the daemon and build again."
+    )]
+    NoDigestInputs(String),
+}
+
+/// Explain cache misses by comparing the actions of two builds.
+///
+/// Actions are matched by target, category and identifier. For every pair of matching actions
+/// whose action digests differ, this lists what differs between them: arguments, environment,
+/// working directory, input digests or platform properties.
+///
+/// Both builds must have been run with `buck2.log_action_digest_inputs = true`.
+#[derive(Debug, clap::Parser)]
+pub struct DiffActionsCommand {
+    /// The event log of the first build.
+    #[clap(value_name = "PATH")]
+    first: PathArg,
+
+    /// The event log of the second build.
+    #[clap(value_name = "PATH")]
+    second: PathArg,
+}
+
+impl DiffActionsCommand {
+    pub fn exec(self, _matches: &clap::ArgMatches, ctx: ClientCommandContext<'_>) -> ExitResult {
+        let Self { first, second } = self;
+
+        ctx.with_runtime(async move |ctx| {
+            let first = read_digest_inputs(&first, &ctx).await?;
+            let second = read_digest_inputs(&second, &ctx).await?;
+
+            let mut identical = 0;
+            let mut different = 0;
+            let mut only_in_first = 0;
+            for (action, first_inputs) in &first {
+                let second_inputs = match second.get(action) {
+                    Some(second_inputs) => second_inputs,
+                    None => {
+                        only_in_first += 1;
+                        continue;
+                    }
+                };
+                let diffs = diff_digest_inputs(first_inputs, second_inputs);
+                if diffs.is_empty() {
+                    identical += 1;
+                    continue;
+                }
+                different += 1;
+                buck2_client_ctx::println!("{}", action)?;
+                for diff in diffs {
+                    buck2_client_ctx::println!("  {}", diff)?;
+                }
+            }
+            let only_in_second = second.keys().filter(|a| !first.contains_key(*a)).count();
+
+            buck2_client_ctx::eprintln!(
+                "{} actions differ, {} are identical, {} only in the first log, {} only in the second log",
+                different,
+                identical,
+                only_in_first,
+                only_in_second
+            )?;
+
+            anyhow::Ok(())
+        })?;
+
+        ExitResult::success()
+    }
+}
+
+/// The digest inputs of all the actions in a log, by action identity.
+async fn read_digest_inputs(
+    path: &PathArg,
+    ctx: &ClientCommandContext<'_>,
+) -> anyhow::Result<BTreeMap<String, buck2_data::ActionDigestInputs>> {
+    let log_path = EventLogPathBuf::infer(path.resolve(&ctx.working_dir))?;
+    let (_invocation, mut events) = log_path.unpack_stream().await?;
+
+    let mut actions = BTreeMap::new();
+    while let Some(event) = events.try_next().await? {
+        let end = match event {
+            StreamValue::Event(event) => match event.data {
+                Some(buck2_data::buck_event::Data::SpanEnd(end)) => end,
+                _ => continue,
+            },
+            _ => continue,
+        };
+        if let Some(buck2_data::span_end_event::Data::ActionExecution(action)) = end.data {
+            if let Some(digest_inputs) = action.digest_inputs {
+                let identity = display::display_action_identity(
+                    action.key.as_ref(),
+                    action.name.as_ref(),
+                    TargetDisplayOptions::for_log(),
+                )?;
+                actions.insert(identity, digest_inputs);
+            }
+        }
+    }
+
+    if actions.is_empty() {
+        return Err(DiffActionsError::NoDigestInputs(path.display().to_string()).into());
+    }
+    Ok(actions)
+}
+
+/// What differs between two actions, empty if they have the same action digest.
+fn diff_digest_inputs(
+    first: &buck2_data::ActionDigestInputs,
+    second: &buck2_data::ActionDigestInputs,
+) -> Vec<String> {
+    let mut diffs = Vec::new();
+    if first.action_digest == second.action_digest {
+        return diffs;
+    }
+
+    diff_maps(
+        "argv",
+        first.argv.iter().enumerate().collect(),
+        second.argv.iter().enumerate().collect(),
+        &mut diffs,
+    );
+    diff_maps(
+        "env",
+        first.env.iter().map(|e| (&e.key, &e.value)).collect(),
+        second.env.iter().map(|e| (&e.key, &e.value)).collect(),
+        &mut diffs,
+    );
+    if first.working_directory != second.working_directory {
+        diffs.push(format!(
+            "working directory: {:?} -> {:?}",
+            first.working_directory, second.working_directory
+        ));
+    }
+    diff_maps(
+        "input",
+        first
+            .inputs
+            .iter()
+            .map(|i| (&i.path, display_input(i)))
+            .collect(),
+        second
+            .inputs
+            .iter()
+            .map(|i| (&i.path, display_input(i)))
+            .collect(),
+        &mut diffs,
+    );
+    // Only the paths of the outputs go into the digest.
+    let output_paths = |inputs: &buck2_data::ActionDigestInputs| {
+        inputs
+            .outputs
+            .iter()
+            .map(|o| o.path.clone())
+            .collect::<BTreeSet<_>>()
+    };
+    let (first_outputs, second_outputs) = (output_paths(first), output_paths(second));
+    for path in first_outputs.difference(&second_outputs) {
+        diffs.push(format!("output {:?}: declared -> missing", path));
+    }
+    for path in second_outputs.difference(&first_outputs) {
+        diffs.push(format!("output {:?}: missing -> declared", path));
+    }
+    let platform_properties = |inputs: &buck2_data::ActionDigestInputs| {
+        inputs
+            .platform
+            .iter()
+            .flat_map(|p| &p.properties)
+            .map(|p| (p.name.clone(), p.value.clone()))
+            .collect::<BTreeMap<_, _>>()
+    };
+    diff_maps(
+        "platform property",
+        platform_properties(first),
+        platform_properties(second),
+        &mut diffs,
+    );
+
+    if diffs.is_empty() {
+        // Timeouts are part of the digest too, but aren't recorded.
+        diffs.push(format!(
+            "action digest: {} -> {}, but the recorded inputs are identical",
+            first.action_digest, second.action_digest
+        ));
+    }
+    diffs
+}
+
+fn display_input(input: &buck2_data::action_digest_inputs::Input) -> String {
+    let data = match &input.data {
+        Some(Data::Digest(digest)) => digest.clone(),
+        Some(Data::SymlinkTarget(target)) => format!("symlink to {}", target),
+        None => "unknown".to_owned(),
+    };
+    if input.is_executable {
+        format!("{} (executable)", data)
+    } else {
+        data
+    }
+}
+
+fn diff_maps<K: Ord + Debug, V: PartialEq + Debug>(
+    what: &str,
+    first: BTreeMap<K, V>,
+    second: BTreeMap<K, V>,
+    diffs: &mut Vec<String>,
+) {
+    let keys: BTreeSet<&K> = first.keys().chain(second.keys()).collect();
+    for key in keys {
+        match (first.get(key), second.get(key)) {
+            (Some(a), Some(b)) if a == b => {}
+            (Some(a), Some(b)) => diffs.push(format!("{} {:?}: {:?} -> {:?}", what, key, a, b)),
+            (Some(a), None) => diffs.push(format!("{} {:?}: {:?} -> missing", what, key, a)),
+            (None, Some(b)) => diffs.push(format!("{} {:?}: missing -> {:?}", what, key, b)),
+            (None, None) => unreachable!("key comes from one of the maps"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn input(path: &str, digest: &str) -> buck2_data::action_digest_inputs::Input {
+        buck2_data::action_digest_inputs::Input {
+            path: path.to_owned(),
+            data: Some(Data::Digest(digest.to_owned())),
+            is_executable: false,
+        }
+    }
+
+    #[test]
+    fn test_diff_digest_inputs() {
+        let first = buck2_data::ActionDigestInputs {
+            action_digest: "aaaa:1".to_owned(),
+            argv: vec!["cc".to_owned(), "-O1".to_owned()],
+            env: vec![buck2_data::EnvironmentEntry {
+                key: "LANG".to_owned(),
+                value: "C".to_owned(),
+            }],
+            inputs: vec![input("a.c", "1111:10"), input("b.h", "2222:20")],
+            ..Default::default()
+        };
+        let second = buck2_data::ActionDigestInputs {
+            action_digest: "bbbb:1".to_owned(),
+            argv: vec!["cc".to_owned(), "-O2".to_owned()],
+            env: first.env.clone(),
+            inputs: vec![input("a.c", "3333:10")],
+            ..Default::default()
+        };
+
+        assert_eq!(
+            vec![
+                "argv 1: \"-O1\" -> \"-O2\"",
+                "input \"a.c\": \"1111:10\" -> \"3333:10\"",
+                "input \"b.h\": \"2222:20\" -> missing",
+            ],
+            diff_digest_inputs(&first, &second)
+        );
+        assert!(diff_digest_inputs(&first, &first).is_empty());
+    }
+}
@@ -10,9 +10,12 @@
 mod critical_path;
 pub(crate) mod debug_replay;
 pub(crate) mod debug_what_ran;
+mod diff_actions;
 pub(crate) mod options;
 pub(crate) mod path_log;
+mod query;
 mod replay;
+mod repro_bundle;
 mod show_log;
 mod show_user_log;
 mod summary;
@@ -64,6 +67,8 @@ pub enum LogCommand {
     Replay(replay::ReplayCommand),
     ShowUser(show_user_log::ShowUserLogCommand),
     Summary(summary::SummaryCommand),
+    DiffActions(diff_actions::DiffActionsCommand),
+    Query(query::QueryCommand),
 }
 
 impl LogCommand {
@@ -81,6 +86,8 @@ impl LogCommand {
             Self::Replay(cmd) => cmd.exec(matches, ctx),
             Self::ShowUser(cmd) => cmd.exec(matches, ctx),
             Self::Summary(cmd) => cmd.exec(matches, ctx),
+            Self::DiffActions(cmd) => cmd.exec(matches, ctx),
+            Self::Query(cmd) => cmd.exec(matches, ctx),
         }
     }
 
@@ -8,6 +8,7 @@
  */
 
 use std::process::Stdio;
+use std::str::FromStr;
 
 use anyhow::Context;
 use buck2_client_ctx::client_ctx::ClientCommandContext;
@@ -33,6 +34,8 @@ enum EventLogOptionsError {
     ManifoldFailed(String),
     #[error("Log not found locally by trace id `{0}`")]
     LogNotFoundLocally(TraceId),
+    #[error("Unexpected argument `{0}`, the event log was already selected")]
+    LogAlreadySelected(String),
 }
 
 #[derive(Debug, clap::Parser)]
@@ -60,6 +63,16 @@ pub(crate) struct EventLogOptions {
 }
 
 impl EventLogOptions {
+    /// Read the event log at `path`, for commands which take another positional argument before
+    /// the event log.
+    pub(crate) fn set_path(&mut self, path: String) -> anyhow::Result<()> {
+        if self.path.is_some() || self.recent.is_some() || self.trace_id.is_some() {
+            return Err(EventLogOptionsError::LogAlreadySelected(path).into());
+        }
+        self.path = Some(PathArg::from_str(&path)?);
+        Ok(())
+    }
+
     pub(crate) async fn get(
         &self,
         ctx: &ClientCommandContext<'_>,
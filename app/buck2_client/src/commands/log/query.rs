@@ -0,0 +1,369 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+mod expr;
+
+use std::time::Duration;
+
+use buck2_client_ctx::client_ctx::ClientCommandContext;
+use buck2_client_ctx::exit_result::ExitResult;
+use buck2_client_ctx::stream_value::StreamValue;
+use buck2_common::convert::ProstDurationExt;
+use buck2_event_observer::display;
+use buck2_event_observer::display::TargetDisplayOptions;
+use tokio_stream::StreamExt;
+
+use crate::commands::log::options::EventLogOptions;
+use crate::commands::log::query::expr::Expr;
+use crate::commands::log::query::expr::Field;
+use crate::commands::log::query::expr::Fields;
+use crate::commands::log::query::expr::Value;
+use crate::commands::log::LogCommandOutputFormat;
+
+/// Filter the actions, analyses, package loads and test results of selected invocation.
+///
+/// Entries are filtered with an expression over their fields, for example:
+///
+/// category == "cxx_compile" && duration > 10s && !cache_hit
+///
+/// The fields are:
+///
+/// `kind`: `action`, `analysis`, `load` or `test`.
+/// `target`: the target, without configuration.
+/// `category`: the category of an action, or the rule of an analysis.
+/// `identifier`: the identifier of an action, the module of a load, or the name of a test.
+/// `duration`: compared with durations such as `500ms`, `10s` or `1.5m`.
+/// `executor`: for actions, `local`, `remote`, `cache`, `worker`, `simple` or `deferred`.
+/// `cache_hit`: for actions.
+/// `exit_code`: for actions which ran a command.
+/// `failed`.
+///
+/// Strings are quoted, and conditions are combined with `&&`, `||`, `!` and parentheses.
+/// Comparisons with a field an entry doesn't have are false.
+///
+/// The output is a tab-separated list containing the kind, the target, the category, the
+/// identifier, the duration in milliseconds, the executor, whether it was a cache hit, the exit
+/// code and whether it failed.
+#[derive(Debug, clap::Parser)]
+pub struct QueryCommand {
+    /// The filter expression.
+    #[clap(value_name = "EXPR")]
+    expr: String,
+
+    #[clap(flatten)]
+    event_log: EventLogOptions,
+
+    #[clap(
+        long = "format",
+        help = "Which output format to use for this command",
+        default_value = "tabulated",
+        ignore_case = true,
+        arg_enum
+    )]
+    output: LogCommandOutputFormat,
+}
+
+impl QueryCommand {
+    pub fn exec(self, _matches: &clap::ArgMatches, ctx: ClientCommandContext<'_>) -> ExitResult {
+        let Self {
+            expr,
+            event_log,
+            output,
+        } = self;
+        // Check the expression before reading the log.
+        let expr = Expr::parse(&expr)?;
+
+        ctx.with_runtime(async move |ctx| {
+            let log_path = event_log.get(&ctx).await?;
+
+            let (invocation, mut events) = log_path.unpack_stream().await?;
+
+            buck2_client_ctx::eprintln!(
+                "Querying entries from: {}",
+                invocation.display_command_line()
+            )?;
+
+            if let LogCommandOutputFormat::Csv = output {
+                buck2_client_ctx::stdio::print_with_writer(|w| {
+                    csv::Writer::from_writer(w).write_record(CSV_HEADER)
+                })?;
+            }
+
+            while let Some(event) = events.try_next().await? {
+                match event {
+                    StreamValue::Event(event) => {
+                        if let Some(entry) = Entry::from_event(&event)? {
+                            if expr.eval(&entry) {
+                                write_output(&output, &entry)?;
+                            }
+                        }
+                    }
+                    StreamValue::Result(..) | StreamValue::PartialResult(..) => {}
+                }
+            }
+
+            anyhow::Ok(())
+        })?;
+        ExitResult::success()
+    }
+}
+
+/// The names of the fields of `Entry`, in the order they are serialized.
+const CSV_HEADER: [&str; 9] = [
+    "kind",
+    "target",
+    "category",
+    "identifier",
+    "duration_ms",
+    "executor",
+    "cache_hit",
+    "exit_code",
+    "failed",
+];
+
+#[derive(Debug, Default, serde::Serialize)]
+struct Entry {
+    kind: &'static str,
+    target: String,
+    category: String,
+    identifier: String,
+    duration_ms: Option<u64>,
+    #[serde(skip)]
+    duration: Option<Duration>,
+    executor: Option<&'static str>,
+    cache_hit: Option<bool>,
+    exit_code: Option<i32>,
+    failed: bool,
+}
+
+impl Fields for Entry {
+    fn get(&self, field: Field) -> Option<Value> {
+        match field {
+            Field::Kind => Some(Value::String(self.kind.to_owned())),
+            Field::Target => Some(Value::String(self.target.clone())),
+            Field::Category => Some(Value::String(self.category.clone())),
+            Field::Identifier => Some(Value::String(self.identifier.clone())),
+            Field::Duration => self.duration.map(Value::Duration),
+            Field::Executor => self.executor.map(|e| Value::String(e.to_owned())),
+            Field::CacheHit => self.cache_hit.map(Value::Bool),
+            Field::ExitCode => self.exit_code.map(|c| Value::Integer(c.into())),
+            Field::Failed => Some(Value::Bool(self.failed)),
+        }
+    }
+}
+
+impl Entry {
+    fn from_event(event: &buck2_data::BuckEvent) -> anyhow::Result<Option<Entry>> {
+        use buck2_data::buck_event::Data;
+
+        let opts = TargetDisplayOptions::for_console(false);
+        let entry = match &event.data {
+            Some(Data::SpanEnd(end)) => {
+                let duration = end
+                    .duration
+                    .as_ref()
+                    .map(|d| d.try_into_duration())
+                    .transpose()?;
+                match &end.data {
+                    Some(buck2_data::span_end_event::Data::ActionExecution(action)) => {
+                        Self::action(action, opts)?
+                    }
+                    Some(buck2_data::span_end_event::Data::Analysis(analysis)) => {
+                        use buck2_data::analysis_end::Target;
+
+                        Entry {
+                            kind: "analysis",
+                            target: match &analysis.target {
+                                Some(Target::StandardTarget(t)) => {
+                                    display::display_configured_target_label(t, opts)?
+                                }
+                                Some(Target::AnonTarget(t)) => display::display_anon_target(t)?,
+                                None => String::new(),
+                            },
+                            category: analysis.rule.clone(),
+                            ..Default::default()
+                        }
+                    }
+                    Some(buck2_data::span_end_event::Data::Load(load)) => Entry {
+                        kind: "load",
+                        identifier: load.module_id.clone(),
+                        failed: load.error.is_some(),
+                        ..Default::default()
+                    },
+                    _ => return Ok(None),
+                }
+                .with_duration(duration)
+            }
+            Some(Data::Instant(instant)) => match &instant.data {
+                Some(buck2_data::instant_event::Data::TestResult(result)) => {
+                    use buck2_data::TestStatus;
+
+                    Entry {
+                        kind: "test",
+                        target: match &result.target_label {
+                            Some(t) => display::display_configured_target_label(t, opts)?,
+                            None => String::new(),
+                        },
+                        identifier: result.name.clone(),
+                        failed: matches!(
+                            TestStatus::from_i32(result.status),
+                            Some(
+                                TestStatus::Fail
+                                    | TestStatus::Fatal
+                                    | TestStatus::Timeout
+                                    | TestStatus::ListingFailed
+                            )
+                        ),
+                        ..Default::default()
+                    }
+                    .with_duration(
+                        result
+                            .duration
+                            .as_ref()
+                            .map(|d| d.try_into_duration())
+                            .transpose()?,
+                    )
+                }
+                _ => return Ok(None),
+            },
+            _ => return Ok(None),
+        };
+        Ok(Some(entry))
+    }
+
+    fn action(
+        action: &buck2_data::ActionExecutionEnd,
+        opts: TargetDisplayOptions,
+    ) -> anyhow::Result<Entry> {
+        use buck2_data::ActionExecutionKind;
+
+        let (executor, cache_hit) = match ActionExecutionKind::from_i32(action.execution_kind) {
+            Some(ActionExecutionKind::Local) => (Some("local"), false),
+            Some(ActionExecutionKind::LocalWorker) => (Some("worker"), false),
+            Some(ActionExecutionKind::Remote) => (Some("remote"), false),
+            Some(
+                ActionExecutionKind::ActionCache
+                | ActionExecutionKind::LocalDepFile
+                | ActionExecutionKind::RemoteDepFileCache,
+            ) => (Some("cache"), true),
+            Some(ActionExecutionKind::Simple) => (Some("simple"), false),
+            Some(ActionExecutionKind::Deferred) => (Some("deferred"), false),
+            Some(ActionExecutionKind::NotSet) | None => (None, false),
+        };
+        let (category, identifier) = action.name.as_ref().map_or_else(
+            || (String::new(), String::new()),
+            |n| (n.category.clone(), n.identifier.clone()),
+        );
+        Ok(Entry {
+            kind: "action",
+            target: match &action.key {
+                Some(key) => display::display_action_key(key, opts)?,
+                None => String::new(),
+            },
+            category,
+            identifier,
+            executor,
+            cache_hit: Some(cache_hit),
+            exit_code: action
+                .commands
+                .last()
+                .and_then(|c| c.details.as_ref())
+                .and_then(|d| d.signed_exit_code),
+            failed: action.failed,
+            ..Default::default()
+        })
+    }
+
+    fn with_duration(self, duration: Option<Duration>) -> Entry {
+        Entry {
+            duration_ms: duration.map(|d| d.as_millis() as u64),
+            duration,
+            ..self
+        }
+    }
+}
+
+fn write_output(output: &LogCommandOutputFormat, entry: &Entry) -> anyhow::Result<()> {
+    fn or_empty<T: ToString>(value: Option<T>) -> String {
+        value.map_or_else(String::new, |v| v.to_string())
+    }
+
+    match output {
+        LogCommandOutputFormat::Tabulated => buck2_client_ctx::println!(
+            "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+            entry.kind,
+            entry.target,
+            entry.category,
+            entry.identifier,
+            or_empty(entry.duration_ms),
+            or_empty(entry.executor),
+            or_empty(entry.cache_hit),
+            or_empty(entry.exit_code),
+            entry.failed,
+        ),
+        LogCommandOutputFormat::Csv => buck2_client_ctx::stdio::print_with_writer(|w| {
+            let mut writer = csv::WriterBuilder::new().has_headers(false).from_writer(w);
+            writer.serialize(entry)
+        }),
+        LogCommandOutputFormat::Json => buck2_client_ctx::stdio::print_with_writer(|mut w| {
+            serde_json::to_writer(&mut w, &entry)?;
+            w.write_all(b"\n")
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_action_entry() -> anyhow::Result<()> {
+        let event = buck2_data::BuckEvent {
+            data: Some(
+                buck2_data::SpanEndEvent {
+                    duration: Some(prost_types::Duration {
+                        seconds: 12,
+                        nanos: 0,
+                    }),
+                    data: Some(
+                        buck2_data::ActionExecutionEnd {
+                            name: Some(buck2_data::ActionName {
+                                category: "cxx_compile".to_owned(),
+                                identifier: "main.cpp".to_owned(),
+                            }),
+                            execution_kind: buck2_data::ActionExecutionKind::Remote as i32,
+                            ..Default::default()
+                        }
+                        .into(),
+                    ),
+                    ..Default::default()
+                }
+                .into(),
+            ),
+            ..Default::default()
+        };
+        let entry = Entry::from_event(&event)?.unwrap();
+        assert_eq!(Some(12000), entry.duration_ms);
+        assert!(
+            Expr::parse(r#"category == "cxx_compile" && duration > 10s && !cache_hit"#)?
+                .eval(&entry)
+        );
+        assert!(!Expr::parse(r#"executor == "local" || exit_code == 0"#)?.eval(&entry));
+        Ok(())
+    }
+
+    #[test]
+    fn test_csv_header() -> anyhow::Result<()> {
+        let mut writer = csv::Writer::from_writer(Vec::new());
+        writer.serialize(Entry::default())?;
+        let csv = String::from_utf8(writer.into_inner()?)?;
+        assert_eq!(Some(CSV_HEADER.join(",").as_str()), csv.lines().next());
+        Ok(())
+    }
+}
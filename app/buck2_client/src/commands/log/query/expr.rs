@@ -0,0 +1,506 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! The filter language of `buck2 log query`.
+//!
+//! ```text
+//! expr       := and ("||" and)*
+//! and        := unary ("&&" unary)*
+//! unary      := "!" unary | "(" expr ")" | comparison | FIELD | "true" | "false"
+//! comparison := FIELD OP LITERAL | LITERAL OP FIELD
+//! OP         := "==" | "!=" | "<" | "<=" | ">" | ">="
+//! LITERAL    := STRING | INTEGER | DURATION | "true" | "false"
+//! ```
+//!
+//! Fields are typed, and expressions are checked when parsed: strings are quoted, durations
+//! have a unit (`us`, `ms`, `s`, `m` or `h`), and only boolean fields can be used as conditions
+//! on their own. A comparison with a field that is absent from an entry is false.
+
+use std::cmp::Ordering;
+use std::iter::Peekable;
+use std::str::CharIndices;
+use std::time::Duration;
+
+use dupe::Dupe;
+
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum ExprError {
+    #[error("Unexpected character `{0}` at offset {1}")]
+    UnexpectedChar(char, usize),
+    #[error("Unterminated string starting at offset {0}")]
+    UnterminatedString(usize),
+    #[error("Invalid number `{0}`")]
+    InvalidNumber(String),
+    #[error("Unknown duration unit in `{0}`, expected `us`, `ms`, `s`, `m` or `h`")]
+    InvalidDurationUnit(String),
+    #[error("Unknown field `{0}`, expected one of: {}", Field::ALL.map(|f| f.name()).join(", "))]
+    UnknownField(String),
+    #[error("Unexpected `{0}`")]
+    UnexpectedToken(String),
+    #[error("Unexpected end of expression")]
+    UnexpectedEnd,
+    #[error("Field `{0}` has type {1}, it can't be compared with a value of type {2}")]
+    TypeMismatch(&'static str, &'static str, &'static str),
+    #[error("Field `{0}` has type {1}, it is not a condition on its own. Compare it with a value")]
+    NotBoolean(&'static str, &'static str),
+    #[error("Booleans can only be compared with `==` and `!=`")]
+    BooleanOrdering,
+}
+
+/// What an entry of the event log can be filtered on.
+#[derive(Debug, Clone, Copy, Dupe, PartialEq, Eq)]
+pub(crate) enum Field {
+    Kind,
+    Target,
+    Category,
+    Identifier,
+    Duration,
+    Executor,
+    CacheHit,
+    ExitCode,
+    Failed,
+}
+
+impl Field {
+    pub(crate) const ALL: [Field; 9] = [
+        Field::Kind,
+        Field::Target,
+        Field::Category,
+        Field::Identifier,
+        Field::Duration,
+        Field::Executor,
+        Field::CacheHit,
+        Field::ExitCode,
+        Field::Failed,
+    ];
+
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Field::Kind => "kind",
+            Field::Target => "target",
+            Field::Category => "category",
+            Field::Identifier => "identifier",
+            Field::Duration => "duration",
+            Field::Executor => "executor",
+            Field::CacheHit => "cache_hit",
+            Field::ExitCode => "exit_code",
+            Field::Failed => "failed",
+        }
+    }
+
+    fn ty(self) -> &'static str {
+        match self {
+            Field::Kind | Field::Target | Field::Category | Field::Identifier | Field::Executor => {
+                "string"
+            }
+            Field::Duration => "duration",
+            Field::CacheHit | Field::Failed => "boolean",
+            Field::ExitCode => "integer",
+        }
+    }
+}
+
+/// The value of a field, or a literal.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Value {
+    String(String),
+    Integer(i64),
+    Duration(Duration),
+    Bool(bool),
+}
+
+impl Value {
+    fn ty(&self) -> &'static str {
+        match self {
+            Value::String(..) => "string",
+            Value::Integer(..) => "integer",
+            Value::Duration(..) => "duration",
+            Value::Bool(..) => "boolean",
+        }
+    }
+
+    fn compare(&self, other: &Value) -> Option<Ordering> {
+        match (self, other) {
+            (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+            (Value::Integer(a), Value::Integer(b)) => Some(a.cmp(b)),
+            (Value::Duration(a), Value::Duration(b)) => Some(a.cmp(b)),
+            (Value::Bool(a), Value::Bool(b)) => Some(a.cmp(b)),
+            _ => None,
+        }
+    }
+}
+
+/// Something to filter, which has values for some of the fields.
+pub(crate) trait Fields {
+    fn get(&self, field: Field) -> Option<Value>;
+}
+
+#[derive(Debug, Clone, Copy, Dupe, PartialEq, Eq)]
+pub(crate) enum CompareOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl CompareOp {
+    /// The operator with its operands swapped, `a < b` is `b > a`.
+    fn flip(self) -> CompareOp {
+        match self {
+            CompareOp::Eq | CompareOp::Ne => self,
+            CompareOp::Lt => CompareOp::Gt,
+            CompareOp::Le => CompareOp::Ge,
+            CompareOp::Gt => CompareOp::Lt,
+            CompareOp::Ge => CompareOp::Le,
+        }
+    }
+
+    fn matches(self, ordering: Ordering) -> bool {
+        match self {
+            CompareOp::Eq => ordering == Ordering::Equal,
+            CompareOp::Ne => ordering != Ordering::Equal,
+            CompareOp::Lt => ordering == Ordering::Less,
+            CompareOp::Le => ordering != Ordering::Greater,
+            CompareOp::Gt => ordering == Ordering::Greater,
+            CompareOp::Ge => ordering != Ordering::Less,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Expr {
+    Const(bool),
+    /// A boolean field.
+    Field(Field),
+    Compare(Field, CompareOp, Value),
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+}
+
+impl Expr {
+    pub(crate) fn parse(s: &str) -> anyhow::Result<Expr> {
+        let tokens = tokenize(s)?;
+        let mut parser = Parser {
+            tokens: &tokens,
+            pos: 0,
+        };
+        let expr = parser.or()?;
+        match parser.next() {
+            None => Ok(expr),
+            Some(token) => Err(ExprError::UnexpectedToken(token.to_string()).into()),
+        }
+    }
+
+    pub(crate) fn eval(&self, entry: &impl Fields) -> bool {
+        match self {
+            Expr::Const(b) => *b,
+            Expr::Field(field) => entry.get(*field) == Some(Value::Bool(true)),
+            Expr::Compare(field, op, value) => match entry.get(*field) {
+                Some(actual) => actual
+                    .compare(value)
+                    .map_or(false, |ordering| op.matches(ordering)),
+                None => false,
+            },
+            Expr::Not(e) => !e.eval(entry),
+            Expr::And(a, b) => a.eval(entry) && b.eval(entry),
+            Expr::Or(a, b) => a.eval(entry) || b.eval(entry),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Literal(Value),
+    Op(CompareOp),
+    Not,
+    And,
+    Or,
+    LParen,
+    RParen,
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::Ident(s) => write!(f, "{}", s),
+            Token::Literal(v) => write!(f, "{:?}", v),
+            Token::Op(op) => write!(f, "{:?}", op),
+            Token::Not => write!(f, "!"),
+            Token::And => write!(f, "&&"),
+            Token::Or => write!(f, "||"),
+            Token::LParen => write!(f, "("),
+            Token::RParen => write!(f, ")"),
+        }
+    }
+}
+
+fn tokenize(s: &str) -> anyhow::Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = s.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        let token = match c {
+            c if c.is_whitespace() => continue,
+            '(' => Token::LParen,
+            ')' => Token::RParen,
+            '&' if followed_by(&mut chars, '&') => Token::And,
+            '|' if followed_by(&mut chars, '|') => Token::Or,
+            '=' if followed_by(&mut chars, '=') => Token::Op(CompareOp::Eq),
+            '!' if followed_by(&mut chars, '=') => Token::Op(CompareOp::Ne),
+            '!' => Token::Not,
+            '<' if followed_by(&mut chars, '=') => Token::Op(CompareOp::Le),
+            '<' => Token::Op(CompareOp::Lt),
+            '>' if followed_by(&mut chars, '=') => Token::Op(CompareOp::Ge),
+            '>' => Token::Op(CompareOp::Gt),
+            '"' => {
+                let mut string = String::new();
+                loop {
+                    match chars.next() {
+                        Some((_, '"')) => break,
+                        Some((_, '\\')) => match chars.next() {
+                            Some((_, c)) => string.push(c),
+                            None => return Err(ExprError::UnterminatedString(start).into()),
+                        },
+                        Some((_, c)) => string.push(c),
+                        None => return Err(ExprError::UnterminatedString(start).into()),
+                    }
+                }
+                Token::Literal(Value::String(string))
+            }
+            c if c.is_ascii_digit() || c == '-' => {
+                let mut end = start + c.len_utf8();
+                while let Some((i, c)) =
+                    chars.next_if(|(_, c)| c.is_ascii_alphanumeric() || *c == '.')
+                {
+                    end = i + c.len_utf8();
+                }
+                Token::Literal(number(&s[start..end])?)
+            }
+            c if c.is_ascii_alphabetic() || c == '_' => {
+                let mut end = start + c.len_utf8();
+                while let Some((i, c)) =
+                    chars.next_if(|(_, c)| c.is_ascii_alphanumeric() || *c == '_')
+                {
+                    end = i + c.len_utf8();
+                }
+                match &s[start..end] {
+                    "true" => Token::Literal(Value::Bool(true)),
+                    "false" => Token::Literal(Value::Bool(false)),
+                    ident => Token::Ident(ident.to_owned()),
+                }
+            }
+            c => return Err(ExprError::UnexpectedChar(c, start).into()),
+        };
+        tokens.push(token);
+    }
+    Ok(tokens)
+}
+
+fn followed_by(chars: &mut Peekable<CharIndices>, next: char) -> bool {
+    chars.next_if(|(_, c)| *c == next).is_some()
+}
+
+/// An integer, or a duration if it has a unit.
+fn number(s: &str) -> anyhow::Result<Value> {
+    let split = s
+        .find(|c: char| !(c.is_ascii_digit() || c == '.' || c == '-'))
+        .unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+    if unit.is_empty() {
+        return Ok(Value::Integer(
+            number
+                .parse()
+                .map_err(|_| ExprError::InvalidNumber(s.to_owned()))?,
+        ));
+    }
+    let number: f64 = number
+        .parse()
+        .map_err(|_| ExprError::InvalidNumber(s.to_owned()))?;
+    let seconds = match unit {
+        "us" => number / 1_000_000.0,
+        "ms" => number / 1_000.0,
+        "s" => number,
+        "m" => number * 60.0,
+        "h" => number * 3600.0,
+        _ => return Err(ExprError::InvalidDurationUnit(s.to_owned()).into()),
+    };
+    Ok(Value::Duration(
+        Duration::try_from_secs_f64(seconds).map_err(|_| ExprError::InvalidNumber(s.to_owned()))?,
+    ))
+}
+
+struct Parser<'a> {
+    tokens: &'a [Token],
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&'a Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<&'a Token> {
+        let token = self.tokens.get(self.pos);
+        self.pos += 1;
+        token
+    }
+
+    fn or(&mut self) -> anyhow::Result<Expr> {
+        let mut expr = self.and()?;
+        while self.peek() == Some(&Token::Or) {
+            self.pos += 1;
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> anyhow::Result<Expr> {
+        let mut expr = self.unary()?;
+        while self.peek() == Some(&Token::And) {
+            self.pos += 1;
+            expr = Expr::And(Box::new(expr), Box::new(self.unary()?));
+        }
+        Ok(expr)
+    }
+
+    fn unary(&mut self) -> anyhow::Result<Expr> {
+        match self.next().ok_or(ExprError::UnexpectedEnd)? {
+            Token::Not => Ok(Expr::Not(Box::new(self.unary()?))),
+            Token::LParen => {
+                let expr = self.or()?;
+                match self.next() {
+                    Some(Token::RParen) => Ok(expr),
+                    Some(token) => Err(ExprError::UnexpectedToken(token.to_string()).into()),
+                    None => Err(ExprError::UnexpectedEnd.into()),
+                }
+            }
+            Token::Ident(name) => {
+                let field = field(name)?;
+                match self.peek() {
+                    Some(Token::Op(op)) => {
+                        self.pos += 1;
+                        let value = self.literal()?;
+                        compare(field, *op, value)
+                    }
+                    _ if field.ty() == "boolean" => Ok(Expr::Field(field)),
+                    _ => Err(ExprError::NotBoolean(field.name(), field.ty()).into()),
+                }
+            }
+            Token::Literal(value) => match self.peek() {
+                Some(Token::Op(op)) => {
+                    self.pos += 1;
+                    let field = match self.next().ok_or(ExprError::UnexpectedEnd)? {
+                        Token::Ident(name) => field(name)?,
+                        token => return Err(ExprError::UnexpectedToken(token.to_string()).into()),
+                    };
+                    compare(field, op.flip(), value.clone())
+                }
+                _ => match value {
+                    Value::Bool(b) => Ok(Expr::Const(*b)),
+                    _ => Err(ExprError::UnexpectedToken(format!("{:?}", value)).into()),
+                },
+            },
+            token => Err(ExprError::UnexpectedToken(token.to_string()).into()),
+        }
+    }
+
+    fn literal(&mut self) -> anyhow::Result<Value> {
+        match self.next().ok_or(ExprError::UnexpectedEnd)? {
+            Token::Literal(value) => Ok(value.clone()),
+            token => Err(ExprError::UnexpectedToken(token.to_string()).into()),
+        }
+    }
+}
+
+fn field(name: &str) -> anyhow::Result<Field> {
+    Field::ALL
+        .into_iter()
+        .find(|f| f.name() == name)
+        .ok_or_else(|| ExprError::UnknownField(name.to_owned()).into())
+}
+
+fn compare(field: Field, op: CompareOp, value: Value) -> anyhow::Result<Expr> {
+    if field.ty() != value.ty() {
+        return Err(ExprError::TypeMismatch(field.name(), field.ty(), value.ty()).into());
+    }
+    if matches!(value, Value::Bool(..)) && !matches!(op, CompareOp::Eq | CompareOp::Ne) {
+        return Err(ExprError::BooleanOrdering.into());
+    }
+    Ok(Expr::Compare(field, op, value))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    struct Entry(HashMap<&'static str, Value>);
+
+    impl Fields for Entry {
+        fn get(&self, field: Field) -> Option<Value> {
+            self.0.get(field.name()).cloned()
+        }
+    }
+
+    fn compile() -> Entry {
+        Entry(HashMap::from_iter([
+            ("kind", Value::String("action".to_owned())),
+            ("category", Value::String("cxx_compile".to_owned())),
+            ("duration", Value::Duration(Duration::from_secs(12))),
+            ("cache_hit", Value::Bool(false)),
+            ("failed", Value::Bool(false)),
+        ]))
+    }
+
+    fn eval(expr: &str) -> anyhow::Result<bool> {
+        Ok(Expr::parse(expr)?.eval(&compile()))
+    }
+
+    #[test]
+    fn test_eval() -> anyhow::Result<()> {
+        assert!(eval(
+            r#"category == "cxx_compile" && duration > 10s && !cache_hit"#
+        )?);
+        assert!(!eval(r#"category == "cxx_compile" && duration > 1.5m"#)?);
+        assert!(eval(r#"10000ms < duration || failed"#)?);
+        assert!(eval(r#"!(kind != "action") && cache_hit == false"#)?);
+        assert!(eval("true || false && false")?);
+        // Absent fields don't match any comparison.
+        assert!(!eval("exit_code == 0")?);
+        assert!(!eval("exit_code != 0")?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let error = |expr: &str| Expr::parse(expr).unwrap_err().to_string();
+        assert_eq!(
+            "Field `duration` has type duration, it can't be compared with a value of type integer",
+            error("duration > 10")
+        );
+        assert_eq!(
+            "Field `category` has type string, it is not a condition on its own. Compare it with a value",
+            error("category")
+        );
+        assert!(error("name == \"x\"").starts_with("Unknown field `name`"));
+        assert_eq!("Unexpected end of expression", error("failed &&"));
+        assert_eq!(
+            "Unterminated string starting at offset 12",
+            error("category == \"cxx")
+        );
+        assert_eq!(
+            "Unknown duration unit in `10d`, expected `us`, `ms`, `s`, `m` or `h`",
+            error("duration > 10d")
+        );
+    }
+}
@@ -0,0 +1,409 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Export of an action as a self-contained directory that reproduces it without Buck2.
+//!
+//! The bundle is built from what `what-ran` knows about the action, and contains:
+//!
+//! * `action.json`: the record of the action, as printed by `what-ran --format json`.
+//! * `run.sh`: for a local command, runs it with its exact argv and environment.
+//! * `root/`: for a local command, the files it takes as arguments, at their paths relative to
+//!   the project root. The event log doesn't list the inputs of actions, so inputs which are not
+//!   arguments (e.g. headers) are not included.
+
+use std::path::Path;
+
+use anyhow::Context;
+use buck2_cli_proto::new_generic::MaterializeRequest;
+use buck2_cli_proto::new_generic::NewGenericRequest;
+use buck2_client_ctx::client_ctx::ClientCommandContext;
+use buck2_client_ctx::command_outcome::CommandOutcome;
+use buck2_client_ctx::daemon::client::connect::BuckdConnectOptions;
+use buck2_client_ctx::path_arg::PathArg;
+use buck2_core::fs::fs_util;
+use buck2_core::fs::paths::abs_path::AbsPath;
+use buck2_event_observer::what_ran::CommandReproducer;
+use buck2_event_observer::what_ran::WhatRanOutputCommand;
+use buck2_event_observer::what_ran::WhatRanOutputWriter;
+
+use crate::commands::log::what_ran::json_command;
+
+#[derive(Debug, thiserror::Error)]
+enum ReproBundleError {
+    #[error("No action `{0}` was found")]
+    ActionNotFound(String),
+    #[error("The daemon failed to materialize the inputs")]
+    MaterializationFailed,
+    #[error("Not overwriting `{0}`, which already exists")]
+    AlreadyExists(String),
+}
+
+/// A command which ran locally.
+#[derive(Debug, PartialEq)]
+struct BundledCommand {
+    argv: Vec<String>,
+    env: Vec<(String, String)>,
+}
+
+/// The action to bundle, as reported by what-ran.
+pub(crate) struct BundledAction {
+    identity: String,
+    /// Only known if it didn't run locally.
+    action_digest: Option<String>,
+    /// The record printed by `what-ran --format json`.
+    record: serde_json::Value,
+    command: Option<BundledCommand>,
+}
+
+/// Finds the last command that ran for an action, by identity or action digest.
+pub(crate) struct ActionFinder<'a> {
+    action: &'a str,
+    found: Option<BundledAction>,
+}
+
+impl<'a> ActionFinder<'a> {
+    pub(crate) fn new(action: &'a str) -> Self {
+        Self {
+            action,
+            found: None,
+        }
+    }
+
+    pub(crate) fn found(self) -> anyhow::Result<BundledAction> {
+        self.found
+            .ok_or_else(|| ReproBundleError::ActionNotFound(self.action.to_owned()).into())
+    }
+}
+
+impl WhatRanOutputWriter for ActionFinder<'_> {
+    fn emit_command(&mut self, command: WhatRanOutputCommand<'_>) -> anyhow::Result<()> {
+        let action_digest = match command.repro {
+            CommandReproducer::CacheQuery(cache) => Some(&cache.action_digest),
+            CommandReproducer::CacheHit(cache) => Some(&cache.action_digest),
+            CommandReproducer::ReExecute(re_execute) => Some(&re_execute.action_digest),
+            CommandReproducer::LocalExecute(..)
+            | CommandReproducer::WorkerExecute(..)
+            | CommandReproducer::WorkerInit(..) => None,
+        };
+        let digest_matches = action_digest.map_or(false, |d| d == self.action);
+        if command.identity != self.action && !digest_matches {
+            return Ok(());
+        }
+
+        // Worker executions are requests to a running worker, they can't be run on their own.
+        let local_command = match command.repro {
+            CommandReproducer::LocalExecute(local_execute) => {
+                local_execute.command.as_ref().map(|c| BundledCommand {
+                    argv: c.argv.clone(),
+                    env: c
+                        .env
+                        .iter()
+                        .map(|e| (e.key.clone(), e.value.clone()))
+                        .collect(),
+                })
+            }
+            _ => None,
+        };
+
+        self.found = Some(BundledAction {
+            identity: command.identity.to_owned(),
+            action_digest: action_digest.cloned(),
+            record: serde_json::to_value(json_command(command))?,
+            command: local_command,
+        });
+        Ok(())
+    }
+}
+
+pub(crate) async fn write_repro_bundle(
+    ctx: &ClientCommandContext<'_>,
+    action: BundledAction,
+    bundle_dir: Option<&PathArg>,
+) -> anyhow::Result<()> {
+    let bundle_dir = match bundle_dir {
+        Some(dir) => dir.resolve(&ctx.working_dir),
+        None => {
+            let name = match &action.action_digest {
+                Some(digest) => format!("repro-{}", digest.split(':').next().unwrap_or_default()),
+                None => "repro".to_owned(),
+            };
+            ctx.working_dir.resolve(Path::new(&name))
+        }
+    };
+    if fs_util::try_exists(&bundle_dir)? {
+        return Err(ReproBundleError::AlreadyExists(bundle_dir.display().to_string()).into());
+    }
+
+    if let Some(command) = &action.command {
+        materialize_inputs(ctx, command).await;
+    }
+
+    let missing_inputs = write_bundle(&bundle_dir, ctx.paths()?.project_root().root(), &action)?;
+    for path in &missing_inputs {
+        buck2_client_ctx::eprintln!("Argument is not on disk, not copied: {}", path)?;
+    }
+
+    buck2_client_ctx::eprintln!("Wrote reproduction bundle for: {}", action.identity)?;
+    if action.command.is_none() {
+        buck2_client_ctx::eprintln!(
+            "The action did not run locally, see `action.json` for its action digest"
+        )?;
+    }
+    buck2_client_ctx::println!("{}", bundle_dir.display())?;
+    Ok(())
+}
+
+/// Write the bundle to `bundle_dir`. Returns the arguments which look like inputs in `buck-out`
+/// but are not on disk.
+fn write_bundle(
+    bundle_dir: &AbsPath,
+    project_root: &AbsPath,
+    action: &BundledAction,
+) -> anyhow::Result<Vec<String>> {
+    fs_util::create_dir_all(bundle_dir)?;
+    fs_util::write(
+        bundle_dir.join("action.json"),
+        serde_json::to_string_pretty(&action.record)?,
+    )?;
+
+    let command = match &action.command {
+        Some(command) => command,
+        None => return Ok(Vec::new()),
+    };
+
+    let root = bundle_dir.join("root");
+    fs_util::create_dir_all(&root)?;
+    let mut missing_inputs = Vec::new();
+    for input in input_candidates(command) {
+        let src = project_root.join(input);
+        // Inputs in `buck-out` may be symlinks, we copy what they point to.
+        if fs_util::metadata(&src).map_or(false, |m| m.is_file()) {
+            let dest = root.join(input);
+            if let Some(parent) = dest.parent() {
+                fs_util::create_dir_all(parent)?;
+            }
+            // `copy` preserves the permissions, so executables stay executable.
+            fs_util::copy(&src, &dest)
+                .with_context(|| format!("Error copying input `{}`", input))?;
+        } else if input.starts_with("buck-out/") {
+            missing_inputs.push(input.to_owned());
+        }
+    }
+
+    let script = bundle_dir.join("run.sh");
+    fs_util::write(&script, run_script(&action.identity, command))?;
+    fs_util::set_executable(&script)?;
+
+    Ok(missing_inputs)
+}
+
+/// The arguments of the command which may be paths relative to the project root: plain
+/// arguments, the values of `--flag=value` and argument files (`@path`).
+fn input_candidates(command: &BundledCommand) -> impl Iterator<Item = &str> {
+    command
+        .argv
+        .iter()
+        .map(|arg| {
+            let arg = arg.strip_prefix('@').unwrap_or(arg.as_str());
+            match arg.split_once('=') {
+                Some((flag, value)) if flag.starts_with('-') => value,
+                _ => arg,
+            }
+        })
+        .filter(|arg| {
+            !arg.is_empty()
+                && !arg.starts_with('-')
+                && Path::new(arg).is_relative()
+                && !Path::new(arg)
+                    .components()
+                    .any(|c| c == std::path::Component::ParentDir)
+        })
+}
+
+/// Ask the daemon to materialize the arguments in `buck-out`, since they may only exist in the
+/// CAS. This is best effort: the daemon may not be running anymore, or may not know the
+/// artifacts.
+async fn materialize_inputs(ctx: &ClientCommandContext<'_>, command: &BundledCommand) {
+    let paths: Vec<String> = input_candidates(command)
+        .filter(|arg| arg.starts_with("buck-out/"))
+        .map(|arg| arg.to_owned())
+        .collect();
+    if paths.is_empty() {
+        return;
+    }
+
+    let result: anyhow::Result<()> = try {
+        let mut buckd = ctx
+            .connect_buckd(BuckdConnectOptions::existing_only_no_console())
+            .await?;
+        let outcome = buckd
+            .with_flushing()
+            .new_generic(
+                ctx.empty_client_context("log-what-ran")?,
+                NewGenericRequest::Materialize(MaterializeRequest { paths }),
+                None,
+            )
+            .await?;
+        if let CommandOutcome::Failure(..) = outcome {
+            Err(ReproBundleError::MaterializationFailed)?;
+        }
+    };
+    if let Err(e) = result {
+        tracing::warn!(
+            "Error materializing inputs, only copying those already on disk: {:#}",
+            e
+        );
+    }
+}
+
+fn run_script(identity: &str, command: &BundledCommand) -> String {
+    let mut lines = vec![
+        "#!/bin/sh".to_owned(),
+        format!("# Reproduces: {}", identity),
+        "set -e".to_owned(),
+        // Local commands run from the project root.
+        "cd \"$(dirname \"$0\")/root\"".to_owned(),
+    ];
+
+    let env: Vec<String> = command
+        .env
+        .iter()
+        .map(|(key, value)| format!("{}={}", key, value))
+        .collect();
+    lines.push(format!(
+        "exec env -i {}",
+        shlex::join(
+            env.iter()
+                .map(|e| e.as_str())
+                .chain(command.argv.iter().map(|a| a.as_str()))
+        )
+    ));
+
+    let mut script = lines.join("\n");
+    script.push('\n');
+    script
+}
+
+#[cfg(test)]
+mod tests {
+    use buck2_core::fs::paths::abs_path::AbsPathBuf;
+
+    use super::*;
+
+    fn compile_command() -> BundledCommand {
+        BundledCommand {
+            argv: vec![
+                "cc".to_owned(),
+                "-c".to_owned(),
+                "src/a b.c".to_owned(),
+                "--include=src/a.h".to_owned(),
+                "@buck-out/v2/gen/args".to_owned(),
+                "-o".to_owned(),
+                "buck-out/v2/gen/a.o".to_owned(),
+            ],
+            env: vec![("LANG".to_owned(), "C".to_owned())],
+        }
+    }
+
+    #[test]
+    fn test_run_script() {
+        assert_eq!(
+            r#"#!/bin/sh
+# Reproduces: root//:a (compile)
+set -e
+cd "$(dirname "$0")/root"
+exec env -i LANG=C cc -c 'src/a b.c' --include=src/a.h @buck-out/v2/gen/args -o buck-out/v2/gen/a.o
+"#,
+            run_script("root//:a (compile)", &compile_command())
+        );
+    }
+
+    #[test]
+    fn test_input_candidates() {
+        assert_eq!(
+            vec![
+                "cc",
+                "src/a b.c",
+                "src/a.h",
+                "buck-out/v2/gen/args",
+                "buck-out/v2/gen/a.o"
+            ],
+            input_candidates(&compile_command()).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_write_bundle() -> anyhow::Result<()> {
+        let tempdir = tempfile::tempdir()?;
+        let project_root = AbsPathBuf::new(tempdir.path().join("project"))?;
+        let bundle_dir = AbsPathBuf::new(tempdir.path().join("bundle"))?;
+        fs_util::create_dir_all(project_root.join("src"))?;
+        fs_util::write(project_root.join("src/a b.c"), "int main;")?;
+        fs_util::create_dir_all(project_root.join("buck-out/v2/gen"))?;
+        fs_util::write(project_root.join("buck-out/v2/gen/args"), "-O2")?;
+
+        let record = serde_json::json!({
+            "reason": "build",
+            "identity": "root//:a (compile)",
+            "reproducer": {"executor": "Local", "details": {"command": ["cc"], "env": {}}},
+        });
+        let action = BundledAction {
+            identity: "root//:a (compile)".to_owned(),
+            action_digest: None,
+            record: record.clone(),
+            command: Some(compile_command()),
+        };
+
+        let missing = write_bundle(&bundle_dir, &project_root, &action)?;
+
+        // The output doesn't exist yet, and isn't an input anyway.
+        assert_eq!(vec!["buck-out/v2/gen/a.o".to_owned()], missing);
+        assert_eq!(
+            "int main;",
+            fs_util::read_to_string(bundle_dir.join("root/src/a b.c"))?
+        );
+        assert_eq!(
+            "-O2",
+            fs_util::read_to_string(bundle_dir.join("root/buck-out/v2/gen/args"))?
+        );
+        assert!(!fs_util::try_exists(bundle_dir.join("root/src/a.h"))?);
+        assert_eq!(
+            record,
+            serde_json::from_str::<serde_json::Value>(&fs_util::read_to_string(
+                bundle_dir.join("action.json")
+            )?)?
+        );
+        assert_eq!(
+            run_script("root//:a (compile)", &compile_command()),
+            fs_util::read_to_string(bundle_dir.join("run.sh"))?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_bundle_remote() -> anyhow::Result<()> {
+        let tempdir = tempfile::tempdir()?;
+        let project_root = AbsPathBuf::new(tempdir.path().join("project"))?;
+        let bundle_dir = AbsPathBuf::new(tempdir.path().join("bundle"))?;
+
+        let action = BundledAction {
+            identity: "root//:a (compile)".to_owned(),
+            action_digest: Some("abc:10".to_owned()),
+            record: serde_json::json!({"identity": "root//:a (compile)"}),
+            command: None,
+        };
+        assert!(write_bundle(&bundle_dir, &project_root, &action)?.is_empty());
+
+        // Only the record, since there is nothing to run.
+        assert!(fs_util::try_exists(bundle_dir.join("action.json"))?);
+        assert!(!fs_util::try_exists(bundle_dir.join("run.sh"))?);
+        assert!(!fs_util::try_exists(bundle_dir.join("root"))?);
+        Ok(())
+    }
+}
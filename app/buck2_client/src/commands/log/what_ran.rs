@@ -13,6 +13,7 @@ use std::collections::HashMap;
 use anyhow::Context;
 use buck2_client_ctx::client_ctx::ClientCommandContext;
 use buck2_client_ctx::exit_result::ExitResult;
+use buck2_client_ctx::path_arg::PathArg;
 use buck2_client_ctx::stream_value::StreamValue;
 use buck2_data::re_platform::Property;
 use buck2_event_observer::what_ran;
@@ -24,13 +25,21 @@ use buck2_event_observer::what_ran::WhatRanOutputWriter;
 use buck2_event_observer::what_ran::WhatRanRelevantAction;
 use buck2_event_observer::what_ran::WhatRanState;
 use buck2_events::span::SpanId;
+use dupe::Dupe;
 use futures::stream::Stream;
 use futures::TryStreamExt;
 use indexmap::IndexMap;
 
 use crate::commands::log::options::EventLogOptions;
+use crate::commands::log::repro_bundle;
 use crate::commands::log::LogCommandOutputFormat;
 
+#[derive(Debug, thiserror::Error)]
+enum WhatRanCommandError {
+    #[error("`--format repro-bundle` requires an ACTION")]
+    NoAction,
+}
+
 /// Output everything Buck2 ran from selected invocation.
 ///
 /// The output is presented as a series of tab-delimited records with the following structure:
@@ -53,6 +62,10 @@ use crate::commands::log::LogCommandOutputFormat;
 /// To reproduce an action that ran locally, make sure your working directory is the project root
 /// (if unsure, use `buck2 root --kind project` to find it), then run the command. The command is
 /// already shell-quoted.
+///
+/// With `--format repro-bundle ACTION`, the action is instead exported as a directory that
+/// reproduces it without Buck2: its JSON record, and for a local command, a `run.sh` script with
+/// the exact argv and environment along with the files it takes as arguments.
 #[derive(Debug, clap::Parser)]
 pub struct WhatRanCommand {
     #[clap(flatten)]
@@ -65,6 +78,11 @@ pub struct WhatRanCommand {
 
 #[derive(Debug, clap::Parser)]
 pub struct WhatRanCommandCommon {
+    /// With `--format repro-bundle`, the action to export: its identity as printed by what-ran,
+    /// or its action digest. With other formats, this is the event log to read, like `PATH`.
+    #[clap(value_name = "ACTION")]
+    action: Option<String>,
+
     #[clap(flatten)]
     event_log: EventLogOptions,
 
@@ -75,12 +93,26 @@ pub struct WhatRanCommandCommon {
         ignore_case = true,
         arg_enum
     )]
-    pub output: LogCommandOutputFormat,
+    pub output: WhatRanOutputFormat,
+
+    /// With `--format repro-bundle`, the directory to write. Defaults to `repro-<digest>` for
+    /// remote actions, and `repro` otherwise.
+    #[clap(long, value_name = "PATH")]
+    bundle_dir: Option<PathArg>,
 
     #[clap(flatten)]
     pub options: WhatRanOptions,
 }
 
+#[derive(Debug, Clone, Copy, Dupe, clap::ArgEnum)]
+#[clap(rename_all = "kebab-case")]
+pub enum WhatRanOutputFormat {
+    Tabulated,
+    Json,
+    Csv,
+    ReproBundle,
+}
+
 struct WhatRanCommandOptions {
     options: WhatRanOptions,
 
@@ -93,24 +125,53 @@ impl WhatRanCommand {
         let Self {
             common:
                 WhatRanCommandCommon {
-                    event_log,
-                    mut output,
+                    mut event_log,
+                    output,
+                    mut action,
+                    bundle_dir,
                     options,
                 },
             failed,
         } = self;
 
         ctx.with_runtime(async move |ctx| {
-            let log_path = event_log.get(&ctx).await?;
+            // Only the repro bundle takes an action, otherwise this is the event log.
+            if !matches!(output, WhatRanOutputFormat::ReproBundle) {
+                if let Some(path) = action.take() {
+                    event_log.set_path(path)?;
+                }
+            }
 
+            let log_path = event_log.get(&ctx).await?;
             let (invocation, events) = log_path.unpack_stream().await?;
+            let options = WhatRanCommandOptions { options, failed };
+
+            let mut output = match output {
+                WhatRanOutputFormat::Tabulated => LogCommandOutputFormat::Tabulated,
+                WhatRanOutputFormat::Json => LogCommandOutputFormat::Json,
+                WhatRanOutputFormat::Csv => LogCommandOutputFormat::Csv,
+                WhatRanOutputFormat::ReproBundle => {
+                    let action = action.ok_or(WhatRanCommandError::NoAction)?;
+                    buck2_client_ctx::eprintln!(
+                        "Exporting action from: {}",
+                        invocation.display_command_line()
+                    )?;
+                    let mut finder = repro_bundle::ActionFinder::new(&action);
+                    WhatRanCommandState::execute(events, &mut finder, &options).await?;
+                    return repro_bundle::write_repro_bundle(
+                        &ctx,
+                        finder.found()?,
+                        bundle_dir.as_ref(),
+                    )
+                    .await;
+                }
+            };
 
             buck2_client_ctx::eprintln!(
                 "Showing commands from: {}",
                 invocation.display_command_line()
             )?;
 
-            let options = WhatRanCommandOptions { options, failed };
             WhatRanCommandState::execute(events, &mut output, &options).await?;
 
             anyhow::Ok(())
@@ -277,68 +338,7 @@ impl WhatRanOutputWriter for LogCommandOutputFormat {
                 )
             }
             Self::Json => {
-                let reproducer = match command.repro {
-                    CommandReproducer::CacheQuery(cache_hit) => JsonReproducer::CacheQuery {
-                        digest: &cache_hit.action_digest,
-                    },
-                    CommandReproducer::CacheHit(cache_hit) => JsonReproducer::Cache {
-                        digest: &cache_hit.action_digest,
-                        action_key: cache_hit.action_key.as_deref(),
-                    },
-                    CommandReproducer::ReExecute(re_execute) => JsonReproducer::Re {
-                        digest: &re_execute.action_digest,
-                        platform_properties: into_index_map(&re_execute.platform),
-                        action_key: re_execute.action_key.as_deref(),
-                    },
-                    CommandReproducer::LocalExecute(local_execute) => JsonReproducer::Local {
-                        command: local_execute.command.as_ref().map_or_else(
-                            || Cow::Owned(Vec::new()),
-                            |command| Cow::Borrowed(command.argv.as_ref()),
-                        ),
-                        env: local_execute
-                            .command
-                            .as_ref()
-                            .into_iter()
-                            .flat_map(|command| command.env.iter())
-                            .map(|entry| (entry.key.as_ref(), entry.value.as_ref()))
-                            .collect(),
-                    },
-                    CommandReproducer::WorkerExecute(worker_execute) => JsonReproducer::Worker {
-                        command: worker_execute.command.as_ref().map_or_else(
-                            || Cow::Owned(Vec::new()),
-                            |command| Cow::Borrowed(command.argv.as_ref()),
-                        ),
-                        env: worker_execute
-                            .command
-                            .as_ref()
-                            .into_iter()
-                            .flat_map(|command| command.env.iter())
-                            .map(|entry| (entry.key.as_ref(), entry.value.as_ref()))
-                            .collect(),
-                    },
-                    // TODO(ctolliday): use the worker_id as the `identity`, and add it to worker execution events.
-                    // Currently the identity is the first target that used the worker, which might be misleading.
-                    CommandReproducer::WorkerInit(worker_init) => JsonReproducer::WorkerInit {
-                        command: worker_init.command.as_ref().map_or_else(
-                            || Cow::Owned(Vec::new()),
-                            |command| Cow::Borrowed(command.argv.as_ref()),
-                        ),
-                        env: worker_init
-                            .command
-                            .as_ref()
-                            .into_iter()
-                            .flat_map(|command| command.env.iter())
-                            .map(|entry| (entry.key.as_ref(), entry.value.as_ref()))
-                            .collect(),
-                    },
-                };
-
-                let command = JsonCommand {
-                    reason: command.reason,
-                    identity: command.identity,
-                    reproducer,
-                    extra: command.extra.map(Into::into),
-                };
+                let command = json_command(command);
 
                 buck2_client_ctx::stdio::print_with_writer(|mut w| {
                     serde_json::to_writer(&mut w, &command)?;
@@ -368,6 +368,72 @@ impl WhatRanOutputWriter for LogCommandOutputFormat {
     }
 }
 
+/// The record of a command printed by `--format json`.
+pub(crate) fn json_command(command: WhatRanOutputCommand<'_>) -> JsonCommand<'_> {
+    let reproducer = match command.repro {
+        CommandReproducer::CacheQuery(cache_hit) => JsonReproducer::CacheQuery {
+            digest: &cache_hit.action_digest,
+        },
+        CommandReproducer::CacheHit(cache_hit) => JsonReproducer::Cache {
+            digest: &cache_hit.action_digest,
+            action_key: cache_hit.action_key.as_deref(),
+        },
+        CommandReproducer::ReExecute(re_execute) => JsonReproducer::Re {
+            digest: &re_execute.action_digest,
+            platform_properties: into_index_map(&re_execute.platform),
+            action_key: re_execute.action_key.as_deref(),
+        },
+        CommandReproducer::LocalExecute(local_execute) => JsonReproducer::Local {
+            command: local_execute.command.as_ref().map_or_else(
+                || Cow::Owned(Vec::new()),
+                |command| Cow::Borrowed(command.argv.as_ref()),
+            ),
+            env: local_execute
+                .command
+                .as_ref()
+                .into_iter()
+                .flat_map(|command| command.env.iter())
+                .map(|entry| (entry.key.as_ref(), entry.value.as_ref()))
+                .collect(),
+        },
+        CommandReproducer::WorkerExecute(worker_execute) => JsonReproducer::Worker {
+            command: worker_execute.command.as_ref().map_or_else(
+                || Cow::Owned(Vec::new()),
+                |command| Cow::Borrowed(command.argv.as_ref()),
+            ),
+            env: worker_execute
+                .command
+                .as_ref()
+                .into_iter()
+                .flat_map(|command| command.env.iter())
+                .map(|entry| (entry.key.as_ref(), entry.value.as_ref()))
+                .collect(),
+        },
+        // TODO(ctolliday): use the worker_id as the `identity`, and add it to worker execution events.
+        // Currently the identity is the first target that used the worker, which might be misleading.
+        CommandReproducer::WorkerInit(worker_init) => JsonReproducer::WorkerInit {
+            command: worker_init.command.as_ref().map_or_else(
+                || Cow::Owned(Vec::new()),
+                |command| Cow::Borrowed(command.argv.as_ref()),
+            ),
+            env: worker_init
+                .command
+                .as_ref()
+                .into_iter()
+                .flat_map(|command| command.env.iter())
+                .map(|entry| (entry.key.as_ref(), entry.value.as_ref()))
+                .collect(),
+        },
+    };
+
+    JsonCommand {
+        reason: command.reason,
+        identity: command.identity,
+        reproducer,
+        extra: command.extra.map(Into::into),
+    }
+}
+
 fn into_index_map(platform: &Option<buck2_data::RePlatform>) -> IndexMap<&str, &str> {
     platform.as_ref().map_or_else(IndexMap::new, |p| {
         p.properties
@@ -378,7 +444,7 @@ fn into_index_map(platform: &Option<buck2_data::RePlatform>) -> IndexMap<&str, &
 }
 
 #[derive(serde::Serialize)]
-struct JsonCommand<'a> {
+pub(crate) struct JsonCommand<'a> {
     reason: &'a str,
     identity: &'a str,
     reproducer: JsonReproducer<'a>,
@@ -546,4 +612,30 @@ mod tests {
         assert_eq!(expected, serde_json::to_string_pretty(&command)?);
         Ok(())
     }
+
+    #[test]
+    fn parse_repro_bundle_action() -> anyhow::Result<()> {
+        use clap::Parser;
+
+        let command = WhatRanCommand::try_parse_from([
+            "what-ran",
+            "--format",
+            "repro-bundle",
+            "root//:a (compile)",
+            "--recent",
+            "1",
+        ])?;
+        assert_eq!(Some("root//:a (compile)"), command.common.action.as_deref());
+
+        // The action comes before the optional event log path.
+        let command = WhatRanCommand::try_parse_from([
+            "what-ran",
+            "--format",
+            "repro-bundle",
+            "root//:a (compile)",
+            "log.pb.zst",
+        ])?;
+        assert_eq!(Some("root//:a (compile)"), command.common.action.as_deref());
+        Ok(())
+    }
 }
@@ -25,6 +25,9 @@ enum QueryOutputFormatArg {
     Dot,
     Json,
     DotCompact,
+    Build,
+    Proto,
+    Jsonproto,
 }
 
 /// Args common to all the query commands
@@ -53,9 +56,12 @@ pub(crate) struct CommonQueryOptions {
         long_help = "Output format (default: list). \n
            dot -  dot graph format. \n
            dot_compact - compact alternative to dot format. \n
-           json - JSON format.
+           json - JSON format. \n
+           build - BUCK file syntax, uquery only. \n
+           proto - binary `analysis.ActionGraphContainer` proto, as produced by Bazel, aquery only. \n
+           jsonproto - `analysis.ActionGraphContainer` as JSON, aquery only.
          ",
-        value_name = "dot|dot_compact|json",
+        value_name = "dot|dot_compact|json|build|proto|jsonproto",
         arg_enum
     )]
     output_format: Option<QueryOutputFormatArg>,
@@ -87,6 +93,9 @@ impl CommonQueryOptions {
             Some(QueryOutputFormatArg::Json) => QueryOutputFormat::Json,
             Some(QueryOutputFormatArg::Dot) => QueryOutputFormat::Dot,
             Some(QueryOutputFormatArg::DotCompact) => QueryOutputFormat::DotCompact,
+            Some(QueryOutputFormatArg::Build) => QueryOutputFormat::Build,
+            Some(QueryOutputFormatArg::Proto) => QueryOutputFormat::Proto,
+            Some(QueryOutputFormatArg::Jsonproto) => QueryOutputFormat::Jsonproto,
             None => {
                 if self.json {
                     QueryOutputFormat::Json
@@ -74,6 +74,28 @@ pub struct CqueryCommand {
     )]
     show_providers: bool,
 
+    #[clap(
+        long,
+        help = "For each edge between targets in the result (e.g. of `somepath` or `allpaths`), \
+                print the attribute which created it and the kind of the dep, and the configuration change if any"
+    )]
+    explain_edges: bool,
+
+    #[clap(
+        long,
+        help = "Print the wall time, result size, and package loading and configuration time of \
+                each subexpression after the results"
+    )]
+    profile: bool,
+
+    #[clap(
+        long,
+        help = "For each edge between targets in the result which changes the configuration, \
+                print the transition (or the execution platform for exec deps) and the \
+                configurations before and after it. Also labels the edges in dot output"
+    )]
+    show_transitions: bool,
+
     #[allow(rustdoc::bare_urls)]
     /// Enable deprecated `owner()` function behavior.
     ///
@@ -127,6 +149,9 @@ impl StreamingCommand for CqueryCommand {
                     show_providers: self.show_providers,
                     unstable_output_format,
                     correct_owner,
+                    explain_edges: self.explain_edges,
+                    profile: self.profile,
+                    show_transitions: self.show_transitions,
                 },
                 ctx.stdin()
                     .console_interaction_stream(&self.common_opts.console_opts),
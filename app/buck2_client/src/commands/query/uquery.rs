@@ -59,6 +59,20 @@ pub struct UqueryCommand {
 
     #[clap(flatten)]
     query_common: CommonQueryOptions,
+
+    #[clap(
+        long,
+        help = "For each edge between targets in the result (e.g. of `somepath` or `allpaths`), \
+                print the attribute which created it and the kind of the dep"
+    )]
+    explain_edges: bool,
+
+    #[clap(
+        long,
+        help = "Print the wall time, result size, and package loading and configuration time of \
+                each subexpression after the results"
+    )]
+    profile: bool,
 }
 
 #[async_trait]
@@ -84,6 +98,8 @@ impl StreamingCommand for UqueryCommand {
                     query_args,
                     context: Some(context),
                     output_attributes,
+                    explain_edges: self.explain_edges,
+                    profile: self.profile,
                     unstable_output_format,
                 },
                 ctx.stdin()
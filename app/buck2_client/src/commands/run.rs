@@ -7,6 +7,7 @@
  * of this source tree.
  */
 
+use std::collections::BTreeSet;
 use std::collections::HashMap;
 use std::fs::File;
 use std::io::Write;
@@ -31,6 +32,9 @@ use buck2_client_ctx::daemon::client::NoPartialResultHandler;
 use buck2_client_ctx::exit_result::ExitResult;
 use buck2_client_ctx::path_arg::PathArg;
 use buck2_client_ctx::streaming::StreamingCommand;
+use buck2_core::fs::fs_util;
+use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
+use buck2_core::fs::paths::forward_rel_path::ForwardRelativePath;
 use buck2_wrapper_common::BUCK2_WRAPPER_ENV_VAR;
 use buck2_wrapper_common::BUCK_WRAPPER_UUID_ENV_VAR;
 use serde::Serialize;
@@ -39,6 +43,9 @@ use thiserror::Error;
 use crate::commands::build::print_build_failed;
 use crate::commands::build::print_build_result;
 use crate::commands::build::print_build_succeeded;
+use crate::commands::build::watch::watch;
+use crate::commands::build::watch::WatchOptions;
+use crate::commands::build::watch::WatchedCommand;
 
 /// Build and run the selected target.
 ///
@@ -56,6 +63,10 @@ pub struct RunCommand {
     #[clap(flatten)]
     build_opts: CommonBuildOptions,
 
+    /// With `--watch`, the process is restarted only when the outputs of the target change.
+    #[clap(flatten)]
+    watch_opts: WatchOptions,
+
     #[clap(
         long = "command-args-file",
         help = "Write the command to a file instead of executing it.",
@@ -85,17 +96,17 @@ pub struct RunCommand {
     extra_run_args: Vec<String>,
 }
 
-#[async_trait]
-impl StreamingCommand for RunCommand {
-    const COMMAND_NAME: &'static str = "run";
-
-    async fn exec_impl(
-        self,
-        buckd: &mut BuckdClientConnector,
+impl RunCommand {
+    /// Build the target and return the command to run it, without the extra arguments. If the
+    /// build fails, the errors are printed and the `ExitResult` is returned instead.
+    async fn build(
+        &self,
+        buckd: &mut BuckdClientConnector<'_>,
         matches: &clap::ArgMatches,
         ctx: &mut ClientCommandContext<'_>,
-    ) -> ExitResult {
-        let context = ctx.client_context(matches, &self)?;
+        output_hashes_file: Option<String>,
+    ) -> anyhow::Result<Result<Vec<String>, ExitResult>> {
+        let context = ctx.client_context(matches, self)?;
         // TODO(rafaelc): fail fast on the daemon if the target doesn't have RunInfo
         let response = buckd
             .with_flushing()
@@ -115,7 +126,7 @@ impl StreamingCommand for RunCommand {
                     build_opts: Some(self.build_opts.to_proto()),
                     final_artifact_materializations: Materializations::Materialize as i32,
                     target_universe: Vec::new(),
-                    output_hashes_file: None,
+                    output_hashes_file,
                 },
                 ctx.stdin()
                     .console_interaction_stream(&self.common_opts.console_opts),
@@ -132,27 +143,74 @@ impl StreamingCommand for RunCommand {
         if !success {
             print_build_failed(&console)?;
         }
-        let response = response??;
+        let response = match response? {
+            CommandOutcome::Success(response) => response,
+            CommandOutcome::Failure(exit_result) => return Ok(Err(exit_result)),
+        };
         print_build_result(&console, &response.errors)?;
 
         if !success {
-            return ExitResult::from_errors(&response.errors);
+            return Ok(Err(ExitResult::from_errors(&response.errors)));
         }
 
         if response.build_targets.len() > 1 {
-            return ExitResult::err(RunCommandError::MultipleTargets.into());
+            return Err(RunCommandError::MultipleTargets.into());
         }
 
         // TODO(rafaelc): use absolute paths for artifacts in the cli
         //      we should run the command from the current dir, not the project root
         if response.build_targets.is_empty() || response.build_targets[0].run_args.is_empty() {
-            return ExitResult::err(RunCommandError::NonBinaryRule(self.target).into());
+            return Err(RunCommandError::NonBinaryRule(self.target.clone()).into());
         }
-        let mut run_args = response.build_targets[0].run_args.clone();
-        run_args.extend(self.extra_run_args);
 
         print_build_succeeded(&console, ctx)?;
 
+        Ok(Ok(response.build_targets[0].run_args.clone()))
+    }
+}
+
+#[async_trait]
+impl StreamingCommand for RunCommand {
+    const COMMAND_NAME: &'static str = "run";
+
+    async fn exec_impl(
+        self,
+        buckd: &mut BuckdClientConnector,
+        matches: &clap::ArgMatches,
+        ctx: &mut ClientCommandContext<'_>,
+    ) -> ExitResult {
+        if self.watch_opts.watch {
+            if self.command_args_file.is_some() || self.emit_shell {
+                return ExitResult::err(RunCommandError::WatchWithExecOptions.into());
+            }
+            let tmp_dir = ctx.paths()?.tmp_dir();
+            fs_util::create_dir_all(&tmp_dir)?;
+            let output_hashes_file = tmp_dir.join(ForwardRelativePath::new(&format!(
+                "run_watch_output_hashes_{}.json",
+                ctx.trace_id
+            ))?);
+            let watch_opts = self.watch_opts.clone();
+            return watch(
+                RunWatcher {
+                    cmd: self,
+                    output_hashes_file,
+                    output_hashes: None,
+                    process: None,
+                },
+                &watch_opts,
+                buckd,
+                matches,
+                ctx,
+            )
+            .await;
+        }
+
+        let mut run_args = match self.build(buckd, matches, ctx, None).await? {
+            Ok(run_args) => run_args,
+            Err(exit_result) => return exit_result,
+        };
+        run_args.extend(self.extra_run_args);
+
         // Special case for recursive invocations of buck; `BUCK2_WRAPPER` is set by wrapper scripts that execute
         // Buck2. We're not a wrapper script, so we unset it to prevent `run` from inheriting it.
         std::env::remove_var(BUCK2_WRAPPER_ENV_VAR);
@@ -250,4 +308,139 @@ pub enum RunCommandError {
         "`buck2 run` only supports a single target, but multiple targets were requested. Only executing the first one built."
     )]
     MultipleTargets,
+    #[error("`--watch` cannot be used with `--command-args-file` or `--emit-shell`")]
+    WatchWithExecOptions,
+}
+
+/// State kept between the iterations of `buck2 run --watch`.
+struct RunWatcher {
+    cmd: RunCommand,
+    /// Where the daemon writes the outputs of the target and their digests.
+    output_hashes_file: AbsNormPathBuf,
+    /// The outputs the current process was started from.
+    output_hashes: Option<BTreeSet<String>>,
+    process: Option<tokio::process::Child>,
+}
+
+impl RunWatcher {
+    fn read_output_hashes(&self) -> anyhow::Result<BTreeSet<String>> {
+        // The order of the entries isn't stable, so we compare them as a set.
+        let entries: Vec<serde_json::Value> =
+            serde_json::from_slice(&fs_util::read(&self.output_hashes_file)?)
+                .context("Failed to parse output hashes file")?;
+        Ok(entries.iter().map(|entry| entry.to_string()).collect())
+    }
+}
+
+impl Drop for RunWatcher {
+    fn drop(&mut self) {
+        // The file doesn't exist if no build got far enough to write it.
+        let _ignored = fs_util::remove_file(&self.output_hashes_file);
+    }
+}
+
+#[async_trait]
+impl WatchedCommand for RunWatcher {
+    type Command = RunCommand;
+
+    fn command(&self) -> &RunCommand {
+        &self.cmd
+    }
+
+    async fn run_once(
+        &mut self,
+        buckd: &mut BuckdClientConnector,
+        matches: &clap::ArgMatches,
+        ctx: &mut ClientCommandContext<'_>,
+    ) -> ExitResult {
+        let output_hashes_file = self.output_hashes_file.to_string();
+        // If the build fails, the previous process keeps running.
+        let mut run_args = match self
+            .cmd
+            .build(buckd, matches, ctx, Some(output_hashes_file))
+            .await?
+        {
+            Ok(run_args) => run_args,
+            Err(exit_result) => return exit_result,
+        };
+        run_args.extend(self.cmd.extra_run_args.iter().cloned());
+
+        let console = self.cmd.common_opts.console_opts.final_console();
+        let output_hashes = self.read_output_hashes()?;
+        if self.output_hashes.as_ref() == Some(&output_hashes) {
+            console.print_stderr("Outputs unchanged, not restarting")?;
+            return ExitResult::success();
+        }
+
+        if let Some(mut process) = self.process.take() {
+            console.print_stderr("Outputs changed, restarting")?;
+            process
+                .kill()
+                .await
+                .context("Failed to stop the previous process")?;
+        }
+
+        let mut command = tokio::process::Command::new(&run_args[0]);
+        command
+            .args(&run_args[1..])
+            .env("BUCK_RUN_BUILD_ID", ctx.trace_id.to_string())
+            .env_remove(BUCK2_WRAPPER_ENV_VAR)
+            .env_remove(BUCK_WRAPPER_UUID_ENV_VAR)
+            .kill_on_drop(true);
+        if let Some(chdir) = &self.cmd.chdir {
+            command.current_dir(chdir.resolve(&ctx.working_dir).as_path());
+        }
+        self.process = Some(
+            command
+                .spawn()
+                .with_context(|| format!("Failed to start `{}`", run_args[0]))?,
+        );
+        self.output_hashes = Some(output_hashes);
+
+        ExitResult::success()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::*;
+
+    #[test]
+    fn test_run_watcher_output_hashes() -> anyhow::Result<()> {
+        let tempdir = tempfile::tempdir()?;
+        let output_hashes_file = AbsNormPathBuf::new(tempdir.path().join("hashes.json"))?;
+        let watcher = RunWatcher {
+            cmd: RunCommand::try_parse_from(["run", "--watch", "cell//pkg:bin"])?,
+            output_hashes_file: output_hashes_file.clone(),
+            output_hashes: None,
+            process: None,
+        };
+
+        fs_util::write(
+            &output_hashes_file,
+            r#"[{"path": "a", "digest": "1:1"}, {"path": "b", "digest": "2:2"}]"#,
+        )?;
+        let first = watcher.read_output_hashes()?;
+        assert_eq!(2, first.len());
+
+        // The same outputs in a different order.
+        fs_util::write(
+            &output_hashes_file,
+            r#"[{"path": "b", "digest": "2:2"}, {"path": "a", "digest": "1:1"}]"#,
+        )?;
+        assert_eq!(first, watcher.read_output_hashes()?);
+
+        fs_util::write(
+            &output_hashes_file,
+            r#"[{"path": "a", "digest": "3:3"}, {"path": "b", "digest": "2:2"}]"#,
+        )?;
+        assert_ne!(first, watcher.read_output_hashes()?);
+
+        // The file is deleted once we stop watching.
+        drop(watcher);
+        assert!(!fs_util::try_exists(&output_hashes_file)?);
+        Ok(())
+    }
 }
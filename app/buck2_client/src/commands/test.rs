@@ -7,8 +7,12 @@
  * of this source tree.
  */
 
+use std::collections::HashMap;
+
 use anyhow::Context;
 use async_trait::async_trait;
+use buck2_cli_proto::test_request::TestCases;
+use buck2_cli_proto::test_request::TestShard;
 use buck2_cli_proto::CounterWithExamples;
 use buck2_cli_proto::TestRequest;
 use buck2_cli_proto::TestSessionOptions;
@@ -26,8 +30,11 @@ use buck2_client_ctx::output_destination_arg::OutputDestinationArg;
 use buck2_client_ctx::path_arg::PathArg;
 use buck2_client_ctx::stdio::eprint_line;
 use buck2_client_ctx::streaming::StreamingCommand;
+use buck2_client_ctx::subscribers::subscriber::EventSubscriber;
 use buck2_client_ctx::subscribers::superconsole::test::span_from_build_failure_count;
 use buck2_client_ctx::subscribers::superconsole::test::TestCounterColumn;
+use buck2_client_ctx::subscribers::test_report;
+use buck2_client_ctx::subscribers::test_report::TestReportWriter;
 use buck2_core::fs::fs_util;
 use buck2_core::fs::working_dir::WorkingDir;
 use gazebo::prelude::*;
@@ -35,6 +42,11 @@ use superconsole::Line;
 use superconsole::Span;
 
 use crate::commands::build::print_build_result;
+use crate::commands::build::rerun_failed::previous_failures;
+use crate::commands::build::rerun_failed::RerunFailedOptions;
+use crate::commands::build::watch::watch;
+use crate::commands::build::watch::WatchOptions;
+use crate::commands::build::watch::WatchedCommand;
 
 fn forward_output_to_path(
     output: &str,
@@ -45,6 +57,43 @@ fn forward_output_to_path(
         .context("Failed to write test executor output to path")
 }
 
+fn percentage(hit: u64, found: u64) -> String {
+    if found == 0 {
+        return "-".to_owned();
+    }
+    format!("{:.1}%", hit as f64 * 100.0 / found as f64)
+}
+
+fn print_coverage(
+    console: &FinalConsole,
+    coverage: &buck2_cli_proto::test_response::Coverage,
+) -> anyhow::Result<()> {
+    console.print_stderr(&format!(
+        "Coverage: {} of lines ({}/{}), {} of functions ({}/{}), {} of branches ({}/{}) in {} files",
+        percentage(coverage.lines_hit, coverage.lines_found),
+        coverage.lines_hit,
+        coverage.lines_found,
+        percentage(coverage.functions_hit, coverage.functions_found),
+        coverage.functions_hit,
+        coverage.functions_found,
+        percentage(coverage.branches_hit, coverage.branches_found),
+        coverage.branches_hit,
+        coverage.branches_found,
+        coverage.files,
+    ))?;
+    console.print_stderr(&format!("Coverage report: {}", coverage.lcov_path))?;
+    if !coverage.errors.is_empty() {
+        console.print_warning(&format!(
+            "{} TEST EXECUTIONS WITHOUT COVERAGE",
+            coverage.errors.len()
+        ))?;
+        for error in &coverage.errors {
+            console.print_warning(&format!("  {}", error))?;
+        }
+    }
+    Ok(())
+}
+
 fn print_error_counter(
     console: &FinalConsole,
     counter: &CounterWithExamples,
@@ -74,6 +123,12 @@ pub struct TestCommand {
     #[clap(flatten)]
     build_opts: CommonBuildOptions,
 
+    #[clap(flatten)]
+    watch_opts: WatchOptions,
+
+    #[clap(flatten)]
+    rerun_failed_opts: RerunFailedOptions,
+
     #[clap(
         long = "exclude",
         multiple_values = true,
@@ -108,10 +163,15 @@ If include patterns are present, regardless of whether exclude patterns are pres
     #[clap(long = "deep")]
     deep: bool,
 
-    // ignored. only for e2e tests. compatibility with v1.
-    #[clap(long = "xml")]
-    #[allow(unused)] // for v1 compat
-    xml: Option<String>,
+    /// Write a JUnit XML report of the test results to this path.
+    #[clap(long = "xml", value_name = "PATH")]
+    xml: Option<PathArg>,
+
+    /// Write a JSON report of the test results to this path. Like the JUnit XML report, it
+    /// contains every test target and test case, with their statuses, durations, the end of their
+    /// output and the files they wrote to `$TEST_OUTPUTS_DIR`.
+    #[clap(long, value_name = "PATH")]
+    test_report: Option<PathArg>,
 
     /// Will allow tests that are compatible with RE (setup to run from the repo root and
     /// use relative paths) to run from RE.
@@ -123,9 +183,40 @@ If include patterns are present, regardless of whether exclude patterns are pres
     #[clap(long, group = "re_options", alias = "unstable-force-tests-on-re")]
     unstable_allow_all_tests_on_re: bool,
 
+    /// Run the tests even if the test result cache, enabled by `test.cache_results`, has a
+    /// passing result for them. The new results are still stored in the cache.
+    #[clap(long)]
+    no_cache_test_results: bool,
+
+    /// Collect the code coverage of the tests. Tests write coverage data to the directory in
+    /// `$COVERAGE_DIR`, which is merged into a single LCOV report.
+    #[clap(long)]
+    coverage: bool,
+
     #[clap(name = "TARGET_PATTERNS", help = "Patterns to test")]
     patterns: Vec<String>,
 
+    /// Only run the test targets of this shard, starting at 0, when partitioning them across
+    /// `--shard-count` shards, for example to split tests across CI machines. Every shard
+    /// configures and analyzes all the test targets, but only builds and runs its own.
+    #[clap(long, requires = "shard-count", value_name = "INDEX")]
+    shard_index: Option<u32>,
+
+    /// The number of shards to partition the test targets across.
+    #[clap(long, requires = "shard-index", value_name = "COUNT")]
+    shard_count: Option<u32>,
+
+    /// A JUnit XML or JSON test report of an earlier run (see `--xml` and `--test-report`), to
+    /// balance the shards by the durations of the test targets. Without it, test targets are
+    /// assigned to shards by a stable hash of their label.
+    #[clap(long, requires = "shard-count", value_name = "PATH")]
+    shard_timings: Option<PathArg>,
+
+    /// Print the shard, the label and the assumed duration in milliseconds of every test target,
+    /// without building or running tests.
+    #[clap(long, requires = "shard-count")]
+    shard_dry_run: bool,
+
     /// Writes the test executor stdout to the provided path
     ///
     /// --test-executor-stdout=- will write to stdout
@@ -158,15 +249,49 @@ If include patterns are present, regardless of whether exclude patterns are pres
     test_executor_args: Vec<String>,
 }
 
-#[async_trait]
-impl StreamingCommand for TestCommand {
-    const COMMAND_NAME: &'static str = "test";
+impl TestCommand {
+    /// Takes the options which configure and select the tests from an earlier invocation, to run
+    /// again what failed in it.
+    fn with_options_of(mut self, earlier: TestCommand) -> Self {
+        self.common_opts.config_opts = earlier.common_opts.config_opts;
+        self.include = earlier.include;
+        self.exclude = earlier.exclude;
+        self.always_exclude = earlier.always_exclude;
+        self.build_filtered_targets = earlier.build_filtered_targets;
+        self.test_executor_args = earlier.test_executor_args;
+        self
+    }
 
-    async fn exec_impl(
-        self,
-        buckd: &mut BuckdClientConnector,
+    fn shard(&self, working_dir: &WorkingDir) -> anyhow::Result<Option<TestShard>> {
+        let (index, count) = match (self.shard_index, self.shard_count) {
+            (Some(index), Some(count)) => (index, count),
+            _ => return Ok(None),
+        };
+        let durations_ms = match &self.shard_timings {
+            Some(path) => {
+                let path = path.resolve(working_dir);
+                let report = fs_util::read_to_string(&path)?;
+                test_report::read_durations(&report)
+                    .with_context(|| format!("Error reading test durations from `{}`", path))?
+            }
+            None => HashMap::new(),
+        };
+        Ok(Some(TestShard {
+            index,
+            count,
+            durations_ms,
+            dry_run: self.shard_dry_run,
+        }))
+    }
+
+    /// Runs the tests, only running the given test cases of these targets again if the test runner
+    /// supports it.
+    async fn test(
+        &self,
+        buckd: &mut BuckdClientConnector<'_>,
         matches: &clap::ArgMatches,
         ctx: &mut ClientCommandContext<'_>,
+        rerun_test_cases: HashMap<String, TestCases>,
     ) -> ExitResult {
         let context = ctx.client_context(matches, &self)?;
         let response = buckd
@@ -177,9 +302,9 @@ impl StreamingCommand for TestCommand {
                     target_patterns: self
                         .patterns
                         .map(|pat| buck2_data::TargetPattern { value: pat.clone() }),
-                    test_executor_args: self.test_executor_args,
-                    excluded_labels: self.exclude,
-                    included_labels: self.include,
+                    test_executor_args: self.test_executor_args.clone(),
+                    excluded_labels: self.exclude.clone(),
+                    included_labels: self.include.clone(),
                     always_exclude: self.always_exclude,
                     build_filtered_targets: self.build_filtered_targets,
                     // we don't currently have a different flag for this, so just use the build one.
@@ -190,7 +315,11 @@ impl StreamingCommand for TestCommand {
                             || self.unstable_allow_all_tests_on_re,
                         force_use_project_relative_paths: self.unstable_allow_all_tests_on_re,
                         force_run_from_project_root: self.unstable_allow_all_tests_on_re,
+                        no_cache_test_results: self.no_cache_test_results,
+                        coverage: self.coverage,
                     }),
+                    shard: self.shard(&ctx.working_dir)?,
+                    rerun_test_cases,
                 },
                 ctx.stdin()
                     .console_interaction_stream(&self.common_opts.console_opts),
@@ -211,9 +340,28 @@ impl StreamingCommand for TestCommand {
         let failed = statuses.failed.as_ref().context("Missing `failed`")?;
         let fatals = statuses.fatals.as_ref().context("Missing `fatals`")?;
         let skipped = statuses.skipped.as_ref().context("Missing `skipped`")?;
+        // Older daemons don't report flaky tests.
+        let flaky = statuses.flaky.clone().unwrap_or_default();
 
         let console = self.common_opts.console_opts.final_console();
         print_build_result(&console, &response.errors)?;
+
+        if self.shard_dry_run {
+            let mut assignment = response.shard_assignment.iter().collect::<Vec<_>>();
+            assignment.sort_by_key(|a| (a.shard, &a.target));
+            for a in assignment {
+                buck2_client_ctx::println!(
+                    "{}\t{}\t{}",
+                    a.shard,
+                    a.target,
+                    a.duration_ms.map_or_else(String::new, |d| d.to_string())
+                )?;
+            }
+            if response.errors.is_empty() {
+                return ExitResult::success();
+            }
+            return ExitResult::from_errors(&response.errors);
+        }
         if !response.errors.is_empty() {
             console.print_error(&format!("{} BUILDS FAILED", response.errors.len()))?;
         }
@@ -238,24 +386,38 @@ impl StreamingCommand for TestCommand {
             line.push(column.to_span_from_test_statuses(statuses)?);
             line.push(Span::new_unstyled_lossy(". "));
         }
+        if flaky.count > 0 {
+            line.push(TestCounterColumn::FLAKY.to_span_from_test_statuses(statuses)?);
+            line.push(Span::new_unstyled_lossy(". "));
+        }
         line.push(span_from_build_failure_count(response.errors.len())?);
         eprint_line(&line)?;
 
         print_error_counter(&console, listing_failed, "LISTINGS FAILED", "⚠")?;
         print_error_counter(&console, failed, "TESTS FAILED", "✗")?;
         print_error_counter(&console, fatals, "TESTS FATALS", "⚠")?;
-        if passed.count + failed.count + fatals.count + skipped.count == 0 {
+        if flaky.count > 0 {
+            console.print_warning(&format!("{} TESTS FLAKY", flaky.count))?;
+            for test_name in &flaky.example_tests {
+                console.print_warning(&format!("  ↯ {}", test_name))?;
+            }
+        }
+        if passed.count + failed.count + fatals.count + skipped.count + flaky.count == 0 {
             console.print_warning("NO TESTS RAN")?;
         }
 
+        if let Some(coverage) = &response.coverage {
+            print_coverage(&console, coverage)?;
+        }
+
         let info_messages = response.executor_info_messages;
         for message in info_messages {
             console.print_stderr(message.as_str())?;
         }
 
-        match self.test_executor_stderr {
+        match &self.test_executor_stderr {
             Some(OutputDestinationArg::Path(path)) => {
-                forward_output_to_path(&response.executor_stderr, &path, &ctx.working_dir)?;
+                forward_output_to_path(&response.executor_stderr, path, &ctx.working_dir)?;
             }
             Some(OutputDestinationArg::Stream) => {
                 console.print_error(&response.executor_stderr)?;
@@ -269,9 +431,9 @@ impl StreamingCommand for TestCommand {
             ExitResult::from_errors(&response.errors)
         };
 
-        match self.test_executor_stdout {
+        match &self.test_executor_stdout {
             Some(OutputDestinationArg::Path(path)) => {
-                forward_output_to_path(&response.executor_stdout, &path, &ctx.working_dir)?;
+                forward_output_to_path(&response.executor_stdout, path, &ctx.working_dir)?;
                 exit_result
             }
             Some(OutputDestinationArg::Stream) => {
@@ -280,6 +442,38 @@ impl StreamingCommand for TestCommand {
             _ => exit_result,
         }
     }
+}
+
+#[async_trait]
+impl StreamingCommand for TestCommand {
+    const COMMAND_NAME: &'static str = "test";
+
+    async fn exec_impl(
+        self,
+        buckd: &mut BuckdClientConnector,
+        matches: &clap::ArgMatches,
+        ctx: &mut ClientCommandContext<'_>,
+    ) -> ExitResult {
+        if self.watch_opts.watch {
+            let watch_opts = self.watch_opts.clone();
+            return watch(self, &watch_opts, buckd, matches, ctx).await;
+        }
+        if self.rerun_failed_opts.rerun_failed {
+            let rerun = previous_failures::<Self>(ctx, Self::COMMAND_NAME).await?;
+            let mut cmd = self.with_options_of(rerun.command);
+            cmd.patterns = rerun.targets;
+            let rerun_test_cases = rerun
+                .test_cases
+                .into_iter()
+                .map(|(target, cases)| {
+                    let names = cases.into_iter().collect();
+                    (target, TestCases { names })
+                })
+                .collect();
+            return cmd.test(buckd, &rerun.matches, ctx, rerun_test_cases).await;
+        }
+        self.test(buckd, matches, ctx, HashMap::new()).await
+    }
 
     fn console_opts(&self) -> &CommonConsoleOptions {
         &self.common_opts.console_opts
@@ -292,4 +486,76 @@ impl StreamingCommand for TestCommand {
     fn common_opts(&self) -> &CommonBuildConfigurationOptions {
         &self.common_opts.config_opts
     }
+
+    fn extra_subscribers(
+        &self,
+        ctx: &ClientCommandContext<'_>,
+    ) -> anyhow::Result<Vec<Box<dyn EventSubscriber>>> {
+        if self.xml.is_none() && self.test_report.is_none() {
+            return Ok(Vec::new());
+        }
+        Ok(vec![Box::new(TestReportWriter::new(
+            self.xml.as_ref().map(|p| p.resolve(&ctx.working_dir)),
+            self.test_report
+                .as_ref()
+                .map(|p| p.resolve(&ctx.working_dir)),
+        ))])
+    }
+}
+
+#[async_trait]
+impl WatchedCommand for TestCommand {
+    type Command = Self;
+
+    fn command(&self) -> &Self {
+        self
+    }
+
+    async fn run_once(
+        &mut self,
+        buckd: &mut BuckdClientConnector,
+        matches: &clap::ArgMatches,
+        ctx: &mut ClientCommandContext<'_>,
+    ) -> ExitResult {
+        self.test(buckd, matches, ctx, HashMap::new()).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::*;
+
+    fn parse(args: &[&str]) -> anyhow::Result<TestCommand> {
+        Ok(TestCommand::from_iter_safe(
+            std::iter::once("program").chain(args.iter().copied()),
+        )?)
+    }
+
+    #[test]
+    fn test_rerun_keeps_options() -> anyhow::Result<()> {
+        let earlier = parse(&[
+            "-c",
+            "a.b=c",
+            "--include",
+            "unit",
+            "--exclude",
+            "slow",
+            "--always-exclude",
+            "--build-filtered",
+            "//:t",
+            "--",
+            "--env",
+            "X=1",
+        ])?;
+        let rerun = parse(&["--rerun-failed"])?.with_options_of(earlier);
+        assert_eq!(vec!["a.b=c"], rerun.common_opts.config_opts.config_values);
+        assert_eq!(vec!["unit"], rerun.include);
+        assert_eq!(vec!["slow"], rerun.exclude);
+        assert!(rerun.always_exclude);
+        assert!(rerun.build_filtered_targets);
+        assert_eq!(vec!["--env", "X=1"], rerun.test_executor_args);
+        Ok(())
+    }
 }
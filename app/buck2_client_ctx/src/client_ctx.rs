@@ -146,6 +146,7 @@ impl<'a> ClientCommandContext<'a> {
                 .map(|path| path.to_string())
                 .collect(),
             target_call_stacks: config_opts.target_call_stacks,
+            record_execution_log: cmd.event_log_opts().execution_log.is_some(),
             ..self.empty_client_context(cmd.logging_name())?
         })
     }
@@ -186,6 +187,7 @@ impl<'a> ClientCommandContext<'a> {
             buck2_hard_error: BUCK2_HARD_ERROR_ENV_VAR.get()?.cloned().unwrap_or_default(),
             command_name: command_name.to_owned(),
             exit_when_different_state: false,
+            record_execution_log: false,
             client_metadata: self
                 .client_metadata
                 .iter()
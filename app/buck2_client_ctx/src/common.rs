@@ -128,6 +128,11 @@ pub struct CommonDaemonCommandOptions {
     /// regarding the stability of the format.
     #[clap(long, value_name = "PATH")]
     pub(crate) unstable_write_invocation_record: Option<PathArg>,
+
+    /// Write a record of every command executed by the build (as JSON lines) to this path. The
+    /// format is stable and versioned.
+    #[clap(long, value_name = "PATH")]
+    pub(crate) execution_log: Option<PathArg>,
 }
 
 impl CommonDaemonCommandOptions {
@@ -137,6 +142,7 @@ impl CommonDaemonCommandOptions {
             no_event_log: false,
             write_build_id: None,
             unstable_write_invocation_record: None,
+            execution_log: None,
         };
         &DEFAULT
     }
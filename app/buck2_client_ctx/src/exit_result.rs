@@ -140,6 +140,24 @@ impl ExitResult {
         }
     }
 
+    /// Print the buffered stdout and the error (if any), but don't exit. Returns whether this
+    /// was a success. Used by commands which keep running after a result (`--watch`).
+    pub fn report_without_exit(self) -> anyhow::Result<bool> {
+        crate::stdio::print_bytes(&self.stdout)?;
+        match self.variant {
+            ExitResultVariant::Status(ExitCode::Success) => Ok(true),
+            ExitResultVariant::Status(_) => Ok(false),
+            ExitResultVariant::Buck2RunExec(args) => Err(anyhow::anyhow!(
+                "Cannot exec `{}` without exiting",
+                args.prog
+            )),
+            ExitResultVariant::StatusWithErr(_, e) => {
+                crate::eprintln!("Command failed: {:#}", e)?;
+                Ok(false)
+            }
+        }
+    }
+
     pub fn from_errors(errors: &[buck2_data::ErrorReport]) -> Self {
         let mut has_infra = false;
         let mut has_user = false;
@@ -32,6 +32,7 @@ use crate::subscribers::get::get_console_with_root;
 use crate::subscribers::get::try_get_build_graph_stats;
 use crate::subscribers::get::try_get_build_id_writer;
 use crate::subscribers::get::try_get_event_log_subscriber;
+use crate::subscribers::get::try_get_execution_log_writer;
 use crate::subscribers::get::try_get_re_log_subscriber;
 use crate::subscribers::recorder::try_get_invocation_recorder;
 use crate::subscribers::subscriber::EventSubscriber;
@@ -69,6 +70,9 @@ fn default_subscribers<'a, T: StreamingCommand>(
     if let Some(build_id_writer) = try_get_build_id_writer(cmd.event_log_opts(), ctx)? {
         subscribers.push(build_id_writer)
     }
+    if let Some(execution_log_writer) = try_get_execution_log_writer(cmd.event_log_opts(), ctx)? {
+        subscribers.push(execution_log_writer)
+    }
     if let Some(build_graph_stats) = try_get_build_graph_stats(cmd, ctx)? {
         subscribers.push(build_graph_stats)
     }
@@ -81,10 +85,23 @@ fn default_subscribers<'a, T: StreamingCommand>(
     )?;
     subscribers.push(recorder);
 
-    subscribers.extend(cmd.extra_subscribers());
+    subscribers.extend(cmd.extra_subscribers(ctx)?);
     Ok(subscribers)
 }
 
+/// Connect to the running daemon with the subscribers (console, event log, ...) of a new
+/// invocation of `cmd`. This is used to run a command several times in one invocation.
+pub async fn connect_for_command<'a, T: StreamingCommand>(
+    cmd: &T,
+    ctx: &ClientCommandContext<'a>,
+) -> anyhow::Result<BuckdClientConnector<'a>> {
+    ctx.connect_buckd(BuckdConnectOptions {
+        subscribers: default_subscribers(cmd, ctx)?,
+        constraints: BuckdConnectConstraints::ExistingOnly,
+    })
+    .await
+}
+
 /// Trait to generalize the behavior of executable buck2 commands that rely on a server.
 /// This trait is most helpful when the command wants a superconsole, to stream events, etc.
 /// However, this is the most robustly tested of our code paths, and there is little cost to defaulting to it.
@@ -119,8 +136,11 @@ pub trait StreamingCommand: Sized + Send + Sync {
 
     fn common_opts(&self) -> &CommonBuildConfigurationOptions;
 
-    fn extra_subscribers(&self) -> Vec<Box<dyn EventSubscriber>> {
-        vec![]
+    fn extra_subscribers(
+        &self,
+        _ctx: &ClientCommandContext<'_>,
+    ) -> anyhow::Result<Vec<Box<dyn EventSubscriber>>> {
+        Ok(vec![])
     }
 
     fn sanitize_argv(&self, argv: Argv) -> SanitizedArgv {
@@ -0,0 +1,363 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! A log of every command executed by a build, as one JSON object per line.
+//!
+//! Unlike the event log, this format is stable: fields may be added, but any other change bumps
+//! the version. See `docs/users/build_observability/execution_log.md`.
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::BufWriter;
+use std::io::Write;
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use anyhow::Context;
+use async_trait::async_trait;
+use buck2_common::convert::ProstDurationExt;
+use buck2_core::fs::paths::abs_path::AbsPathBuf;
+use buck2_data::action_digest_inputs::input;
+use buck2_data::command_execution::Status;
+use buck2_data::command_execution_kind::Command;
+use buck2_data::CacheHitType;
+use buck2_event_observer::display::display_action_key;
+use buck2_event_observer::display::TargetDisplayOptions;
+use buck2_events::BuckEvent;
+use serde::Serialize;
+
+use crate::subscribers::subscriber::EventSubscriber;
+
+/// Bumped on any change to the records that is not the addition of a field.
+const EXECUTION_LOG_VERSION: u32 = 1;
+
+#[derive(Serialize, Debug, PartialEq)]
+struct Input<'a> {
+    path: &'a str,
+    digest: Option<&'a str>,
+    symlink_target: Option<&'a str>,
+    is_executable: bool,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+struct Output<'a> {
+    path: &'a str,
+    digest: Option<&'a str>,
+}
+
+/// One command executed, or looked up in a cache, by an action.
+#[derive(Serialize, Debug)]
+struct ExecutionLogRecord<'a> {
+    version: u32,
+    target: Option<String>,
+    category: &'a str,
+    identifier: &'a str,
+    action_digest: Option<&'a str>,
+    /// `local`, `remote`, `cache` or `worker`.
+    executor: &'static str,
+    /// `action_cache` or `remote_dep_file_cache`, for cache hits.
+    cache_hit_type: Option<&'static str>,
+    /// `success`, `failure`, `timeout`, `error` or `cancelled`.
+    status: &'static str,
+    exit_code: Option<i32>,
+    argv: &'a [String],
+    env: BTreeMap<&'a str, &'a str>,
+    working_directory: Option<&'a str>,
+    inputs: Vec<Input<'a>>,
+    outputs: Vec<Output<'a>>,
+    start_time_ms: Option<u64>,
+    wall_time_ms: Option<u64>,
+    execution_time_ms: Option<u64>,
+    queue_time_ms: Option<u64>,
+    input_materialization_time_ms: Option<u64>,
+}
+
+fn millis(duration: Option<&prost_types::Duration>) -> Option<u64> {
+    duration
+        .and_then(|d| d.try_into_duration().ok())
+        .and_then(|d| u64::try_from(d.as_millis()).ok())
+}
+
+fn env(entries: &[buck2_data::EnvironmentEntry]) -> BTreeMap<&str, &str> {
+    entries
+        .iter()
+        .map(|e| (e.key.as_str(), e.value.as_str()))
+        .collect()
+}
+
+fn records(action: &buck2_data::ActionExecutionEnd) -> Vec<ExecutionLogRecord<'_>> {
+    let target = action
+        .key
+        .as_ref()
+        .and_then(|key| display_action_key(key, TargetDisplayOptions::for_log()).ok());
+    let (category, identifier) = action
+        .name
+        .as_ref()
+        .map_or(("", ""), |n| (n.category.as_str(), n.identifier.as_str()));
+
+    action
+        .commands
+        .iter()
+        .map(|command| {
+            let details = command.details.as_ref();
+            let metadata = details.and_then(|d| d.metadata.as_ref());
+            let kind = details
+                .and_then(|d| d.command_kind.as_ref())
+                .and_then(|k| k.command.as_ref());
+
+            let (executor, action_digest, command_line, cache_hit_type, queue_time) = match kind {
+                Some(Command::LocalCommand(c)) => (
+                    "local",
+                    Some(c.action_digest.as_str()),
+                    Some((&c.argv, &c.env)),
+                    None,
+                    None,
+                ),
+                Some(Command::OmittedLocalCommand(c)) => {
+                    ("local", Some(c.action_digest.as_str()), None, None, None)
+                }
+                Some(Command::WorkerCommand(c)) => (
+                    "worker",
+                    Some(c.action_digest.as_str()),
+                    Some((&c.argv, &c.env)),
+                    None,
+                    None,
+                ),
+                Some(Command::WorkerInitCommand(c)) => {
+                    ("worker", None, Some((&c.argv, &c.env)), None, None)
+                }
+                Some(Command::RemoteCommand(c)) => {
+                    let cache_hit_type = match c.cache_hit_type() {
+                        CacheHitType::ActionCache => Some("action_cache"),
+                        CacheHitType::RemoteDepFileCache => Some("remote_dep_file_cache"),
+                        CacheHitType::Executed => None,
+                    };
+                    (
+                        if c.cache_hit { "cache" } else { "remote" },
+                        Some(c.action_digest.as_str()),
+                        None,
+                        cache_hit_type.filter(|_| c.cache_hit),
+                        c.queue_time.as_ref(),
+                    )
+                }
+                None => ("local", None, None, None, None),
+            };
+
+            // The recorded inputs are those of the last command prepared by the action, which
+            // is this one if the digests match.
+            let digest_inputs = action
+                .digest_inputs
+                .as_ref()
+                .filter(|d| action_digest == Some(d.action_digest.as_str()));
+            let (argv, env) = match (command_line, digest_inputs) {
+                (Some((argv, entries)), _) => (argv.as_slice(), env(entries)),
+                (None, Some(d)) => (d.argv.as_slice(), env(&d.env)),
+                (None, None) => (&[][..], BTreeMap::new()),
+            };
+
+            ExecutionLogRecord {
+                version: EXECUTION_LOG_VERSION,
+                target: target.clone(),
+                category,
+                identifier,
+                action_digest,
+                executor,
+                cache_hit_type,
+                status: match &command.status {
+                    Some(Status::Success(..)) => "success",
+                    Some(Status::Failure(..)) => "failure",
+                    Some(Status::Timeout(..)) => "timeout",
+                    Some(Status::Cancelled(..)) => "cancelled",
+                    Some(Status::Error(..)) | None => "error",
+                },
+                exit_code: details.and_then(|d| d.signed_exit_code),
+                argv,
+                env,
+                working_directory: digest_inputs
+                    .map(|d| d.working_directory.as_str())
+                    .filter(|d| !d.is_empty()),
+                inputs: digest_inputs.map_or_else(Vec::new, |d| {
+                    d.inputs
+                        .iter()
+                        .map(|i| Input {
+                            path: &i.path,
+                            digest: match &i.data {
+                                Some(input::Data::Digest(digest)) => Some(digest.as_str()),
+                                _ => None,
+                            },
+                            symlink_target: match &i.data {
+                                Some(input::Data::SymlinkTarget(target)) => Some(target.as_str()),
+                                _ => None,
+                            },
+                            is_executable: i.is_executable,
+                        })
+                        .collect()
+                }),
+                outputs: digest_inputs.map_or_else(Vec::new, |d| {
+                    d.outputs
+                        .iter()
+                        .map(|o| Output {
+                            path: &o.path,
+                            digest: o.digest.as_deref(),
+                        })
+                        .collect()
+                }),
+                start_time_ms: metadata
+                    .and_then(|m| m.start_time.clone())
+                    .and_then(|t| SystemTime::try_from(t).ok())
+                    .and_then(|t| t.duration_since(SystemTime::UNIX_EPOCH).ok())
+                    .and_then(|d| u64::try_from(d.as_millis()).ok()),
+                wall_time_ms: millis(metadata.and_then(|m| m.wall_time.as_ref())),
+                execution_time_ms: millis(metadata.and_then(|m| m.execution_time.as_ref())),
+                queue_time_ms: millis(queue_time),
+                input_materialization_time_ms: millis(
+                    metadata.and_then(|m| m.input_materialization_duration.as_ref()),
+                ),
+            }
+        })
+        .collect()
+}
+
+/// Writes the commands executed by the actions of a build to a file, for `--execution-log`.
+pub struct ExecutionLogWriter {
+    file: BufWriter<File>,
+}
+
+impl ExecutionLogWriter {
+    /// The file is created up front, so that an empty log means that no commands ran.
+    pub fn new(path: AbsPathBuf) -> anyhow::Result<Self> {
+        let file = File::create(&path)
+            .with_context(|| format!("Error creating execution log `{}`", path.display()))?;
+        Ok(Self {
+            file: BufWriter::new(file),
+        })
+    }
+}
+
+#[async_trait]
+impl EventSubscriber for ExecutionLogWriter {
+    async fn handle_events(&mut self, events: &[Arc<BuckEvent>]) -> anyhow::Result<()> {
+        for event in events {
+            if let Some(buck2_data::span_end_event::Data::ActionExecution(action)) =
+                event.span_end_event().and_then(|end| end.data.as_ref())
+            {
+                for record in records(action) {
+                    serde_json::to_writer(&mut self.file, &record)?;
+                    self.file.write_all(b"\n")?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn exit(&mut self) -> anyhow::Result<()> {
+        self.file.flush().context("Error writing execution log")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_records() {
+        let action = buck2_data::ActionExecutionEnd {
+            name: Some(buck2_data::ActionName {
+                category: "cxx_compile".to_owned(),
+                identifier: "main.cpp".to_owned(),
+            }),
+            commands: vec![
+                buck2_data::CommandExecution {
+                    details: Some(buck2_data::CommandExecutionDetails {
+                        signed_exit_code: Some(0),
+                        command_kind: Some(buck2_data::CommandExecutionKind {
+                            command: Some(
+                                buck2_data::RemoteCommand {
+                                    action_digest: "abc:10".to_owned(),
+                                    cache_hit: false,
+                                    queue_time: Some(prost_types::Duration {
+                                        seconds: 1,
+                                        nanos: 0,
+                                    }),
+                                    ..Default::default()
+                                }
+                                .into(),
+                            ),
+                        }),
+                        ..Default::default()
+                    }),
+                    status: Some(buck2_data::command_execution::Cancelled {}.into()),
+                },
+                buck2_data::CommandExecution {
+                    details: Some(buck2_data::CommandExecutionDetails {
+                        signed_exit_code: Some(1),
+                        command_kind: Some(buck2_data::CommandExecutionKind {
+                            command: Some(
+                                buck2_data::OmittedLocalCommand {
+                                    action_digest: "abc:10".to_owned(),
+                                }
+                                .into(),
+                            ),
+                        }),
+                        ..Default::default()
+                    }),
+                    status: Some(buck2_data::command_execution::Failure {}.into()),
+                },
+            ],
+            digest_inputs: Some(buck2_data::ActionDigestInputs {
+                action_digest: "abc:10".to_owned(),
+                argv: vec!["clang".to_owned(), "main.cpp".to_owned()],
+                env: vec![buck2_data::EnvironmentEntry {
+                    key: "LANG".to_owned(),
+                    value: "C".to_owned(),
+                }],
+                inputs: vec![buck2_data::action_digest_inputs::Input {
+                    path: "main.cpp".to_owned(),
+                    data: Some(input::Data::Digest("def:5".to_owned())),
+                    is_executable: false,
+                }],
+                outputs: vec![buck2_data::action_digest_inputs::Output {
+                    path: "buck-out/v2/gen/main.o".to_owned(),
+                    digest: None,
+                }],
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        let records = records(&action);
+        assert_eq!(2, records.len());
+
+        let remote = &records[0];
+        assert_eq!(("remote", "cancelled"), (remote.executor, remote.status));
+        assert_eq!(None, remote.cache_hit_type);
+        assert_eq!(Some(1000), remote.queue_time_ms);
+
+        let local = &records[1];
+        assert_eq!(("local", "failure"), (local.executor, local.status));
+        assert_eq!(Some(1), local.exit_code);
+        assert_eq!(vec!["clang".to_owned(), "main.cpp".to_owned()], local.argv);
+        assert_eq!(Some(&"C"), local.env.get("LANG"));
+        assert_eq!(
+            vec![Input {
+                path: "main.cpp",
+                digest: Some("def:5"),
+                symlink_target: None,
+                is_executable: false,
+            }],
+            local.inputs
+        );
+
+        let json = serde_json::to_value(local).unwrap();
+        assert_eq!(1, json["version"]);
+        assert_eq!("cxx_compile", json["category"]);
+        assert_eq!("buck-out/v2/gen/main.o", json["outputs"][0]["path"]);
+        assert!(json["outputs"][0]["digest"].is_null());
+    }
+}
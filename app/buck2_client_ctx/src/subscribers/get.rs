@@ -22,6 +22,7 @@ use crate::streaming::StreamingCommand;
 use crate::subscribers::build_graph_stats::BuildGraphStats;
 use crate::subscribers::build_id_writer::BuildIdWriter;
 use crate::subscribers::event_log::subscriber::EventLog;
+use crate::subscribers::execution_log::ExecutionLogWriter;
 use crate::subscribers::re_log::ReLog;
 use crate::subscribers::simpleconsole::SimpleConsole;
 use crate::subscribers::subscriber::EventSubscriber;
@@ -140,6 +141,19 @@ pub(crate) fn try_get_build_id_writer<'a>(
     }
 }
 
+pub(crate) fn try_get_execution_log_writer<'a>(
+    opts: &CommonDaemonCommandOptions,
+    ctx: &ClientCommandContext<'a>,
+) -> anyhow::Result<Option<Box<dyn EventSubscriber + 'a>>> {
+    if let Some(file_loc) = opts.execution_log.as_ref() {
+        Ok(Some(Box::new(ExecutionLogWriter::new(
+            file_loc.resolve(&ctx.working_dir),
+        )?)))
+    } else {
+        Ok(None)
+    }
+}
+
 pub(crate) fn try_get_build_graph_stats<'a, T: StreamingCommand>(
     cmd: &T,
     ctx: &ClientCommandContext<'a>,
@@ -20,6 +20,7 @@ use tokio::task::JoinHandle;
 pub(crate) mod build_graph_stats;
 pub(crate) mod build_id_writer;
 pub mod event_log;
+pub(crate) mod execution_log;
 pub mod get;
 pub(crate) mod observer;
 pub mod re_log;
@@ -29,6 +30,7 @@ pub mod stdout_stderr_forwarder;
 pub mod subscriber;
 pub mod subscriber_unpack;
 pub mod superconsole;
+pub mod test_report;
 
 pub fn should_upload_log() -> anyhow::Result<bool> {
     if buck2_core::is_open_source() {
@@ -0,0 +1,508 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! JUnit XML and JSON reports of the test results of `buck2 test`.
+//!
+//! The reports contain every discovered test target and every reported test case, with the latest
+//! result of each test case if the command ran the tests several times, as with `--watch`.
+//!
+//! The durations of the test targets can be read back from either report, to balance test shards.
+
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Context;
+use async_trait::async_trait;
+use buck2_common::convert::ProstDurationExt;
+use buck2_core::fs::fs_util;
+use buck2_core::fs::paths::abs_path::AbsPathBuf;
+use buck2_data::TestStatus;
+use buck2_event_observer::display::display_configured_target_label;
+use buck2_event_observer::display::TargetDisplayOptions;
+use buck2_events::BuckEvent;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::subscribers::subscriber::EventSubscriber;
+
+/// Bumped on any change to the JSON report that is not the addition of a field.
+const TEST_REPORT_VERSION: u32 = 1;
+
+/// Only the end of the output of a test case is kept in the reports.
+const MAX_OUTPUT_EXCERPT_BYTES: usize = 16 * 1024;
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+struct TestCase {
+    name: String,
+    status: &'static str,
+    duration_ms: Option<u64>,
+    message: Option<String>,
+    output: String,
+    artifacts: Vec<String>,
+}
+
+#[derive(Serialize, Debug, Default, PartialEq)]
+struct TestTarget {
+    target: String,
+    duration_ms: u64,
+    tests: usize,
+    failures: usize,
+    errors: usize,
+    skipped: usize,
+    flaky: usize,
+    test_cases: Vec<TestCase>,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+struct TestReport {
+    version: u32,
+    targets: Vec<TestTarget>,
+    /// The summary of the coverage report of `buck2 test --coverage`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    coverage: Option<buck2_cli_proto::test_response::Coverage>,
+}
+
+/// How a status is reported in JUnit XML.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Outcome {
+    Pass,
+    Failure,
+    Error,
+    Skipped,
+    Flaky,
+}
+
+fn status_name(status: TestStatus) -> &'static str {
+    match status {
+        TestStatus::NotSetTestStatus => "not_set",
+        TestStatus::Pass => "pass",
+        TestStatus::Fail => "fail",
+        TestStatus::Skip => "skip",
+        TestStatus::Omitted => "omitted",
+        TestStatus::Fatal => "fatal",
+        TestStatus::Timeout => "timeout",
+        TestStatus::Unknown => "unknown",
+        TestStatus::Rerun => "rerun",
+        TestStatus::ListingSuccess => "listing_success",
+        TestStatus::ListingFailed => "listing_failed",
+        TestStatus::Flaky => "flaky",
+    }
+}
+
+fn outcome(status: &str) -> Outcome {
+    match status {
+        "pass" => Outcome::Pass,
+        "fail" | "timeout" => Outcome::Failure,
+        "skip" | "omitted" => Outcome::Skipped,
+        "flaky" => Outcome::Flaky,
+        _ => Outcome::Error,
+    }
+}
+
+/// The end of the output, on a char boundary.
+fn excerpt(output: &str) -> Cow<'_, str> {
+    if output.len() <= MAX_OUTPUT_EXCERPT_BYTES {
+        return Cow::Borrowed(output);
+    }
+    let mut start = output.len() - MAX_OUTPUT_EXCERPT_BYTES;
+    while !output.is_char_boundary(start) {
+        start += 1;
+    }
+    Cow::Owned(format!("[{} bytes truncated]\n{}", start, &output[start..]))
+}
+
+/// Escapes text for XML attributes and content, dropping the characters XML can't represent.
+fn escape_xml(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\t' | '\n' | '\r' => escaped.push(c),
+            c if c < ' ' => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn seconds(duration_ms: u64) -> String {
+    format!("{:.3}", duration_ms as f64 / 1000.0)
+}
+
+impl TestReport {
+    fn junit_xml(&self) -> String {
+        let total = |f: fn(&TestTarget) -> usize| self.targets.iter().map(f).sum::<usize>();
+        let mut xml = String::new();
+        xml.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        xml.push_str(&format!(
+            "<testsuites tests=\"{}\" failures=\"{}\" errors=\"{}\" skipped=\"{}\" time=\"{}\">\n",
+            total(|t| t.tests),
+            total(|t| t.failures),
+            total(|t| t.errors),
+            total(|t| t.skipped),
+            seconds(self.targets.iter().map(|t| t.duration_ms).sum()),
+        ));
+        for target in &self.targets {
+            let name = escape_xml(&target.target);
+            xml.push_str(&format!(
+                "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"{}\" skipped=\"{}\" time=\"{}\">\n",
+                name,
+                target.tests,
+                target.failures,
+                target.errors,
+                target.skipped,
+                seconds(target.duration_ms),
+            ));
+            for case in &target.test_cases {
+                xml.push_str(&format!(
+                    "    <testcase name=\"{}\" classname=\"{}\" time=\"{}\">",
+                    escape_xml(&case.name),
+                    name,
+                    seconds(case.duration_ms.unwrap_or_default()),
+                ));
+                let message = escape_xml(case.message.as_deref().unwrap_or(case.status));
+                let mut output = escape_xml(&case.output);
+                let mut children = String::new();
+                // The output of failing test cases goes in the failure, the rest in `system-out`.
+                let element = match outcome(case.status) {
+                    Outcome::Pass => None,
+                    Outcome::Failure => Some("failure"),
+                    Outcome::Error => Some("error"),
+                    // The Surefire extension understood by most CI systems.
+                    Outcome::Flaky => Some("flakyFailure"),
+                    Outcome::Skipped => {
+                        children.push_str(&format!("\n      <skipped message=\"{}\"/>", message));
+                        None
+                    }
+                };
+                if let Some(element) = element {
+                    children.push_str(&format!(
+                        "\n      <{} message=\"{}\" type=\"{}\">{}</{}>",
+                        element, message, case.status, output, element
+                    ));
+                    output.clear();
+                }
+                // The convention for attachments, understood by Jenkins among others.
+                for artifact in &case.artifacts {
+                    if !output.is_empty() {
+                        output.push('\n');
+                    }
+                    output.push_str(&format!("[[ATTACHMENT|{}]]", escape_xml(artifact)));
+                }
+                if !output.is_empty() {
+                    children.push_str(&format!("\n      <system-out>{}</system-out>", output));
+                }
+                if !children.is_empty() {
+                    xml.push_str(&children);
+                    xml.push_str("\n    ");
+                }
+                xml.push_str("</testcase>\n");
+            }
+            xml.push_str("  </testsuite>\n");
+        }
+        xml.push_str("</testsuites>\n");
+        xml
+    }
+}
+
+fn unescape_xml(s: &str) -> String {
+    s.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+/// The value of an attribute of an XML start tag, assuming it is double quoted like ours.
+fn xml_attribute<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    let pattern = format!("{}=\"", name);
+    let mut rest = tag;
+    loop {
+        let start = rest.find(&pattern)?;
+        let preceded_by_space = rest[..start].ends_with(char::is_whitespace);
+        rest = &rest[start + pattern.len()..];
+        if preceded_by_space {
+            return rest.find('"').map(|end| &rest[..end]);
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct TargetDuration {
+    target: String,
+    duration_ms: u64,
+}
+
+#[derive(Deserialize)]
+struct TargetDurations {
+    targets: Vec<TargetDuration>,
+}
+
+/// Reads the durations of the test targets, in milliseconds, from a JUnit XML or JSON report.
+pub fn read_durations(report: &str) -> anyhow::Result<HashMap<String, u64>> {
+    let mut durations = HashMap::new();
+    if report.trim_start().starts_with('<') {
+        for (start, _) in report.match_indices("<testsuite ") {
+            let tag = &report[start..];
+            let tag = &tag[..tag.find('>').context("Unterminated `testsuite` element")?];
+            if let (Some(name), Some(time)) =
+                (xml_attribute(tag, "name"), xml_attribute(tag, "time"))
+            {
+                let seconds: f64 = time
+                    .parse()
+                    .with_context(|| format!("Invalid `time` of `{}`: `{}`", name, time))?;
+                *durations.entry(unescape_xml(name)).or_default() +=
+                    (seconds * 1000.0).round() as u64;
+            }
+        }
+    } else {
+        let report: TargetDurations = serde_json::from_str(report)?;
+        for target in report.targets {
+            *durations.entry(target.target).or_default() += target.duration_ms;
+        }
+    }
+    Ok(durations)
+}
+
+/// Collects the test results of the command, and writes the reports when it exits.
+pub struct TestReportWriter {
+    junit_xml: Option<AbsPathBuf>,
+    json: Option<AbsPathBuf>,
+    /// Test cases by name, by target.
+    targets: BTreeMap<String, BTreeMap<String, TestCase>>,
+    coverage: Option<buck2_cli_proto::test_response::Coverage>,
+}
+
+impl TestReportWriter {
+    pub fn new(junit_xml: Option<AbsPathBuf>, json: Option<AbsPathBuf>) -> Self {
+        Self {
+            junit_xml,
+            json,
+            targets: BTreeMap::new(),
+            coverage: None,
+        }
+    }
+
+    fn target(label: Option<&buck2_data::ConfiguredTargetLabel>) -> anyhow::Result<String> {
+        match label {
+            Some(label) => {
+                display_configured_target_label(label, TargetDisplayOptions::for_console(false))
+            }
+            None => Ok("unknown".to_owned()),
+        }
+    }
+
+    fn handle_event(&mut self, event: &BuckEvent) -> anyhow::Result<()> {
+        use buck2_data::buck_event::Data;
+        use buck2_data::instant_event::Data as InstantData;
+
+        let instant = match event.data() {
+            Data::Instant(instant) => instant,
+            _ => return Ok(()),
+        };
+        match &instant.data {
+            Some(InstantData::TestDiscovery(discovery)) => {
+                if let Some(buck2_data::test_discovery::Data::Tests(suite)) = &discovery.data {
+                    self.targets
+                        .entry(Self::target(suite.target_label.as_ref())?)
+                        .or_default();
+                }
+            }
+            Some(InstantData::TestResult(result)) => {
+                let status = TestStatus::from_i32(result.status).unwrap_or(TestStatus::Unknown);
+                if matches!(status, TestStatus::ListingSuccess | TestStatus::Rerun) {
+                    return Ok(());
+                }
+                let duration = result
+                    .duration
+                    .as_ref()
+                    .and_then(|d| d.try_into_duration().ok());
+                let case = TestCase {
+                    name: result.name.clone(),
+                    status: status_name(status),
+                    duration_ms: duration.map(|d: Duration| d.as_millis() as u64),
+                    message: result.msg.as_ref().map(|m| m.msg.clone()),
+                    output: excerpt(&result.details).into_owned(),
+                    artifacts: result.artifacts.clone(),
+                };
+                self.targets
+                    .entry(Self::target(result.target_label.as_ref())?)
+                    .or_default()
+                    .insert(case.name.clone(), case);
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn report(&self) -> TestReport {
+        let targets = self
+            .targets
+            .iter()
+            .map(|(target, cases)| {
+                let mut target = TestTarget {
+                    target: target.clone(),
+                    ..Default::default()
+                };
+                for case in cases.values() {
+                    target.tests += 1;
+                    target.duration_ms += case.duration_ms.unwrap_or_default();
+                    match outcome(case.status) {
+                        Outcome::Pass => {}
+                        Outcome::Failure => target.failures += 1,
+                        Outcome::Error => target.errors += 1,
+                        Outcome::Skipped => target.skipped += 1,
+                        Outcome::Flaky => target.flaky += 1,
+                    }
+                    target.test_cases.push(case.clone());
+                }
+                target
+            })
+            .collect();
+        TestReport {
+            version: TEST_REPORT_VERSION,
+            targets,
+            coverage: self.coverage.clone(),
+        }
+    }
+}
+
+#[async_trait]
+impl EventSubscriber for TestReportWriter {
+    async fn handle_events(&mut self, events: &[Arc<BuckEvent>]) -> anyhow::Result<()> {
+        for event in events {
+            self.handle_event(event)?;
+        }
+        Ok(())
+    }
+
+    async fn handle_command_result(
+        &mut self,
+        result: &buck2_cli_proto::CommandResult,
+    ) -> anyhow::Result<()> {
+        if let Some(buck2_cli_proto::command_result::Result::TestResponse(response)) =
+            &result.result
+        {
+            self.coverage = response.coverage.clone();
+        }
+        Ok(())
+    }
+
+    async fn exit(&mut self) -> anyhow::Result<()> {
+        let report = self.report();
+        if let Some(path) = &self.junit_xml {
+            fs_util::write(path, report.junit_xml()).context("Error writing JUnit XML report")?;
+        }
+        if let Some(path) = &self.json {
+            fs_util::write(path, serde_json::to_string_pretty(&report)?)
+                .context("Error writing test report")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn case(name: &str, status: &'static str, output: &str) -> TestCase {
+        TestCase {
+            name: name.to_owned(),
+            status,
+            duration_ms: Some(1500),
+            message: None,
+            output: output.to_owned(),
+            artifacts: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_junit_xml() {
+        let report = TestReport {
+            version: TEST_REPORT_VERSION,
+            targets: vec![TestTarget {
+                target: "root//foo:bar".to_owned(),
+                duration_ms: 3000,
+                tests: 2,
+                failures: 1,
+                test_cases: vec![
+                    case("a", "pass", ""),
+                    TestCase {
+                        artifacts: vec!["/out/test_outputs".to_owned()],
+                        ..case("b", "fail", "expected <1>")
+                    },
+                ],
+                ..Default::default()
+            }],
+            coverage: None,
+        };
+        assert_eq!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuites tests="2" failures="1" errors="0" skipped="0" time="3.000">
+  <testsuite name="root//foo:bar" tests="2" failures="1" errors="0" skipped="0" time="3.000">
+    <testcase name="a" classname="root//foo:bar" time="1.500"></testcase>
+    <testcase name="b" classname="root//foo:bar" time="1.500">
+      <failure message="fail" type="fail">expected &lt;1&gt;</failure>
+      <system-out>[[ATTACHMENT|/out/test_outputs]]</system-out>
+    </testcase>
+  </testsuite>
+</testsuites>
+"#,
+            report.junit_xml()
+        );
+    }
+
+    #[test]
+    fn test_read_durations() -> anyhow::Result<()> {
+        let report = TestReport {
+            version: TEST_REPORT_VERSION,
+            targets: vec![
+                TestTarget {
+                    target: "root//foo:a&b".to_owned(),
+                    duration_ms: 1500,
+                    ..Default::default()
+                },
+                TestTarget {
+                    target: "root//foo:c".to_owned(),
+                    duration_ms: 20,
+                    ..Default::default()
+                },
+            ],
+            coverage: None,
+        };
+        let expected = HashMap::from([
+            ("root//foo:a&b".to_owned(), 1500),
+            ("root//foo:c".to_owned(), 20),
+        ]);
+        assert_eq!(expected, read_durations(&report.junit_xml())?);
+        assert_eq!(expected, read_durations(&serde_json::to_string(&report)?)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_excerpt() {
+        assert_eq!("short", excerpt("short"));
+        let long = "é".repeat(MAX_OUTPUT_EXCERPT_BYTES);
+        let excerpt = excerpt(&long);
+        assert!(excerpt.starts_with(&format!("[{} bytes truncated]\n", long.len() / 2)));
+        assert!(excerpt.ends_with('é'));
+    }
+
+    #[test]
+    fn test_escape_xml() {
+        assert_eq!("a&amp;b &quot;c&quot;\n", escape_xml("a&b \"c\"\u{1b}\n"));
+    }
+}
@@ -26,6 +26,49 @@ pub struct Graph {
 }
 
 impl Graph {
+    /// Create a graph from the dependencies of each vertex, given as indices of vertices. Unlike
+    /// with a GraphBuilder, dependencies don't need to come first, so this may produce a graph
+    /// that is not a DAG.
+    pub fn from_deps<D>(deps: impl IntoIterator<Item = D>) -> Result<Self, FromDepsError>
+    where
+        D: IntoIterator<Item = u32>,
+    {
+        let mut vertices = Vec::new();
+        let mut edges = Vec::new();
+
+        for vertex_deps in deps {
+            let edges_idx: u32 = edges
+                .len()
+                .try_into()
+                .map_err(|_| FromDepsError::Overflow)?;
+            edges.extend(vertex_deps.into_iter().map(VertexId::new));
+            let edges_count = (edges.len() - edges_idx as usize)
+                .try_into()
+                .map_err(|_| FromDepsError::Overflow)?;
+            vertices.push(GraphVertex {
+                edges_idx,
+                edges_count,
+            });
+        }
+
+        // Like GraphBuilder, we constrain ourselves to i32::MAX in order to support optionals.
+        if vertices.len() > i32::MAX as usize {
+            return Err(FromDepsError::Overflow);
+        }
+
+        if let Some(dep) = edges
+            .iter()
+            .find(|e| e.into_inner() as usize >= vertices.len())
+        {
+            return Err(FromDepsError::InvalidDependency(dep.into_inner()));
+        }
+
+        Ok(Self {
+            vertices: VertexData::new(vertices),
+            edges,
+        })
+    }
+
     #[inline]
     pub fn iter_vertices(&self) -> impl Iterator<Item = VertexId> + DoubleEndedIterator {
         self.vertices.keys()
@@ -237,6 +280,15 @@ impl Graph {
     }
 }
 
+#[derive(Error, Debug)]
+pub enum FromDepsError {
+    #[error("overflow")]
+    Overflow,
+
+    #[error("dependency on vertex {0}, which does not exist")]
+    InvalidDependency(u32),
+}
+
 #[derive(Error, Debug)]
 pub enum TopoSortError {
     #[error("cycle")]
@@ -314,6 +366,24 @@ mod test {
         assert_eq!(vec![(K2, K3), (K0, K1), (K0, K2)], edges);
     }
 
+    #[test]
+    fn test_from_deps() {
+        let (graph, _keys, _data) = test_graph();
+        let from_deps = Graph::from_deps(graph.iter_vertices().map(|i| {
+            graph
+                .iter_edges(i)
+                .map(|e| e.into_inner())
+                .collect::<Vec<_>>()
+        }))
+        .unwrap();
+        assert_eq!(
+            graph.iter_all_edges().collect::<Vec<_>>(),
+            from_deps.iter_all_edges().collect::<Vec<_>>()
+        );
+
+        assert!(Graph::from_deps(vec![vec![1]]).is_err());
+    }
+
     #[test]
     fn test_reverse() {
         let (graph, _keys, data) = test_graph();
@@ -521,6 +521,7 @@ pub fn format_test_result(test_result: &buck2_data::TestResult) -> anyhow::Resul
         TestStatus::UNKNOWN => Span::new_styled("? Unknown".to_owned().cyan()),
         TestStatus::RERUN => Span::new_styled("↻ Rerun".to_owned().cyan()),
         TestStatus::LISTING_FAILED => Span::new_styled("⚠ Listing failed".to_owned().red()),
+        TestStatus::FLAKY => Span::new_styled("↯ Flaky".to_owned().yellow()),
     }?;
     let mut base = Line::from_iter([prefix, Span::new_unstyled(format!(": {}", name,))?]);
     if let Some(duration) = duration {
@@ -22,6 +22,7 @@ pub struct TestState {
     pub unknown: u64,
     pub listing_success: u64,
     pub listing_failed: u64,
+    pub flaky: u64,
 }
 
 impl TestState {
@@ -38,6 +39,7 @@ impl TestState {
             TestStatus::RERUN => &mut self.retry,
             TestStatus::LISTING_SUCCESS => &mut self.listing_success,
             TestStatus::LISTING_FAILED => &mut self.listing_failed,
+            TestStatus::FLAKY => &mut self.flaky,
         };
         *counter += 1;
 
@@ -0,0 +1,170 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! A sink that reports builds and tests using Bazel's Build Event Protocol (BEP), so that
+//! existing BEP consumers can be used with buck2.
+//!
+//! Events can be written to a file, in the formats of Bazel's `--build_event_binary_file` and
+//! `--build_event_json_file`, or streamed to a `PublishBuildEvent` service, like Bazel's
+//! `--bes_backend`.
+
+mod publish;
+mod translate;
+
+use std::fs::File;
+use std::io::BufWriter;
+use std::io::Write;
+
+use anyhow::Context;
+use buck2_bep_proto::build_event_stream as bes;
+use buck2_core::fs::paths::abs_path::AbsPathBuf;
+use buck2_wrapper_common::invocation_id::TraceId;
+use dupe::Dupe;
+use prost::Message;
+use tokio::sync::mpsc;
+
+use crate::sink::bep::publish::BepPublisher;
+use crate::sink::bep::translate::is_relevant;
+use crate::sink::bep::translate::BepTranslator;
+use crate::BuckEvent;
+use crate::Event;
+use crate::EventSink;
+
+/// Where BEP events are sent, configured in the `buck2` buckconfig section.
+#[derive(Debug, Clone, Default)]
+pub struct BepSinkConfig {
+    /// Write length-delimited `BuildEvent` protos to this file.
+    pub binary_file: Option<AbsPathBuf>,
+    /// Write `BuildEvent`s to this file, as one JSON object per line.
+    pub json_file: Option<AbsPathBuf>,
+    /// Stream `BuildEvent`s to this `PublishBuildEvent` service, a `grpc://` or `grpcs://` URL.
+    pub backend: Option<String>,
+    /// Absolute path of the project root, output paths are relative to it.
+    pub project_root: String,
+}
+
+impl BepSinkConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.binary_file.is_some() || self.json_file.is_some() || self.backend.is_some()
+    }
+}
+
+/// Sink for the events of one command. Only build and test commands are reported.
+///
+/// Events are translated and written on a separate task. Failing to report a build does not
+/// fail the build, errors are only logged.
+pub struct BepSink {
+    sender: mpsc::UnboundedSender<BuckEvent>,
+}
+
+impl BepSink {
+    /// Must be called from within a Tokio runtime.
+    pub fn new(config: BepSinkConfig, trace_id: TraceId) -> Self {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            if let Err(e) = report(config, trace_id, receiver).await {
+                tracing::warn!("Error reporting build events: {:#}", e);
+            }
+        });
+        Self { sender }
+    }
+}
+
+impl EventSink for BepSink {
+    fn send(&self, event: Event) {
+        if let Event::Buck(event) = event {
+            if is_relevant(&event) {
+                // The receiver is gone if the command isn't reported, or reporting failed.
+                let _ignored = self.sender.send(event);
+            }
+        }
+    }
+}
+
+struct Outputs {
+    binary_file: Option<BufWriter<File>>,
+    json_file: Option<BufWriter<File>>,
+    publisher: Option<BepPublisher>,
+}
+
+impl Outputs {
+    async fn open(config: &BepSinkConfig, trace_id: &TraceId) -> anyhow::Result<Self> {
+        let create = |path: &Option<AbsPathBuf>| -> anyhow::Result<_> {
+            path.as_ref()
+                .map(|path| {
+                    File::create(path)
+                        .map(BufWriter::new)
+                        .with_context(|| format!("Error creating `{}`", path.display()))
+                })
+                .transpose()
+        };
+        let publisher = match &config.backend {
+            Some(backend) => Some(BepPublisher::connect(backend, trace_id).await?),
+            None => None,
+        };
+        Ok(Self {
+            binary_file: create(&config.binary_file)?,
+            json_file: create(&config.json_file)?,
+            publisher,
+        })
+    }
+
+    fn write(&mut self, event: &bes::BuildEvent) -> anyhow::Result<()> {
+        if let Some(binary_file) = &mut self.binary_file {
+            binary_file.write_all(&event.encode_length_delimited_to_vec())?;
+        }
+        if let Some(json_file) = &mut self.json_file {
+            serde_json::to_writer(&mut *json_file, event)?;
+            json_file.write_all(b"\n")?;
+        }
+        if let Some(publisher) = &mut self.publisher {
+            publisher.publish(event)?;
+        }
+        Ok(())
+    }
+
+    async fn finish(self) -> anyhow::Result<()> {
+        for mut file in self.binary_file.into_iter().chain(self.json_file) {
+            file.flush()?;
+        }
+        if let Some(publisher) = self.publisher {
+            publisher.finish().await?;
+        }
+        Ok(())
+    }
+}
+
+async fn report(
+    config: BepSinkConfig,
+    trace_id: TraceId,
+    mut receiver: mpsc::UnboundedReceiver<BuckEvent>,
+) -> anyhow::Result<()> {
+    let mut translator = BepTranslator::new(trace_id.dupe(), config.project_root.clone());
+    // Outputs are only opened once we know the command is reported, so that other commands
+    // don't overwrite the files.
+    let mut outputs = None;
+    while let Some(event) = receiver.recv().await {
+        let events = translator.translate(&event);
+        if !events.is_empty() && outputs.is_none() {
+            outputs = Some(Outputs::open(&config, &trace_id).await?);
+        }
+        if let Some(outputs) = &mut outputs {
+            for event in &events {
+                outputs.write(event)?;
+            }
+        }
+        if translator.is_done() {
+            break;
+        }
+    }
+    match outputs {
+        Some(outputs) => outputs.finish().await,
+        None => Ok(()),
+    }
+}
@@ -0,0 +1,245 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Streaming of BEP events to a `PublishBuildEvent` service.
+
+use std::time::SystemTime;
+
+use anyhow::Context;
+use buck2_bep_proto::build_event_stream as bes;
+use buck2_bep_proto::google::devtools::build::v1 as bep;
+use buck2_bep_proto::google::devtools::build::v1::build_event::build_component_stream_finished::FinishType;
+use buck2_bep_proto::google::devtools::build::v1::publish_build_event_client::PublishBuildEventClient;
+use buck2_bep_proto::google::devtools::build::v1::stream_id::BuildComponent;
+use buck2_wrapper_common::invocation_id::TraceId;
+use prost::Message;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio_stream::wrappers::UnboundedReceiverStream;
+use tokio_stream::StreamExt;
+
+#[derive(Debug, thiserror::Error)]
+enum BepPublishError {
+    #[error("Invalid BEP backend `{0}`, expected a `grpc://` or `grpcs://` URL")]
+    InvalidBackend(String),
+    #[error("BEP backend acknowledged {acknowledged} of {sent} events")]
+    MissingAcknowledgements { acknowledged: i64, sent: i64 },
+}
+
+/// Bazel's `--bes_backend` uses `grpc://` and `grpcs://` URLs.
+fn backend_uri(backend: &str) -> anyhow::Result<String> {
+    if let Some(rest) = backend.strip_prefix("grpc://") {
+        Ok(format!("http://{}", rest))
+    } else if let Some(rest) = backend.strip_prefix("grpcs://") {
+        Ok(format!("https://{}", rest))
+    } else {
+        Err(BepPublishError::InvalidBackend(backend.to_owned()).into())
+    }
+}
+
+/// Publishes the events of one build as a single ordered stream.
+pub(crate) struct BepPublisher {
+    stream_id: bep::StreamId,
+    sequence_number: i64,
+    requests: Option<mpsc::UnboundedSender<bep::PublishBuildToolEventStreamRequest>>,
+    /// Resolves to the sequence number of the last acknowledged event.
+    task: JoinHandle<anyhow::Result<i64>>,
+}
+
+impl BepPublisher {
+    pub(crate) async fn connect(backend: &str, trace_id: &TraceId) -> anyhow::Result<Self> {
+        let uri = backend_uri(backend)?;
+        let mut client = PublishBuildEventClient::connect(uri)
+            .await
+            .with_context(|| format!("Error connecting to BEP backend `{}`", backend))?;
+
+        let (requests, rx) = mpsc::unbounded_channel();
+        let task = tokio::spawn(async move {
+            let mut responses = client
+                .publish_build_tool_event_stream(UnboundedReceiverStream::new(rx))
+                .await?
+                .into_inner();
+            let mut acknowledged = 0;
+            while let Some(response) = responses.try_next().await? {
+                acknowledged = response.sequence_number;
+            }
+            anyhow::Ok(acknowledged)
+        });
+
+        Ok(Self {
+            stream_id: bep::StreamId {
+                build_id: trace_id.to_string(),
+                invocation_id: trace_id.to_string(),
+                component: BuildComponent::Tool as i32,
+            },
+            sequence_number: 0,
+            requests: Some(requests),
+            task,
+        })
+    }
+
+    pub(crate) fn publish(&mut self, event: &bes::BuildEvent) -> anyhow::Result<()> {
+        self.send(bep::build_event::Event::BazelEvent(prost_types::Any {
+            type_url: "type.googleapis.com/build_event_stream.BuildEvent".to_owned(),
+            value: event.encode_to_vec(),
+        }))
+    }
+
+    /// Close the stream and wait for the backend to acknowledge all the events.
+    pub(crate) async fn finish(mut self) -> anyhow::Result<()> {
+        self.send(bep::build_event::Event::ComponentStreamFinished(
+            bep::build_event::BuildComponentStreamFinished {
+                r#type: FinishType::Finished as i32,
+            },
+        ))?;
+        self.requests = None;
+
+        let acknowledged = self.task.await??;
+        if acknowledged != self.sequence_number {
+            return Err(BepPublishError::MissingAcknowledgements {
+                acknowledged,
+                sent: self.sequence_number,
+            }
+            .into());
+        }
+        Ok(())
+    }
+
+    fn send(&mut self, event: bep::build_event::Event) -> anyhow::Result<()> {
+        self.sequence_number += 1;
+        let request = bep::PublishBuildToolEventStreamRequest {
+            ordered_build_event: Some(bep::OrderedBuildEvent {
+                stream_id: Some(self.stream_id.clone()),
+                sequence_number: self.sequence_number,
+                event: Some(bep::BuildEvent {
+                    event_time: Some(SystemTime::now().into()),
+                    event: Some(event),
+                }),
+            }),
+            notification_keywords: Vec::new(),
+            project_id: String::new(),
+        };
+        self.requests
+            .as_ref()
+            .and_then(|requests| requests.send(request).ok())
+            .context("BEP stream was closed by the backend")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::pin::Pin;
+    use std::sync::Arc;
+    use std::sync::Mutex;
+
+    use buck2_bep_proto::google::devtools::build::v1::publish_build_event_server::PublishBuildEvent;
+    use buck2_bep_proto::google::devtools::build::v1::publish_build_event_server::PublishBuildEventServer;
+    use futures::Stream;
+    use tokio::net::TcpListener;
+    use tokio_stream::wrappers::TcpListenerStream;
+    use tonic::Request;
+    use tonic::Response;
+    use tonic::Status;
+    use tonic::Streaming;
+
+    use super::*;
+
+    /// Records the events it receives and acknowledges them.
+    #[derive(Default, Clone)]
+    struct FakeBackend {
+        received: Arc<Mutex<Vec<bep::OrderedBuildEvent>>>,
+    }
+
+    #[tonic::async_trait]
+    impl PublishBuildEvent for FakeBackend {
+        type PublishBuildToolEventStreamStream = Pin<
+            Box<
+                dyn Stream<Item = Result<bep::PublishBuildToolEventStreamResponse, Status>>
+                    + Send
+                    + 'static,
+            >,
+        >;
+
+        async fn publish_build_tool_event_stream(
+            &self,
+            request: Request<Streaming<bep::PublishBuildToolEventStreamRequest>>,
+        ) -> Result<Response<Self::PublishBuildToolEventStreamStream>, Status> {
+            let received = self.received.clone();
+            let responses = request.into_inner().map(move |request| {
+                let event = request?.ordered_build_event.unwrap();
+                let response = bep::PublishBuildToolEventStreamResponse {
+                    stream_id: event.stream_id.clone(),
+                    sequence_number: event.sequence_number,
+                };
+                received.lock().unwrap().push(event);
+                Ok(response)
+            });
+            Ok(Response::new(Box::pin(responses)))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_publish() -> anyhow::Result<()> {
+        let backend = FakeBackend::default();
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        tokio::spawn(
+            tonic::transport::Server::builder()
+                .add_service(PublishBuildEventServer::new(backend.clone()))
+                .serve_with_incoming(TcpListenerStream::new(listener)),
+        );
+
+        let trace_id = TraceId::new();
+        let mut publisher = BepPublisher::connect(&format!("grpc://{}", addr), &trace_id).await?;
+        let event = bes::BuildEvent {
+            last_message: true,
+            ..Default::default()
+        };
+        publisher.publish(&event)?;
+        publisher.publish(&event)?;
+        publisher.finish().await?;
+
+        let received = backend.received.lock().unwrap();
+        assert_eq!(
+            vec![1, 2, 3],
+            received
+                .iter()
+                .map(|e| e.sequence_number)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            trace_id.to_string(),
+            received[0].stream_id.as_ref().unwrap().invocation_id
+        );
+        match received[0].event.as_ref().unwrap().event.as_ref().unwrap() {
+            bep::build_event::Event::BazelEvent(any) => {
+                assert_eq!(event, bes::BuildEvent::decode(any.value.as_slice())?)
+            }
+            e => panic!("unexpected event {:?}", e),
+        }
+        assert!(matches!(
+            received[2].event.as_ref().unwrap().event,
+            Some(bep::build_event::Event::ComponentStreamFinished(..))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_backend_uri() {
+        assert_eq!(
+            "http://localhost:1985",
+            backend_uri("grpc://localhost:1985").unwrap()
+        );
+        assert_eq!(
+            "https://bes.example.com",
+            backend_uri("grpcs://bes.example.com").unwrap()
+        );
+        assert!(backend_uri("localhost:1985").is_err());
+    }
+}
@@ -0,0 +1,752 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Translation of buck2 events into Build Event Protocol events.
+//!
+//! Every BEP event but `BuildStarted` must be announced as a child of an earlier event. Like
+//! Bazel, we use a chain of `Progress` events to announce events which have no natural parent.
+
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::time::Duration;
+use std::time::SystemTime;
+
+use buck2_bep_proto::build_event_stream as bes;
+use buck2_bep_proto::build_event_stream::build_event::Payload;
+use buck2_bep_proto::build_event_stream::build_event_id;
+use buck2_bep_proto::build_event_stream::build_event_id::Id;
+use buck2_wrapper_common::invocation_id::TraceId;
+
+use crate::BuckEvent;
+
+/// Whether the translator needs to see this event. Everything else is dropped before it gets
+/// sent to the translator.
+pub(crate) fn is_relevant(event: &BuckEvent) -> bool {
+    use buck2_data::buck_event::Data;
+
+    match event.data() {
+        Data::SpanStart(start) => matches!(
+            start.data,
+            Some(buck2_data::span_start_event::Data::Command(..))
+        ),
+        Data::SpanEnd(end) => matches!(
+            end.data,
+            Some(buck2_data::span_end_event::Data::Command(..))
+                | Some(buck2_data::span_end_event::Data::Analysis(..))
+        ),
+        Data::Instant(instant) => matches!(
+            instant.data,
+            Some(buck2_data::instant_event::Data::TestResult(..))
+                | Some(buck2_data::instant_event::Data::TargetBuildResult(..))
+        ),
+        Data::Record(..) => false,
+    }
+}
+
+enum State {
+    /// Waiting for the command to start.
+    NotStarted,
+    /// The command is not one we report on.
+    Ignored,
+    Started {
+        command: &'static str,
+    },
+    Finished,
+}
+
+/// Results of the test cases of a test target, which are reported as a single test run.
+struct TestTarget {
+    status: bes::TestStatus,
+    failed_cases: Vec<String>,
+    first_start_millis: i64,
+    last_stop_millis: i64,
+    duration_millis: i64,
+}
+
+pub(crate) struct BepTranslator {
+    trace_id: TraceId,
+    /// Absolute path of the project root, used to construct file URIs.
+    project_root: String,
+    state: State,
+    /// Count of the `Progress` event that has been announced but not posted yet.
+    progress_count: i32,
+    configurations: HashSet<String>,
+    named_sets: u64,
+    /// Rule type of the analyzed targets, by configured target label.
+    rule_kinds: HashMap<(String, String), String>,
+    tests: BTreeMap<(String, String), TestTarget>,
+}
+
+impl BepTranslator {
+    pub(crate) fn new(trace_id: TraceId, project_root: String) -> Self {
+        Self {
+            trace_id,
+            project_root,
+            state: State::NotStarted,
+            progress_count: 0,
+            configurations: HashSet::new(),
+            named_sets: 0,
+            rule_kinds: HashMap::new(),
+            tests: BTreeMap::new(),
+        }
+    }
+
+    /// No more events will be produced.
+    pub(crate) fn is_done(&self) -> bool {
+        matches!(self.state, State::Ignored | State::Finished)
+    }
+
+    pub(crate) fn translate(&mut self, event: &BuckEvent) -> Vec<bes::BuildEvent> {
+        use buck2_data::buck_event::Data;
+
+        let mut out = Vec::new();
+        match (&self.state, event.data()) {
+            (State::NotStarted, Data::SpanStart(start)) => {
+                if let Some(buck2_data::span_start_event::Data::Command(command)) = &start.data {
+                    self.start(event, command, &mut out);
+                }
+            }
+            (State::Started { .. }, Data::SpanEnd(end)) => match &end.data {
+                Some(buck2_data::span_end_event::Data::Analysis(analysis)) => {
+                    if let Some(buck2_data::analysis_end::Target::StandardTarget(target)) =
+                        &analysis.target
+                    {
+                        if let Some(key) = target_key(target) {
+                            self.rule_kinds.insert(key, analysis.rule.clone());
+                        }
+                    }
+                }
+                Some(buck2_data::span_end_event::Data::Command(command)) => {
+                    self.finish(event, command, &mut out);
+                }
+                _ => {}
+            },
+            (State::Started { .. }, Data::Instant(instant)) => match &instant.data {
+                Some(buck2_data::instant_event::Data::TargetBuildResult(result)) => {
+                    self.target_built(result, &mut out);
+                }
+                Some(buck2_data::instant_event::Data::TestResult(result)) => {
+                    self.test_result(event, result);
+                }
+                _ => {}
+            },
+            _ => {}
+        }
+        out
+    }
+
+    fn start(
+        &mut self,
+        event: &BuckEvent,
+        command: &buck2_data::CommandStart,
+        out: &mut Vec<bes::BuildEvent>,
+    ) {
+        let command = match command.data {
+            Some(buck2_data::command_start::Data::Build(..)) => "build",
+            Some(buck2_data::command_start::Data::Test(..)) => "test",
+            _ => {
+                self.state = State::Ignored;
+                return;
+            }
+        };
+        self.state = State::Started { command };
+
+        out.push(build_event(
+            Id::Started(build_event_id::BuildStartedId {}),
+            vec![progress_id(0), build_finished_id()],
+            Payload::Started(bes::BuildStarted {
+                uuid: self.trace_id.to_string(),
+                start_time_millis: millis_since_epoch(event.timestamp()),
+                build_tool_version: buck2_build_info::revision().unwrap_or_default().to_owned(),
+                command: command.to_owned(),
+                workspace_directory: self.project_root.clone(),
+                server_pid: std::process::id().into(),
+                ..Default::default()
+            }),
+        ));
+    }
+
+    fn target_built(
+        &mut self,
+        result: &buck2_data::TargetBuildResult,
+        out: &mut Vec<bes::BuildEvent>,
+    ) {
+        let configuration = configuration_name(result.target.as_ref());
+        self.configuration(&configuration, out);
+
+        let label = result.providers_label.clone();
+        let completed_id = Id::TargetCompleted(build_event_id::TargetCompletedId {
+            label: label.clone(),
+            configuration: Some(build_event_id::ConfigurationId {
+                id: configuration.clone(),
+            }),
+            aspect: String::new(),
+        });
+        let configured_id = Id::TargetConfigured(build_event_id::TargetConfiguredId {
+            label,
+            aspect: String::new(),
+        });
+        let named_set_id = if result.outputs.is_empty() {
+            None
+        } else {
+            self.named_sets += 1;
+            Some(build_event_id::NamedSetOfFilesId {
+                id: self.named_sets.to_string(),
+            })
+        };
+
+        let mut announced = vec![event_id(configured_id.clone())];
+        announced.extend(named_set_id.clone().map(|id| event_id(Id::NamedSet(id))));
+        self.announce(announced, out);
+
+        let target_kind = result
+            .target
+            .as_ref()
+            .and_then(target_key)
+            .and_then(|key| self.rule_kinds.get(&key))
+            .map(|rule| format!("{} rule", rule))
+            .unwrap_or_default();
+        out.push(build_event(
+            configured_id,
+            vec![event_id(completed_id.clone())],
+            Payload::Configured(bes::TargetConfigured {
+                target_kind,
+                ..Default::default()
+            }),
+        ));
+
+        if let Some(named_set_id) = &named_set_id {
+            let files = result
+                .outputs
+                .iter()
+                .map(|output| bes::File {
+                    name: output.path.clone(),
+                    file: Some(bes::file::File::Uri(format!(
+                        "file://{}/{}",
+                        self.project_root, output.path
+                    ))),
+                    digest: output.digest.clone().unwrap_or_default(),
+                    length: output.size as i64,
+                    ..Default::default()
+                })
+                .collect();
+            out.push(build_event(
+                Id::NamedSet(named_set_id.clone()),
+                Vec::new(),
+                Payload::NamedSetOfFiles(bes::NamedSetOfFiles {
+                    files,
+                    file_sets: Vec::new(),
+                }),
+            ));
+        }
+
+        out.push(build_event(
+            completed_id,
+            Vec::new(),
+            Payload::Completed(bes::TargetComplete {
+                success: result.success,
+                output_group: vec![bes::OutputGroup {
+                    name: "default".to_owned(),
+                    file_sets: named_set_id.into_iter().collect(),
+                    incomplete: !result.success,
+                }],
+                tag: Vec::new(),
+            }),
+        ));
+    }
+
+    fn test_result(&mut self, event: &BuckEvent, result: &buck2_data::TestResult) {
+        let status = match test_status(result.status) {
+            Some(status) => status,
+            None => return,
+        };
+        let label = result
+            .target_label
+            .as_ref()
+            .and_then(|t| t.label.as_ref())
+            .map(|l| format!("{}:{}", l.package, l.name))
+            .unwrap_or_default();
+        let configuration = configuration_name(result.target_label.as_ref());
+
+        let stop_millis = millis_since_epoch(event.timestamp());
+        let duration_millis = result
+            .duration
+            .clone()
+            .and_then(|d| Duration::try_from(d).ok())
+            .map_or(0, |d| d.as_millis() as i64);
+        let start_millis = stop_millis - duration_millis;
+
+        let target = self
+            .tests
+            .entry((label, configuration))
+            .or_insert_with(|| TestTarget {
+                status: bes::TestStatus::Passed,
+                failed_cases: Vec::new(),
+                first_start_millis: start_millis,
+                last_stop_millis: stop_millis,
+                duration_millis: 0,
+            });
+        if status_rank(status) > status_rank(target.status) {
+            target.status = status;
+        }
+        if status != bes::TestStatus::Passed {
+            target.failed_cases.push(result.name.clone());
+        }
+        target.first_start_millis = target.first_start_millis.min(start_millis);
+        target.last_stop_millis = target.last_stop_millis.max(stop_millis);
+        target.duration_millis += duration_millis;
+    }
+
+    fn finish(
+        &mut self,
+        event: &BuckEvent,
+        command: &buck2_data::CommandEnd,
+        out: &mut Vec<bes::BuildEvent>,
+    ) {
+        let is_test = matches!(self.state, State::Started { command: "test" });
+
+        let tests = std::mem::take(&mut self.tests);
+        let tests_failed = tests.values().any(|t| t.status != bes::TestStatus::Passed);
+        for ((label, configuration), test) in tests {
+            self.configuration(&configuration, out);
+            let configuration_id = build_event_id::ConfigurationId { id: configuration };
+            let result_id = Id::TestResult(build_event_id::TestResultId {
+                label: label.clone(),
+                configuration: Some(configuration_id.clone()),
+                run: 1,
+                shard: 1,
+                attempt: 1,
+            });
+            let summary_id = Id::TestSummary(build_event_id::TestSummaryId {
+                label,
+                configuration: Some(configuration_id),
+            });
+            self.announce(
+                vec![event_id(result_id.clone()), event_id(summary_id.clone())],
+                out,
+            );
+
+            let status_details = if test.failed_cases.is_empty() {
+                String::new()
+            } else {
+                format!("Failed: {}", test.failed_cases.join(", "))
+            };
+            out.push(build_event(
+                result_id,
+                Vec::new(),
+                Payload::TestResult(bes::TestResult {
+                    status: test.status as i32,
+                    status_details,
+                    test_attempt_start_millis_epoch: test.first_start_millis,
+                    test_attempt_duration_millis: test.duration_millis,
+                    ..Default::default()
+                }),
+            ));
+            out.push(build_event(
+                summary_id,
+                Vec::new(),
+                Payload::TestSummary(bes::TestSummary {
+                    overall_status: test.status as i32,
+                    total_run_count: 1,
+                    attempt_count: 1,
+                    shard_count: 1,
+                    first_start_time_millis: test.first_start_millis,
+                    last_stop_time_millis: test.last_stop_millis,
+                    total_run_duration_millis: test.duration_millis,
+                    ..Default::default()
+                }),
+            ));
+        }
+
+        // The last progress event has been announced, it announces nothing itself.
+        out.push(build_event(
+            Id::Progress(build_event_id::ProgressId {
+                opaque_count: self.progress_count,
+            }),
+            Vec::new(),
+            Payload::Progress(bes::Progress::default()),
+        ));
+
+        // Exit codes are Bazel's.
+        let (name, code) = if command.is_success {
+            ("SUCCESS", 0)
+        } else if is_test && tests_failed {
+            ("TESTS_FAILED", 3)
+        } else {
+            ("BUILD_FAILURE", 1)
+        };
+        let mut finished = build_event(
+            Id::BuildFinished(build_event_id::BuildFinishedId {}),
+            Vec::new(),
+            Payload::Finished(bes::BuildFinished {
+                overall_success: command.is_success,
+                exit_code: Some(bes::build_finished::ExitCode {
+                    name: name.to_owned(),
+                    code,
+                }),
+                finish_time_millis: millis_since_epoch(event.timestamp()),
+            }),
+        );
+        finished.last_message = true;
+        out.push(finished);
+
+        self.state = State::Finished;
+    }
+
+    /// Post the event for a configuration the first time it's seen.
+    fn configuration(&mut self, configuration: &str, out: &mut Vec<bes::BuildEvent>) {
+        if !self.configurations.insert(configuration.to_owned()) {
+            return;
+        }
+        let id = Id::Configuration(build_event_id::ConfigurationId {
+            id: configuration.to_owned(),
+        });
+        self.announce(vec![event_id(id.clone())], out);
+        out.push(build_event(
+            id,
+            Vec::new(),
+            Payload::Configuration(bes::Configuration {
+                mnemonic: configuration.to_owned(),
+                platform_name: configuration
+                    .split('#')
+                    .next()
+                    .unwrap_or_default()
+                    .to_owned(),
+                ..Default::default()
+            }),
+        ));
+    }
+
+    /// Post the pending progress event, announcing `children` (which can then be posted) and the
+    /// next progress event.
+    fn announce(&mut self, mut children: Vec<bes::BuildEventId>, out: &mut Vec<bes::BuildEvent>) {
+        let id = Id::Progress(build_event_id::ProgressId {
+            opaque_count: self.progress_count,
+        });
+        self.progress_count += 1;
+        children.push(progress_id(self.progress_count));
+        out.push(build_event(
+            id,
+            children,
+            Payload::Progress(bes::Progress::default()),
+        ));
+    }
+}
+
+fn build_event(id: Id, children: Vec<bes::BuildEventId>, payload: Payload) -> bes::BuildEvent {
+    bes::BuildEvent {
+        id: Some(event_id(id)),
+        children,
+        last_message: false,
+        payload: Some(payload),
+    }
+}
+
+fn event_id(id: Id) -> bes::BuildEventId {
+    bes::BuildEventId { id: Some(id) }
+}
+
+fn progress_id(opaque_count: i32) -> bes::BuildEventId {
+    event_id(Id::Progress(build_event_id::ProgressId { opaque_count }))
+}
+
+fn build_finished_id() -> bes::BuildEventId {
+    event_id(Id::BuildFinished(build_event_id::BuildFinishedId {}))
+}
+
+fn target_key(target: &buck2_data::ConfiguredTargetLabel) -> Option<(String, String)> {
+    let label = target.label.as_ref()?;
+    Some((
+        format!("{}:{}", label.package, label.name),
+        configuration_name(Some(target)),
+    ))
+}
+
+fn configuration_name(target: Option<&buck2_data::ConfiguredTargetLabel>) -> String {
+    target
+        .and_then(|t| t.configuration.as_ref())
+        .map(|c| c.full_name.clone())
+        .unwrap_or_default()
+}
+
+fn millis_since_epoch(time: SystemTime) -> i64 {
+    time.duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as i64)
+}
+
+/// The BEP status of a test case, `None` if the test case didn't run.
+fn test_status(status: i32) -> Option<bes::TestStatus> {
+    use buck2_data::TestStatus;
+
+    match TestStatus::from_i32(status)? {
+        TestStatus::Pass => Some(bes::TestStatus::Passed),
+        TestStatus::Fail | TestStatus::Fatal | TestStatus::ListingFailed => {
+            Some(bes::TestStatus::Failed)
+        }
+        TestStatus::Timeout => Some(bes::TestStatus::Timeout),
+        TestStatus::Flaky => Some(bes::TestStatus::Flaky),
+        TestStatus::NotSetTestStatus
+        | TestStatus::Skip
+        | TestStatus::Omitted
+        | TestStatus::Unknown
+        | TestStatus::Rerun
+        | TestStatus::ListingSuccess => None,
+    }
+}
+
+/// The status of a test target is the worst status of its test cases.
+fn status_rank(status: bes::TestStatus) -> u8 {
+    match status {
+        bes::TestStatus::Failed => 3,
+        bes::TestStatus::Timeout => 2,
+        bes::TestStatus::Passed => 0,
+        _ => 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::span::SpanId;
+
+    fn event(data: buck2_data::buck_event::Data) -> BuckEvent {
+        BuckEvent::new(SystemTime::now(), TraceId::new(), None, None, data)
+    }
+
+    fn target(name: &str) -> buck2_data::ConfiguredTargetLabel {
+        buck2_data::ConfiguredTargetLabel {
+            label: Some(buck2_data::TargetLabel {
+                package: "root//foo".to_owned(),
+                name: name.to_owned(),
+            }),
+            configuration: Some(buck2_data::Configuration {
+                full_name: "cfg#abc".to_owned(),
+            }),
+            execution_configuration: None,
+        }
+    }
+
+    fn command_start(data: buck2_data::command_start::Data) -> BuckEvent {
+        BuckEvent::new(
+            SystemTime::now(),
+            TraceId::new(),
+            Some(SpanId::next()),
+            None,
+            buck2_data::SpanStartEvent {
+                data: Some(
+                    buck2_data::CommandStart {
+                        metadata: HashMap::new(),
+                        data: Some(data),
+                    }
+                    .into(),
+                ),
+            }
+            .into(),
+        )
+    }
+
+    fn command_end(is_success: bool) -> BuckEvent {
+        event(
+            buck2_data::SpanEndEvent {
+                data: Some(
+                    buck2_data::CommandEnd {
+                        is_success,
+                        ..Default::default()
+                    }
+                    .into(),
+                ),
+                ..Default::default()
+            }
+            .into(),
+        )
+    }
+
+    fn instant(data: buck2_data::instant_event::Data) -> BuckEvent {
+        event(buck2_data::InstantEvent { data: Some(data) }.into())
+    }
+
+    fn ids(events: &[bes::BuildEvent]) -> Vec<String> {
+        events
+            .iter()
+            .map(|e| match e.id.as_ref().and_then(|id| id.id.as_ref()) {
+                Some(Id::Progress(p)) => format!("progress {}", p.opaque_count),
+                Some(Id::Started(..)) => "started".to_owned(),
+                Some(Id::Configuration(c)) => format!("configuration {}", c.id),
+                Some(Id::TargetConfigured(t)) => format!("configured {}", t.label),
+                Some(Id::NamedSet(n)) => format!("named set {}", n.id),
+                Some(Id::TargetCompleted(t)) => format!("completed {}", t.label),
+                Some(Id::TestResult(t)) => format!("test result {}", t.label),
+                Some(Id::TestSummary(t)) => format!("test summary {}", t.label),
+                Some(Id::BuildFinished(..)) => "finished".to_owned(),
+                _ => "unknown".to_owned(),
+            })
+            .collect()
+    }
+
+    /// Every event but the first was announced by an earlier one, and every announced event
+    /// was posted.
+    fn assert_announced(events: &[bes::BuildEvent]) {
+        let mut announced = Vec::new();
+        for (i, e) in events.iter().enumerate() {
+            let id = e.id.clone().unwrap();
+            if i > 0 {
+                let pos = announced
+                    .iter()
+                    .position(|a| a == &id)
+                    .unwrap_or_else(|| panic!("{:?} was not announced", id));
+                announced.remove(pos);
+            }
+            announced.extend(e.children.iter().cloned());
+        }
+        assert_eq!(Vec::<bes::BuildEventId>::new(), announced);
+    }
+
+    #[test]
+    fn test_build() {
+        let mut translator = BepTranslator::new(TraceId::new(), "/repo".to_owned());
+        let mut events = Vec::new();
+        events
+            .extend(translator.translate(&command_start(buck2_data::BuildCommandStart {}.into())));
+        events.extend(
+            translator.translate(&event(
+                buck2_data::SpanEndEvent {
+                    data: Some(
+                        buck2_data::AnalysisEnd {
+                            target: Some(buck2_data::analysis_end::Target::StandardTarget(target(
+                                "bar",
+                            ))),
+                            rule: "cxx_binary".to_owned(),
+                            profile: None,
+                            failed: false,
+                        }
+                        .into(),
+                    ),
+                    ..Default::default()
+                }
+                .into(),
+            )),
+        );
+        events.extend(
+            translator.translate(&instant(
+                buck2_data::TargetBuildResult {
+                    providers_label: "root//foo:bar".to_owned(),
+                    target: Some(target("bar")),
+                    success: true,
+                    outputs: vec![buck2_data::TargetBuildOutput {
+                        path: "buck-out/v2/gen/bar".to_owned(),
+                        digest: Some("abcd".to_owned()),
+                        size: 12,
+                    }],
+                }
+                .into(),
+            )),
+        );
+        events.extend(translator.translate(&command_end(true)));
+
+        assert_eq!(
+            vec![
+                "started",
+                "progress 0",
+                "configuration cfg#abc",
+                "progress 1",
+                "configured root//foo:bar",
+                "named set 1",
+                "completed root//foo:bar",
+                "progress 2",
+                "finished",
+            ],
+            ids(&events)
+        );
+        assert_announced(&events);
+        assert!(translator.is_done());
+
+        match &events[4].payload {
+            Some(Payload::Configured(configured)) => {
+                assert_eq!("cxx_binary rule", configured.target_kind)
+            }
+            p => panic!("unexpected payload {:?}", p),
+        }
+        match &events[5].payload {
+            Some(Payload::NamedSetOfFiles(set)) => assert_eq!(
+                Some(bes::file::File::Uri(
+                    "file:///repo/buck-out/v2/gen/bar".to_owned()
+                )),
+                set.files[0].file
+            ),
+            p => panic!("unexpected payload {:?}", p),
+        }
+        assert!(events.last().unwrap().last_message);
+    }
+
+    #[test]
+    fn test_tests() {
+        let mut translator = BepTranslator::new(TraceId::new(), "/repo".to_owned());
+        let mut events = Vec::new();
+        events.extend(translator.translate(&command_start(buck2_data::TestCommandStart {}.into())));
+        for (name, status) in [
+            ("a", buck2_data::TestStatus::Pass),
+            ("b", buck2_data::TestStatus::Fail),
+        ] {
+            events.extend(
+                translator.translate(&instant(
+                    buck2_data::TestResult {
+                        name: name.to_owned(),
+                        status: status as i32,
+                        target_label: Some(target("test")),
+                        ..Default::default()
+                    }
+                    .into(),
+                )),
+            );
+        }
+        events.extend(translator.translate(&command_end(false)));
+
+        assert_eq!(
+            vec![
+                "started",
+                "progress 0",
+                "configuration cfg#abc",
+                "progress 1",
+                "test result root//foo:test",
+                "test summary root//foo:test",
+                "progress 2",
+                "finished",
+            ],
+            ids(&events)
+        );
+        assert_announced(&events);
+        match &events[4].payload {
+            Some(Payload::TestResult(result)) => {
+                assert_eq!(bes::TestStatus::Failed as i32, result.status);
+                assert_eq!("Failed: b", result.status_details);
+            }
+            p => panic!("unexpected payload {:?}", p),
+        }
+        match &events[7].payload {
+            Some(Payload::Finished(finished)) => {
+                assert_eq!(3, finished.exit_code.as_ref().unwrap().code)
+            }
+            p => panic!("unexpected payload {:?}", p),
+        }
+    }
+
+    #[test]
+    fn test_other_commands_are_ignored() {
+        let mut translator = BepTranslator::new(TraceId::new(), "/repo".to_owned());
+        assert!(
+            translator
+                .translate(&command_start(buck2_data::TargetsCommandStart {}.into()))
+                .is_empty()
+        );
+        assert!(translator.is_done());
+    }
+}
@@ -0,0 +1,193 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! A sink that exports spans as OpenTelemetry traces, so builds can be viewed in a tracing
+//! backend alongside the traces of the systems that run them.
+//!
+//! Spans are exported over OTLP/gRPC or OTLP/HTTP to a collector, or appended to a file in the
+//! OTLP JSON format, one `TracesData` per line.
+
+mod export;
+mod translate;
+
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::str::FromStr;
+
+use anyhow::Context;
+use buck2_core::fs::paths::abs_path::AbsPathBuf;
+use buck2_http::HttpClient;
+use buck2_otlp_proto::opentelemetry::proto::common::v1::InstrumentationScope;
+use buck2_otlp_proto::opentelemetry::proto::resource::v1::Resource;
+use buck2_otlp_proto::opentelemetry::proto::trace::v1::ResourceSpans;
+use buck2_otlp_proto::opentelemetry::proto::trace::v1::ScopeSpans;
+use buck2_otlp_proto::opentelemetry::proto::trace::v1::Span;
+use buck2_otlp_proto::opentelemetry::proto::trace::v1::TracesData;
+use buck2_wrapper_common::invocation_id::TraceId;
+use dupe::Dupe;
+use tokio::sync::mpsc;
+
+use crate::sink::otlp::export::OtlpExporter;
+use crate::sink::otlp::translate::string_attribute;
+use crate::sink::otlp::translate::OtlpTranslator;
+use crate::BuckEvent;
+use crate::Event;
+use crate::EventSink;
+
+/// Spans are exported in batches of this size, and when the command ends.
+const BATCH_SIZE: usize = 512;
+
+#[derive(Debug, thiserror::Error)]
+enum OtlpSinkError {
+    #[error("Unknown OTLP protocol `{0}`, expected `grpc` or `http/protobuf`")]
+    UnknownProtocol(String),
+}
+
+/// How to talk to the collector, named like the values of `OTEL_EXPORTER_OTLP_PROTOCOL`.
+#[derive(Debug, Clone, Copy, Dupe, Default, PartialEq, Eq)]
+pub enum OtlpProtocol {
+    #[default]
+    Grpc,
+    HttpProtobuf,
+}
+
+impl FromStr for OtlpProtocol {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "grpc" => Ok(Self::Grpc),
+            "http/protobuf" => Ok(Self::HttpProtobuf),
+            _ => Err(OtlpSinkError::UnknownProtocol(s.to_owned()).into()),
+        }
+    }
+}
+
+/// Where spans are exported, configured in the `buck2` buckconfig section.
+#[derive(Debug, Clone, Default)]
+pub struct OtlpSinkConfig {
+    /// URL of the collector, e.g. `http://localhost:4317` for gRPC.
+    pub endpoint: Option<String>,
+    pub protocol: OtlpProtocol,
+    /// Append spans to this file, in the OTLP JSON format.
+    pub json_file: Option<AbsPathBuf>,
+}
+
+impl OtlpSinkConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.endpoint.is_some() || self.json_file.is_some()
+    }
+}
+
+/// Sink for the spans of one command.
+///
+/// Spans are translated and exported on a separate task. Failing to export spans does not fail
+/// the command, errors are only logged.
+pub struct OtlpSink {
+    sender: mpsc::UnboundedSender<BuckEvent>,
+}
+
+impl OtlpSink {
+    /// Must be called from within a Tokio runtime.
+    pub fn new(config: OtlpSinkConfig, http_client: HttpClient, trace_id: TraceId) -> Self {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            if let Err(e) = export(config, http_client, trace_id, receiver).await {
+                tracing::warn!("Error exporting spans: {:#}", e);
+            }
+        });
+        Self { sender }
+    }
+}
+
+impl EventSink for OtlpSink {
+    fn send(&self, event: Event) {
+        if let Event::Buck(event) = event {
+            if event.span_start_event().is_some() || event.span_end_event().is_some() {
+                // The receiver is gone if exporting failed.
+                let _ignored = self.sender.send(event);
+            }
+        }
+    }
+}
+
+fn resource_spans(spans: Vec<Span>) -> ResourceSpans {
+    let mut attributes = vec![string_attribute("service.name", "buck2")];
+    if let Some(revision) = buck2_build_info::revision() {
+        attributes.push(string_attribute("service.version", revision));
+    }
+    if let Ok(hostname) = hostname::get() {
+        attributes.push(string_attribute(
+            "host.name",
+            hostname.to_string_lossy().into_owned(),
+        ));
+    }
+    ResourceSpans {
+        resource: Some(Resource { attributes }),
+        scope_spans: vec![ScopeSpans {
+            scope: Some(InstrumentationScope {
+                name: "buck2".to_owned(),
+                version: String::new(),
+            }),
+            spans,
+        }],
+    }
+}
+
+async fn export(
+    config: OtlpSinkConfig,
+    http_client: HttpClient,
+    trace_id: TraceId,
+    mut receiver: mpsc::UnboundedReceiver<BuckEvent>,
+) -> anyhow::Result<()> {
+    let mut exporter = match &config.endpoint {
+        Some(endpoint) => {
+            Some(OtlpExporter::connect(endpoint, config.protocol, http_client).await?)
+        }
+        None => None,
+    };
+    let mut translator = OtlpTranslator::new(&trace_id);
+    let mut batch = Vec::new();
+    while let Some(event) = receiver.recv().await {
+        batch.extend(translator.translate(&event));
+        if batch.len() >= BATCH_SIZE || translator.is_done() {
+            let resource_spans = resource_spans(std::mem::take(&mut batch));
+            if let Some(json_file) = &config.json_file {
+                write_json(json_file, &resource_spans).await?;
+            }
+            if let Some(exporter) = &mut exporter {
+                exporter.export(resource_spans).await?;
+            }
+        }
+        if translator.is_done() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+async fn write_json(path: &AbsPathBuf, resource_spans: &ResourceSpans) -> anyhow::Result<()> {
+    let mut line = serde_json::to_vec(&TracesData {
+        resource_spans: vec![resource_spans.clone()],
+    })?;
+    line.push(b'\n');
+    let path = path.clone();
+    // Commands may run concurrently, so write whole lines at once. The file IO is blocking, so
+    // keep it off the runtime threads.
+    tokio::task::spawn_blocking(move || {
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .and_then(|mut file| file.write_all(&line))
+            .with_context(|| format!("Error writing spans to `{}`", path.display()))
+    })
+    .await
+    .context("Writing spans panicked")?
+}
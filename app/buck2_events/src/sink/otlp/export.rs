@@ -0,0 +1,160 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Export of spans to an OTLP collector.
+
+use anyhow::Context;
+use buck2_http::HttpClient;
+use buck2_otlp_proto::opentelemetry::proto::collector::trace::v1::trace_service_client::TraceServiceClient;
+use buck2_otlp_proto::opentelemetry::proto::collector::trace::v1::ExportTraceServiceRequest;
+use buck2_otlp_proto::opentelemetry::proto::trace::v1::ResourceSpans;
+use bytes::Bytes;
+use prost::Message;
+use tonic::transport::Channel;
+
+use crate::sink::otlp::OtlpProtocol;
+
+#[derive(Debug, thiserror::Error)]
+enum OtlpExportError {
+    #[error("OTLP collector rejected {0} spans: {1}")]
+    Rejected(i64, String),
+}
+
+pub(crate) enum OtlpExporter {
+    Grpc(TraceServiceClient<Channel>),
+    Http { client: HttpClient, uri: String },
+}
+
+impl OtlpExporter {
+    pub(crate) async fn connect(
+        endpoint: &str,
+        protocol: OtlpProtocol,
+        http_client: HttpClient,
+    ) -> anyhow::Result<Self> {
+        match protocol {
+            OtlpProtocol::Grpc => Ok(Self::Grpc(
+                TraceServiceClient::connect(endpoint.to_owned())
+                    .await
+                    .with_context(|| {
+                        format!("Error connecting to OTLP collector `{}`", endpoint)
+                    })?,
+            )),
+            // Like `OTEL_EXPORTER_OTLP_ENDPOINT`, the endpoint is the base URL of the collector.
+            OtlpProtocol::HttpProtobuf => Ok(Self::Http {
+                client: http_client,
+                uri: format!("{}/v1/traces", endpoint.trim_end_matches('/')),
+            }),
+        }
+    }
+
+    pub(crate) async fn export(&mut self, resource_spans: ResourceSpans) -> anyhow::Result<()> {
+        let request = ExportTraceServiceRequest {
+            resource_spans: vec![resource_spans],
+        };
+        let partial_success = match self {
+            Self::Grpc(client) => client.export(request).await?.into_inner().partial_success,
+            Self::Http { client, uri } => {
+                // The response is an `ExportTraceServiceResponse` too, but we only need to know
+                // whether the request succeeded.
+                client
+                    .post(
+                        uri,
+                        Bytes::from(request.encode_to_vec()),
+                        vec![(
+                            "Content-Type".to_owned(),
+                            "application/x-protobuf".to_owned(),
+                        )],
+                    )
+                    .await?;
+                None
+            }
+        };
+        match partial_success {
+            Some(p) if p.rejected_spans > 0 => {
+                Err(OtlpExportError::Rejected(p.rejected_spans, p.error_message).into())
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::sync::Mutex;
+
+    use buck2_http::HttpClientBuilder;
+    use buck2_otlp_proto::opentelemetry::proto::collector::trace::v1::trace_service_server::TraceService;
+    use buck2_otlp_proto::opentelemetry::proto::collector::trace::v1::trace_service_server::TraceServiceServer;
+    use buck2_otlp_proto::opentelemetry::proto::collector::trace::v1::ExportTraceServiceResponse;
+    use buck2_otlp_proto::opentelemetry::proto::trace::v1::ScopeSpans;
+    use buck2_otlp_proto::opentelemetry::proto::trace::v1::Span;
+    use tokio::net::TcpListener;
+    use tokio_stream::wrappers::TcpListenerStream;
+    use tonic::Request;
+    use tonic::Response;
+    use tonic::Status;
+
+    use super::*;
+
+    /// Records the spans it receives.
+    #[derive(Default, Clone)]
+    struct FakeCollector {
+        received: Arc<Mutex<Vec<ExportTraceServiceRequest>>>,
+    }
+
+    #[tonic::async_trait]
+    impl TraceService for FakeCollector {
+        async fn export(
+            &self,
+            request: Request<ExportTraceServiceRequest>,
+        ) -> Result<Response<ExportTraceServiceResponse>, Status> {
+            self.received.lock().unwrap().push(request.into_inner());
+            Ok(Response::new(ExportTraceServiceResponse::default()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_export_grpc() -> anyhow::Result<()> {
+        let collector = FakeCollector::default();
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        tokio::spawn(
+            tonic::transport::Server::builder()
+                .add_service(TraceServiceServer::new(collector.clone()))
+                .serve_with_incoming(TcpListenerStream::new(listener)),
+        );
+
+        let mut exporter = OtlpExporter::connect(
+            &format!("http://{}", addr),
+            OtlpProtocol::Grpc,
+            HttpClientBuilder::oss()?.build(),
+        )
+        .await?;
+        let resource_spans = ResourceSpans {
+            resource: None,
+            scope_spans: vec![ScopeSpans {
+                scope: None,
+                spans: vec![Span {
+                    name: "Command".to_owned(),
+                    ..Default::default()
+                }],
+            }],
+        };
+        exporter.export(resource_spans.clone()).await?;
+
+        assert_eq!(
+            vec![ExportTraceServiceRequest {
+                resource_spans: vec![resource_spans]
+            }],
+            *collector.received.lock().unwrap()
+        );
+        Ok(())
+    }
+}
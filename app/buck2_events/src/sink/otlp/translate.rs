@@ -0,0 +1,315 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Translation of buck2 spans into OpenTelemetry spans.
+//!
+//! The OpenTelemetry trace id is the invocation trace id, and span ids are buck2 span ids, so
+//! spans can be correlated with the event log.
+
+use std::collections::HashMap;
+use std::time::SystemTime;
+
+use buck2_otlp_proto::opentelemetry::proto::common::v1::any_value;
+use buck2_otlp_proto::opentelemetry::proto::common::v1::AnyValue;
+use buck2_otlp_proto::opentelemetry::proto::common::v1::KeyValue;
+use buck2_otlp_proto::opentelemetry::proto::trace::v1::span::SpanKind;
+use buck2_otlp_proto::opentelemetry::proto::trace::v1::status::StatusCode;
+use buck2_otlp_proto::opentelemetry::proto::trace::v1::Span;
+use buck2_otlp_proto::opentelemetry::proto::trace::v1::Status;
+use buck2_wrapper_common::invocation_id::TraceId;
+use gazebo::variants::VariantName;
+
+use crate::span::SpanId;
+use crate::BuckEvent;
+
+/// A span which has started but not ended yet.
+struct OpenSpan {
+    name: &'static str,
+    parent_id: Option<SpanId>,
+    start_time: SystemTime,
+    attributes: Vec<KeyValue>,
+}
+
+pub(crate) struct OtlpTranslator {
+    trace_id: Vec<u8>,
+    open: HashMap<SpanId, OpenSpan>,
+    done: bool,
+}
+
+impl OtlpTranslator {
+    pub(crate) fn new(trace_id: &TraceId) -> Self {
+        Self {
+            trace_id: trace_id.as_bytes().to_vec(),
+            open: HashMap::new(),
+            done: false,
+        }
+    }
+
+    /// The command span has ended, no more spans will be produced.
+    pub(crate) fn is_done(&self) -> bool {
+        self.done
+    }
+
+    /// Returns a span when `event` ends one.
+    pub(crate) fn translate(&mut self, event: &BuckEvent) -> Option<Span> {
+        let span_id = event.span_id()?;
+        match event.data() {
+            buck2_data::buck_event::Data::SpanStart(start) => {
+                let data = start.data.as_ref()?;
+                self.open.insert(
+                    span_id,
+                    OpenSpan {
+                        name: data.variant_name(),
+                        parent_id: event.parent_id(),
+                        start_time: event.timestamp(),
+                        attributes: start_attributes(data),
+                    },
+                );
+                None
+            }
+            buck2_data::buck_event::Data::SpanEnd(end) => {
+                let open = self.open.remove(&span_id)?;
+                if let Some(buck2_data::span_end_event::Data::Command(..)) = &end.data {
+                    self.done = true;
+                }
+                Some(Span {
+                    trace_id: self.trace_id.clone(),
+                    span_id: span_id_bytes(span_id),
+                    parent_span_id: open.parent_id.map(span_id_bytes).unwrap_or_default(),
+                    name: open.name.to_owned(),
+                    kind: SpanKind::Internal as i32,
+                    start_time_unix_nano: unix_nanos(open.start_time),
+                    end_time_unix_nano: unix_nanos(event.timestamp()),
+                    attributes: open.attributes,
+                    events: Vec::new(),
+                    status: end.data.as_ref().and_then(status),
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+fn span_id_bytes(span_id: SpanId) -> Vec<u8> {
+    u64::from(span_id).to_be_bytes().to_vec()
+}
+
+fn unix_nanos(time: SystemTime) -> u64 {
+    time.duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |d| d.as_nanos() as u64)
+}
+
+pub(crate) fn string_attribute(key: &str, value: impl Into<String>) -> KeyValue {
+    KeyValue {
+        key: key.to_owned(),
+        value: Some(AnyValue {
+            value: Some(any_value::Value::StringValue(value.into())),
+        }),
+    }
+}
+
+fn target_label(target: &buck2_data::ConfiguredTargetLabel) -> String {
+    let (package, name) = target
+        .label
+        .as_ref()
+        .map_or(("", ""), |l| (l.package.as_str(), l.name.as_str()));
+    match &target.configuration {
+        Some(configuration) => format!("{}:{} ({})", package, name, configuration.full_name),
+        None => format!("{}:{}", package, name),
+    }
+}
+
+fn start_attributes(data: &buck2_data::span_start_event::Data) -> Vec<KeyValue> {
+    use buck2_data::span_start_event::Data;
+
+    let mut attributes = Vec::new();
+    match data {
+        Data::Command(command) => {
+            if let Some(data) = &command.data {
+                attributes.push(string_attribute(
+                    "buck2.command",
+                    data.variant_name().to_lowercase(),
+                ));
+            }
+        }
+        Data::ActionExecution(action) => {
+            if let Some(buck2_data::action_key::Owner::TargetLabel(target)) =
+                action.key.as_ref().and_then(|k| k.owner.as_ref())
+            {
+                attributes.push(string_attribute("buck2.target", target_label(target)));
+            }
+            if let Some(name) = &action.name {
+                attributes.push(string_attribute("buck2.action.category", &name.category));
+                attributes.push(string_attribute(
+                    "buck2.action.identifier",
+                    &name.identifier,
+                ));
+            }
+        }
+        Data::Analysis(analysis) => {
+            if let Some(buck2_data::analysis_start::Target::StandardTarget(target)) =
+                &analysis.target
+            {
+                attributes.push(string_attribute("buck2.target", target_label(target)));
+            }
+            attributes.push(string_attribute("buck2.rule", &analysis.rule));
+        }
+        Data::Load(load) => {
+            attributes.push(string_attribute("buck2.module", &load.module_id));
+        }
+        _ => {}
+    }
+    attributes
+}
+
+fn status(data: &buck2_data::span_end_event::Data) -> Option<Status> {
+    use buck2_data::span_end_event::Data;
+
+    let failed = match data {
+        Data::Command(command) => !command.is_success,
+        Data::ActionExecution(action) => action.failed,
+        Data::Load(load) => load.error.is_some(),
+        _ => return None,
+    };
+    let code = if failed {
+        StatusCode::Error
+    } else {
+        StatusCode::Ok
+    };
+    Some(Status {
+        message: String::new(),
+        code: code as i32,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn start(
+        span_id: SpanId,
+        parent_id: Option<SpanId>,
+        data: buck2_data::span_start_event::Data,
+    ) -> BuckEvent {
+        BuckEvent::new(
+            SystemTime::now(),
+            TraceId::new(),
+            Some(span_id),
+            parent_id,
+            buck2_data::SpanStartEvent { data: Some(data) }.into(),
+        )
+    }
+
+    fn end(span_id: SpanId, data: buck2_data::span_end_event::Data) -> BuckEvent {
+        BuckEvent::new(
+            SystemTime::now(),
+            TraceId::new(),
+            Some(span_id),
+            None,
+            buck2_data::SpanEndEvent {
+                data: Some(data),
+                ..Default::default()
+            }
+            .into(),
+        )
+    }
+
+    #[test]
+    fn test_translate() {
+        let trace_id = TraceId::new();
+        let mut translator = OtlpTranslator::new(&trace_id);
+        let command = SpanId::next();
+        let load = SpanId::next();
+
+        assert!(
+            translator
+                .translate(&start(
+                    command,
+                    None,
+                    buck2_data::CommandStart {
+                        metadata: HashMap::new(),
+                        data: Some(buck2_data::BuildCommandStart {}.into()),
+                    }
+                    .into(),
+                ))
+                .is_none()
+        );
+        assert!(
+            translator
+                .translate(&start(
+                    load,
+                    Some(command),
+                    buck2_data::LoadBuildFileStart {
+                        module_id: "root//foo:BUCK".to_owned(),
+                        cell: "root".to_owned(),
+                    }
+                    .into(),
+                ))
+                .is_none()
+        );
+
+        let load_span = translator
+            .translate(&end(
+                load,
+                buck2_data::LoadBuildFileEnd {
+                    module_id: "root//foo:BUCK".to_owned(),
+                    cell: "root".to_owned(),
+                    error: Some("syntax error".to_owned()),
+                    ..Default::default()
+                }
+                .into(),
+            ))
+            .unwrap();
+        assert_eq!(trace_id.as_bytes().to_vec(), load_span.trace_id);
+        assert_eq!(span_id_bytes(load), load_span.span_id);
+        assert_eq!(span_id_bytes(command), load_span.parent_span_id);
+        assert_eq!("Load", load_span.name);
+        assert_eq!(
+            vec![string_attribute("buck2.module", "root//foo:BUCK")],
+            load_span.attributes
+        );
+        assert_eq!(StatusCode::Error as i32, load_span.status.unwrap().code);
+        assert!(!translator.is_done());
+
+        let command_span = translator
+            .translate(&end(
+                command,
+                buck2_data::CommandEnd {
+                    is_success: true,
+                    ..Default::default()
+                }
+                .into(),
+            ))
+            .unwrap();
+        assert!(command_span.parent_span_id.is_empty());
+        assert_eq!(
+            vec![string_attribute("buck2.command", "build")],
+            command_span.attributes
+        );
+        assert!(command_span.start_time_unix_nano <= command_span.end_time_unix_nano);
+        assert!(translator.is_done());
+    }
+
+    #[test]
+    fn test_json_encoding() {
+        let span = Span {
+            trace_id: vec![0xab; 16],
+            span_id: span_id_bytes(SpanId::from_u64(1).unwrap()),
+            start_time_unix_nano: 1_000_000_000_000_000_000,
+            attributes: vec![string_attribute("buck2.command", "build")],
+            ..Default::default()
+        };
+        let json = serde_json::to_value(&span).unwrap();
+        assert_eq!("abababababababababababababababab", json["traceId"]);
+        assert_eq!("0000000000000001", json["spanId"]);
+        assert_eq!("", json["parentSpanId"]);
+        assert_eq!("1000000000000000000", json["startTimeUnixNano"]);
+        assert_eq!("build", json["attributes"][0]["value"]["stringValue"]);
+    }
+}
@@ -271,6 +271,9 @@ mod fbcode {
                 // Save some bytes.
                 truncate_cmd(last_command, !action_execution_end.failed);
             }
+
+            // Only useful when comparing event logs, and can be arbitrarily large.
+            action_execution_end.digest_inputs = None;
         }
 
         fn truncate_command_end(
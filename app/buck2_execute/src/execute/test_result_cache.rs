@@ -0,0 +1,165 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! The passing test executions the daemon keeps in memory for the test result cache, enabled by
+//! `test.cache_results`.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use buck2_core::fs::buck_out_path::BuckOutTestPath;
+use buck2_core::fs::paths::forward_rel_path::ForwardRelativePath;
+use buck2_core::fs::paths::forward_rel_path::ForwardRelativePathBuf;
+use dice::DiceComputations;
+use dice::UserComputationData;
+use dupe::Dupe;
+use linked_hash_map::LinkedHashMap;
+use parking_lot::Mutex;
+use tokio::sync::OwnedMutexGuard;
+
+use crate::execute::action_digest::ActionDigest;
+use crate::execute::result::CommandExecutionMetadata;
+
+/// How many test executions the daemon keeps by default.
+const DEFAULT_CAPACITY: usize = 10000;
+
+/// What is reported for a test execution served by the cache.
+pub struct CachedTestExecution {
+    pub stdout: Vec<u8>,
+    pub stderr: Vec<u8>,
+    pub timing: CommandExecutionMetadata,
+    /// The outputs the test wrote, which are still in place as long as the test doesn't run again.
+    pub outputs: Vec<BuckOutTestPath>,
+}
+
+/// Test executions keyed by the digest of their action, evicting the least recently used ones.
+pub struct TestResultCache {
+    capacity: usize,
+    executions: Mutex<LinkedHashMap<ActionDigest, Arc<CachedTestExecution>>>,
+    /// Cacheable executions of a test always write to the same output directory, so they must not
+    /// run at the same time, including in different commands.
+    output_roots: Mutex<HashMap<ForwardRelativePathBuf, Arc<tokio::sync::Mutex<()>>>>,
+}
+
+impl Default for TestResultCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_CAPACITY)
+    }
+}
+
+impl TestResultCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            executions: Mutex::new(LinkedHashMap::new()),
+            output_roots: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn lookup(&self, digest: &ActionDigest) -> Option<Arc<CachedTestExecution>> {
+        self.executions.lock().get_refresh(digest).map(|e| e.dupe())
+    }
+
+    pub fn store(&self, digest: ActionDigest, execution: CachedTestExecution) {
+        let mut executions = self.executions.lock();
+        executions.insert(digest, Arc::new(execution));
+        while executions.len() > self.capacity {
+            executions.pop_front();
+        }
+    }
+
+    /// Waits until no other execution uses this output directory, and keeps it until the guard
+    /// is dropped.
+    pub async fn lock_output_root(&self, output_root: &ForwardRelativePath) -> OwnedMutexGuard<()> {
+        let lock = {
+            let mut output_roots = self.output_roots.lock();
+            if output_roots.len() > self.capacity {
+                // Drop the locks which nobody holds or waits for.
+                output_roots.retain(|_, lock| Arc::strong_count(lock) > 1);
+            }
+            output_roots.entry(output_root.to_buf()).or_default().dupe()
+        };
+        lock.lock_owned().await
+    }
+}
+
+pub trait SetTestResultCache {
+    fn set_test_result_cache(&mut self, cache: Arc<TestResultCache>);
+}
+
+pub trait HasTestResultCache {
+    fn get_test_result_cache(&self) -> Arc<TestResultCache>;
+}
+
+impl SetTestResultCache for UserComputationData {
+    fn set_test_result_cache(&mut self, cache: Arc<TestResultCache>) {
+        self.data.set(cache);
+    }
+}
+
+impl HasTestResultCache for DiceComputations {
+    fn get_test_result_cache(&self) -> Arc<TestResultCache> {
+        self.per_transaction_data()
+            .data
+            .get::<Arc<TestResultCache>>()
+            .expect("TestResultCache should be set")
+            .dupe()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use buck2_common::cas_digest::CasDigestConfig;
+
+    use super::*;
+
+    fn digest(content: &str) -> ActionDigest {
+        ActionDigest::from_content(content.as_bytes(), CasDigestConfig::testing_default())
+    }
+
+    fn execution(stdout: &str) -> CachedTestExecution {
+        CachedTestExecution {
+            stdout: stdout.as_bytes().to_vec(),
+            stderr: Vec::new(),
+            timing: CommandExecutionMetadata::default(),
+            outputs: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_evicts_least_recently_used() {
+        let cache = TestResultCache::new(2);
+        cache.store(digest("a"), execution("a"));
+        cache.store(digest("b"), execution("b"));
+        // Looking up `a` makes `b` the least recently used.
+        assert_eq!(b"a", cache.lookup(&digest("a")).unwrap().stdout.as_slice());
+        cache.store(digest("c"), execution("c"));
+        assert!(cache.lookup(&digest("a")).is_some());
+        assert!(cache.lookup(&digest("b")).is_none());
+        assert!(cache.lookup(&digest("c")).is_some());
+    }
+    #[tokio::test]
+    async fn test_lock_output_root() {
+        let cache = TestResultCache::new(2);
+        let a = ForwardRelativePath::new("cacheable/a").unwrap();
+        let b = ForwardRelativePath::new("cacheable/b").unwrap();
+        let guard = cache.lock_output_root(a).await;
+        // Other output directories can be locked at the same time.
+        drop(cache.lock_output_root(b).await);
+        assert!(
+            tokio::time::timeout(Duration::from_millis(10), cache.lock_output_root(a))
+                .await
+                .is_err()
+        );
+        drop(guard);
+        drop(cache.lock_output_root(a).await);
+    }
+}
@@ -31,6 +31,12 @@ pub trait FileWatcher: Allocative + Send + Sync + 'static {
         &self,
         dice: DiceTransactionUpdater,
     ) -> anyhow::Result<(DiceTransactionUpdater, Mergebase)>;
+
+    /// Resolves once the watcher sees a change (to a file which isn't ignored) made after this
+    /// was called. The change is not consumed: it will be picked up by the next `sync`.
+    ///
+    /// This is used by `--watch` to know when to start a new build.
+    async fn wait_for_changes(&self) -> anyhow::Result<()>;
 }
 
 impl dyn FileWatcher {
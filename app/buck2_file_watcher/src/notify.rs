@@ -33,6 +33,7 @@ use notify::EventKind;
 use notify::RecommendedWatcher;
 use notify::Watcher;
 use starlark_map::ordered_set::OrderedSet;
+use tokio::sync::Notify;
 use tracing::info;
 
 use crate::file_watcher::FileWatcher;
@@ -93,14 +94,17 @@ impl NotifyFileData {
         }
     }
 
+    /// Record an event. Returns whether it contained any change which isn't ignored, even if
+    /// that change was already pending.
     fn process(
         &mut self,
         event: notify::Result<notify::Event>,
         root: &ProjectRoot,
         cells: &CellResolver,
         ignore_specs: &HashMap<CellName, IgnoreSet>,
-    ) -> anyhow::Result<()> {
+    ) -> anyhow::Result<bool> {
         let event = event?;
+        let mut changed = false;
         let change_type = ChangeType::new(event.kind);
         for path in event.paths {
             // Testing shows that we get absolute paths back from the `notify` library.
@@ -132,9 +136,10 @@ impl NotifyFileData {
                 self.ignored += 1;
             } else {
                 self.events.insert((cell_path, change_type));
+                changed = true;
             }
         }
-        Ok(())
+        Ok(changed)
     }
 
     fn sync(self) -> (buck2_data::FileWatcherStats, FileChangeTracker) {
@@ -181,6 +186,9 @@ pub struct NotifyFileWatcher {
     #[allocative(skip)]
     watcher: RecommendedWatcher,
     data: Arc<Mutex<anyhow::Result<NotifyFileData>>>,
+    /// Notified whenever a change which isn't ignored is recorded in `data`.
+    #[allocative(skip)]
+    changed: Arc<Notify>,
 }
 
 impl NotifyFileWatcher {
@@ -191,17 +199,32 @@ impl NotifyFileWatcher {
     ) -> anyhow::Result<Self> {
         let data = Arc::new(Mutex::new(Ok(NotifyFileData::new())));
         let data2 = data.dupe();
+        let changed = Arc::new(Notify::new());
+        let changed2 = changed.dupe();
         let root2 = root.dupe();
         let mut watcher = notify::recommended_watcher(move |event| {
             let mut guard = data2.lock().unwrap();
             if let Ok(state) = &mut *guard {
-                if let Err(e) = state.process(event, &root2, &cells, &ignore_specs) {
-                    *guard = Err(e);
+                match state.process(event, &root2, &cells, &ignore_specs) {
+                    Ok(changed) => {
+                        if changed {
+                            changed2.notify_waiters();
+                        }
+                    }
+                    Err(e) => {
+                        *guard = Err(e);
+                        // Wake up the waiters so that the next sync reports the error.
+                        changed2.notify_waiters();
+                    }
                 }
             }
         })?;
         watcher.watch(root.root().as_path(), notify::RecursiveMode::Recursive)?;
-        Ok(Self { watcher, data })
+        Ok(Self {
+            watcher,
+            data,
+            changed,
+        })
     }
 
     fn sync2(
@@ -239,4 +262,9 @@ impl FileWatcher for NotifyFileWatcher {
         )
         .await
     }
+
+    async fn wait_for_changes(&self) -> anyhow::Result<()> {
+        self.changed.notified().await;
+        Ok(())
+    }
 }
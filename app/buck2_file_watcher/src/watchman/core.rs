@@ -24,6 +24,8 @@ use tokio::sync::mpsc::UnboundedReceiver;
 use tokio::sync::mpsc::UnboundedSender;
 use tokio::sync::oneshot;
 use watchman_client::prelude::*;
+use watchman_client::Subscription;
+use watchman_client::SubscriptionData;
 
 #[derive(Debug, buck2_error::Error)]
 enum WatchmanClientError {
@@ -36,6 +38,9 @@ enum WatchmanClientError {
     #[buck2(infra, typ = Watchman)]
     #[error(transparent)]
     RequestFailed(watchman_client::Error),
+    #[buck2(infra, typ = Watchman)]
+    #[error("Watchman subscription was canceled")]
+    SubscriptionCanceled,
 }
 
 // We use the "new" field. This is marked as deprecated, but buck1 uses it and
@@ -180,6 +185,36 @@ impl WatchmanClient {
         with_timeout(fut).await
     }
 
+    /// Subscribe to the changes matching `expression` made from now on.
+    pub async fn subscribe<
+        F: serde::de::DeserializeOwned
+            + std::fmt::Debug
+            + Clone
+            + QueryFieldList
+            + Send
+            + Sync
+            + 'static,
+    >(
+        &self,
+        expression: Option<Expr>,
+    ) -> anyhow::Result<Subscription<F>> {
+        // Without a `since`, the first notification of the subscription would list every file.
+        let clock = with_timeout(self.client().clock(self.root(), SyncTimeout::Default)).await?;
+        let (subscription, _) = with_timeout(self.client().subscribe::<F>(
+            self.root(),
+            SubscribeRequest {
+                since: Some(Clock::Spec(clock)),
+                expression,
+                fields: vec!["name"],
+                empty_on_fresh_instance: true,
+                case_sensitive: true,
+                ..SubscribeRequest::default()
+            },
+        ))
+        .await?;
+        Ok(subscription)
+    }
+
     fn root(&self) -> &ResolvedRoot {
         &self.0.1
     }
@@ -215,6 +250,7 @@ pub trait SyncableQueryProcessor: Send + Sync {
 /// commands to be sent to the SyncableQueryHandler.
 enum SyncableQueryCommand<T, P> {
     Sync(P, oneshot::Sender<anyhow::Result<(T, P)>>),
+    Subscribe(oneshot::Sender<anyhow::Result<Subscription<BuckQueryResult>>>),
 }
 
 /// A SyncableQuery is similar to a subscription. When created, it accepts a query expression
@@ -284,6 +320,10 @@ where
                     // job. That's fine.
                     let _ignore = sync_tx.send(res);
                 }
+                Some(SyncableQueryCommand::Subscribe(subscribe_tx)) => {
+                    let res = self.subscribe(&client).await;
+                    let _ignore = subscribe_tx.send(res);
+                }
                 None => {
                     // This indicates the controlling SyncableQuery has been dropped.
                     return;
@@ -350,6 +390,16 @@ where
         Ok(res)
     }
 
+    /// subscribe() starts a watchman subscription with our query, which reports the changes made
+    /// from now on. This doesn't process the events nor update the clock used by sync().
+    async fn subscribe(
+        &self,
+        client: &Option<WatchmanClient>,
+    ) -> anyhow::Result<Subscription<BuckQueryResult>> {
+        let client = client.as_ref().context("No Watchman connection")?;
+        client.subscribe(self.query.expression.clone()).await
+    }
+
     async fn reconnect(&mut self, client: &mut Option<WatchmanClient>) -> anyhow::Result<()> {
         self.last_clock = Default::default();
         self.last_mergebase = None;
@@ -474,6 +524,53 @@ where
         }
     }
 
+    /// Resolves once watchman reports a change made after this was called. The change is not
+    /// processed: it will be picked up by the next `sync()`.
+    pub fn wait_for_changes(&self) -> impl Future<Output = anyhow::Result<()>> + Send + 'static {
+        let (subscribe_done_tx, subscribe_done_rx) = tokio::sync::oneshot::channel();
+        let tx_res = self
+            .control_tx
+            .send(SyncableQueryCommand::Subscribe(subscribe_done_tx));
+
+        async move {
+            tx_res.ok().context("SyncableQueryHandler has exited")?;
+
+            let mut subscription = subscribe_done_rx
+                .await
+                .context("SyncableQueryHandler did not return a response for subscribe request")?
+                .context("Error subscribing to Watchman")?;
+
+            loop {
+                match subscription
+                    .next()
+                    .await
+                    .map_err(WatchmanClientError::RequestFailed)?
+                {
+                    SubscriptionData::FilesChanged(QueryResult {
+                        is_fresh_instance,
+                        files,
+                        ..
+                    }) => {
+                        if is_fresh_instance || files.map_or(false, |files| !files.is_empty()) {
+                            break;
+                        }
+                    }
+                    SubscriptionData::StateEntered { .. } | SubscriptionData::StateLeft { .. } => {}
+                    SubscriptionData::Canceled => {
+                        return Err(WatchmanClientError::SubscriptionCanceled.into());
+                    }
+                }
+            }
+
+            // We only wanted to hear about the first change.
+            subscription
+                .cancel()
+                .await
+                .map_err(WatchmanClientError::RequestFailed)?;
+            Ok(())
+        }
+    }
+
     pub fn new(
         connector: Connector,
         path: impl AsRef<Path>,
@@ -342,4 +342,8 @@ impl FileWatcher for WatchmanFileWatcher {
         )
         .await
     }
+
+    async fn wait_for_changes(&self) -> anyhow::Result<()> {
+        self.query.wait_for_changes().await
+    }
 }
@@ -46,6 +46,7 @@ impl SplitTransitionDepAttrType {
             ConfiguredSplitTransitionDep {
                 deps: configured_providers,
                 required_providers: self.required_providers.dupe(),
+                transition: self.transition.dupe(),
             },
         )))
     }
@@ -61,6 +62,7 @@ pub trait SplitTransitionDepMaybeConfigured: Display + Allocative {
 pub struct ConfiguredSplitTransitionDep {
     pub deps: SortedMap<String, ConfiguredProvidersLabel>,
     pub required_providers: ProviderIdSet,
+    pub transition: Arc<TransitionId>,
 }
 
 impl Display for ConfiguredSplitTransitionDep {
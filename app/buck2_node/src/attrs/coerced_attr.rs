@@ -84,7 +84,7 @@ enum CoercedAttrError {
     InconsistentTupleLength,
 }
 
-enum CoercedSelectorKeyRef<'a> {
+pub(crate) enum CoercedSelectorKeyRef<'a> {
     Target(&'a TargetLabel),
     Default,
 }
@@ -138,7 +138,9 @@ impl CoercedSelector {
         Ok(())
     }
 
-    fn all_entries(&self) -> impl Iterator<Item = (CoercedSelectorKeyRef, &CoercedAttr)> {
+    pub(crate) fn all_entries(
+        &self,
+    ) -> impl Iterator<Item = (CoercedSelectorKeyRef, &CoercedAttr)> {
         self.entries
             .iter()
             .map(|(k, v)| (CoercedSelectorKeyRef::Target(k), v))
@@ -197,7 +197,7 @@ impl ConfiguredAttr {
             ConfiguredAttr::ExplicitConfiguredDep(dep) => dep.as_ref().traverse(traversal),
             ConfiguredAttr::SplitTransitionDep(deps) => {
                 for target in deps.deps.values() {
-                    traversal.dep(target)?;
+                    traversal.split_transition_dep(target, &deps.transition)?;
                 }
                 Ok(())
             }
@@ -7,7 +7,10 @@
  * of this source tree.
  */
 
+use std::sync::Arc;
+
 use buck2_core::buck_path::path::BuckPathRef;
+use buck2_core::configuration::transition::id::TransitionId;
 use buck2_core::plugins::PluginKind;
 use buck2_core::plugins::PluginKindSet;
 use buck2_core::provider::label::ConfiguredProvidersLabel;
@@ -37,6 +40,24 @@ pub trait ConfiguredAttrTraversal {
         self.dep(dep)
     }
 
+    fn transition_dep(
+        &mut self,
+        dep: &ConfiguredProvidersLabel,
+        _tr: &Arc<TransitionId>,
+    ) -> anyhow::Result<()> {
+        // By default, just treat it as a dep. Most things don't care about the distinction.
+        self.dep(dep)
+    }
+
+    fn split_transition_dep(
+        &mut self,
+        dep: &ConfiguredProvidersLabel,
+        _tr: &Arc<TransitionId>,
+    ) -> anyhow::Result<()> {
+        // By default, just treat it as a dep. Most things don't care about the distinction.
+        self.dep(dep)
+    }
+
     fn configuration_dep(&mut self, _dep: &TargetLabel) -> anyhow::Result<()> {
         Ok(())
     }
@@ -31,6 +31,7 @@ pub mod internal;
 pub mod json;
 pub mod serialize;
 pub mod spec;
+pub mod starlark_syntax;
 pub mod testing;
 pub mod traversal;
 pub mod values;
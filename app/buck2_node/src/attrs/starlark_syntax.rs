@@ -0,0 +1,236 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Render coerced attributes as Starlark expressions which can be pasted into a `BUCK` file.
+//!
+//! Unlike [`AttrDisplayWithContext`](crate::attrs::display::AttrDisplayWithContext),
+//! which produces something that only roughly looks like Starlark, the output here
+//! is meant to be valid Starlark which evaluates to the same coerced value.
+
+use std::fmt;
+use std::fmt::Display;
+use std::fmt::Write;
+
+use crate::attrs::coerced_attr::CoercedAttr;
+use crate::attrs::coerced_attr::CoercedSelectorKeyRef;
+
+/// Formats a [`CoercedAttr`] as a Starlark expression.
+pub struct CoercedAttrAsStarlark<'a> {
+    attr: &'a CoercedAttr,
+}
+
+impl CoercedAttr {
+    pub fn as_starlark(&self) -> CoercedAttrAsStarlark<'_> {
+        CoercedAttrAsStarlark { attr: self }
+    }
+}
+
+impl<'a> CoercedAttrAsStarlark<'a> {
+    fn nested(&self, attr: &'a CoercedAttr) -> CoercedAttrAsStarlark<'a> {
+        attr.as_starlark()
+    }
+}
+
+impl<'a> Display for CoercedAttrAsStarlark<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.attr {
+            CoercedAttr::Selector(s) => {
+                write!(f, "select({{")?;
+                for (i, (key, value)) in s.all_entries().enumerate() {
+                    if i != 0 {
+                        write!(f, ", ")?;
+                    }
+                    match key {
+                        CoercedSelectorKeyRef::Target(k) => {
+                            fmt_string_literal(&k.to_string(), f)?;
+                        }
+                        CoercedSelectorKeyRef::Default => {
+                            fmt_string_literal("DEFAULT", f)?;
+                        }
+                    }
+                    write!(f, ": {}", self.nested(value))?;
+                }
+                write!(f, "}})")
+            }
+            CoercedAttr::Concat(items) => {
+                for (i, item) in items.iter().enumerate() {
+                    if i != 0 {
+                        write!(f, " + ")?;
+                    }
+                    Display::fmt(&self.nested(item), f)?;
+                }
+                Ok(())
+            }
+            CoercedAttr::Bool(v) => write!(f, "{}", if v.0 { "True" } else { "False" }),
+            CoercedAttr::Int(v) => write!(f, "{}", v),
+            CoercedAttr::String(v) | CoercedAttr::EnumVariant(v) => fmt_string_literal(v, f),
+            CoercedAttr::List(list) => {
+                write!(f, "[")?;
+                for (i, v) in list.iter().enumerate() {
+                    if i != 0 {
+                        write!(f, ", ")?;
+                    }
+                    Display::fmt(&self.nested(v), f)?;
+                }
+                write!(f, "]")
+            }
+            CoercedAttr::Tuple(tuple) => {
+                write!(f, "(")?;
+                for (i, v) in tuple.iter().enumerate() {
+                    if i != 0 {
+                        write!(f, ", ")?;
+                    }
+                    Display::fmt(&self.nested(v), f)?;
+                }
+                if tuple.len() == 1 {
+                    write!(f, ",")?;
+                }
+                write!(f, ")")
+            }
+            CoercedAttr::Dict(dict) => {
+                write!(f, "{{")?;
+                for (i, (k, v)) in dict.iter().enumerate() {
+                    if i != 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}: {}", self.nested(k), self.nested(v))?;
+                }
+                write!(f, "}}")
+            }
+            CoercedAttr::None => write!(f, "None"),
+            CoercedAttr::OneOf(box l, _) => Display::fmt(&self.nested(l), f),
+            CoercedAttr::Visibility(v) => fmt_json_as_starlark(&v.to_json(), f),
+            CoercedAttr::WithinView(v) => fmt_json_as_starlark(&v.to_json(), f),
+            CoercedAttr::ExplicitConfiguredDep(e) => {
+                write!(f, "(")?;
+                fmt_string_literal(&e.label.to_string(), f)?;
+                write!(f, ", ")?;
+                fmt_string_literal(&e.platform.to_string(), f)?;
+                write!(f, ")")
+            }
+            CoercedAttr::SplitTransitionDep(e) => fmt_string_literal(&e.to_string(), f),
+            CoercedAttr::ConfiguredDep(e) => fmt_string_literal(&e.to_string(), f),
+            CoercedAttr::ConfigurationDep(e) => fmt_string_literal(&e.to_string(), f),
+            CoercedAttr::PluginDep(e) => fmt_string_literal(&e.to_string(), f),
+            CoercedAttr::Dep(e) => fmt_string_literal(&e.to_string(), f),
+            CoercedAttr::SourceLabel(e) => fmt_string_literal(&e.to_string(), f),
+            CoercedAttr::Label(e) => fmt_string_literal(&e.to_string(), f),
+            CoercedAttr::Arg(e) => fmt_string_literal(&e.to_string(), f),
+            CoercedAttr::Query(e) => fmt_string_literal(e.query(), f),
+            // Source files are written relative to the package, like they are in `BUCK` files.
+            CoercedAttr::SourceFile(e) => fmt_string_literal(e.path().as_str(), f),
+            CoercedAttr::Metadata(m) => fmt_json_as_starlark(&m.to_value(), f),
+        }
+    }
+}
+
+/// Write a double-quoted Starlark string literal.
+///
+/// Other control characters are written as `\xNN` escapes, so the literal stays on one line.
+pub fn fmt_string_literal(s: &str, f: &mut dyn Write) -> fmt::Result {
+    f.write_char('"')?;
+    for c in s.chars() {
+        match c {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\r' => f.write_str("\\r")?,
+            '\t' => f.write_str("\\t")?,
+            c if (c as u32) < 0x20 || c == '\x7f' => write!(f, "\\x{:02x}", c as u32)?,
+            c => f.write_char(c)?,
+        }
+    }
+    f.write_char('"')
+}
+
+/// JSON is almost Starlark: only the constants are spelled differently.
+fn fmt_json_as_starlark(value: &serde_json::Value, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match value {
+        serde_json::Value::Null => write!(f, "None"),
+        serde_json::Value::Bool(b) => write!(f, "{}", if *b { "True" } else { "False" }),
+        serde_json::Value::Number(n) => write!(f, "{}", n),
+        serde_json::Value::String(s) => fmt_string_literal(s, f),
+        serde_json::Value::Array(xs) => {
+            write!(f, "[")?;
+            for (i, x) in xs.iter().enumerate() {
+                if i != 0 {
+                    write!(f, ", ")?;
+                }
+                fmt_json_as_starlark(x, f)?;
+            }
+            write!(f, "]")
+        }
+        serde_json::Value::Object(m) => {
+            write!(f, "{{")?;
+            for (i, (k, v)) in m.iter().enumerate() {
+                if i != 0 {
+                    write!(f, ", ")?;
+                }
+                fmt_string_literal(k, f)?;
+                write!(f, ": ")?;
+                fmt_json_as_starlark(v, f)?;
+            }
+            write!(f, "}}")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use buck2_core::target::label::TargetLabel;
+
+    use crate::attrs::attr_type::bool::BoolLiteral;
+    use crate::attrs::attr_type::list::ListLiteral;
+    use crate::attrs::attr_type::string::StringLiteral;
+    use crate::attrs::coerced_attr::CoercedAttr;
+    use crate::attrs::coerced_attr::CoercedSelector;
+
+    fn string(s: &str) -> CoercedAttr {
+        CoercedAttr::String(StringLiteral(s.into()))
+    }
+
+    #[test]
+    fn test_scalars() {
+        assert_eq!(
+            "True",
+            CoercedAttr::Bool(BoolLiteral(true))
+                .as_starlark()
+                .to_string()
+        );
+        assert_eq!("None", CoercedAttr::None.as_starlark().to_string());
+        assert_eq!(
+            r#""a\"b\\c\n""#,
+            string("a\"b\\c\n").as_starlark().to_string()
+        );
+        assert_eq!(
+            r#""\x00\x1b[0m\x7f""#,
+            string("\0\x1b[0m\x7f").as_starlark().to_string()
+        );
+    }
+
+    #[test]
+    fn test_select_concat() {
+        let list =
+            |xs: &[&str]| CoercedAttr::List(ListLiteral(xs.iter().map(|x| string(x)).collect()));
+        let select = CoercedAttr::Selector(Box::new(
+            CoercedSelector::new(
+                vec![(TargetLabel::testing_parse("root//:linux"), list(&["b"]))]
+                    .into_iter()
+                    .collect(),
+                Some(list(&[])),
+            )
+            .unwrap(),
+        ));
+        let concat = CoercedAttr::Concat(vec![list(&["a"]), select].into_iter().collect());
+        assert_eq!(
+            r#"["a"] + select({"root//:linux": ["b"], "DEFAULT": []})"#,
+            concat.as_starlark().to_string()
+        );
+    }
+}
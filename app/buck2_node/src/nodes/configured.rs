@@ -24,6 +24,7 @@ use buck2_core::configuration::data::ConfigurationData;
 use buck2_core::configuration::pair::ConfigurationNoExec;
 use buck2_core::configuration::transition::applied::TransitionApplied;
 use buck2_core::configuration::transition::id::TransitionId;
+use buck2_core::execution_types::execution::ExecutionPlatform;
 use buck2_core::execution_types::execution::ExecutionPlatformResolution;
 use buck2_core::plugins::PluginKind;
 use buck2_core::plugins::PluginKindSet;
@@ -197,17 +198,29 @@ impl Debug for ConfiguredTargetNodeData {
 impl ConfiguredTargetNode {
     /// Creates a minimal ConfiguredTargetNode. Some operations may unexpectedly fail.
     pub fn testing_new(name: ConfiguredTargetLabel, rule_type: &str) -> Self {
+        Self::testing_new_with_attrs(name, rule_type, Vec::new(), None)
+    }
+
+    /// Like `testing_new`, but with attributes, configured with `execution_platform`
+    /// for exec deps.
+    pub fn testing_new_with_attrs(
+        name: ConfiguredTargetLabel,
+        rule_type: &str,
+        attrs: Vec<(&str, Attribute, CoercedAttr)>,
+        execution_platform: Option<ExecutionPlatform>,
+    ) -> Self {
         use crate::nodes::unconfigured::testing::TargetNodeExt;
 
         let rule_type = RuleType::Starlark(Arc::new(StarlarkRuleType {
             import_path: ImportPath::testing_new("cell//pkg:rules.bzl"),
             name: rule_type.to_owned(),
         }));
-        let execution_platform_resolution = ExecutionPlatformResolution::new(None, Vec::new());
+        let execution_platform_resolution =
+            ExecutionPlatformResolution::new(execution_platform, Vec::new());
 
         Self::new(
             name.dupe(),
-            TargetNode::testing_new(name.unconfigured().dupe(), rule_type, Vec::new()),
+            TargetNode::testing_new(name.unconfigured().dupe(), rule_type, attrs),
             ResolvedConfiguration::new(
                 ConfigurationNoExec::new(name.cfg().dupe()),
                 UnorderedMap::new(),
@@ -7,6 +7,7 @@
  * of this source tree.
  */
 
+use std::fmt::Write;
 use std::hash::Hash;
 use std::hash::Hasher;
 use std::sync::Arc;
@@ -427,6 +428,26 @@ impl TargetNode {
         self.0.call_stack.as_ref().map(|s| s.to_string())
     }
 
+    /// Render this target as a rule call in `BUCK` file syntax.
+    ///
+    /// Only explicitly set attributes are written. The call stack, when it was recorded,
+    /// is written as a comment before the call.
+    pub fn to_build_syntax(&self) -> String {
+        let mut out = String::new();
+        writeln!(out, "# {}", self.label()).unwrap();
+        if let Some(call_stack) = self.call_stack() {
+            for line in call_stack.lines() {
+                writeln!(out, "# {}", line).unwrap();
+            }
+        }
+        writeln!(out, "{}(", self.rule_type().name()).unwrap();
+        for a in self.attrs(AttrInspectOptions::DefinedOnly) {
+            writeln!(out, "    {} = {},", a.name, a.value.as_starlark()).unwrap();
+        }
+        writeln!(out, ")").unwrap();
+        out
+    }
+
     /// Hash the fields that impact how this target is built.
     /// Don't do any recursive hashing of the dependencies.
     pub fn target_hash<H: Hasher>(&self, state: &mut H) {
@@ -557,3 +578,60 @@ pub mod testing {
         Ok(Value::from(map))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use buck2_core::bzl::ImportPath;
+    use buck2_core::target::label::TargetLabel;
+    use buck2_util::arc_str::ArcSlice;
+
+    use crate::attrs::attr::Attribute;
+    use crate::attrs::attr_type::bool::BoolLiteral;
+    use crate::attrs::attr_type::list::ListLiteral;
+    use crate::attrs::attr_type::string::StringLiteral;
+    use crate::attrs::attr_type::AttrType;
+    use crate::attrs::coerced_attr::CoercedAttr;
+    use crate::nodes::unconfigured::testing::TargetNodeExt;
+    use crate::nodes::unconfigured::TargetNode;
+    use crate::rule_type::RuleType;
+    use crate::rule_type::StarlarkRuleType;
+
+    #[test]
+    fn test_to_build_syntax() {
+        let rule_type = RuleType::Starlark(Arc::new(StarlarkRuleType {
+            import_path: ImportPath::testing_new("cell//pkg:rules.bzl"),
+            name: "some_rule".to_owned(),
+        }));
+        let node = TargetNode::testing_new(
+            TargetLabel::testing_parse("cell//pkg:foo"),
+            rule_type,
+            vec![
+                (
+                    "flag",
+                    Attribute::new(None, "", AttrType::bool()),
+                    CoercedAttr::Bool(BoolLiteral(true)),
+                ),
+                (
+                    "srcs",
+                    Attribute::new(None, "", AttrType::list(AttrType::string())),
+                    CoercedAttr::List(ListLiteral(ArcSlice::new([
+                        CoercedAttr::String(StringLiteral("a.c".into())),
+                        CoercedAttr::String(StringLiteral("b\"c.c".into())),
+                    ]))),
+                ),
+            ],
+        );
+        assert_eq!(
+            r#"# cell//pkg:foo
+some_rule(
+    name = "foo",
+    flag = True,
+    srcs = ["a.c", "b\"c.c"],
+)
+"#,
+            node.to_build_syntax()
+        );
+    }
+}
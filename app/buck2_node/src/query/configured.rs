@@ -8,10 +8,15 @@
  */
 
 use std::borrow::Cow;
+use std::sync::Arc;
 
 use buck2_core::build_file_path::BuildFilePath;
 use buck2_core::cells::cell_path::CellPath;
+use buck2_core::configuration::transition::id::TransitionId;
+use buck2_core::provider::label::ConfiguredProvidersLabel;
 use buck2_core::target::configured_target_label::ConfiguredTargetLabel;
+use buck2_query::query::environment::DepEdge;
+use buck2_query::query::environment::DepKind;
 use buck2_query::query::environment::LabeledNode;
 use buck2_query::query::environment::QueryTarget;
 use dupe::Dupe;
@@ -19,6 +24,7 @@ use serde::Serializer;
 
 use crate::attrs::attr_type::any_matches::AnyMatches;
 use crate::attrs::configured_attr::ConfiguredAttr;
+use crate::attrs::configured_traversal::ConfiguredAttrTraversal;
 use crate::attrs::display::AttrDisplayWithContextExt;
 use crate::attrs::fmt_context::AttrFmtContext;
 use crate::attrs::inspect_options::AttrInspectOptions;
@@ -110,6 +116,94 @@ impl QueryTarget for ConfiguredTargetNode {
         self.call_stack()
     }
 
+    fn dep_edges(&self) -> Vec<DepEdge<Self::NodeRef>> {
+        struct DepEdgeCollector<'a> {
+            from: &'a ConfiguredTargetLabel,
+            attr: &'a str,
+            execution_platform: Option<String>,
+            edges: Vec<DepEdge<ConfiguredTargetLabel>>,
+        }
+
+        impl<'a> DepEdgeCollector<'a> {
+            fn push(
+                &mut self,
+                dep: &ConfiguredProvidersLabel,
+                kind: DepKind,
+                tr: Option<&TransitionId>,
+            ) {
+                let dep = dep.target();
+                let configuration_change = if dep.cfg() != self.from.cfg() {
+                    Some((self.from.cfg().to_string(), dep.cfg().to_string()))
+                } else {
+                    None
+                };
+                self.edges.push(DepEdge {
+                    dep: dep.dupe(),
+                    attr: self.attr.to_owned(),
+                    kind,
+                    transition: tr.map(|tr| tr.to_string()),
+                    configuration_change,
+                    execution_platform: match kind {
+                        DepKind::Exec => self.execution_platform.clone(),
+                        _ => None,
+                    },
+                });
+            }
+        }
+
+        impl<'a> ConfiguredAttrTraversal for DepEdgeCollector<'a> {
+            fn dep(&mut self, dep: &ConfiguredProvidersLabel) -> anyhow::Result<()> {
+                self.push(dep, DepKind::Dep, None);
+                Ok(())
+            }
+
+            fn exec_dep(&mut self, dep: &ConfiguredProvidersLabel) -> anyhow::Result<()> {
+                self.push(dep, DepKind::Exec, None);
+                Ok(())
+            }
+
+            fn toolchain_dep(&mut self, dep: &ConfiguredProvidersLabel) -> anyhow::Result<()> {
+                self.push(dep, DepKind::Toolchain, None);
+                Ok(())
+            }
+
+            fn transition_dep(
+                &mut self,
+                dep: &ConfiguredProvidersLabel,
+                tr: &Arc<TransitionId>,
+            ) -> anyhow::Result<()> {
+                self.push(dep, DepKind::Transition, Some(tr));
+                Ok(())
+            }
+
+            fn split_transition_dep(
+                &mut self,
+                dep: &ConfiguredProvidersLabel,
+                tr: &Arc<TransitionId>,
+            ) -> anyhow::Result<()> {
+                self.push(dep, DepKind::SplitTransition, Some(tr));
+                Ok(())
+            }
+        }
+
+        let mut collector = DepEdgeCollector {
+            from: self.label(),
+            attr: "",
+            execution_platform: self
+                .execution_platform_resolution()
+                .platform()
+                .ok()
+                .map(|p| p.id()),
+            edges: Vec::new(),
+        };
+        for a in self.attrs(AttrInspectOptions::All) {
+            collector.attr = a.name;
+            a.traverse(self.label().pkg(), &mut collector)
+                .expect("dep edge collector shouldn't return errors");
+        }
+        collector.edges
+    }
+
     fn attr_to_string_alternate(&self, attr: &Self::Attr<'_>) -> String {
         format!(
             "{:#}",
@@ -132,3 +226,88 @@ impl QueryTarget for ConfiguredTargetNode {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use buck2_core::configuration::data::ConfigurationData;
+    use buck2_core::configuration::data::ConfigurationDataData;
+    use buck2_core::execution_types::execution::ExecutionPlatform;
+    use buck2_core::execution_types::executor_config::CommandExecutorConfig;
+    use buck2_core::plugins::PluginKindSet;
+    use buck2_core::provider::label::ProvidersLabel;
+    use buck2_core::provider::label::ProvidersName;
+    use buck2_core::target::configured_target_label::ConfiguredTargetLabel;
+    use buck2_core::target::label::TargetLabel;
+    use buck2_query::query::environment::DepEdge;
+    use buck2_query::query::environment::DepKind;
+    use buck2_query::query::environment::QueryTarget;
+    use dupe::Dupe;
+
+    use crate::attrs::attr::Attribute;
+    use crate::attrs::attr_type::AttrType;
+    use crate::attrs::coerced_attr::CoercedAttr;
+    use crate::nodes::configured::ConfiguredTargetNode;
+    use crate::provider_id_set::ProviderIdSet;
+
+    fn dep(label: &str) -> CoercedAttr {
+        CoercedAttr::Dep(ProvidersLabel::new(
+            TargetLabel::testing_parse(label),
+            ProvidersName::Default,
+        ))
+    }
+
+    #[test]
+    fn test_dep_edges_execution_platform() {
+        let target_cfg = ConfigurationData::testing_new();
+        let exec_cfg =
+            ConfigurationData::from_platform("exec".to_owned(), ConfigurationDataData::empty())
+                .unwrap();
+        let node = ConfiguredTargetNode::testing_new_with_attrs(
+            ConfiguredTargetLabel::testing_parse("cell//pkg:a", target_cfg.dupe()),
+            "some_rule",
+            vec![
+                (
+                    "dep",
+                    Attribute::new(
+                        None,
+                        "",
+                        AttrType::dep(ProviderIdSet::EMPTY, PluginKindSet::EMPTY),
+                    ),
+                    dep("cell//pkg:b"),
+                ),
+                (
+                    "compiler",
+                    Attribute::new(None, "", AttrType::exec_dep(ProviderIdSet::EMPTY)),
+                    dep("cell//pkg:c"),
+                ),
+            ],
+            Some(ExecutionPlatform::platform(
+                TargetLabel::testing_parse("cell//platforms:exec"),
+                exec_cfg.dupe(),
+                CommandExecutorConfig::testing_local(),
+            )),
+        );
+
+        assert_eq!(
+            vec![
+                DepEdge {
+                    dep: ConfiguredTargetLabel::testing_parse("cell//pkg:b", target_cfg.dupe()),
+                    attr: "dep".to_owned(),
+                    kind: DepKind::Dep,
+                    transition: None,
+                    configuration_change: None,
+                    execution_platform: None,
+                },
+                DepEdge {
+                    dep: ConfiguredTargetLabel::testing_parse("cell//pkg:c", exec_cfg.dupe()),
+                    attr: "compiler".to_owned(),
+                    kind: DepKind::Exec,
+                    transition: None,
+                    configuration_change: Some((target_cfg.to_string(), exec_cfg.to_string())),
+                    execution_platform: Some("cell//platforms:exec".to_owned()),
+                },
+            ],
+            node.dep_edges()
+        );
+    }
+}
@@ -8,10 +8,16 @@
  */
 
 use std::borrow::Cow;
+use std::sync::Arc;
 
+use buck2_core::buck_path::path::BuckPathRef;
 use buck2_core::build_file_path::BuildFilePath;
 use buck2_core::cells::cell_path::CellPath;
+use buck2_core::configuration::transition::id::TransitionId;
+use buck2_core::plugins::PluginKind;
 use buck2_core::target::label::TargetLabel;
+use buck2_query::query::environment::DepEdge;
+use buck2_query::query::environment::DepKind;
 use buck2_query::query::environment::LabeledNode;
 use buck2_query::query::environment::QueryTarget;
 use dupe::Dupe;
@@ -22,6 +28,7 @@ use crate::attrs::display::AttrDisplayWithContextExt;
 use crate::attrs::fmt_context::AttrFmtContext;
 use crate::attrs::inspect_options::AttrInspectOptions;
 use crate::attrs::serialize::AttrSerializeWithContext;
+use crate::attrs::traversal::CoercedAttrTraversal;
 use crate::nodes::unconfigured::TargetNode;
 
 impl LabeledNode for TargetNode {
@@ -109,6 +116,93 @@ impl QueryTarget for TargetNode {
         self.call_stack()
     }
 
+    fn dep_edges(&self) -> Vec<DepEdge<Self::NodeRef>> {
+        struct DepEdgeCollector<'a> {
+            attr: &'a str,
+            edges: Vec<DepEdge<TargetLabel>>,
+        }
+
+        impl<'a> DepEdgeCollector<'a> {
+            fn push(&mut self, dep: &TargetLabel, kind: DepKind, tr: Option<&TransitionId>) {
+                self.edges.push(DepEdge {
+                    dep: dep.dupe(),
+                    attr: self.attr.to_owned(),
+                    kind,
+                    transition: tr.map(|tr| tr.to_string()),
+                    configuration_change: None,
+                    execution_platform: None,
+                });
+            }
+        }
+
+        impl<'a> CoercedAttrTraversal<'a> for DepEdgeCollector<'a> {
+            fn dep(&mut self, dep: &'a TargetLabel) -> anyhow::Result<()> {
+                self.push(dep, DepKind::Dep, None);
+                Ok(())
+            }
+
+            fn exec_dep(&mut self, dep: &'a TargetLabel) -> anyhow::Result<()> {
+                self.push(dep, DepKind::Exec, None);
+                Ok(())
+            }
+
+            fn toolchain_dep(&mut self, dep: &'a TargetLabel) -> anyhow::Result<()> {
+                self.push(dep, DepKind::Toolchain, None);
+                Ok(())
+            }
+
+            fn transition_dep(
+                &mut self,
+                dep: &'a TargetLabel,
+                tr: &Arc<TransitionId>,
+            ) -> anyhow::Result<()> {
+                self.push(dep, DepKind::Transition, Some(tr));
+                Ok(())
+            }
+
+            fn split_transition_dep(
+                &mut self,
+                dep: &'a TargetLabel,
+                tr: &Arc<TransitionId>,
+            ) -> anyhow::Result<()> {
+                self.push(dep, DepKind::SplitTransition, Some(tr));
+                Ok(())
+            }
+
+            fn configuration_dep(&mut self, _dep: &'a TargetLabel) -> anyhow::Result<()> {
+                Ok(())
+            }
+
+            fn platform_dep(&mut self, _dep: &'a TargetLabel) -> anyhow::Result<()> {
+                Ok(())
+            }
+
+            fn plugin_dep(
+                &mut self,
+                dep: &'a TargetLabel,
+                _kind: &PluginKind,
+            ) -> anyhow::Result<()> {
+                self.push(dep, DepKind::Plugin, None);
+                Ok(())
+            }
+
+            fn input(&mut self, _input: BuckPathRef) -> anyhow::Result<()> {
+                Ok(())
+            }
+        }
+
+        let mut collector = DepEdgeCollector {
+            attr: "",
+            edges: Vec::new(),
+        };
+        for a in self.attrs(AttrInspectOptions::All) {
+            collector.attr = a.name;
+            a.traverse(self.label().pkg(), &mut collector)
+                .expect("dep edge collector shouldn't return errors");
+        }
+        collector.edges
+    }
+
     fn attr_to_string_alternate(&self, attr: &Self::Attr<'_>) -> String {
         format!(
             "{:#}",
@@ -131,3 +225,85 @@ impl QueryTarget for TargetNode {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use buck2_core::bzl::ImportPath;
+    use buck2_core::plugins::PluginKindSet;
+    use buck2_core::provider::label::ProvidersLabel;
+    use buck2_core::provider::label::ProvidersName;
+    use buck2_core::target::label::TargetLabel;
+    use buck2_query::query::environment::DepEdge;
+    use buck2_query::query::environment::DepKind;
+    use buck2_query::query::environment::QueryTarget;
+    use buck2_util::arc_str::ArcSlice;
+
+    use crate::attrs::attr::Attribute;
+    use crate::attrs::attr_type::list::ListLiteral;
+    use crate::attrs::attr_type::AttrType;
+    use crate::attrs::coerced_attr::CoercedAttr;
+    use crate::nodes::unconfigured::testing::TargetNodeExt;
+    use crate::nodes::unconfigured::TargetNode;
+    use crate::provider_id_set::ProviderIdSet;
+    use crate::rule_type::RuleType;
+    use crate::rule_type::StarlarkRuleType;
+
+    fn dep(label: &str) -> CoercedAttr {
+        CoercedAttr::Dep(ProvidersLabel::new(
+            TargetLabel::testing_parse(label),
+            ProvidersName::Default,
+        ))
+    }
+
+    fn edge(dep: &str, attr: &str, kind: DepKind) -> DepEdge<TargetLabel> {
+        DepEdge {
+            dep: TargetLabel::testing_parse(dep),
+            attr: attr.to_owned(),
+            kind,
+            transition: None,
+            configuration_change: None,
+            execution_platform: None,
+        }
+    }
+
+    #[test]
+    fn test_dep_edges() {
+        let rule_type = RuleType::Starlark(Arc::new(StarlarkRuleType {
+            import_path: ImportPath::testing_new("cell//pkg:rules.bzl"),
+            name: "some_rule".to_owned(),
+        }));
+        let node = TargetNode::testing_new(
+            TargetLabel::testing_parse("cell//pkg:a"),
+            rule_type,
+            vec![
+                (
+                    "deps",
+                    Attribute::new(
+                        None,
+                        "",
+                        AttrType::list(AttrType::dep(ProviderIdSet::EMPTY, PluginKindSet::EMPTY)),
+                    ),
+                    CoercedAttr::List(ListLiteral(ArcSlice::new([
+                        dep("cell//pkg:b"),
+                        dep("cell//pkg:c"),
+                    ]))),
+                ),
+                (
+                    "compiler",
+                    Attribute::new(None, "", AttrType::exec_dep(ProviderIdSet::EMPTY)),
+                    dep("cell//pkg:b"),
+                ),
+            ],
+        );
+        assert_eq!(
+            vec![
+                edge("cell//pkg:b", "deps", DepKind::Dep),
+                edge("cell//pkg:c", "deps", DepKind::Dep),
+                edge("cell//pkg:b", "compiler", DepKind::Exec),
+            ],
+            node.dep_edges()
+        );
+    }
+}
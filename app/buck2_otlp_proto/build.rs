@@ -0,0 +1,62 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::io;
+
+fn main() -> io::Result<()> {
+    let proto_files = &[
+        "common.proto",
+        "resource.proto",
+        "trace.proto",
+        "trace_service.proto",
+    ];
+
+    buck2_protoc_dev::configure()
+        .setup_protoc()
+        // The OTLP JSON encoding follows the proto3 JSON mapping, with the exceptions below.
+        .type_attribute(
+            ".opentelemetry",
+            "#[derive(::serde::Serialize)] #[serde(rename_all = \"camelCase\")]",
+        )
+        .field_attribute(
+            ".opentelemetry.proto.common.v1.AnyValue.value",
+            "#[serde(flatten)]",
+        )
+        .field_attribute(
+            ".opentelemetry.proto.common.v1.AnyValue.value.int_value",
+            "#[serde(serialize_with = \"crate::serialize_as_string\")]",
+        )
+        // Trace and span ids are hex encoded, rather than base64 encoded.
+        .field_attribute(
+            ".opentelemetry.proto.trace.v1.Span.trace_id",
+            "#[serde(serialize_with = \"crate::serialize_hex\")]",
+        )
+        .field_attribute(
+            ".opentelemetry.proto.trace.v1.Span.span_id",
+            "#[serde(serialize_with = \"crate::serialize_hex\")]",
+        )
+        .field_attribute(
+            ".opentelemetry.proto.trace.v1.Span.parent_span_id",
+            "#[serde(serialize_with = \"crate::serialize_hex\")]",
+        )
+        // 64 bit integers are strings.
+        .field_attribute(
+            ".opentelemetry.proto.trace.v1.Span.start_time_unix_nano",
+            "#[serde(serialize_with = \"crate::serialize_as_string\")]",
+        )
+        .field_attribute(
+            ".opentelemetry.proto.trace.v1.Span.end_time_unix_nano",
+            "#[serde(serialize_with = \"crate::serialize_as_string\")]",
+        )
+        .field_attribute(
+            ".opentelemetry.proto.trace.v1.Span.Event.time_unix_nano",
+            "#[serde(serialize_with = \"crate::serialize_as_string\")]",
+        )
+        .compile(proto_files, &["."])
+}
@@ -88,6 +88,47 @@ impl QueryTargets {
     }
 }
 
+/// How the attribute which created a dependency edge declared it.
+#[derive(Debug, Copy, Clone, Dupe, PartialEq, Eq, derive_more::Display)]
+pub enum DepKind {
+    #[display(fmt = "dep")]
+    Dep,
+    #[display(fmt = "exec_dep")]
+    Exec,
+    #[display(fmt = "toolchain_dep")]
+    Toolchain,
+    #[display(fmt = "transition_dep")]
+    Transition,
+    #[display(fmt = "split_transition_dep")]
+    SplitTransition,
+    #[display(fmt = "plugin_dep")]
+    Plugin,
+}
+
+/// Explanation of an edge from a node to one of its deps.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DepEdge<N> {
+    pub dep: N,
+    /// Name of the attribute which created the edge.
+    pub attr: String,
+    pub kind: DepKind,
+    /// Transition function applied to the dep, if any.
+    pub transition: Option<String>,
+    /// Configurations before and after the edge, if the edge changes the configuration.
+    /// Only set in the configured graph.
+    pub configuration_change: Option<(String, String)>,
+    /// Execution platform resolved for the node, for exec deps.
+    /// Only set in the configured graph.
+    pub execution_platform: Option<String>,
+}
+
+impl<N> DepEdge<N> {
+    /// Whether the dep is configured differently from the node.
+    pub fn crosses_transition(&self) -> bool {
+        self.configuration_change.is_some()
+    }
+}
+
 pub trait QueryTarget: LabeledNode + Dupe + Send + Sync + 'static {
     type Attr<'a>: ?Sized + Debug + 'a;
 
@@ -138,6 +179,12 @@ pub trait QueryTarget: LabeledNode + Dupe + Send + Sync + 'static {
     fn map_attr<R, F: FnMut(Option<&Self::Attr<'_>>) -> R>(&self, key: &str, func: F) -> R;
 
     fn call_stack(&self) -> Option<String>;
+
+    /// Explain the edges from this node to its deps: which attribute created each edge and how.
+    /// Nodes without attributes (e.g. actions) return nothing.
+    fn dep_edges(&self) -> Vec<DepEdge<Self::NodeRef>> {
+        Vec::new()
+    }
 }
 
 #[async_trait]
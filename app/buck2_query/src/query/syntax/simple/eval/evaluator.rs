@@ -21,6 +21,8 @@ use crate::query::environment::QueryEnvironment;
 use crate::query::syntax::simple::eval::error::QueryError;
 use crate::query::syntax::simple::eval::file_set::FileNode;
 use crate::query::syntax::simple::eval::file_set::FileSet;
+use crate::query::syntax::simple::eval::profile::QueryProfileRecorder;
+use crate::query::syntax::simple::eval::profile::QueryProfiler;
 use crate::query::syntax::simple::eval::set::TargetSet;
 use crate::query::syntax::simple::eval::values::QueryEvaluationValue;
 use crate::query::syntax::simple::eval::values::QueryResult;
@@ -29,11 +31,22 @@ use crate::query::syntax::simple::functions::QueryFunctions;
 pub struct QueryEvaluator<'e, Env: QueryEnvironment> {
     env: &'e Env,
     functions: &'e dyn QueryFunctions<Env = Env>,
+    profile: Option<QueryProfileRecorder<'e>>,
 }
 
 impl<'e, Env: QueryEnvironment> QueryEvaluator<'e, Env> {
     pub fn new(env: &'e Env, functions: &'e dyn QueryFunctions<Env = Env>) -> Self {
-        Self { env, functions }
+        Self {
+            env,
+            functions,
+            profile: None,
+        }
+    }
+
+    /// Record the cost of each subexpression of the evaluated query in `profiler`.
+    pub fn with_profiler(mut self, profiler: Option<&'e QueryProfiler>) -> Self {
+        self.profile = profiler.map(|p| p.recorder());
+        self
     }
 
     pub fn env(&self) -> &Env {
@@ -114,7 +127,26 @@ impl<'e, Env: QueryEnvironment> QueryEvaluator<'e, Env> {
     ) -> std::pin::Pin<
         Box<dyn std::future::Future<Output = QueryResult<QueryValue<Env::Target>>> + Send + 'a>,
     > {
-        async move { expr.span(self.eval_internal(&expr.value).await) }.boxed()
+        async move {
+            let value = match &self.profile {
+                Some(profile) => {
+                    profile
+                        .record(
+                            expr.position.clone(),
+                            self.eval_internal(&expr.value),
+                            |res| match res {
+                                Ok(QueryValue::TargetSet(targets)) => Some(targets.len()),
+                                Ok(QueryValue::FileSet(files)) => Some(files.len()),
+                                _ => None,
+                            },
+                        )
+                        .await
+                }
+                None => self.eval_internal(&expr.value).await,
+            };
+            expr.span(value)
+        }
+        .boxed()
     }
 
     pub async fn eval_query<'a>(
@@ -122,7 +154,11 @@ impl<'e, Env: QueryEnvironment> QueryEvaluator<'e, Env> {
         query: &str,
     ) -> anyhow::Result<QueryEvaluationValue<Env::Target>> {
         let parsed_query = parse_expr(query)?;
-        match self.eval_parsed_query(&parsed_query).await {
+        let res = self.eval_parsed_query(&parsed_query).await;
+        if let Some(profile) = &self.profile {
+            profile.finish(query);
+        }
+        match res {
             Ok(v) => Ok(v.value),
             Err(e) => Err(QueryError::convert_error(e, query)),
         }
@@ -13,6 +13,7 @@ pub mod file_set;
 pub mod label_indexed;
 pub mod literals;
 pub mod multi_query;
+pub mod profile;
 pub mod set;
 pub mod tests;
 pub mod values;
@@ -0,0 +1,269 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Per-subexpression profile of query evaluation (`--profile`).
+//!
+//! The evaluator records one entry per evaluated span. Time spent loading packages or
+//! configuring targets is reported by the environment through [`QueryProfileCounters`];
+//! each entry gets the amount accumulated while it was being evaluated. Since sibling
+//! subexpressions are evaluated concurrently, that time may be shared between siblings.
+
+use std::fmt;
+use std::fmt::Display;
+use std::future::Future;
+use std::ops::Range;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::Instant;
+
+use buck2_util::truncate::truncate;
+use parking_lot::Mutex;
+
+/// Time spent in expensive environment operations, accumulated over a whole evaluation.
+#[derive(Default, Debug)]
+pub struct QueryProfileCounters {
+    package_loading_ns: AtomicU64,
+    configuration_ns: AtomicU64,
+}
+
+impl QueryProfileCounters {
+    pub async fn time_package_loading<F: Future>(&self, fut: F) -> F::Output {
+        Self::time(&self.package_loading_ns, fut).await
+    }
+
+    pub async fn time_configuration<F: Future>(&self, fut: F) -> F::Output {
+        Self::time(&self.configuration_ns, fut).await
+    }
+
+    async fn time<F: Future>(counter: &AtomicU64, fut: F) -> F::Output {
+        let start = Instant::now();
+        let res = fut.await;
+        counter.fetch_add(start.elapsed().as_nanos() as u64, Ordering::Relaxed);
+        res
+    }
+
+    fn snapshot(&self) -> (u64, u64) {
+        (
+            self.package_loading_ns.load(Ordering::Relaxed),
+            self.configuration_ns.load(Ordering::Relaxed),
+        )
+    }
+}
+
+/// Collects the profiles of all the queries evaluated for one command.
+#[derive(Default, Debug)]
+pub struct QueryProfiler {
+    counters: Arc<QueryProfileCounters>,
+    literal_resolution: Mutex<Duration>,
+    profiles: Mutex<Vec<QueryProfile>>,
+}
+
+impl QueryProfiler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Counters to be updated by the query environment.
+    pub fn counters(&self) -> &Arc<QueryProfileCounters> {
+        &self.counters
+    }
+
+    /// Time the resolution of target literals, which happens before evaluation.
+    pub async fn time_literal_resolution<F: Future>(&self, fut: F) -> F::Output {
+        let start = Instant::now();
+        let res = fut.await;
+        *self.literal_resolution.lock() += start.elapsed();
+        res
+    }
+
+    pub(crate) fn recorder(&self) -> QueryProfileRecorder<'_> {
+        QueryProfileRecorder {
+            profiler: self,
+            entries: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn literal_resolution(&self) -> Duration {
+        *self.literal_resolution.lock()
+    }
+
+    /// Profiles of the evaluated queries, in completion order.
+    pub fn take_profiles(&self) -> Vec<QueryProfile> {
+        std::mem::take(&mut *self.profiles.lock())
+    }
+}
+
+struct RawEntry {
+    position: Range<usize>,
+    wall_time: Duration,
+    result_count: Option<usize>,
+    package_loading: Duration,
+    configuration: Duration,
+}
+
+/// Records the entries for a single query.
+pub(crate) struct QueryProfileRecorder<'p> {
+    profiler: &'p QueryProfiler,
+    entries: Mutex<Vec<RawEntry>>,
+}
+
+impl<'p> QueryProfileRecorder<'p> {
+    pub(crate) async fn record<T, F: Future<Output = T>>(
+        &self,
+        position: Range<usize>,
+        fut: F,
+        result_count: impl FnOnce(&T) -> Option<usize>,
+    ) -> T {
+        let start = Instant::now();
+        let (package_loading_before, configuration_before) = self.profiler.counters.snapshot();
+        let res = fut.await;
+        let (package_loading_after, configuration_after) = self.profiler.counters.snapshot();
+        self.entries.lock().push(RawEntry {
+            position,
+            wall_time: start.elapsed(),
+            result_count: result_count(&res),
+            package_loading: Duration::from_nanos(package_loading_after - package_loading_before),
+            configuration: Duration::from_nanos(configuration_after - configuration_before),
+        });
+        res
+    }
+
+    pub(crate) fn finish(&self, query: &str) {
+        let profile = QueryProfile::new(query, std::mem::take(&mut *self.entries.lock()));
+        self.profiler.profiles.lock().push(profile);
+    }
+}
+
+/// Profile of a single subexpression.
+#[derive(Debug, Clone)]
+pub struct QueryProfileEntry {
+    /// Source of the subexpression.
+    pub expression: String,
+    /// Nesting depth, `0` for the whole query.
+    pub depth: usize,
+    pub wall_time: Duration,
+    /// Number of targets or files produced, if the subexpression produced a set.
+    pub result_count: Option<usize>,
+    pub package_loading: Duration,
+    pub configuration: Duration,
+}
+
+/// Profile of a query, entries are in pre-order.
+#[derive(Debug, Clone)]
+pub struct QueryProfile {
+    pub query: String,
+    pub entries: Vec<QueryProfileEntry>,
+}
+
+impl QueryProfile {
+    fn new(query: &str, mut raw: Vec<RawEntry>) -> Self {
+        // Spans of subexpressions nest, so sorting by start and then by decreasing length
+        // gives a pre-order traversal of the expression tree.
+        raw.sort_by_key(|e| (e.position.start, std::cmp::Reverse(e.position.end)));
+        let mut stack: Vec<Range<usize>> = Vec::new();
+        let entries = raw
+            .into_iter()
+            .map(|e| {
+                while let Some(parent) = stack.last() {
+                    if parent.start <= e.position.start && e.position.end <= parent.end {
+                        break;
+                    }
+                    stack.pop();
+                }
+                let depth = stack.len();
+                stack.push(e.position.clone());
+                QueryProfileEntry {
+                    expression: query.get(e.position.clone()).unwrap_or_default().to_owned(),
+                    depth,
+                    wall_time: e.wall_time,
+                    result_count: e.result_count,
+                    package_loading: e.package_loading,
+                    configuration: e.configuration,
+                }
+            })
+            .collect();
+        Self {
+            query: query.to_owned(),
+            entries,
+        }
+    }
+}
+
+/// Renders the profile as an annotated tree.
+impl Display for QueryProfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Query profile for `{}`:", truncate(&self.query, 80))?;
+        for entry in &self.entries {
+            write!(
+                f,
+                "{}{:>10.3}ms",
+                "  ".repeat(entry.depth + 1),
+                entry.wall_time.as_secs_f64() * 1000.0
+            )?;
+            if let Some(count) = entry.result_count {
+                write!(f, "  {} results", count)?;
+            }
+            if !entry.package_loading.is_zero() {
+                write!(
+                    f,
+                    "  loading {:.3}ms",
+                    entry.package_loading.as_secs_f64() * 1000.0
+                )?;
+            }
+            if !entry.configuration.is_zero() {
+                write!(
+                    f,
+                    "  configuration {:.3}ms",
+                    entry.configuration.as_secs_f64() * 1000.0
+                )?;
+            }
+            writeln!(f, "  {}", truncate(&entry.expression, 80))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn raw(position: Range<usize>) -> RawEntry {
+        RawEntry {
+            position,
+            wall_time: Duration::ZERO,
+            result_count: None,
+            package_loading: Duration::ZERO,
+            configuration: Duration::ZERO,
+        }
+    }
+
+    #[test]
+    fn test_tree_from_spans() {
+        let query = "deps(//a:b) + set(//c:d)";
+        // Recorded in completion order, children first.
+        let profile =
+            QueryProfile::new(query, vec![raw(5..10), raw(0..11), raw(14..24), raw(0..24)]);
+        let tree: Vec<_> = profile
+            .entries
+            .iter()
+            .map(|e| (e.depth, e.expression.as_str()))
+            .collect();
+        assert_eq!(
+            vec![
+                (0, "deps(//a:b) + set(//c:d)"),
+                (1, "deps(//a:b)"),
+                (2, "//a:b"),
+                (1, "set(//c:d)"),
+            ],
+            tree
+        );
+    }
+}
@@ -13,6 +13,7 @@ use buck2_query::query::environment::QueryEnvironment;
 use buck2_query::query::syntax::simple::eval::evaluator::QueryEvaluator;
 use buck2_query::query::syntax::simple::eval::literals::extract_target_literals;
 use buck2_query::query::syntax::simple::eval::multi_query::process_multi_query;
+use buck2_query::query::syntax::simple::eval::profile::QueryProfiler;
 use buck2_query::query::syntax::simple::eval::values::QueryEvaluationResult;
 use buck2_query::query::syntax::simple::functions::QueryFunctions;
 use buck2_query_parser::placeholder::QUERY_PERCENT_S_PLACEHOLDER;
@@ -38,6 +39,7 @@ pub async fn eval_query<
     functions: &F,
     query: &str,
     query_args: &[A],
+    profiler: Option<&QueryProfiler>,
     environment: impl FnOnce(Vec<String>) -> Fut,
 ) -> anyhow::Result<QueryEvaluationResult<Env::Target>> {
     let mut literals = SmallSet::new();
@@ -58,9 +60,10 @@ pub async fn eval_query<
                 &mut literals,
             )?;
         }
-        let env = environment(literals.into_iter().collect()).await?;
+        let env =
+            time_literal_resolution(profiler, environment(literals.into_iter().collect())).await?;
         let results = process_multi_query(query, query_args, |input, query| {
-            let evaluator = QueryEvaluator::new(&env, functions);
+            let evaluator = QueryEvaluator::new(&env, functions).with_profiler(profiler);
             async move { (input, evaluator.eval_query(&query).await) }
         })
         .await;
@@ -72,11 +75,20 @@ pub async fn eval_query<
         )
     } else {
         extract_target_literals(functions, query, &mut literals)?;
-        let env = environment(literals.into_iter().collect()).await?;
+        let env =
+            time_literal_resolution(profiler, environment(literals.into_iter().collect())).await?;
         Ok(QueryEvaluationResult::Single(
             QueryEvaluator::new(&env, functions)
+                .with_profiler(profiler)
                 .eval_query(query)
                 .await?,
         ))
     }
 }
+
+async fn time_literal_resolution<F: Future>(profiler: Option<&QueryProfiler>, fut: F) -> F::Output {
+    match profiler {
+        Some(profiler) => profiler.time_literal_resolution(fut).await,
+        None => fut.await,
+    }
+}
@@ -37,7 +37,7 @@ impl AqueryEvaluator<'_> {
     ) -> anyhow::Result<QueryEvaluationResult<ActionQueryNode>> {
         let functions = aquery_functions();
 
-        eval_query(&functions, query, query_args, async move |literals| {
+        eval_query(&functions, query, query_args, None, async move |literals| {
             let resolved_literals = PreresolvedQueryLiterals::pre_resolve(
                 &**self.dice_query_delegate.query_data(),
                 &literals,
@@ -17,6 +17,7 @@ use buck2_core::target::label::TargetLabel;
 use buck2_events::dispatch::console_message;
 use buck2_node::configured_universe::CqueryUniverse;
 use buck2_node::nodes::configured::ConfiguredTargetNode;
+use buck2_query::query::syntax::simple::eval::profile::QueryProfiler;
 use buck2_query::query::syntax::simple::eval::values::QueryEvaluationResult;
 use buck2_query::query::syntax::simple::functions::DefaultQueryFunctionsModule;
 use dice::DiceComputations;
@@ -37,43 +38,68 @@ use crate::uquery::environment::UqueryDelegate;
 pub struct CqueryEvaluator<'c> {
     dice_query_delegate: DiceQueryDelegate<'c>,
     functions: DefaultQueryFunctionsModule<CqueryEnvironment<'c>>,
+    profiler: Option<Arc<QueryProfiler>>,
     owner_behavior: CqueryOwnerBehavior,
 }
 
 impl CqueryEvaluator<'_> {
+    /// Profile the evaluated queries with `profiler`.
+    pub fn with_profiler(mut self, profiler: Option<Arc<QueryProfiler>>) -> Self {
+        self.dice_query_delegate
+            .set_profile_counters(profiler.as_ref().map(|p| p.counters().dupe()));
+        self.profiler = profiler;
+        self
+    }
+
     pub async fn eval_query<A: AsRef<str>, U: AsRef<str>>(
         &self,
         query: &str,
         query_args: &[A],
         target_universe: Option<&[U]>,
     ) -> anyhow::Result<QueryEvaluationResult<ConfiguredTargetNode>> {
-        eval_query(&self.functions, query, query_args, async move |literals| {
-            let (universe, resolved_literals) = match target_universe {
-                None => {
-                    if literals.is_empty() {
-                        console_message(
-                            "Query has no target literals and `--target-universe` is not specified.\n\
-                            Such query is correct, but the result is always empty.\n\
-                            Consider specifying `--target-universe` for this query\n\
-                            or using `uquery` instead of `cquery`".to_owned());
-                    }
-                    // In the absence of a user-provided target universe, we use the target
-                    // literals in the cquery as the universe.
-                    resolve_literals_in_universe(&self.dice_query_delegate, self.dice_query_delegate.query_data().dupe(), &literals, &literals)
+        eval_query(
+            &self.functions,
+            query,
+            query_args,
+            self.profiler.as_deref(),
+            async move |literals| {
+                let (universe, resolved_literals) = match target_universe {
+                    None => {
+                        if literals.is_empty() {
+                            console_message(
+                                "Query has no target literals and `--target-universe` is not specified.\n\
+                                Such query is correct, but the result is always empty.\n\
+                                Consider specifying `--target-universe` for this query\n\
+                                or using `uquery` instead of `cquery`".to_owned());
+                        }
+                        // In the absence of a user-provided target universe, we use the target
+                        // literals in the cquery as the universe.
+                        resolve_literals_in_universe(
+                            &self.dice_query_delegate,
+                            self.dice_query_delegate.query_data().dupe(),
+                            &literals,
+                            &literals,
+                        )
                         .await?
-                }
-                Some(universe) => {
-                    resolve_literals_in_universe(&self.dice_query_delegate, self.dice_query_delegate.query_data().dupe(), &literals, universe)
+                    }
+                    Some(universe) => {
+                        resolve_literals_in_universe(
+                            &self.dice_query_delegate,
+                            self.dice_query_delegate.query_data().dupe(),
+                            &literals,
+                            universe,
+                        )
                         .await?
-                }
-            };
-            Ok(CqueryEnvironment::new(
-                &self.dice_query_delegate,
-                Arc::new(resolved_literals),
-                Some(universe),
-                self.owner_behavior,
-            ))
-        })
+                    }
+                };
+                Ok(CqueryEnvironment::new(
+                    &self.dice_query_delegate,
+                    Arc::new(resolved_literals),
+                    Some(universe),
+                    self.owner_behavior,
+                ))
+            },
+        )
         .await
     }
 }
@@ -107,6 +133,7 @@ pub async fn get_cquery_evaluator<'a, 'c: 'a>(
     Ok(CqueryEvaluator {
         dice_query_delegate,
         functions,
+        profiler: None,
         owner_behavior,
     })
 }
@@ -203,6 +203,14 @@ fn compute_tset_node<'c>(
             .as_transitive_set()
             .get_projection_sub_inputs(key.projection)?;
 
+        let artifacts = sub_inputs
+            .iter()
+            .filter_map(|input| match input.assert_resolved() {
+                ResolvedArtifactGroup::Artifact(a) => Some(a.dupe()),
+                ResolvedArtifactGroup::TransitiveSetProjection(..) => None,
+            })
+            .collect();
+
         let inputs = convert_inputs(ctx, node_cache, sub_inputs.iter()).await?;
 
         let (direct, children) = inputs.into_iter().partition_map(|v| match v {
@@ -210,7 +218,12 @@ fn compute_tset_node<'c>(
             ActionInput::IndirectInputs(projection) => Either::Right(projection),
         });
 
-        Ok(SetProjectionInputs::new(key.dupe(), direct, children))
+        Ok(SetProjectionInputs::new(
+            key.dupe(),
+            direct,
+            artifacts,
+            children,
+        ))
     }
     .boxed()
 }
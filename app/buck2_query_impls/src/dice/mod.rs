@@ -8,6 +8,7 @@
  */
 
 use std::collections::HashMap;
+use std::future::Future;
 use std::sync::Arc;
 
 use async_trait::async_trait;
@@ -53,6 +54,7 @@ use buck2_node::nodes::unconfigured::TargetNode;
 use buck2_node::target_calculation::ConfiguredTargetCalculation;
 use buck2_query::query::syntax::simple::eval::file_set::FileNode;
 use buck2_query::query::syntax::simple::eval::file_set::FileSet;
+use buck2_query::query::syntax::simple::eval::profile::QueryProfileCounters;
 use buck2_query::query::syntax::simple::eval::set::TargetSet;
 use dice::DiceComputations;
 use dupe::Dupe;
@@ -140,6 +142,7 @@ pub struct DiceQueryDelegate<'c> {
     cell_resolver: CellResolver,
     query_data: Arc<DiceQueryData>,
     package_boundary_exceptions: Arc<PackageBoundaryExceptions>,
+    profile_counters: Option<Arc<QueryProfileCounters>>,
 }
 
 pub struct DiceQueryData {
@@ -197,6 +200,34 @@ impl<'c> DiceQueryDelegate<'c> {
             cell_resolver: cell_resolver.dupe(),
             query_data,
             package_boundary_exceptions,
+            profile_counters: None,
+        }
+    }
+
+    /// Report time spent loading packages and configuring targets to `--profile`.
+    pub(crate) fn set_profile_counters(&mut self, counters: Option<Arc<QueryProfileCounters>>) {
+        self.profile_counters = counters;
+    }
+
+    async fn time_package_loading<F: Future>(&self, fut: F) -> F::Output {
+        match &self.profile_counters {
+            Some(counters) => counters.time_package_loading(fut).await,
+            None => fut.await,
+        }
+    }
+
+    /// Configuring a target loads its package: when profiling, it is loaded first, so that this
+    /// is reported as loading and not also as configuration.
+    async fn time_configuration<F: Future>(&self, package: PackageLabel, fut: F) -> F::Output {
+        match &self.profile_counters {
+            Some(counters) => {
+                // Errors are reported by the configuration.
+                let _ignored = counters
+                    .time_package_loading(self.ctx.get_interpreter_results(package))
+                    .await;
+                counters.time_configuration(fut).await
+            }
+            None => fut.await,
         }
     }
 
@@ -215,7 +246,8 @@ impl<'c> UqueryDelegate for DiceQueryDelegate<'c> {
         &self,
         package: PackageLabel,
     ) -> anyhow::Result<Arc<EvaluationResult>> {
-        self.ctx.get_interpreter_results(package).await
+        self.time_package_loading(self.ctx.get_interpreter_results(package))
+            .await
     }
 
     async fn eval_module_imports(&self, path: &ImportPath) -> anyhow::Result<Vec<ImportPath>> {
@@ -288,39 +320,46 @@ impl<'c> CqueryDelegate for DiceQueryDelegate<'c> {
         &self,
         target: &TargetLabel,
     ) -> anyhow::Result<MaybeCompatible<ConfiguredTargetNode>> {
-        let target = self
-            .ctx
-            .get_configured_target(target, self.query_data.global_target_platform.as_ref())
-            .await?;
-        Ok(self.ctx.get_configured_target_node(&target).await?)
+        self.time_configuration(target.pkg(), async {
+            let target = self
+                .ctx
+                .get_configured_target(target, self.query_data.global_target_platform.as_ref())
+                .await?;
+            self.ctx.get_configured_target_node(&target).await
+        })
+        .await
     }
 
     async fn get_node_for_configured_target(
         &self,
         target: &ConfiguredTargetLabel,
     ) -> anyhow::Result<ConfiguredTargetNode> {
-        Ok(self
-            .ctx
-            .get_configured_target_node(target)
+        self.time_configuration(target.pkg(), self.ctx.get_configured_target_node(target))
             .await?
-            .require_compatible()?)
+            .require_compatible()
     }
 
     async fn get_node_for_default_configured_target(
         &self,
         target: &TargetLabel,
     ) -> anyhow::Result<MaybeCompatible<ConfiguredTargetNode>> {
-        let target = self.ctx.get_default_configured_target(target).await?;
-        self.ctx.get_configured_target_node(&target).await
+        self.time_configuration(target.pkg(), async {
+            let target = self.ctx.get_default_configured_target(target).await?;
+            self.ctx.get_configured_target_node(&target).await
+        })
+        .await
     }
 
     async fn get_configured_target(
         &self,
         target: &TargetLabel,
     ) -> anyhow::Result<ConfiguredTargetLabel> {
-        self.ctx
-            .get_configured_target(target, self.query_data.global_target_platform.as_ref())
-            .await
+        self.time_configuration(
+            target.pkg(),
+            self.ctx
+                .get_configured_target(target, self.query_data.global_target_platform.as_ref()),
+        )
+        .await
     }
 
     fn ctx(&self) -> &DiceComputations {
@@ -7,6 +7,8 @@
  * of this source tree.
  */
 
+use std::sync::Arc;
+
 use async_trait::async_trait;
 use buck2_build_api::actions::query::ActionQueryNode;
 use buck2_build_api::query::oneshot::CqueryOwnerBehavior;
@@ -17,6 +19,7 @@ use buck2_core::target::label::TargetLabel;
 use buck2_node::configured_universe::CqueryUniverse;
 use buck2_node::nodes::configured::ConfiguredTargetNode;
 use buck2_node::nodes::unconfigured::TargetNode;
+use buck2_query::query::syntax::simple::eval::profile::QueryProfiler;
 use buck2_query::query::syntax::simple::eval::values::QueryEvaluationResult;
 use dice::DiceComputations;
 
@@ -41,8 +44,11 @@ impl QueryFrontend for QueryFrontendImpl {
         query: &str,
         query_args: &[String],
         global_target_platform: Option<TargetLabel>,
+        profiler: Option<Arc<QueryProfiler>>,
     ) -> anyhow::Result<QueryEvaluationResult<TargetNode>> {
-        let evaluator = get_uquery_evaluator(ctx, working_dir, global_target_platform).await?;
+        let evaluator = get_uquery_evaluator(ctx, working_dir, global_target_platform)
+            .await?
+            .with_profiler(profiler);
 
         evaluator.eval_query(query, query_args).await
     }
@@ -56,9 +62,12 @@ impl QueryFrontend for QueryFrontendImpl {
         query_args: &[String],
         global_target_platform: Option<TargetLabel>,
         target_universe: Option<&[String]>,
+        profiler: Option<Arc<QueryProfiler>>,
     ) -> anyhow::Result<QueryEvaluationResult<ConfiguredTargetNode>> {
         let evaluator =
-            get_cquery_evaluator(ctx, working_dir, global_target_platform, owner_behavior).await?;
+            get_cquery_evaluator(ctx, working_dir, global_target_platform, owner_behavior)
+                .await?
+                .with_profiler(profiler);
 
         // TODO(nga): this should support configured target patterns
         //   similarly to what we do for `build` command.
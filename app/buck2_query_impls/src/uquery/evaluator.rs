@@ -13,9 +13,11 @@ use std::sync::Arc;
 use buck2_core::fs::project_rel_path::ProjectRelativePath;
 use buck2_core::target::label::TargetLabel;
 use buck2_node::nodes::unconfigured::TargetNode;
+use buck2_query::query::syntax::simple::eval::profile::QueryProfiler;
 use buck2_query::query::syntax::simple::eval::values::QueryEvaluationResult;
 use buck2_query::query::syntax::simple::functions::DefaultQueryFunctionsModule;
 use dice::DiceComputations;
+use dupe::Dupe;
 
 use crate::analysis::evaluator::eval_query;
 use crate::dice::get_dice_query_delegate;
@@ -26,26 +28,41 @@ use crate::uquery::environment::UqueryEnvironment;
 pub struct UqueryEvaluator<'c> {
     dice_query_delegate: DiceQueryDelegate<'c>,
     functions: DefaultQueryFunctionsModule<UqueryEnvironment<'c>>,
+    profiler: Option<Arc<QueryProfiler>>,
 }
 
 impl UqueryEvaluator<'_> {
+    /// Profile the evaluated queries with `profiler`.
+    pub fn with_profiler(mut self, profiler: Option<Arc<QueryProfiler>>) -> Self {
+        self.dice_query_delegate
+            .set_profile_counters(profiler.as_ref().map(|p| p.counters().dupe()));
+        self.profiler = profiler;
+        self
+    }
+
     pub async fn eval_query(
         &self,
         query: &str,
         query_args: &[String],
     ) -> anyhow::Result<QueryEvaluationResult<TargetNode>> {
-        eval_query(&self.functions, query, query_args, async move |literals| {
-            let resolved_literals = PreresolvedQueryLiterals::pre_resolve(
-                &**self.dice_query_delegate.query_data(),
-                &literals,
-                self.dice_query_delegate.ctx(),
-            )
-            .await;
-            Ok(UqueryEnvironment::new(
-                &self.dice_query_delegate,
-                Arc::new(resolved_literals),
-            ))
-        })
+        eval_query(
+            &self.functions,
+            query,
+            query_args,
+            self.profiler.as_deref(),
+            async move |literals| {
+                let resolved_literals = PreresolvedQueryLiterals::pre_resolve(
+                    &**self.dice_query_delegate.query_data(),
+                    &literals,
+                    self.dice_query_delegate.ctx(),
+                )
+                .await;
+                Ok(UqueryEnvironment::new(
+                    &self.dice_query_delegate,
+                    Arc::new(resolved_literals),
+                ))
+            },
+        )
         .await
     }
 }
@@ -64,5 +81,6 @@ pub async fn get_uquery_evaluator<'a, 'c: 'a>(
     Ok(UqueryEvaluator {
         dice_query_delegate,
         functions,
+        profiler: None,
     })
 }
@@ -67,6 +67,8 @@ use buck2_execute::execute::blocking::SetBlockingExecutor;
 use buck2_execute::execute::dice_data::set_fallback_executor_config;
 use buck2_execute::execute::dice_data::SetCommandExecutor;
 use buck2_execute::execute::dice_data::SetReClient;
+use buck2_execute::execute::test_result_cache::SetTestResultCache;
+use buck2_execute::execute::test_result_cache::TestResultCache;
 use buck2_execute::knobs::ExecutorGlobalKnobs;
 use buck2_execute::materialize::materializer::Materializer;
 use buck2_execute::materialize::materializer::SetMaterializer;
@@ -213,6 +215,9 @@ pub struct ServerCommandContext<'a> {
     cancellations: &'a ExplicitCancellationContext,
 
     exit_when_different_state: bool,
+
+    /// The client writes an execution log, which needs the inputs of every action.
+    record_execution_log: bool,
 }
 
 impl<'a> ServerCommandContext<'a> {
@@ -334,6 +339,7 @@ impl<'a> ServerCommandContext<'a> {
             debugger_handle,
             cancellations,
             exit_when_different_state: client_context.exit_when_different_state,
+            record_execution_log: client_context.record_execution_log,
         })
     }
 
@@ -367,6 +373,8 @@ impl<'a> ServerCommandContext<'a> {
                 .base_context
                 .daemon
                 .use_network_action_output_cache,
+            log_action_digest_inputs: self.base_context.daemon.log_action_digest_inputs
+                || self.record_execution_log,
             ..Default::default()
         };
 
@@ -396,6 +404,8 @@ impl<'a> ServerCommandContext<'a> {
         let create_unhashed_symlink_lock =
             self.base_context.daemon.create_unhashed_outputs_lock.dupe();
 
+        let test_result_cache = self.base_context.daemon.test_result_cache.dupe();
+
         DiceCommandDataProvider {
             cell_configs_loader: self.cell_configs_loader.dupe(),
             events: self.events().dupe(),
@@ -412,6 +422,7 @@ impl<'a> ServerCommandContext<'a> {
             skip_cache_read,
             skip_cache_write,
             create_unhashed_symlink_lock,
+            test_result_cache,
             starlark_debugger: self.debugger_handle.dupe(),
             keep_going: self
                 .build_options
@@ -521,6 +532,7 @@ struct DiceCommandDataProvider {
     skip_cache_read: bool,
     skip_cache_write: bool,
     create_unhashed_symlink_lock: Arc<Mutex<()>>,
+    test_result_cache: Arc<TestResultCache>,
     starlark_debugger: Option<BuckStarlarkDebuggerHandle>,
     keep_going: bool,
     http_client: HttpClient,
@@ -618,6 +630,10 @@ impl DiceDataProvider for DiceCommandDataProvider {
             .parse("buck2", "critical_path_backend2")?
             .unwrap_or(CriticalPathBackendName::Default);
 
+        let log_build_graph = root_config
+            .parse::<bool>("buck2", "log_build_graph")?
+            .unwrap_or(false);
+
         set_fallback_executor_config(&mut data.data, self.executor_config.dupe());
         data.set_re_client(self.re_connection.get_client());
         data.set_command_executor(Box::new(CommandExecutorFactory::new(
@@ -645,9 +661,11 @@ impl DiceDataProvider for DiceCommandDataProvider {
         data.set_build_signals(self.build_signals.build_signals.dupe());
         data.set_run_action_knobs(run_action_knobs);
         data.set_create_unhashed_symlink_lock(self.create_unhashed_symlink_lock.dupe());
+        data.set_test_result_cache(self.test_result_cache.dupe());
         data.set_starlark_debugger_handle(self.starlark_debugger.clone().map(|v| Box::new(v) as _));
         data.set_keep_going(self.keep_going);
         data.set_critical_path_backend(critical_path_backend);
+        data.set_log_build_graph(log_build_graph);
         data.spawner = self.spawner.dupe();
 
         let tags = vec![
@@ -1321,7 +1321,13 @@ impl DaemonApi for BuckdServer {
              partial_result_dispatcher,
              _client_ctx,
              req: StreamingRequestHandler<SubscriptionRequestWrapper>| {
-                run_subscription_server_command(ctx, partial_result_dispatcher, req).boxed()
+                run_subscription_server_command(
+                    ctx,
+                    ctx.base_context.daemon.file_watcher.dupe(),
+                    partial_result_dispatcher,
+                    req,
+                )
+                .boxed()
             },
         )
         .await
@@ -36,13 +36,19 @@ use buck2_core::is_open_source;
 use buck2_core::rollout_percentage::RolloutPercentage;
 use buck2_core::tag_result;
 use buck2_events::dispatch::EventDispatcher;
+use buck2_events::sink::bep::BepSink;
+use buck2_events::sink::bep::BepSinkConfig;
+use buck2_events::sink::otlp::OtlpSink;
+use buck2_events::sink::otlp::OtlpSinkConfig;
 use buck2_events::sink::scribe;
 use buck2_events::sink::tee::TeeSink;
 use buck2_events::source::ChannelEventSource;
+use buck2_events::EventSink;
 use buck2_events::EventSinkWithStats;
 use buck2_execute::digest_config::DigestConfig;
 use buck2_execute::execute::blocking::BlockingExecutor;
 use buck2_execute::execute::blocking::BuckBlockingExecutor;
+use buck2_execute::execute::test_result_cache::TestResultCache;
 use buck2_execute::materialize::materializer::MaterializationMethod;
 use buck2_execute::materialize::materializer::Materializer;
 use buck2_execute::re::manager::ReConnectionManager;
@@ -135,6 +141,14 @@ pub struct DaemonStateData {
     #[allocative(skip)]
     pub scribe_sink: Option<Arc<dyn EventSinkWithStats>>,
 
+    /// Where to report builds and tests using the Build Event Protocol.
+    #[allocative(skip)]
+    pub bep_sink_config: BepSinkConfig,
+
+    /// Where to export spans as OpenTelemetry traces.
+    #[allocative(skip)]
+    pub otlp_sink_config: OtlpSinkConfig,
+
     /// Whether or not to hash all commands
     pub hash_all_commands: bool,
 
@@ -148,6 +162,9 @@ pub struct DaemonStateData {
     /// it needs to be downloaded again).
     pub use_network_action_output_cache: bool,
 
+    /// Whether to record the inputs of action digests in the event log.
+    pub log_action_digest_inputs: bool,
+
     /// What buck2 state to store on disk, ex. materializer state on sqlite
     pub disk_state_options: DiskStateOptions,
 
@@ -156,6 +173,10 @@ pub struct DaemonStateData {
     #[allocative(skip)]
     pub create_unhashed_outputs_lock: Arc<Mutex<()>>,
 
+    /// The passing test executions kept for the test result cache.
+    #[allocative(skip)]
+    pub test_result_cache: Arc<TestResultCache>,
+
     /// A unique identifier for the materializer state.
     pub materializer_state_identity: Option<MaterializerStateIdentity>,
 
@@ -441,8 +462,14 @@ impl DaemonState {
                 .parse("buck2", "use_network_action_output_cache")?
                 .unwrap_or(false);
 
+            let log_action_digest_inputs = root_config
+                .parse("buck2", "log_action_digest_inputs")?
+                .unwrap_or(false);
+
             let create_unhashed_outputs_lock = Arc::new(Mutex::new(()));
 
+            let test_result_cache = Arc::new(TestResultCache::default());
+
             let buffer_size = root_config
                 .parse("buck2", "event_log_buffer_size")?
                 .unwrap_or(10000);
@@ -464,6 +491,26 @@ impl DaemonState {
             )
             .context("failed to init scribe sink")?;
 
+            // Relative paths are relative to the project root.
+            let config_path = |key: &str| {
+                root_config
+                    .get("buck2", key)
+                    .map(|path| paths.project_root().root().as_abs_path().join(path))
+            };
+            let bep_sink_config = BepSinkConfig {
+                binary_file: config_path("bep_binary_file"),
+                json_file: config_path("bep_json_file"),
+                backend: root_config.get("buck2", "bep_backend").map(str::to_owned),
+                project_root: paths.project_root().root().to_string(),
+            };
+            let otlp_sink_config = OtlpSinkConfig {
+                endpoint: root_config.get("buck2", "otlp_endpoint").map(str::to_owned),
+                protocol: root_config
+                    .parse("buck2", "otlp_protocol")?
+                    .unwrap_or_default(),
+                json_file: config_path("otlp_json_file"),
+            };
+
             let enable_restarter = root_config
                 .parse::<RolloutPercentage>("buck2", "restarter")?
                 .unwrap_or_else(RolloutPercentage::never)
@@ -494,11 +541,15 @@ impl DaemonState {
                 materializer,
                 forkserver,
                 scribe_sink,
+                bep_sink_config,
+                otlp_sink_config,
                 hash_all_commands,
                 use_network_action_output_cache,
+                log_action_digest_inputs,
                 disk_state_options,
                 start_time: std::time::Instant::now(),
                 create_unhashed_outputs_lock,
+                test_result_cache,
                 materializer_state_identity,
                 enable_restarter,
                 http_client,
@@ -616,6 +667,26 @@ impl DaemonState {
         facebook_only();
         let (events, sink) = buck2_events::create_source_sink_pair();
         let data = self.data()?;
+        let sink: Arc<dyn EventSink> = if data.bep_sink_config.is_enabled() {
+            Arc::new(TeeSink::new(
+                BepSink::new(data.bep_sink_config.clone(), trace_id.dupe()),
+                sink,
+            ))
+        } else {
+            Arc::new(sink)
+        };
+        let sink: Arc<dyn EventSink> = if data.otlp_sink_config.is_enabled() {
+            Arc::new(TeeSink::new(
+                OtlpSink::new(
+                    data.otlp_sink_config.clone(),
+                    data.http_client.dupe(),
+                    trace_id.dupe(),
+                ),
+                sink,
+            ))
+        } else {
+            sink
+        };
         let dispatcher = if let Some(scribe_sink) = data.scribe_sink.dupe() {
             EventDispatcher::new(trace_id, TeeSink::new(scribe_sink.to_event_sync(), sink))
         } else {
@@ -7,14 +7,18 @@
  * of this source tree.
  */
 
+use std::sync::Arc;
 use std::time::Duration;
 
 use anyhow::Context as _;
 use buck2_events::dispatch::span_async;
+use buck2_file_watcher::file_watcher::FileWatcher;
 use buck2_server_ctx::command_end::command_end;
 use buck2_server_ctx::ctx::ServerCommandContextTrait;
 use buck2_server_ctx::partial_result_dispatcher::PartialResultDispatcher;
 use buck2_server_ctx::streaming_request_handler::StreamingRequestHandler;
+use futures::future::BoxFuture;
+use futures::future::Fuse;
 use futures::future::FutureExt;
 use gazebo::prelude::*;
 use tokio::time::MissedTickBehavior;
@@ -23,6 +27,7 @@ use crate::active_commands;
 
 pub(crate) async fn run_subscription_server_command(
     ctx: &dyn ServerCommandContextTrait,
+    file_watcher: Arc<dyn FileWatcher>,
     mut partial_result_dispatcher: PartialResultDispatcher<
         buck2_cli_proto::SubscriptionResponseWrapper,
     >,
@@ -50,6 +55,8 @@ pub(crate) async fn run_subscription_server_command(
 
             let mut wants_active_commands = false;
 
+            let mut file_changes: Fuse<BoxFuture<'_, anyhow::Result<()>>> = Fuse::terminated();
+
             let mut ticker = tokio::time::interval(Duration::from_millis(100));
             ticker.set_missed_tick_behavior(MissedTickBehavior::Skip);
 
@@ -73,8 +80,19 @@ pub(crate) async fn run_subscription_server_command(
                             Request::SubscribeToActiveCommands(buck2_subscription_proto::SubscribeToActiveCommands {}) => {
                                 wants_active_commands = true;
                             }
+                            Request::WaitForFileChanges(buck2_subscription_proto::WaitForFileChanges {}) => {
+                                file_changes = file_watcher.wait_for_changes().fuse();
+                            }
                         }
                     }
+                    res = file_changes => {
+                        res.context("Error waiting for file changes")?;
+                        partial_result_dispatcher.emit(buck2_cli_proto::SubscriptionResponseWrapper {
+                            response: Some(buck2_subscription_proto::SubscriptionResponse {
+                                response: Some(buck2_subscription_proto::FilesChanged {}.into())
+                            })
+                        });
+                    }
                     path = materializer_subscription.next_materialization().fuse() => {
                         let path = path.context("Materializer hung up")?;
                         partial_result_dispatcher.emit(buck2_cli_proto::SubscriptionResponseWrapper {
@@ -22,6 +22,7 @@ use buck2_artifact::artifact::artifact_dump::SymlinkInfo;
 use buck2_build_api::actions::artifact::get_artifact_fs::GetArtifactFs;
 use buck2_build_api::build;
 use buck2_build_api::build::BuildEvent;
+use buck2_build_api::build::BuildProviderType;
 use buck2_build_api::build::BuildTargetResult;
 use buck2_build_api::build::ConfiguredBuildEvent;
 use buck2_build_api::build::ConvertMaterializationContext;
@@ -53,8 +54,10 @@ use buck2_core::pattern::ParsedPattern;
 use buck2_core::provider::label::ProvidersLabel;
 use buck2_core::provider::label::ProvidersName;
 use buck2_core::target::label::TargetLabel;
+use buck2_data::ToProtoMessage;
 use buck2_events::dispatch::console_message;
 use buck2_events::dispatch::span_async;
+use buck2_execute::artifact::artifact_dyn::ArtifactDyn;
 use buck2_execute::directory::ActionDirectoryBuilder;
 use buck2_execute::directory::ActionDirectoryMember;
 use buck2_node::configured_universe::CqueryUniverse;
@@ -319,6 +322,8 @@ async fn process_build_result(
         None
     };
 
+    report_target_build_results(server_ctx, &artifact_fs, &build_result)?;
+
     let mut provider_artifacts = Vec::new();
     for v in build_result.configured.into_values() {
         // We omit skipped targets here.
@@ -402,6 +407,43 @@ async fn process_build_result(
     })
 }
 
+/// Record the outcome and default outputs of every requested target in the event log.
+fn report_target_build_results(
+    server_ctx: &dyn ServerCommandContextTrait,
+    artifact_fs: &ArtifactFs,
+    build_result: &BuildTargetResult,
+) -> anyhow::Result<()> {
+    for (label, result) in &build_result.configured {
+        // We omit skipped targets here.
+        let Some(result) = result else { continue };
+
+        let mut outputs = Vec::new();
+        for output in &result.outputs {
+            let Ok(output) = output else { continue };
+            if !matches!(output.provider_type, BuildProviderType::Default) {
+                continue;
+            }
+            for (artifact, value) in output.values.iter() {
+                outputs.push(buck2_data::TargetBuildOutput {
+                    path: artifact.resolve_path(artifact_fs)?.to_string(),
+                    digest: value.digest().map(|d| d.raw_digest().to_string()),
+                    size: value.digest().map_or(0, |d| d.size()),
+                });
+            }
+        }
+
+        server_ctx
+            .events()
+            .instant_event(buck2_data::TargetBuildResult {
+                providers_label: label.unconfigured().to_string(),
+                target: Some(label.target().as_proto()),
+                success: result.errors.is_empty() && result.outputs.iter().all(|o| o.is_ok()),
+                outputs,
+            });
+    }
+    Ok(())
+}
+
 async fn build_targets(
     ctx: &DiceComputations,
     spec: ResolvedPattern<ConfiguredProvidersPatternExtra>,
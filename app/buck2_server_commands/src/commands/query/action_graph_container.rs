@@ -0,0 +1,347 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Conversion of `aquery` results into Bazel's `analysis.ActionGraphContainer`
+//! (`--output-format=proto|jsonproto`).
+
+use std::collections::HashMap;
+
+use buck2_aquery_proto as analysis;
+use buck2_artifact::artifact::artifact_type::Artifact;
+use buck2_artifact::artifact::artifact_type::BaseArtifactKind;
+use buck2_build_api::actions::query::ActionInput;
+use buck2_build_api::actions::query::ActionQueryNode;
+use buck2_build_api::actions::query::SetProjectionInputs;
+use buck2_build_api::artifact_groups::ArtifactGroup;
+use buck2_build_api::artifact_groups::TransitiveSetProjectionKey;
+use buck2_core::base_deferred_key::BaseDeferredKey;
+use buck2_core::configuration::data::ConfigurationData;
+use buck2_core::fs::artifact_path_resolver::ArtifactFs;
+use buck2_core::fs::project_rel_path::ProjectRelativePathBuf;
+use buck2_execute::artifact::fs::ExecutorFs;
+use buck2_execute::execute::request::OutputType;
+use dupe::Dupe;
+
+/// Accumulates actions into an `ActionGraphContainer`, assigning ids to artifacts, path
+/// fragments, targets, configurations and dep sets as they are first seen.
+///
+/// Ids start at 1: 0 means "unset" in proto3.
+#[derive(Default)]
+pub(crate) struct ActionGraphContainerBuilder {
+    container: analysis::ActionGraphContainer,
+    artifacts: HashMap<ProjectRelativePathBuf, u32>,
+    path_fragments: HashMap<(u32, String), u32>,
+    targets: HashMap<String, u32>,
+    configurations: HashMap<ConfigurationData, u32>,
+    dep_sets: HashMap<TransitiveSetProjectionKey, u32>,
+}
+
+impl ActionGraphContainerBuilder {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    pub(crate) fn build(self) -> analysis::ActionGraphContainer {
+        self.container
+    }
+
+    /// Add an action to the container. Analysis nodes are ignored.
+    pub(crate) fn add(&mut self, node: &ActionQueryNode) -> anyhow::Result<()> {
+        let data = match node.action_data() {
+            Some(data) => data,
+            None => return Ok(()),
+        };
+        let action = data.action();
+        let fs = data.fs();
+        let executor_fs = ExecutorFs::new(fs, action.execution_config().options.path_separator);
+
+        let (target_id, configuration_id) = match action.key().owner() {
+            BaseDeferredKey::TargetLabel(label) => (
+                self.target_id(label.unconfigured().to_string()),
+                self.configuration_id(label.cfg()),
+            ),
+            owner => (self.target_id(owner.to_string()), 0),
+        };
+
+        let (arguments, environment_variables) =
+            match action.action().aquery_command_line(&executor_fs)? {
+                Some(command) => (
+                    command.exe.into_iter().chain(command.args).collect(),
+                    command
+                        .env
+                        .into_iter()
+                        .map(|(key, value)| analysis::KeyValuePair { key, value })
+                        .collect(),
+                ),
+                None => (Vec::new(), Vec::new()),
+            };
+
+        let mut input_dep_set_ids = Vec::new();
+        let mut direct_artifact_ids = Vec::new();
+        for input in action.action().inputs()?.iter() {
+            if let ArtifactGroup::Artifact(artifact) = input {
+                direct_artifact_ids.push(
+                    self.artifact_id(artifact.get_path().resolve(fs)?, is_tree_artifact(artifact)),
+                );
+            }
+        }
+        if !direct_artifact_ids.is_empty() {
+            let id = self.next_dep_set_id();
+            self.container
+                .dep_set_of_files
+                .push(analysis::DepSetOfFiles {
+                    id,
+                    transitive_dep_set_ids: Vec::new(),
+                    direct_artifact_ids,
+                });
+            input_dep_set_ids.push(id);
+        }
+        for input in data.deps() {
+            if let ActionInput::IndirectInputs(projection) = input {
+                input_dep_set_ids.push(self.dep_set_id(projection, fs)?);
+            }
+        }
+
+        let output_ids: Vec<u32> = action
+            .action()
+            .outputs()?
+            .iter()
+            .map(|output| {
+                self.artifact_id(
+                    fs.resolve_build(output.get_path()),
+                    output.output_type() == OutputType::Directory,
+                )
+            })
+            .collect();
+
+        let mut attrs = data.attrs();
+        let execution_platform = attrs.remove("executor_configuration").unwrap_or_default();
+
+        self.container.actions.push(analysis::Action {
+            target_id,
+            action_key: action.key().to_string(),
+            mnemonic: action.category().to_string(),
+            configuration_id,
+            arguments,
+            environment_variables,
+            input_dep_set_ids,
+            primary_output_id: output_ids.first().copied().unwrap_or_default(),
+            output_ids,
+            execution_info: attrs
+                .into_iter()
+                .map(|(key, value)| analysis::KeyValuePair { key, value })
+                .collect(),
+            execution_platform,
+        });
+        Ok(())
+    }
+
+    fn target_id(&mut self, label: String) -> u32 {
+        if let Some(id) = self.targets.get(&label) {
+            return *id;
+        }
+        let id = self.container.targets.len() as u32 + 1;
+        self.container.targets.push(analysis::Target {
+            id,
+            label: label.clone(),
+            rule_class_id: 0,
+        });
+        self.targets.insert(label, id);
+        id
+    }
+
+    fn configuration_id(&mut self, cfg: &ConfigurationData) -> u32 {
+        if let Some(id) = self.configurations.get(cfg) {
+            return *id;
+        }
+        let id = self.container.configuration.len() as u32 + 1;
+        self.container.configuration.push(analysis::Configuration {
+            id,
+            mnemonic: cfg.to_string(),
+            platform_name: cfg.short_name().to_owned(),
+            checksum: cfg.output_hash().to_string(),
+        });
+        self.configurations.insert(cfg.dupe(), id);
+        id
+    }
+
+    fn artifact_id(&mut self, path: ProjectRelativePathBuf, is_tree_artifact: bool) -> u32 {
+        if let Some(id) = self.artifacts.get(&path) {
+            return *id;
+        }
+        let mut path_fragment_id = 0;
+        for component in path.iter() {
+            path_fragment_id = self.path_fragment_id(path_fragment_id, component.as_str());
+        }
+        let id = self.container.artifacts.len() as u32 + 1;
+        self.container.artifacts.push(analysis::Artifact {
+            id,
+            path_fragment_id,
+            is_tree_artifact,
+        });
+        self.artifacts.insert(path, id);
+        id
+    }
+
+    fn path_fragment_id(&mut self, parent_id: u32, label: &str) -> u32 {
+        let key = (parent_id, label.to_owned());
+        if let Some(id) = self.path_fragments.get(&key) {
+            return *id;
+        }
+        let id = self.container.path_fragments.len() as u32 + 1;
+        self.container.path_fragments.push(analysis::PathFragment {
+            id,
+            label: label.to_owned(),
+            parent_id,
+        });
+        self.path_fragments.insert(key, id);
+        id
+    }
+
+    fn next_dep_set_id(&self) -> u32 {
+        self.container.dep_set_of_files.len() as u32 + 1
+    }
+
+    /// Dep set for a transitive set projection, shared between all the actions using it.
+    fn dep_set_id(&mut self, inputs: &SetProjectionInputs, fs: &ArtifactFs) -> anyhow::Result<u32> {
+        let node = &inputs.node;
+        if let Some(id) = self.dep_sets.get(node.key()) {
+            return Ok(*id);
+        }
+        let transitive_dep_set_ids = node
+            .children
+            .iter()
+            .map(|child| self.dep_set_id(child, fs))
+            .collect::<anyhow::Result<_>>()?;
+        let direct_artifact_ids = node
+            .artifacts
+            .iter()
+            .map(|artifact| {
+                Ok(self.artifact_id(artifact.get_path().resolve(fs)?, is_tree_artifact(artifact)))
+            })
+            .collect::<anyhow::Result<_>>()?;
+        let id = self.next_dep_set_id();
+        self.container
+            .dep_set_of_files
+            .push(analysis::DepSetOfFiles {
+                id,
+                transitive_dep_set_ids,
+                direct_artifact_ids,
+            });
+        self.dep_sets.insert(node.key().dupe(), id);
+        Ok(id)
+    }
+}
+
+/// Whether an artifact is a directory, like a Bazel tree artifact.
+///
+/// Only outputs declared as directories are known to be directories: source artifacts and
+/// projections into outputs are reported as files.
+fn is_tree_artifact(artifact: &Artifact) -> bool {
+    match artifact.as_parts() {
+        (BaseArtifactKind::Build(build), None) => build.output_type() == OutputType::Directory,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use buck2_aquery_proto as analysis;
+    use buck2_core::fs::project_rel_path::ProjectRelativePathBuf;
+    use prost::Message;
+
+    use crate::commands::query::action_graph_container::ActionGraphContainerBuilder;
+
+    #[test]
+    fn test_ids() {
+        let mut builder = ActionGraphContainerBuilder::new();
+        let a = builder.artifact_id(ProjectRelativePathBuf::testing_new("buck-out/a"), false);
+        let b = builder.artifact_id(ProjectRelativePathBuf::testing_new("buck-out/b"), true);
+        assert_eq!(
+            a,
+            builder.artifact_id(ProjectRelativePathBuf::testing_new("buck-out/a"), false)
+        );
+        assert_eq!((1, 2), (a, b));
+        assert_eq!(1, builder.target_id("root//:x".to_owned()));
+        assert_eq!(2, builder.target_id("root//:y".to_owned()));
+        assert_eq!(1, builder.target_id("root//:x".to_owned()));
+
+        let container = builder.build();
+        // `buck-out` is shared by both artifacts.
+        assert_eq!(
+            vec![
+                analysis::PathFragment {
+                    id: 1,
+                    label: "buck-out".to_owned(),
+                    parent_id: 0,
+                },
+                analysis::PathFragment {
+                    id: 2,
+                    label: "a".to_owned(),
+                    parent_id: 1,
+                },
+                analysis::PathFragment {
+                    id: 3,
+                    label: "b".to_owned(),
+                    parent_id: 1,
+                },
+            ],
+            container.path_fragments
+        );
+        assert_eq!(
+            vec![
+                analysis::Artifact {
+                    id: 1,
+                    path_fragment_id: 2,
+                    is_tree_artifact: false,
+                },
+                analysis::Artifact {
+                    id: 2,
+                    path_fragment_id: 3,
+                    is_tree_artifact: true,
+                },
+            ],
+            container.artifacts
+        );
+    }
+
+    #[test]
+    fn test_proto_and_jsonproto() -> anyhow::Result<()> {
+        let mut builder = ActionGraphContainerBuilder::new();
+        let id = builder.artifact_id(ProjectRelativePathBuf::testing_new("out"), true);
+        let target_id = builder.target_id("root//:x".to_owned());
+        builder.container.actions.push(analysis::Action {
+            target_id,
+            mnemonic: "run".to_owned(),
+            primary_output_id: id,
+            output_ids: vec![id],
+            ..Default::default()
+        });
+        let container = builder.build();
+
+        assert_eq!(
+            container,
+            analysis::ActionGraphContainer::decode(container.encode_to_vec().as_slice())?
+        );
+
+        // Field names follow the proto3 JSON mapping, like Bazel's `--output=jsonproto`.
+        let json = serde_json::to_value(&container)?;
+        assert_eq!(
+            serde_json::json!([{"id": 1, "pathFragmentId": 1, "isTreeArtifact": true}]),
+            json["artifacts"]
+        );
+        assert_eq!(
+            serde_json::json!([{"id": 1, "label": "root//:x", "ruleClassId": 0}]),
+            json["targets"]
+        );
+        assert_eq!(serde_json::json!([1]), json["actions"][0]["outputIds"]);
+        assert_eq!(1, json["actions"][0]["primaryOutputId"]);
+        Ok(())
+    }
+}
@@ -11,18 +11,24 @@ use std::io::Write;
 
 use anyhow::Context;
 use async_trait::async_trait;
+use buck2_build_api::actions::query::ActionQueryNode;
 use buck2_build_api::query::oneshot::QUERY_FRONTEND;
+use buck2_cli_proto::QueryOutputFormat;
 use buck2_common::dice::cells::HasCellResolver;
 use buck2_query::query::syntax::simple::eval::values::QueryEvaluationResult;
+use buck2_query::query::syntax::simple::eval::values::QueryEvaluationValue;
 use buck2_server_ctx::ctx::ServerCommandContextTrait;
 use buck2_server_ctx::partial_result_dispatcher::PartialResultDispatcher;
 use buck2_server_ctx::pattern::target_platform_from_client_context;
 use buck2_server_ctx::template::run_server_command;
 use buck2_server_ctx::template::ServerCommandTemplate;
 use dice::DiceTransaction;
+use prost::Message;
 
+use crate::commands::query::action_graph_container::ActionGraphContainerBuilder;
 use crate::commands::query::printer::QueryResultPrinter;
 use crate::commands::query::printer::ShouldPrintProviders;
+use crate::commands::query::QueryCommandError;
 
 pub(crate) async fn aquery_command(
     ctx: &dyn ServerCommandContextTrait,
@@ -101,6 +107,21 @@ async fn aquery(
         )
         .await?;
 
+    if request.unstable_output_format == QueryOutputFormat::Proto as i32
+        || request.unstable_output_format == QueryOutputFormat::Jsonproto as i32
+    {
+        let actions = match query_result {
+            QueryEvaluationResult::Single(actions) => actions,
+            QueryEvaluationResult::Multiple(results) => results.merged()?,
+        };
+        print_action_graph(
+            &mut stdout,
+            actions,
+            request.unstable_output_format == QueryOutputFormat::Jsonproto as i32,
+        )?;
+        return Ok(buck2_cli_proto::AqueryResponse {});
+    }
+
     match query_result {
         QueryEvaluationResult::Single(targets) => {
             output_configuration
@@ -115,3 +136,29 @@ async fn aquery(
     };
     Ok(buck2_cli_proto::AqueryResponse {})
 }
+
+/// Print the actions as a Bazel `analysis.ActionGraphContainer`, either binary or as JSON.
+fn print_action_graph(
+    mut stdout: impl Write,
+    result: QueryEvaluationValue<ActionQueryNode>,
+    json: bool,
+) -> anyhow::Result<()> {
+    let actions = match result {
+        QueryEvaluationValue::TargetSet(actions) => actions,
+        QueryEvaluationValue::FileSet(_) => {
+            return Err(QueryCommandError::FileSetHasNoActionGraph.into());
+        }
+    };
+    let mut builder = ActionGraphContainerBuilder::new();
+    for action in actions.iter() {
+        builder.add(action)?;
+    }
+    let container = builder.build();
+    if json {
+        serde_json::to_writer_pretty(&mut stdout, &container)?;
+        writeln!(stdout)?;
+    } else {
+        stdout.write_all(&container.encode_to_vec())?;
+    }
+    Ok(())
+}
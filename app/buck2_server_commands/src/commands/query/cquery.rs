@@ -8,6 +8,7 @@
  */
 
 use std::io::Write;
+use std::sync::Arc;
 
 use anyhow::Context;
 use async_trait::async_trait;
@@ -22,6 +23,7 @@ use buck2_core::configuration::compatibility::MaybeCompatible;
 use buck2_core::provider::label::ConfiguredProvidersLabel;
 use buck2_core::provider::label::ProvidersName;
 use buck2_node::nodes::configured::ConfiguredTargetNode;
+use buck2_query::query::syntax::simple::eval::profile::QueryProfiler;
 use buck2_query::query::syntax::simple::eval::values::QueryEvaluationResult;
 use buck2_server_ctx::ctx::ServerCommandContextTrait;
 use buck2_server_ctx::partial_result_dispatcher::PartialResultDispatcher;
@@ -36,6 +38,7 @@ use dupe::Dupe;
 use crate::commands::query::printer::ProviderLookUp;
 use crate::commands::query::printer::QueryResultPrinter;
 use crate::commands::query::printer::ShouldPrintProviders;
+use crate::commands::query::profile::report_query_profile;
 
 pub(crate) async fn cquery_command(
     ctx: &dyn ServerCommandContextTrait,
@@ -95,7 +98,9 @@ async fn cquery(
         &cell_resolver,
         &request.output_attributes,
         request.unstable_output_format,
-    )?;
+    )?
+    .with_explain_edges(request.explain_edges)
+    .with_show_transitions(request.show_transitions);
 
     let CqueryRequest {
         query,
@@ -126,6 +131,8 @@ async fn cquery(
         false => CqueryOwnerBehavior::Deprecated,
     };
 
+    let profiler = request.profile.then(|| Arc::new(QueryProfiler::new()));
+
     let query_result = QUERY_FRONTEND
         .get()?
         .eval_cquery(
@@ -136,6 +143,7 @@ async fn cquery(
             query_args,
             global_target_platform,
             target_universe,
+            profiler.dupe(),
         )
         .await?;
 
@@ -167,6 +175,7 @@ async fn cquery(
                 .await?
         }
     };
+    report_query_profile(server_ctx.events(), profiler);
 
     Ok(CqueryResponse {})
 }
@@ -9,9 +9,11 @@
 
 use thiserror::Error;
 
+mod action_graph_container;
 pub mod aquery;
 pub mod cquery;
 pub mod printer;
+mod profile;
 pub mod uquery;
 
 #[derive(Debug, Error)]
@@ -20,4 +22,14 @@ enum QueryCommandError {
         "query result was a set of files and one or more --output-attribute was requested, but files have not attributes"
     )]
     FileSetHasNoAttributes,
+    #[error("`--output-format=build` is only supported by `uquery`")]
+    BuildOutputFormatNotSupported,
+    #[error("query result was a set of files, but `--output-format=build` can only print targets")]
+    FileSetHasNoBuildSyntax,
+    #[error(
+        "`--output-format=proto` and `--output-format=jsonproto` are only supported by `aquery`"
+    )]
+    ProtoOutputFormatNotSupported,
+    #[error("query result was a set of files, but `--output-format=proto` can only print actions")]
+    FileSetHasNoActionGraph,
 }
@@ -20,6 +20,7 @@ use buck2_build_api::interpreter::rule_defs::provider::collection::FrozenProvide
 use buck2_cli_proto::QueryOutputFormat;
 use buck2_core::cells::CellResolver;
 use buck2_core::configuration::compatibility::MaybeCompatible;
+use buck2_query::query::environment::DepEdge;
 use buck2_query::query::environment::QueryTarget;
 use buck2_query::query::environment::QueryTargets;
 use buck2_query::query::syntax::simple::eval::file_set::FileSet;
@@ -29,6 +30,7 @@ use buck2_query::query::syntax::simple::eval::values::QueryEvaluationValue;
 use buck2_util::indent::indent;
 use dupe::Clone_;
 use dupe::Copy_;
+use dupe::Dupe;
 use dupe::Dupe_;
 use gazebo::variants::UnpackVariants;
 use indent_write::fmt::IndentWriter;
@@ -60,6 +62,19 @@ pub struct QueryResultPrinter<'a> {
     resolver: &'a CellResolver,
     attributes: Option<RegexSet>,
     output_format: QueryOutputFormat,
+    explain_edges: bool,
+    show_transitions: bool,
+}
+
+/// Which edges to print alongside each target.
+#[derive(Debug, Copy, Clone, Dupe, PartialEq, Eq)]
+enum PrintEdges {
+    No,
+    /// Edges to other targets in the result (`--explain-edges`).
+    All,
+    /// Edges to other targets in the result which change the configuration
+    /// (`--show-transitions`).
+    Transitions,
 }
 
 struct TargetSetJsonPrinter<'a, T: QueryTarget> {
@@ -70,15 +85,23 @@ struct TargetSetJsonPrinter<'a, T: QueryTarget> {
 impl<'a, T: QueryTarget> TargetSetJsonPrinter<'a, T> {
     async fn new(
         target_call_stacks: bool,
+        print_edges: PrintEdges,
         print_providers: ShouldPrintProviders<'a, T>,
         attributes: &'a Option<RegexSet>,
         targets: &'a TargetSet<T>,
     ) -> anyhow::Result<TargetSetJsonPrinter<'a, T>> {
         Ok(TargetSetJsonPrinter {
-            value: printable_targets(targets, print_providers, attributes, target_call_stacks)
-                .await?,
+            value: printable_targets(
+                targets,
+                print_providers,
+                attributes,
+                target_call_stacks,
+                print_edges,
+            )
+            .await?,
             is_complex: attributes.is_some()
                 || target_call_stacks
+                || print_edges != PrintEdges::No
                 || print_providers.unpack_yes().is_some(),
         })
     }
@@ -89,6 +112,49 @@ struct PrintableQueryTarget<'a, T: QueryTarget> {
     attributes: &'a Option<RegexSet>,
     providers: Option<FrozenProviderCollectionValue>,
     target_call_stacks: bool,
+    /// Edges to other targets in the result set, when `--explain-edges` or
+    /// `--show-transitions` is requested.
+    edges: Option<Vec<DepEdge<T::NodeRef>>>,
+}
+
+struct DepEdgeJson<'a, N>(&'a DepEdge<N>);
+
+impl<'a, N: Display> Serialize for DepEdgeJson<'a, N> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let edge = self.0;
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("dep", &edge.dep.to_string())?;
+        map.serialize_entry("attr", &edge.attr)?;
+        map.serialize_entry("kind", &edge.kind.to_string())?;
+        if let Some(transition) = &edge.transition {
+            map.serialize_entry("transition", transition)?;
+        }
+        if let Some((from, to)) = &edge.configuration_change {
+            map.serialize_entry("configuration_from", from)?;
+            map.serialize_entry("configuration_to", to)?;
+        }
+        if let Some(execution_platform) = &edge.execution_platform {
+            map.serialize_entry("execution_platform", execution_platform)?;
+        }
+        map.end()
+    }
+}
+
+fn fmt_dep_edge<N: Display>(edge: &DepEdge<N>, f: &mut Formatter<'_>) -> std::fmt::Result {
+    write!(f, "  -> {} via `{}` ({}", edge.dep, edge.attr, edge.kind)?;
+    if let Some(transition) = &edge.transition {
+        write!(f, ", transition {}", transition)?;
+    }
+    if let Some(execution_platform) = &edge.execution_platform {
+        write!(f, ", exec platform {}", execution_platform)?;
+    }
+    if let Some((from, to)) = &edge.configuration_change {
+        write!(f, ", {} -> {}", from, to)?;
+    }
+    writeln!(f, ")")
 }
 
 impl<'a, T: QueryTarget> PrintableQueryTarget<'a, T> {
@@ -101,7 +167,7 @@ impl<'a, T: QueryTarget> Display for PrintableQueryTarget<'a, T> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}", self.value.node_ref())?;
 
-        if self.target_call_stacks || self.providers.is_some() {
+        if self.target_call_stacks || self.providers.is_some() || self.edges.is_some() {
             writeln!(f)?;
         }
 
@@ -116,6 +182,12 @@ impl<'a, T: QueryTarget> Display for PrintableQueryTarget<'a, T> {
             }
         }
 
+        if let Some(edges) = &self.edges {
+            for edge in edges {
+                fmt_dep_edge(edge, f)?;
+            }
+        }
+
         if let Some(providers) = &self.providers {
             use std::fmt::Write;
             write!(
@@ -169,6 +241,13 @@ impl<'a, T: QueryTarget> Serialize for PrintableQueryTarget<'a, T> {
             map.serialize_entry("buck.target_call_stack", &self.value.call_stack())?;
         }
 
+        if let Some(edges) = &self.edges {
+            map.serialize_entry(
+                "buck.edges",
+                &edges.iter().map(DepEdgeJson).collect::<Vec<_>>(),
+            )?;
+        }
+
         if let Some(providers) = &self.providers {
             map.serialize_entry("buck.providers", providers)?;
         }
@@ -250,9 +329,39 @@ impl<'a> QueryResultPrinter<'a> {
             resolver,
             attributes,
             output_format,
+            explain_edges: false,
+            show_transitions: false,
         })
     }
 
+    /// Annotate each printed target with the edges to other targets in the result,
+    /// e.g. to explain the paths found by `somepath` or `allpaths`.
+    pub fn with_explain_edges(self, explain_edges: bool) -> Self {
+        Self {
+            explain_edges,
+            ..self
+        }
+    }
+
+    /// Annotate each edge which changes the configuration with the transition (or the
+    /// execution platform for exec deps) and the configurations before and after it.
+    pub fn with_show_transitions(self, show_transitions: bool) -> Self {
+        Self {
+            show_transitions,
+            ..self
+        }
+    }
+
+    fn print_edges(&self) -> PrintEdges {
+        if self.explain_edges {
+            PrintEdges::All
+        } else if self.show_transitions {
+            PrintEdges::Transitions
+        } else {
+            PrintEdges::No
+        }
+    }
+
     pub async fn print_multi_output<'b, T: QueryTarget, W: std::io::Write>(
         &self,
         mut output: W,
@@ -278,6 +387,7 @@ impl<'a> QueryResultPrinter<'a> {
                                 &arg,
                                 &TargetSetJsonPrinter::new(
                                     target_call_stacks,
+                                    self.print_edges(),
                                     print_providers,
                                     &self.attributes,
                                     &targets,
@@ -329,9 +439,14 @@ impl<'a> QueryResultPrinter<'a> {
         match result {
             QueryEvaluationValue::TargetSet(targets) => match self.output_format {
                 QueryOutputFormat::Default => {
-                    for target in
-                        printable_targets(&targets, print_providers, &self.attributes, call_stack)
-                            .await?
+                    for target in printable_targets(
+                        &targets,
+                        print_providers,
+                        &self.attributes,
+                        call_stack,
+                        self.print_edges(),
+                    )
+                    .await?
                     {
                         writeln!(&mut output, "{}", target)?;
                     }
@@ -340,6 +455,7 @@ impl<'a> QueryResultPrinter<'a> {
                     let mut ser = serde_json::Serializer::pretty(&mut output);
                     TargetSetJsonPrinter::new(
                         call_stack,
+                        self.print_edges(),
                         print_providers,
                         &self.attributes,
                         &targets,
@@ -355,6 +471,7 @@ impl<'a> QueryResultPrinter<'a> {
                         &DotTargetGraph {
                             targets,
                             attributes: self.attributes.clone(),
+                            show_transitions: self.show_transitions,
                         },
                         &mut output,
                     )?;
@@ -364,10 +481,17 @@ impl<'a> QueryResultPrinter<'a> {
                         &DotTargetGraph {
                             targets,
                             attributes: self.attributes.clone(),
+                            show_transitions: self.show_transitions,
                         },
                         &mut output,
                     )?;
                 }
+                QueryOutputFormat::Build => {
+                    return Err(QueryCommandError::BuildOutputFormatNotSupported.into());
+                }
+                QueryOutputFormat::Proto | QueryOutputFormat::Jsonproto => {
+                    return Err(QueryCommandError::ProtoOutputFormatNotSupported.into());
+                }
             },
             QueryEvaluationValue::FileSet(files) => {
                 if self.attributes.is_some() {
@@ -400,6 +524,12 @@ impl<'a> QueryResultPrinter<'a> {
                     QueryOutputFormat::DotCompact => {
                         unimplemented!("dot_compact output for files not implemented yet")
                     }
+                    QueryOutputFormat::Build => {
+                        return Err(QueryCommandError::FileSetHasNoBuildSyntax.into());
+                    }
+                    QueryOutputFormat::Proto | QueryOutputFormat::Jsonproto => {
+                        return Err(QueryCommandError::ProtoOutputFormatNotSupported.into());
+                    }
                 }
             }
         }
@@ -413,6 +543,7 @@ async fn printable_targets<'a, T: QueryTarget>(
     print_providers: ShouldPrintProviders<'a, T>,
     attributes: &'a Option<RegexSet>,
     target_call_stacks: bool,
+    print_edges: PrintEdges,
 ) -> anyhow::Result<Vec<PrintableQueryTarget<'a, T>>> {
     futures::future::join_all(targets.iter().map(|t| {
         let print_providers = &print_providers;
@@ -421,6 +552,18 @@ async fn printable_targets<'a, T: QueryTarget>(
                 value: t,
                 attributes,
                 target_call_stacks,
+                edges: match print_edges {
+                    PrintEdges::No => None,
+                    PrintEdges::All | PrintEdges::Transitions => Some(
+                        t.dep_edges()
+                            .into_iter()
+                            .filter(|edge| {
+                                targets.contains(&edge.dep)
+                                    && (print_edges == PrintEdges::All || edge.crosses_transition())
+                            })
+                            .collect(),
+                    ),
+                },
                 providers: match print_providers {
                     ShouldPrintProviders::No => None,
                     ShouldPrintProviders::Yes(lookup) => {
@@ -479,3 +622,174 @@ pub(crate) fn init_print_action_node() {
         ))
     });
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use buck2_core::bzl::ImportPath;
+    use buck2_core::configuration::data::ConfigurationData;
+    use buck2_core::configuration::data::ConfigurationDataData;
+    use buck2_core::execution_types::execution::ExecutionPlatform;
+    use buck2_core::execution_types::executor_config::CommandExecutorConfig;
+    use buck2_core::plugins::PluginKindSet;
+    use buck2_core::provider::label::ProvidersLabel;
+    use buck2_core::provider::label::ProvidersName;
+    use buck2_core::target::configured_target_label::ConfiguredTargetLabel;
+    use buck2_core::target::label::TargetLabel;
+    use buck2_node::attrs::attr::Attribute;
+    use buck2_node::attrs::attr_type::list::ListLiteral;
+    use buck2_node::attrs::attr_type::AttrType;
+    use buck2_node::attrs::coerced_attr::CoercedAttr;
+    use buck2_node::nodes::configured::ConfiguredTargetNode;
+    use buck2_node::nodes::unconfigured::testing::TargetNodeExt;
+    use buck2_node::nodes::unconfigured::TargetNode;
+    use buck2_node::provider_id_set::ProviderIdSet;
+    use buck2_node::rule_type::RuleType;
+    use buck2_node::rule_type::StarlarkRuleType;
+    use buck2_query::query::syntax::simple::eval::set::TargetSet;
+    use buck2_util::arc_str::ArcSlice;
+    use dupe::Dupe;
+
+    use crate::commands::query::printer::printable_targets;
+    use crate::commands::query::printer::PrintEdges;
+    use crate::commands::query::printer::ShouldPrintProviders;
+
+    fn node(label: &str, deps: &[&str]) -> TargetNode {
+        let rule_type = RuleType::Starlark(Arc::new(StarlarkRuleType {
+            import_path: ImportPath::testing_new("cell//pkg:rules.bzl"),
+            name: "some_rule".to_owned(),
+        }));
+        let deps = deps
+            .iter()
+            .map(|d| {
+                CoercedAttr::Dep(ProvidersLabel::new(
+                    TargetLabel::testing_parse(d),
+                    ProvidersName::Default,
+                ))
+            })
+            .collect::<Vec<_>>();
+        TargetNode::testing_new(
+            TargetLabel::testing_parse(label),
+            rule_type,
+            vec![(
+                "deps",
+                Attribute::new(
+                    None,
+                    "",
+                    AttrType::list(AttrType::dep(ProviderIdSet::EMPTY, PluginKindSet::EMPTY)),
+                ),
+                CoercedAttr::List(ListLiteral(ArcSlice::from(deps))),
+            )],
+        )
+    }
+
+    #[tokio::test]
+    async fn test_explain_edges() -> anyhow::Result<()> {
+        // `cell//pkg:c` is not in the result, so the edge to it is not explained.
+        let targets: TargetSet<TargetNode> = [
+            node("cell//pkg:a", &["cell//pkg:b", "cell//pkg:c"]),
+            node("cell//pkg:b", &[]),
+        ]
+        .into_iter()
+        .collect();
+        let printable = printable_targets(
+            &targets,
+            ShouldPrintProviders::No,
+            &None,
+            false,
+            PrintEdges::All,
+        )
+        .await?;
+
+        assert_eq!(
+            "cell//pkg:a\n  -> cell//pkg:b via `deps` (dep)\n",
+            printable[0].to_string()
+        );
+        assert_eq!("cell//pkg:b\n", printable[1].to_string());
+        assert_eq!(
+            r#"{"buck.edges":[{"dep":"cell//pkg:b","attr":"deps","kind":"dep"}]}"#,
+            serde_json::to_string(&printable[0])?
+        );
+
+        let printable = printable_targets(
+            &targets,
+            ShouldPrintProviders::No,
+            &None,
+            false,
+            PrintEdges::No,
+        )
+        .await?;
+        assert_eq!("cell//pkg:a", printable[0].to_string());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_show_transitions() -> anyhow::Result<()> {
+        let target_cfg = ConfigurationData::testing_new();
+        let exec_cfg =
+            ConfigurationData::from_platform("exec".to_owned(), ConfigurationDataData::empty())?;
+        let dep = |label: &str| {
+            CoercedAttr::Dep(ProvidersLabel::new(
+                TargetLabel::testing_parse(label),
+                ProvidersName::Default,
+            ))
+        };
+        let node = ConfiguredTargetNode::testing_new_with_attrs(
+            ConfiguredTargetLabel::testing_parse("cell//pkg:a", target_cfg.dupe()),
+            "some_rule",
+            vec![
+                (
+                    "dep",
+                    Attribute::new(
+                        None,
+                        "",
+                        AttrType::dep(ProviderIdSet::EMPTY, PluginKindSet::EMPTY),
+                    ),
+                    dep("cell//pkg:b"),
+                ),
+                (
+                    "compiler",
+                    Attribute::new(None, "", AttrType::exec_dep(ProviderIdSet::EMPTY)),
+                    dep("cell//pkg:tool"),
+                ),
+            ],
+            Some(ExecutionPlatform::platform(
+                TargetLabel::testing_parse("cell//platforms:exec"),
+                exec_cfg.dupe(),
+                CommandExecutorConfig::testing_local(),
+            )),
+        );
+        let b = ConfiguredTargetNode::testing_new(
+            ConfiguredTargetLabel::testing_parse("cell//pkg:b", target_cfg.dupe()),
+            "some_rule",
+        );
+        let tool = ConfiguredTargetNode::testing_new(
+            ConfiguredTargetLabel::testing_parse("cell//pkg:tool", exec_cfg.dupe()),
+            "some_rule",
+        );
+        let targets: TargetSet<ConfiguredTargetNode> =
+            [node.dupe(), b, tool.dupe()].into_iter().collect();
+        let printable = printable_targets(
+            &targets,
+            ShouldPrintProviders::No,
+            &None,
+            false,
+            PrintEdges::Transitions,
+        )
+        .await?;
+
+        // Only the exec dep changes the configuration.
+        assert_eq!(
+            format!(
+                "{}\n  -> {} via `compiler` (exec_dep, exec platform cell//platforms:exec, {} -> {})\n",
+                node.label(),
+                tool.label(),
+                target_cfg,
+                exec_cfg
+            ),
+            printable[0].to_string()
+        );
+        Ok(())
+    }
+}
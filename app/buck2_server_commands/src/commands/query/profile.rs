@@ -0,0 +1,49 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::sync::Arc;
+
+use buck2_events::dispatch::EventDispatcher;
+use buck2_query::query::syntax::simple::eval::profile::QueryProfiler;
+
+/// Print the profiles collected by `--profile` to the console (so they don't mix with the
+/// results on stdout), and record them in the event log.
+pub(crate) fn report_query_profile(
+    dispatcher: &EventDispatcher,
+    profiler: Option<Arc<QueryProfiler>>,
+) {
+    let profiler = match profiler {
+        Some(profiler) => profiler,
+        None => return,
+    };
+    let literal_resolution = profiler.literal_resolution();
+    dispatcher.console_message(format!(
+        "Literal resolution: {:.3}ms",
+        literal_resolution.as_secs_f64() * 1000.0
+    ));
+    for profile in profiler.take_profiles() {
+        dispatcher.console_message(profile.to_string());
+        dispatcher.instant_event(buck2_data::QueryProfile {
+            query: profile.query,
+            literal_resolution_us: literal_resolution.as_micros() as u64,
+            entries: profile
+                .entries
+                .into_iter()
+                .map(|entry| buck2_data::QueryProfileEntry {
+                    expression: entry.expression,
+                    depth: entry.depth as u32,
+                    wall_time_us: entry.wall_time.as_micros() as u64,
+                    result_count: entry.result_count.map(|c| c as u64),
+                    package_loading_us: entry.package_loading.as_micros() as u64,
+                    configuration_us: entry.configuration.as_micros() as u64,
+                })
+                .collect(),
+        });
+    }
+}
@@ -8,23 +8,31 @@
  */
 
 use std::io::Write;
+use std::sync::Arc;
 
 use anyhow::Context;
 use async_trait::async_trait;
 use buck2_build_api::query::oneshot::QUERY_FRONTEND;
+use buck2_cli_proto::QueryOutputFormat;
 use buck2_cli_proto::UqueryRequest;
 use buck2_cli_proto::UqueryResponse;
 use buck2_common::dice::cells::HasCellResolver;
+use buck2_node::nodes::unconfigured::TargetNode;
+use buck2_query::query::syntax::simple::eval::profile::QueryProfiler;
 use buck2_query::query::syntax::simple::eval::values::QueryEvaluationResult;
+use buck2_query::query::syntax::simple::eval::values::QueryEvaluationValue;
 use buck2_server_ctx::ctx::ServerCommandContextTrait;
 use buck2_server_ctx::partial_result_dispatcher::PartialResultDispatcher;
 use buck2_server_ctx::pattern::target_platform_from_client_context;
 use buck2_server_ctx::template::run_server_command;
 use buck2_server_ctx::template::ServerCommandTemplate;
 use dice::DiceTransaction;
+use dupe::Dupe;
 
 use crate::commands::query::printer::QueryResultPrinter;
 use crate::commands::query::printer::ShouldPrintProviders;
+use crate::commands::query::profile::report_query_profile;
+use crate::commands::query::QueryCommandError;
 
 pub(crate) async fn uquery_command(
     ctx: &dyn ServerCommandContextTrait,
@@ -76,7 +84,8 @@ async fn uquery(
         &cell_resolver,
         &request.output_attributes,
         request.unstable_output_format,
-    )?;
+    )?
+    .with_explain_edges(request.explain_edges);
 
     let UqueryRequest {
         query,
@@ -94,6 +103,8 @@ async fn uquery(
     let global_target_platform =
         target_platform_from_client_context(client_ctx, server_ctx, &mut ctx).await?;
 
+    let profiler = request.profile.then(|| Arc::new(QueryProfiler::new()));
+
     let query_result = QUERY_FRONTEND
         .get()?
         .eval_uquery(
@@ -102,9 +113,20 @@ async fn uquery(
             query,
             query_args,
             global_target_platform,
+            profiler.dupe(),
         )
         .await?;
 
+    if request.unstable_output_format == QueryOutputFormat::Build as i32 {
+        let targets = match query_result {
+            QueryEvaluationResult::Single(targets) => targets,
+            QueryEvaluationResult::Multiple(results) => results.merged()?,
+        };
+        print_build_syntax(&mut stdout, targets)?;
+        report_query_profile(server_ctx.events(), profiler);
+        return Ok(UqueryResponse {});
+    }
+
     match query_result {
         QueryEvaluationResult::Single(targets) => {
             output_configuration
@@ -127,6 +149,27 @@ async fn uquery(
                 .await?
         }
     };
+    report_query_profile(server_ctx.events(), profiler);
 
     Ok(UqueryResponse {})
 }
+
+/// Print each target as a rule call, so the output can be pasted into a `BUCK` file.
+fn print_build_syntax(
+    mut stdout: impl Write,
+    result: QueryEvaluationValue<TargetNode>,
+) -> anyhow::Result<()> {
+    let targets = match result {
+        QueryEvaluationValue::TargetSet(targets) => targets,
+        QueryEvaluationValue::FileSet(_) => {
+            return Err(QueryCommandError::FileSetHasNoBuildSyntax.into());
+        }
+    };
+    for (i, target) in targets.iter().enumerate() {
+        if i != 0 {
+            writeln!(stdout)?;
+        }
+        write!(stdout, "{}", target.to_build_syntax())?;
+    }
+    Ok(())
+}
@@ -7,6 +7,7 @@
  * of this source tree.
  */
 
+use buck2_query::query::environment::DepEdge;
 use buck2_query::query::environment::QueryTarget;
 use buck2_query::query::environment::QueryTargets;
 use buck2_query::query::syntax::simple::eval::set::TargetSet;
@@ -24,6 +25,8 @@ pub struct DotTargetGraphNode<'a, T: QueryTarget>(&'a T, &'a DotTargetGraph<T>);
 pub struct DotTargetGraph<T: QueryTarget> {
     pub targets: TargetSet<T>,
     pub attributes: Option<RegexSet>,
+    /// Label edges which change the configuration with the transition and configurations.
+    pub show_transitions: bool,
 }
 
 impl<'a, T: QueryTarget> DotDigraph<'a> for DotTargetGraph<T> {
@@ -48,12 +51,35 @@ impl<'a, T: QueryTarget> DotDigraph<'a> for DotTargetGraph<T> {
         node: &Self::Node,
         mut f: F,
     ) -> anyhow::Result<()> {
+        if self.show_transitions {
+            // A dep can be referenced from several attributes. Draw a single edge to it,
+            // like `deps()` does, labelled if any of the references crosses a transition.
+            let mut labels: SmallMap<T::NodeRef, Option<String>> = SmallMap::new();
+            for edge in node.0.dep_edges() {
+                if self.targets.contains(&edge.dep) {
+                    let label = transition_label(&edge);
+                    let entry = labels.entry(edge.dep).or_insert(None);
+                    if entry.is_none() {
+                        *entry = label;
+                    }
+                }
+            }
+            for (dep, label) in labels {
+                f(&DotEdge {
+                    from: &node.0.node_ref().to_string(),
+                    to: &dep.to_string(),
+                    label,
+                })?;
+            }
+            return Ok(());
+        }
         for dep in node.0.deps() {
             // Only include edges to other nodes within the subgraph.
             if self.targets.contains(dep) {
                 f(&DotEdge {
                     from: &node.0.node_ref().to_string(),
                     to: &dep.to_string(),
+                    label: None,
                 })?;
             }
         }
@@ -61,6 +87,17 @@ impl<'a, T: QueryTarget> DotDigraph<'a> for DotTargetGraph<T> {
     }
 }
 
+fn transition_label<N>(edge: &DepEdge<N>) -> Option<String> {
+    let (from, to) = edge.configuration_change.as_ref()?;
+    let via = match (&edge.transition, &edge.execution_platform) {
+        (Some(transition), _) => transition.clone(),
+        (None, Some(platform)) => format!("exec platform {}", platform),
+        (None, None) => edge.kind.to_string(),
+    };
+    // `\n` is a line break in dot labels.
+    Some(format!("{}\\n{} -> {}", via, from, to))
+}
+
 impl<'a, T: QueryTarget> DotNode for DotTargetGraphNode<'a, T> {
     fn attrs(&self) -> anyhow::Result<DotNodeAttrs> {
         let extra = match &self.1.attributes {
@@ -94,3 +131,117 @@ impl<'a, T: QueryTarget> DotNode for DotTargetGraphNode<'a, T> {
         self.0.node_ref().to_string()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use buck2_core::configuration::data::ConfigurationData;
+    use buck2_core::configuration::data::ConfigurationDataData;
+    use buck2_core::execution_types::execution::ExecutionPlatform;
+    use buck2_core::execution_types::executor_config::CommandExecutorConfig;
+    use buck2_core::provider::label::ProvidersLabel;
+    use buck2_core::provider::label::ProvidersName;
+    use buck2_core::target::configured_target_label::ConfiguredTargetLabel;
+    use buck2_core::target::label::TargetLabel;
+    use buck2_node::attrs::attr::Attribute;
+    use buck2_node::attrs::attr_type::AttrType;
+    use buck2_node::attrs::coerced_attr::CoercedAttr;
+    use buck2_node::nodes::configured::ConfiguredTargetNode;
+    use buck2_node::provider_id_set::ProviderIdSet;
+    use buck2_query::query::environment::DepEdge;
+    use buck2_query::query::environment::DepKind;
+    use dupe::Dupe;
+
+    use crate::dot::targets::transition_label;
+    use crate::dot::targets::DotTargetGraph;
+    use crate::dot::Dot;
+
+    fn edge(
+        transition: Option<&str>,
+        execution_platform: Option<&str>,
+        configuration_change: Option<(&str, &str)>,
+    ) -> DepEdge<String> {
+        DepEdge {
+            dep: "cell//pkg:b".to_owned(),
+            attr: "dep".to_owned(),
+            kind: DepKind::Transition,
+            transition: transition.map(|t| t.to_owned()),
+            configuration_change: configuration_change
+                .map(|(from, to)| (from.to_owned(), to.to_owned())),
+            execution_platform: execution_platform.map(|p| p.to_owned()),
+        }
+    }
+
+    #[test]
+    fn test_transition_label() {
+        assert_eq!(None, transition_label(&edge(None, None, None)));
+        assert_eq!(
+            Some("cell//:tr\\nfrom -> to".to_owned()),
+            transition_label(&edge(Some("cell//:tr"), None, Some(("from", "to"))))
+        );
+        assert_eq!(
+            Some("exec platform cell//:exec\\nfrom -> to".to_owned()),
+            transition_label(&edge(None, Some("cell//:exec"), Some(("from", "to"))))
+        );
+        assert_eq!(
+            Some("transition_dep\\nfrom -> to".to_owned()),
+            transition_label(&edge(None, None, Some(("from", "to"))))
+        );
+    }
+
+    #[test]
+    fn test_show_transitions() -> anyhow::Result<()> {
+        let target_cfg = ConfigurationData::testing_new();
+        let exec_cfg =
+            ConfigurationData::from_platform("exec".to_owned(), ConfigurationDataData::empty())?;
+        let exec_dep = || {
+            (
+                Attribute::new(None, "", AttrType::exec_dep(ProviderIdSet::EMPTY)),
+                CoercedAttr::Dep(ProvidersLabel::new(
+                    TargetLabel::testing_parse("cell//pkg:tool"),
+                    ProvidersName::Default,
+                )),
+            )
+        };
+        let (compiler_attr, compiler) = exec_dep();
+        let (linker_attr, linker) = exec_dep();
+        // The tool is referenced from two attributes, but gets a single edge.
+        let node = ConfiguredTargetNode::testing_new_with_attrs(
+            ConfiguredTargetLabel::testing_parse("cell//pkg:a", target_cfg.dupe()),
+            "some_rule",
+            vec![
+                ("compiler", compiler_attr, compiler),
+                ("linker", linker_attr, linker),
+            ],
+            Some(ExecutionPlatform::platform(
+                TargetLabel::testing_parse("cell//platforms:exec"),
+                exec_cfg.dupe(),
+                CommandExecutorConfig::testing_local(),
+            )),
+        );
+        let tool = ConfiguredTargetNode::testing_new(
+            ConfiguredTargetLabel::testing_parse("cell//pkg:tool", exec_cfg.dupe()),
+            "some_rule",
+        );
+        let graph = DotTargetGraph {
+            targets: [node.dupe(), tool.dupe()].into_iter().collect(),
+            attributes: None,
+            show_transitions: true,
+        };
+
+        let mut out = Vec::new();
+        Dot::render(&graph, &mut out)?;
+        let out = String::from_utf8(out)?;
+        let edges: Vec<&str> = out.lines().filter(|l| l.contains("->")).collect();
+        assert_eq!(
+            vec![format!(
+                "  \"{}\" -> \"{}\" [label=\"exec platform cell//platforms:exec\\n{} -> {}\"];",
+                node.label(),
+                tool.label(),
+                target_cfg,
+                exec_cfg,
+            )],
+            edges
+        );
+        Ok(())
+    }
+}
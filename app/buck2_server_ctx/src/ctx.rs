@@ -176,6 +176,9 @@ impl ServerCommandDiceContext for dyn ServerCommandContextTrait + '_ {
                                                     isolation_prefix: self
                                                         .isolation_prefix()
                                                         .to_owned(),
+                                                    log_build_graph: dice
+                                                        .per_transaction_data()
+                                                        .get_log_build_graph(),
                                                 },
                                                 || exec(self, dice),
                                             )
@@ -14,6 +14,7 @@ use std::sync::Arc;
 
 use anyhow::Context;
 use async_trait::async_trait;
+use buck2_build_api::actions::artifact::get_artifact_fs::GetArtifactFs;
 use buck2_build_api::analysis::calculation::RuleAnalysisCalculation;
 use buck2_build_api::artifact_groups::calculation::ArtifactGroupCalculation;
 use buck2_build_api::artifact_groups::ArtifactGroup;
@@ -35,8 +36,10 @@ use buck2_core::cells::name::CellName;
 use buck2_core::cells::CellResolver;
 use buck2_core::configuration::compatibility::MaybeCompatible;
 use buck2_core::env_helper::EnvHelper;
+use buck2_core::fs::buck_out_path::BuckOutTestPath;
 use buck2_core::fs::fs_util;
 use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
+use buck2_core::fs::paths::forward_rel_path::ForwardRelativePathBuf;
 use buck2_core::fs::project_rel_path::ProjectRelativePathBuf;
 use buck2_core::package::PackageLabel;
 use buck2_core::pattern::pattern_type::ConfiguredProvidersPatternExtra;
@@ -92,6 +95,8 @@ use crate::orchestrator::BuckTestOrchestrator;
 use crate::orchestrator::ExecutorMessage;
 use crate::session::TestSession;
 use crate::session::TestSessionOptions;
+use crate::sharding::ShardAssignment;
+use crate::sharding::Sharding;
 use crate::translations::build_configured_target_handle;
 
 #[derive(Debug, Serialize)]
@@ -105,6 +110,8 @@ struct TestOutcome {
     executor_report: ExecutorReport,
     executor_stdout: String,
     executor_stderr: String,
+    shard_assignment: Vec<ShardAssignment>,
+    coverage: Option<buck2_cli_proto::test_response::Coverage>,
 }
 
 impl TestOutcome {
@@ -185,6 +192,7 @@ struct TestStatuses {
     fatals: CounterWithExamples,
     listing_success: CounterWithExamples,
     listing_failed: CounterWithExamples,
+    flaky: CounterWithExamples,
 }
 impl TestStatuses {
     fn ingest(&mut self, result: &TestResult) {
@@ -199,6 +207,7 @@ impl TestStatuses {
             TestStatus::RERUN => {}
             TestStatus::LISTING_SUCCESS => self.listing_success.add(&result.name),
             TestStatus::LISTING_FAILED => self.listing_failed.add(&result.name),
+            TestStatus::FLAKY => self.flaky.add(&result.name),
         }
     }
 }
@@ -277,6 +286,7 @@ async fn test(
         .await?
         .filter(|s| !s.is_empty());
 
+    let mut external_runner_args = request.test_executor_args.clone();
     let (test_executor, test_executor_args) = match test_executor_config {
         Some(config) => {
             let test_executor = post_process_test_executor(config.as_ref())
@@ -288,6 +298,16 @@ async fn test(
             // If no v2_test_executor config was set, fall back to the internal test runner.
             let test_executor = std::env::current_exe()?;
             let test_executor_args = vec!["internal-test-runner".to_owned()];
+            // Only the internal test runner is known to support running single test cases again.
+            for (target, cases) in &request.rerun_test_cases {
+                for case in &cases.names {
+                    external_runner_args.extend([
+                        "--rerun-case".to_owned(),
+                        target.clone(),
+                        case.clone(),
+                    ]);
+                }
+            }
             (test_executor, test_executor_args)
         }
     };
@@ -310,12 +330,26 @@ async fn test(
         .as_ref()
         .context("Missing `options`")?;
 
+    let cache_results = ctx
+        .parse_legacy_config_property(cell_resolver.root_cell(), "test", "cache_results")
+        .await?
+        .unwrap_or_default();
+
     let session = TestSession::new(TestSessionOptions {
         allow_re: options.allow_re,
         force_use_project_relative_paths: options.force_use_project_relative_paths,
         force_run_from_project_root: options.force_run_from_project_root,
+        cache_results,
+        use_cached_results: cache_results && !options.no_cache_test_results,
+        coverage: options.coverage,
     });
 
+    let sharding = request
+        .shard
+        .as_ref()
+        .map(Sharding::from_proto)
+        .transpose()?;
+
     let build_opts = request
         .build_opts
         .as_ref()
@@ -324,7 +358,7 @@ async fn test(
         ctx,
         resolved_pattern,
         global_target_platform,
-        request.test_executor_args.clone(),
+        external_runner_args,
         Arc::new(TestLabelFiltering::new(
             request.included_labels.clone(),
             request.excluded_labels.clone(),
@@ -337,6 +371,7 @@ async fn test(
         working_dir_cell,
         build_opts.skip_incompatible_targets,
         MissingTargetBehavior::from_skip(build_opts.skip_missing_targets),
+        sharding,
     )
     .await?;
 
@@ -386,6 +421,13 @@ async fn test(
                 .listing_failed
                 .to_cli_proto_counter(),
         ),
+        flaky: Some(
+            test_outcome
+                .executor_report
+                .statuses
+                .flaky
+                .to_cli_proto_counter(),
+        ),
     };
 
     Ok(TestResponse {
@@ -395,6 +437,16 @@ async fn test(
         executor_stdout: test_outcome.executor_stdout,
         executor_stderr: test_outcome.executor_stderr,
         executor_info_messages: test_outcome.executor_report.info_messages,
+        shard_assignment: test_outcome
+            .shard_assignment
+            .into_iter()
+            .map(|a| buck2_cli_proto::test_response::ShardAssignment {
+                target: a.label.to_string(),
+                shard: a.shard,
+                duration_ms: a.duration_ms,
+            })
+            .collect(),
+        coverage: test_outcome.coverage,
     })
 }
 
@@ -410,6 +462,7 @@ async fn test_targets(
     working_dir_cell: CellName,
     skip_incompatible_targets: bool,
     missing_target_behavior: MissingTargetBehavior,
+    sharding: Option<Sharding>,
 ) -> anyhow::Result<TestOutcome> {
     let session = Arc::new(session);
     let (liveliness_observer, _guard) = LivelinessGuard::create();
@@ -481,6 +534,7 @@ async fn test_targets(
                     working_dir_cell,
                     missing_target_behavior,
                 });
+                driver.sharding = sharding;
 
                 driver.push_pattern(
                     pattern
@@ -530,9 +584,24 @@ async fn test_targets(
                     .await
                     .context("Failed to release local resources")?;
 
+                let coverage = if session.options().coverage {
+                    Some(
+                        write_coverage_report(&ctx, &session)
+                            .await
+                            .context("Failed to write the coverage report")?,
+                    )
+                } else {
+                    None
+                };
+
                 // And finally return our results;
 
-                anyhow::Ok((driver.build_errors, test_statuses))
+                anyhow::Ok((
+                    driver.build_errors,
+                    test_statuses,
+                    driver.shard_assignment,
+                    coverage,
+                ))
             },
         )
     });
@@ -556,7 +625,7 @@ async fn test_targets(
     )));
 
     // TODO(bobyf, torozco) we can use cancellation handle here instead of liveliness observer
-    let (build_errors, executor_report) = test_server
+    let (build_errors, executor_report, shard_assignment, coverage) = test_server
         .await
         .context("Failed to collect executor report")??;
 
@@ -571,6 +640,42 @@ async fn test_targets(
         executor_stdout: executor_output.stdout,
         executor_stderr: executor_output.stderr,
         executor_report,
+        shard_assignment,
+        coverage,
+    })
+}
+
+/// Writes the LCOV report merging the coverage of all the tests of the session.
+async fn write_coverage_report(
+    ctx: &DiceTransaction,
+    session: &TestSession,
+) -> anyhow::Result<buck2_cli_proto::test_response::Coverage> {
+    let fs = ctx.get_artifact_fs().await?;
+    let path = fs.fs().resolve(
+        &fs.buck_out_path_resolver()
+            .resolve_test(&BuckOutTestPath::new(
+                session.prefix().to_buf(),
+                ForwardRelativePathBuf::unchecked_new("coverage.lcov".to_owned()),
+            )),
+    );
+
+    let collector = session.coverage().lock();
+    if let Some(dir) = path.parent() {
+        fs_util::create_dir_all(dir)?;
+    }
+    fs_util::write(&path, collector.lcov.to_lcov())?;
+
+    let totals = collector.lcov.totals();
+    Ok(buck2_cli_proto::test_response::Coverage {
+        lcov_path: path.to_string(),
+        files: totals.files,
+        lines_found: totals.lines_found,
+        lines_hit: totals.lines_hit,
+        functions_found: totals.functions_found,
+        functions_hit: totals.functions_hit,
+        branches_found: totals.branches_found,
+        branches_hit: totals.branches_hit,
+        errors: collector.errors.clone(),
     })
 }
 
@@ -587,6 +692,10 @@ enum TestDriverTask {
     TestTarget {
         label: ConfiguredProvidersLabel,
     },
+    /// A test target to partition across shards, once all have been found.
+    ShardTarget {
+        label: ConfiguredProvidersLabel,
+    },
 }
 
 #[derive(Copy, Clone, Dupe)]
@@ -608,6 +717,10 @@ struct TestDriver<'a, 'e> {
     labels_configured: HashSet<(ProvidersLabel, bool)>,
     labels_tested: HashSet<ConfiguredProvidersLabel>,
     build_errors: Vec<buck2_error::Error>,
+    /// Set until the test targets have been partitioned across shards.
+    sharding: Option<Sharding>,
+    shard_targets: Vec<ConfiguredProvidersLabel>,
+    shard_assignment: Vec<ShardAssignment>,
 }
 
 impl<'a, 'e> TestDriver<'a, 'e> {
@@ -618,6 +731,9 @@ impl<'a, 'e> TestDriver<'a, 'e> {
             labels_configured: HashSet::new(),
             labels_tested: HashSet::new(),
             build_errors: Vec::new(),
+            sharding: None,
+            shard_targets: Vec::new(),
+            shard_assignment: Vec::new(),
         }
     }
 
@@ -641,6 +757,16 @@ impl<'a, 'e> TestDriver<'a, 'e> {
 
     /// Drive the test loop until all work is complete.
     async fn drive_to_completion(&mut self) {
+        loop {
+            self.drive_work().await;
+            match self.sharding.take() {
+                Some(sharding) => self.test_shard(sharding),
+                None => break,
+            }
+        }
+    }
+
+    async fn drive_work(&mut self) {
         while let Some(tasks) = self.work.next().await {
             match tasks {
                 Ok(tasks) => {
@@ -657,7 +783,14 @@ impl<'a, 'e> TestDriver<'a, 'e> {
                                 self.configure_target(label, skippable);
                             }
                             TestDriverTask::TestTarget { label } => {
-                                self.test_target(label);
+                                if self.sharding.is_some() {
+                                    self.find_shard_target(label);
+                                } else {
+                                    self.test_target(label);
+                                }
+                            }
+                            TestDriverTask::ShardTarget { label } => {
+                                self.shard_targets.push(label);
                             }
                         }
                     }
@@ -755,6 +888,47 @@ impl<'a, 'e> TestDriver<'a, 'e> {
         self.work.push(fut);
     }
 
+    /// Analyze a target to check if it is a test to run, without building or running it.
+    fn find_shard_target(&mut self, label: ConfiguredProvidersLabel) {
+        if !self.labels_tested.insert(label.clone()) {
+            return;
+        }
+
+        let state = self.state;
+        let fut = async move {
+            let frozen_providers = state
+                .ctx
+                .get_providers(&label)
+                .await?
+                .require_compatible()?;
+            let is_test =
+                match <dyn TestProvider>::from_collection(frozen_providers.provider_collection()) {
+                    Some(test_info) => !skip_run_based_on_labels(test_info, state.label_filtering),
+                    None => false,
+                };
+            if is_test {
+                anyhow::Ok(vec![TestDriverTask::ShardTarget { label }])
+            } else {
+                anyhow::Ok(vec![])
+            }
+        }
+        .boxed();
+
+        self.work.push(fut);
+    }
+
+    /// Partition the test targets found, and test those of this shard.
+    fn test_shard(&mut self, sharding: Sharding) {
+        let assignment = sharding.assign(std::mem::take(&mut self.shard_targets));
+        if !sharding.dry_run {
+            for a in assignment.iter().filter(|a| sharding.is_own(a)) {
+                self.labels_tested.remove(&a.label);
+                self.test_target(a.label.clone());
+            }
+        }
+        self.shard_assignment = assignment;
+    }
+
     fn test_target(&mut self, label: ConfiguredProvidersLabel) {
         if !self.labels_tested.insert(label.clone()) {
             return;
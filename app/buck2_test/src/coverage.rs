@@ -0,0 +1,334 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Code coverage of `buck2 test --coverage`.
+//!
+//! Test executions write coverage data to the directory in `$COVERAGE_DIR`. The `coverage_merger`
+//! of their `ExternalRunnerTestInfo` converts it to LCOV, or the LCOV files the test wrote there
+//! are used as they are. The LCOV of all the test executions is merged into a single report.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+/// The environment variable with the directory where tests write coverage data.
+pub(crate) const COVERAGE_DIR_ENV: &str = "COVERAGE_DIR";
+
+/// The name of the declared output of test executions holding their coverage data.
+pub(crate) const COVERAGE_OUTPUT: &str = "coverage";
+
+#[derive(Debug, thiserror::Error)]
+enum LcovError {
+    #[error("Invalid LCOV at line {0}: `{1}`")]
+    InvalidLine(usize, String),
+    #[error("Invalid LCOV at line {0}: `{1}` is outside of a `SF:` record")]
+    OutsideRecord(usize, String),
+}
+
+/// Coverage of one source file.
+#[derive(Debug, Default, PartialEq)]
+struct SourceFile {
+    /// Hits by line.
+    lines: BTreeMap<u32, u64>,
+    /// Line and hits by function name.
+    functions: BTreeMap<String, (Option<u32>, u64)>,
+    /// Times taken by line, block and branch, or `None` if the block never ran.
+    branches: BTreeMap<(u32, String, String), Option<u64>>,
+}
+
+/// Coverage by source file, in the subset of LCOV with line, function and branch data.
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct Lcov {
+    files: BTreeMap<String, SourceFile>,
+}
+
+/// Totals of a coverage report.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub(crate) struct CoverageTotals {
+    pub(crate) files: u64,
+    pub(crate) lines_found: u64,
+    pub(crate) lines_hit: u64,
+    pub(crate) functions_found: u64,
+    pub(crate) functions_hit: u64,
+    pub(crate) branches_found: u64,
+    pub(crate) branches_hit: u64,
+}
+
+impl Lcov {
+    /// Parses LCOV, making the paths of source files under `root` relative to it, so that the
+    /// coverage of tests running from different directories merges.
+    pub(crate) fn parse(lcov: &str, root: &str) -> anyhow::Result<Self> {
+        fn number<T: std::str::FromStr>(s: &str) -> Option<T> {
+            s.trim().parse().ok()
+        }
+
+        let mut result = Self::default();
+        let mut current: Option<(String, SourceFile)> = None;
+        for (i, line) in lcov.lines().enumerate() {
+            let line = line.trim();
+            let invalid = || LcovError::InvalidLine(i + 1, line.to_owned());
+            if line.is_empty() {
+                continue;
+            }
+            if let Some(path) = line.strip_prefix("SF:") {
+                let path = match path.strip_prefix(root) {
+                    Some(relative) if !root.is_empty() => {
+                        relative.strip_prefix('/').unwrap_or(path)
+                    }
+                    _ => path,
+                };
+                current = Some((path.to_owned(), SourceFile::default()));
+                continue;
+            }
+            if line == "end_of_record" {
+                if let Some((path, file)) = current.take() {
+                    result.merge_file(path, file);
+                }
+                continue;
+            }
+
+            let (kind, data) = match line.split_once(':') {
+                Some(x) => x,
+                None => return Err(invalid().into()),
+            };
+            if !matches!(kind, "DA" | "FN" | "FNDA" | "BRDA") {
+                // Test names, totals which are computed again, and other extensions.
+                continue;
+            }
+            let file = match &mut current {
+                Some((_, file)) => file,
+                None => return Err(LcovError::OutsideRecord(i + 1, line.to_owned()).into()),
+            };
+            match kind {
+                // `DA:<line>,<hits>[,<checksum>]`
+                "DA" => {
+                    let mut fields = data.split(',');
+                    let (line, hits) = match (
+                        fields.next().and_then(number),
+                        fields.next().and_then(number::<u64>),
+                    ) {
+                        (Some(line), Some(hits)) => (line, hits),
+                        _ => return Err(invalid().into()),
+                    };
+                    *file.lines.entry(line).or_default() += hits;
+                }
+                // `FN:<line>,[<end line>,]<name>`, where the name can contain commas.
+                "FN" => {
+                    let (line, name) = data.split_once(',').ok_or_else(invalid)?;
+                    let name = match name.split_once(',') {
+                        Some((end, rest)) if number::<u32>(end).is_some() => rest,
+                        _ => name,
+                    };
+                    let function = file.functions.entry(name.to_owned()).or_default();
+                    function.0 = function.0.or_else(|| number(line));
+                }
+                // `FNDA:<hits>,<name>`
+                "FNDA" => {
+                    let (hits, name) = data.split_once(',').ok_or_else(invalid)?;
+                    let hits: u64 = number(hits).ok_or_else(invalid)?;
+                    file.functions.entry(name.to_owned()).or_default().1 += hits;
+                }
+                // `BRDA:<line>,<block>,<branch>,<taken>`, where `taken` is `-` if the block never
+                // ran.
+                "BRDA" => {
+                    let fields = data.split(',').collect::<Vec<_>>();
+                    let (line, block, branch, taken) = match fields.as_slice() {
+                        [line, block, branch, taken] => (line, block, branch, taken),
+                        _ => return Err(invalid().into()),
+                    };
+                    let line = number(line).ok_or_else(invalid)?;
+                    let taken = match *taken {
+                        "-" => None,
+                        taken => Some(number::<u64>(taken).ok_or_else(invalid)?),
+                    };
+                    let entry = file
+                        .branches
+                        .entry((line, (*block).to_owned(), (*branch).to_owned()))
+                        .or_default();
+                    *entry = add_taken(*entry, taken);
+                }
+                _ => unreachable!(),
+            }
+        }
+        // Tolerate a missing `end_of_record` at the end.
+        if let Some((path, file)) = current {
+            result.merge_file(path, file);
+        }
+        Ok(result)
+    }
+
+    pub(crate) fn merge(&mut self, other: Lcov) {
+        for (path, file) in other.files {
+            self.merge_file(path, file);
+        }
+    }
+
+    fn merge_file(&mut self, path: String, file: SourceFile) {
+        let merged = self.files.entry(path).or_default();
+        for (line, hits) in file.lines {
+            *merged.lines.entry(line).or_default() += hits;
+        }
+        for (name, (line, hits)) in file.functions {
+            let function = merged.functions.entry(name).or_default();
+            function.0 = function.0.or(line);
+            function.1 += hits;
+        }
+        for (key, taken) in file.branches {
+            let entry = merged.branches.entry(key).or_default();
+            *entry = add_taken(*entry, taken);
+        }
+    }
+
+    pub(crate) fn totals(&self) -> CoverageTotals {
+        let mut totals = CoverageTotals::default();
+        for file in self.files.values() {
+            totals.files += 1;
+            totals.lines_found += file.lines.len() as u64;
+            totals.lines_hit += file.lines.values().filter(|hits| **hits > 0).count() as u64;
+            totals.functions_found += file.functions.len() as u64;
+            totals.functions_hit += file
+                .functions
+                .values()
+                .filter(|(_, hits)| *hits > 0)
+                .count() as u64;
+            totals.branches_found += file.branches.len() as u64;
+            totals.branches_hit += file
+                .branches
+                .values()
+                .filter(|taken| taken.map_or(false, |t| t > 0))
+                .count() as u64;
+        }
+        totals
+    }
+
+    pub(crate) fn to_lcov(&self) -> String {
+        let mut out = String::new();
+        for (path, file) in &self.files {
+            // Writing to a `String` doesn't fail.
+            let _ = write_file(&mut out, path, file);
+        }
+        out
+    }
+}
+
+fn write_file(out: &mut String, path: &str, file: &SourceFile) -> std::fmt::Result {
+    writeln!(out, "SF:{}", path)?;
+    for (name, (line, _)) in &file.functions {
+        if let Some(line) = line {
+            writeln!(out, "FN:{},{}", line, name)?;
+        }
+    }
+    for (name, (_, hits)) in &file.functions {
+        writeln!(out, "FNDA:{},{}", hits, name)?;
+    }
+    writeln!(out, "FNF:{}", file.functions.len())?;
+    writeln!(
+        out,
+        "FNH:{}",
+        file.functions
+            .values()
+            .filter(|(_, hits)| *hits > 0)
+            .count()
+    )?;
+    for ((line, block, branch), taken) in &file.branches {
+        match taken {
+            Some(taken) => writeln!(out, "BRDA:{},{},{},{}", line, block, branch, taken)?,
+            None => writeln!(out, "BRDA:{},{},{},-", line, block, branch)?,
+        }
+    }
+    writeln!(out, "BRF:{}", file.branches.len())?;
+    writeln!(
+        out,
+        "BRH:{}",
+        file.branches
+            .values()
+            .filter(|taken| taken.map_or(false, |t| t > 0))
+            .count()
+    )?;
+    for (line, hits) in &file.lines {
+        writeln!(out, "DA:{},{}", line, hits)?;
+    }
+    writeln!(out, "LF:{}", file.lines.len())?;
+    writeln!(
+        out,
+        "LH:{}",
+        file.lines.values().filter(|hits| **hits > 0).count()
+    )?;
+    writeln!(out, "end_of_record")
+}
+
+fn add_taken(a: Option<u64>, b: Option<u64>) -> Option<u64> {
+    match (a, b) {
+        (None, None) => None,
+        (a, b) => Some(a.unwrap_or_default() + b.unwrap_or_default()),
+    }
+}
+
+/// The coverage collected by a test session.
+#[derive(Default)]
+pub(crate) struct CoverageCollector {
+    pub(crate) lcov: Lcov,
+    /// Why the coverage of some test executions is missing.
+    pub(crate) errors: Vec<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_merge() -> anyhow::Result<()> {
+        let mut lcov = Lcov::parse(
+            "TN:\nSF:/repo/src/a.rs\nFN:1,f\nFN:5,9,g<a, b>\nFNDA:1,f\nFNDA:0,g<a, b>\n\
+            BRDA:2,0,0,1\nBRDA:2,0,1,-\nDA:1,1\nDA:2,1\nDA:5,0\nLF:3\nLH:2\nend_of_record\n",
+            "/repo",
+        )?;
+        lcov.merge(Lcov::parse(
+            "SF:src/a.rs\nFNDA:2,g<a, b>\nBRDA:2,0,1,0\nDA:5,2\nend_of_record\n\
+            SF:src/b.rs\nDA:1,0\nend_of_record\n",
+            "/repo",
+        )?);
+
+        assert_eq!(
+            CoverageTotals {
+                files: 2,
+                lines_found: 4,
+                lines_hit: 3,
+                functions_found: 2,
+                functions_hit: 2,
+                branches_found: 2,
+                branches_hit: 1,
+            },
+            lcov.totals()
+        );
+        assert_eq!(
+            "SF:src/a.rs\nFN:1,f\nFN:5,g<a, b>\nFNDA:1,f\nFNDA:2,g<a, b>\nFNF:2\nFNH:2\n\
+            BRDA:2,0,0,1\nBRDA:2,0,1,0\nBRF:2\nBRH:1\nDA:1,1\nDA:2,1\nDA:5,2\nLF:3\nLH:3\n\
+            end_of_record\n\
+            SF:src/b.rs\nFNF:0\nFNH:0\nBRF:0\nBRH:0\nDA:1,0\nLF:1\nLH:0\nend_of_record\n",
+            lcov.to_lcov()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_roundtrip() -> anyhow::Result<()> {
+        let lcov = Lcov::parse(
+            "SF:a.py\nFN:3,main\nFNDA:1,main\nBRDA:4,0,0,-\nDA:3,1\nDA:4,1\nend_of_record\n",
+            "",
+        )?;
+        assert_eq!(lcov, Lcov::parse(&lcov.to_lcov(), "")?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        assert!(Lcov::parse("SF:a.py\nDA:x,1\nend_of_record\n", "").is_err());
+        assert!(Lcov::parse("DA:1,1\n", "").is_err());
+    }
+}
@@ -12,13 +12,16 @@
 #![feature(async_closure)]
 
 pub mod command;
+pub(crate) mod coverage;
 pub mod downward_api;
 pub mod executor_launcher;
 pub(crate) mod local_resource_api;
 pub(crate) mod local_resource_registry;
 pub(crate) mod local_resource_setup;
 pub mod orchestrator;
+pub(crate) mod result_cache;
 pub mod session;
+pub(crate) mod sharding;
 pub(crate) mod tcp;
 pub mod translations;
 #[cfg(unix)]
@@ -11,6 +11,7 @@
 
 use std::collections::HashMap;
 use std::ffi::OsStr;
+use std::ops::ControlFlow;
 use std::sync::Arc;
 use std::time::Duration;
 
@@ -41,6 +42,8 @@ use buck2_core::execution_types::executor_config::LocalExecutorOptions;
 use buck2_core::execution_types::executor_config::PathSeparatorKind;
 use buck2_core::fs::artifact_path_resolver::ArtifactFs;
 use buck2_core::fs::buck_out_path::BuckOutTestPath;
+use buck2_core::fs::fs_util;
+use buck2_core::fs::paths::abs_norm_path::AbsNormPath;
 use buck2_core::fs::paths::forward_rel_path::ForwardRelativePath;
 use buck2_core::fs::paths::forward_rel_path::ForwardRelativePathBuf;
 use buck2_core::fs::project_rel_path::ProjectRelativePath;
@@ -63,6 +66,7 @@ use buck2_execute::artifact::fs::ExecutorFs;
 use buck2_execute::digest_config::DigestConfig;
 use buck2_execute::digest_config::HasDigestConfig;
 use buck2_execute::execute::blocking::HasBlockingExecutor;
+use buck2_execute::execute::cache_uploader::CacheUploadInfo;
 use buck2_execute::execute::cache_uploader::NoOpCacheUploader;
 use buck2_execute::execute::claim::MutexClaimManager;
 use buck2_execute::execute::command_executor::CommandExecutor;
@@ -84,6 +88,8 @@ use buck2_execute::execute::result::CommandExecutionReport;
 use buck2_execute::execute::result::CommandExecutionResult;
 use buck2_execute::execute::result::CommandExecutionStatus;
 use buck2_execute::execute::target::CommandExecutionTarget;
+use buck2_execute::execute::test_result_cache::CachedTestExecution;
+use buck2_execute::execute::test_result_cache::HasTestResultCache;
 use buck2_execute::materialize::materializer::HasMaterializer;
 use buck2_execute_impl::executors::local::apply_local_execution_environment;
 use buck2_execute_impl::executors::local::create_output_dirs;
@@ -122,10 +128,13 @@ use sorted_vector_map::SortedVectorMap;
 use starlark::values::FrozenRef;
 use uuid::Uuid;
 
+use crate::coverage;
+use crate::coverage::Lcov;
 use crate::local_resource_api::LocalResourcesSetupResult;
 use crate::local_resource_registry::LocalResourceRegistry;
 use crate::local_resource_setup::required_local_resources_setup_contexts;
 use crate::local_resource_setup::LocalResourceSetupContext;
+use crate::result_cache;
 use crate::session::TestSession;
 use crate::translations;
 
@@ -207,10 +216,10 @@ impl<'a> BuckTestOrchestrator<'a> {
         metadata: DisplayMetadata,
         test_target: ConfiguredTargetHandle,
         cmd: Vec<ArgValue>,
-        env: SortedVectorMap<String, ArgValue>,
+        mut env: SortedVectorMap<String, ArgValue>,
         timeout: Duration,
         host_sharing_requirements: HostSharingRequirements,
-        pre_create_dirs: Vec<DeclaredOutput>,
+        mut pre_create_dirs: Vec<DeclaredOutput>,
         executor_override: Option<ExecutorConfigOverride>,
         required_local_resources: RequiredLocalResources,
     ) -> Result<ExecutionResult2, ExecuteError> {
@@ -221,8 +230,41 @@ impl<'a> BuckTestOrchestrator<'a> {
         let fs = self.dice.get_artifact_fs().await?;
 
         let test_info = self.get_test_info(&test_target).await?;
+        let is_test_run = matches!(metadata, DisplayMetadata::Testing { .. });
+        let coverage = self.session.options().coverage && is_test_run;
+        if coverage {
+            let coverage_dir = DeclaredOutput {
+                name: ForwardRelativePathBuf::unchecked_new(coverage::COVERAGE_OUTPUT.to_owned()),
+            };
+            env.insert(
+                coverage::COVERAGE_DIR_ENV.to_owned(),
+                ArgValue {
+                    content: ArgValueContent::DeclaredOutput(coverage_dir.clone()),
+                    format: None,
+                },
+            );
+            pre_create_dirs.push(coverage_dir);
+        }
+        // Only the results of tests are cached, not those of listings. Results served from the
+        // cache would have no coverage.
+        let cacheable = self.session.options().cache_results
+            && is_test_run
+            && !coverage
+            && result_cache::is_cacheable(test_info.labels());
+        let output_root = cacheable
+            .then(|| result_cache::output_root(&test_target, &cmd, &env, &pre_create_dirs));
+        // Held until the outputs of the test are materialized and its result is returned.
+        let _output_root_guard = match &output_root {
+            Some(output_root) => Some(
+                self.dice
+                    .get_test_result_cache()
+                    .lock_output_root(output_root)
+                    .await,
+            ),
+            None => None,
+        };
         let test_executor = self
-            .get_test_executor(&test_target, &test_info, executor_override, &fs)
+            .get_test_executor(&test_target, &test_info, executor_override, cacheable, &fs)
             .await?;
         let test_executable_expanded = self
             .expand_test_executable(
@@ -231,6 +273,7 @@ impl<'a> BuckTestOrchestrator<'a> {
                 cmd,
                 env,
                 pre_create_dirs,
+                output_root,
                 &test_executor.executor_fs(),
             )
             .await?;
@@ -286,13 +329,19 @@ impl<'a> BuckTestOrchestrator<'a> {
             )
             .await?;
 
-        let (stdout, stderr, status, timing, execution_kind, outputs) = self
-            .execute_shared(&test_target, metadata, &test_executor, execution_request)
+        let (stdout, stderr, status, timing, execution_kind, outputs, cached) = self
+            .execute_shared(
+                &test_target,
+                metadata,
+                &test_executor,
+                execution_request,
+                cacheable,
+            )
             .await?;
 
         self.require_alive().await?;
 
-        let (outputs, paths_to_materialize) = outputs
+        let (outputs, paths_to_materialize): (HashMap<_, _>, Vec<_>) = outputs
             .into_iter()
             .map(|test_path| {
                 let project_path = fs.buck_out_path_resolver().resolve_test(&test_path);
@@ -313,6 +362,24 @@ impl<'a> BuckTestOrchestrator<'a> {
             .await
             .context("Error materializing test outputs")?;
 
+        if coverage {
+            let coverage_dir = outputs
+                .iter()
+                .find_map(|(output, Output::LocalPath(path))| {
+                    (output.name.as_str() == coverage::COVERAGE_OUTPUT).then_some(path)
+                });
+            if let Some(coverage_dir) = coverage_dir {
+                let lcov = self
+                    .collect_coverage(&test_target, &test_info, coverage_dir, &fs)
+                    .await;
+                let mut collector = self.session.coverage().lock();
+                match lcov {
+                    Ok(lcov) => collector.lcov.merge(lcov),
+                    Err(e) => collector.errors.push(format!("{}: {:#}", test_target, e)),
+                }
+            }
+        }
+
         Ok(ExecutionResult2 {
             status,
             stdout,
@@ -322,6 +389,7 @@ impl<'a> BuckTestOrchestrator<'a> {
             execution_time: timing.execution_time,
             execution_details: ExecutionDetails {
                 execution_kind: execution_kind.map(|k| k.to_proto(false)),
+                cached,
             },
         })
     }
@@ -442,7 +510,7 @@ impl<'a> TestOrchestrator for BuckTestOrchestrator<'a> {
         let test_info = self.get_test_info(&test_target).await?;
         // Tests are not run, so there is no executor override.
         let executor = self
-            .get_test_executor(&test_target, &test_info, None, &fs)
+            .get_test_executor(&test_target, &test_info, None, false, &fs)
             .await?;
         let test_executable_expanded = self
             .expand_test_executable(
@@ -451,6 +519,7 @@ impl<'a> TestOrchestrator for BuckTestOrchestrator<'a> {
                 cmd,
                 env,
                 pre_create_dirs,
+                None,
                 &executor.executor_fs(),
             )
             .await?;
@@ -531,6 +600,7 @@ impl<'b> BuckTestOrchestrator<'b> {
         metadata: DisplayMetadata,
         executor: &CommandExecutor,
         request: CommandExecutionRequest,
+        cacheable: bool,
     ) -> Result<
         (
             ExecutionStream,
@@ -539,6 +609,7 @@ impl<'b> BuckTestOrchestrator<'b> {
             CommandExecutionMetadata,
             Option<CommandExecutionKind>,
             Vec<BuckOutTestPath>,
+            bool,
         ),
         ExecuteError,
     > {
@@ -563,36 +634,60 @@ impl<'b> BuckTestOrchestrator<'b> {
             action_key_suffix,
         };
 
-        // For test execution, we currently do not do any cache queries
-
         let prepared_action = executor.prepare_action(&request, self.digest_config)?;
+        let use_cached_results = cacheable && self.session.options().use_cached_results;
+        if use_cached_results {
+            let cached = self
+                .dice
+                .get_test_result_cache()
+                .lookup(&prepared_action.action);
+            // The outputs of the test are only restored if they are still in place.
+            let fs = executor.fs();
+            let cached = cached.filter(|cached| {
+                cached.outputs.iter().all(|output| {
+                    let path = fs.buck_out_path_resolver().resolve_test(output);
+                    fs_util::try_exists(fs.fs().resolve(&path)).unwrap_or(false)
+                })
+            });
+            if let Some(cached) = cached {
+                return Ok((
+                    ExecutionStream::Inline(cached.stdout.clone()),
+                    ExecutionStream::Inline(cached.stderr.clone()),
+                    ExecutionStatus::Finished { exitcode: 0 },
+                    cached.timing,
+                    None,
+                    cached.outputs.clone(),
+                    true,
+                ));
+            }
+        }
+
         let prepared_command = PreparedCommand {
             target: &test_target as _,
             request: &request,
             prepared_action: &prepared_action,
             digest_config: self.digest_config,
         };
-        let command = executor.exec_cmd(manager, &prepared_command, self.cancellations);
+        let command = async {
+            let manager = if use_cached_results {
+                match executor
+                    .action_cache(manager, &prepared_command, self.cancellations)
+                    .await
+                {
+                    ControlFlow::Break(result) => return result,
+                    ControlFlow::Continue(manager) => manager,
+                }
+            } else {
+                manager
+            };
+            executor
+                .exec_cmd(manager, &prepared_command, self.cancellations)
+                .await
+        };
 
         // instrument execution with a span.
         // TODO(brasselsprouts): migrate this into the executor to get better accuracy.
-        let CommandExecutionResult {
-            outputs,
-            report:
-                CommandExecutionReport {
-                    std_streams,
-                    exit_code,
-                    status,
-                    timing,
-                    ..
-                },
-            rejected_execution: _,
-            did_cache_upload: _,
-            did_dep_file_cache_upload: _,
-            dep_file_key: _,
-            eligible_for_full_hybrid: _,
-            dep_file_metadata: _,
-        } = match metadata {
+        let result = match metadata {
             DisplayMetadata::Listing(listing) => {
                 let start = TestDiscoveryStart {
                     suite_name: listing.clone(),
@@ -621,6 +716,7 @@ impl<'b> BuckTestOrchestrator<'b> {
                 });
                 let start = TestRunStart {
                     suite: test_suite.clone(),
+                    timeout: request.timeout().and_then(|t| t.try_into().ok()),
                 };
                 self.events
                     .span_async(start, async move {
@@ -640,7 +736,47 @@ impl<'b> BuckTestOrchestrator<'b> {
             }
         };
 
-        let outputs = outputs
+        let cached = matches!(
+            &result.report.status,
+            CommandExecutionStatus::Success {
+                execution_kind: CommandExecutionKind::ActionCache { .. }
+            }
+        );
+        let store_result = cacheable && !cached && result.was_success();
+        if store_result {
+            executor
+                .cache_upload(
+                    &CacheUploadInfo {
+                        target: &test_target as _,
+                        action_digest: prepared_action.action.dupe(),
+                        digest_config: self.digest_config,
+                    },
+                    &result,
+                    None,
+                    &prepared_action.blobs,
+                )
+                .await?;
+        }
+
+        let CommandExecutionResult {
+            outputs,
+            report:
+                CommandExecutionReport {
+                    std_streams,
+                    exit_code,
+                    status,
+                    timing,
+                    ..
+                },
+            rejected_execution: _,
+            did_cache_upload: _,
+            did_dep_file_cache_upload: _,
+            dep_file_key: _,
+            eligible_for_full_hybrid: _,
+            dep_file_metadata: _,
+        } = result;
+
+        let outputs: Vec<_> = outputs
             .into_keys()
             .filter_map(|output| Some(output.into_test_path()?.0))
             .collect();
@@ -649,6 +785,17 @@ impl<'b> BuckTestOrchestrator<'b> {
             .into_bytes()
             .await
             .context("Error accessing test output")?;
+        if store_result {
+            self.dice.get_test_result_cache().store(
+                prepared_action.action.dupe(),
+                CachedTestExecution {
+                    stdout: std_streams.stdout.clone(),
+                    stderr: std_streams.stderr.clone(),
+                    timing,
+                    outputs: outputs.clone(),
+                },
+            );
+        }
         let stdout = ExecutionStream::Inline(std_streams.stdout);
         let stderr = ExecutionStream::Inline(std_streams.stderr);
 
@@ -662,6 +809,7 @@ impl<'b> BuckTestOrchestrator<'b> {
                 timing,
                 Some(execution_kind),
                 outputs,
+                cached,
             ),
             CommandExecutionStatus::Failure { execution_kind } => (
                 stdout,
@@ -672,6 +820,7 @@ impl<'b> BuckTestOrchestrator<'b> {
                 timing,
                 Some(execution_kind),
                 outputs,
+                cached,
             ),
             CommandExecutionStatus::TimedOut {
                 duration,
@@ -683,6 +832,7 @@ impl<'b> BuckTestOrchestrator<'b> {
                 timing,
                 Some(execution_kind),
                 outputs,
+                cached,
             ),
             CommandExecutionStatus::Error { stage: _, error } => (
                 ExecutionStream::Inline(Default::default()),
@@ -693,6 +843,7 @@ impl<'b> BuckTestOrchestrator<'b> {
                 timing,
                 None,
                 outputs,
+                cached,
             ),
             CommandExecutionStatus::Cancelled => {
                 return Err(ExecuteError::Cancelled(Cancelled));
@@ -705,6 +856,7 @@ impl<'b> BuckTestOrchestrator<'b> {
         fs: &ArtifactFs,
         test_target_node: &ConfiguredTargetNode,
         executor_override: Option<&CommandExecutorConfig>,
+        cacheable: bool,
     ) -> anyhow::Result<CommandExecutor> {
         let executor_config = match executor_override {
             Some(o) => o,
@@ -717,14 +869,22 @@ impl<'b> BuckTestOrchestrator<'b> {
         let CommandExecutorResponse {
             executor,
             platform,
-            cache_checker: _,
-            cache_uploader: _,
+            cache_checker,
+            cache_uploader,
         } = self.dice.get_command_executor(fs, executor_config)?;
+        // Only tests using the test result cache query and write to the action cache.
+        let (cache_checker, cache_uploader) = if cacheable {
+            (cache_checker, cache_uploader)
+        } else {
+            (
+                Arc::new(NoOpCommandOptionalExecutor {}) as _,
+                Arc::new(NoOpCacheUploader {}) as _,
+            )
+        };
         let executor = CommandExecutor::new(
             executor,
-            // Caching is not enabled for tests yet. Use the NoOp
-            Arc::new(NoOpCommandOptionalExecutor {}),
-            Arc::new(NoOpCacheUploader {}),
+            cache_checker,
+            cache_uploader,
             fs.clone(),
             executor_config.options,
             platform,
@@ -757,6 +917,110 @@ impl<'b> BuckTestOrchestrator<'b> {
         Ok(executor)
     }
 
+    /// The LCOV of the coverage data a test execution wrote to `coverage_dir`: the output of the
+    /// `coverage_merger` of the test if it has one, else the LCOV files in the directory.
+    async fn collect_coverage(
+        &self,
+        test_target: &ConfiguredProvidersLabel,
+        test_info: &FrozenExternalRunnerTestInfo,
+        coverage_dir: &AbsNormPath,
+        fs: &ArtifactFs,
+    ) -> anyhow::Result<Lcov> {
+        let root = fs.fs().root().to_string();
+
+        let merger = match test_info.coverage_merger() {
+            Some(merger) => merger,
+            None => {
+                let mut lcov = Lcov::default();
+                for entry in fs_util::read_dir(coverage_dir)? {
+                    let path = entry?.path();
+                    if matches!(
+                        path.as_path().extension().and_then(OsStr::to_str),
+                        Some("lcov" | "info")
+                    ) {
+                        let content = fs_util::read_to_string(&path)?;
+                        lcov.merge(
+                            Lcov::parse(&content, &root)
+                                .with_context(|| format!("Error parsing `{}`", path))?,
+                        );
+                    }
+                }
+                return Ok(lcov);
+            }
+        };
+
+        let executor = self.get_local_executor(fs)?;
+        let mut cmd = Vec::<String>::new();
+        {
+            let executor_fs = executor.executor_fs();
+            let mut ctx = DefaultCommandLineContext::new(&executor_fs);
+            merger.add_to_command_line(&mut cmd, &mut ctx)?;
+        }
+        cmd.push(coverage_dir.to_string());
+        let mut artifact_visitor = SimpleCommandLineArtifactVisitor::new();
+        merger.visit_artifacts(&mut artifact_visitor)?;
+
+        let request = self
+            .create_command_execution_request(
+                ProjectRelativePathBuf::unchecked_new(String::new()),
+                cmd,
+                SortedVectorMap::new(),
+                artifact_visitor.inputs,
+                IndexMap::new(),
+                fs,
+                None,
+                None,
+                Some(ExecutorPreference::LocalRequired),
+                Vec::new(),
+            )
+            .await?;
+
+        let manager = CommandExecutionManager::new(
+            Box::new(MutexClaimManager::new()),
+            self.events.dupe(),
+            self.liveliness_observer.dupe(),
+        );
+        let target = TestTarget {
+            target: test_target.target(),
+            action_key_suffix: "coverage".to_owned(),
+        };
+        let prepared_action = executor.prepare_action(&request, self.digest_config)?;
+        let prepared_command = PreparedCommand {
+            target: &target as _,
+            request: &request,
+            prepared_action: &prepared_action,
+            digest_config: self.digest_config,
+        };
+        let CommandExecutionReport {
+            std_streams,
+            exit_code,
+            status,
+            ..
+        } = executor
+            .exec_cmd(manager, &prepared_command, self.cancellations)
+            .await
+            .report;
+
+        let std_streams = std_streams
+            .into_bytes()
+            .await
+            .context("Error accessing coverage merger output")?;
+        match status {
+            CommandExecutionStatus::Success { .. } => {}
+            CommandExecutionStatus::Error { stage: _, error } => return Err(error),
+            _ => {
+                return Err(anyhow::anyhow!(
+                    "Coverage merger failed with `{}` exit code, stderr:\n{}",
+                    exit_code.unwrap_or(1),
+                    String::from_utf8_lossy(&std_streams.stderr),
+                ));
+            }
+        }
+
+        Lcov::parse(&String::from_utf8_lossy(&std_streams.stdout), &root)
+            .context("Error parsing the output of the coverage merger")
+    }
+
     async fn get_test_info(
         &self,
         test_target: &ConfiguredProvidersLabel,
@@ -778,6 +1042,7 @@ impl<'b> BuckTestOrchestrator<'b> {
         test_target: &ConfiguredProvidersLabel,
         test_info: &FrozenExternalRunnerTestInfo,
         executor_override: Option<ExecutorConfigOverride>,
+        cacheable: bool,
         fs: &ArtifactFs,
     ) -> anyhow::Result<CommandExecutor> {
         // NOTE: get_providers() implicitly calls this already but it's not the end of the world
@@ -808,6 +1073,7 @@ impl<'b> BuckTestOrchestrator<'b> {
             fs,
             &node,
             resolved_executor_override.as_ref().map(|a| &***a),
+            cacheable,
         )
         .context("Error constructing CommandExecutor")
     }
@@ -819,14 +1085,16 @@ impl<'b> BuckTestOrchestrator<'b> {
         cmd: Vec<ArgValue>,
         env: SortedVectorMap<String, ArgValue>,
         pre_create_dirs: Vec<DeclaredOutput>,
+        output_root: Option<ForwardRelativePathBuf>,
         executor_fs: &ExecutorFs<'_>,
     ) -> anyhow::Result<ExpandedTestExecutable> {
-        let output_root = self
-            .session
-            .prefix()
-            .join(ForwardRelativePathBuf::unchecked_new(
-                Uuid::new_v4().to_string(),
-            ));
+        let output_root = output_root.unwrap_or_else(|| {
+            self.session
+                .prefix()
+                .join(ForwardRelativePathBuf::unchecked_new(
+                    Uuid::new_v4().to_string(),
+                ))
+        });
 
         let mut declared_outputs = IndexMap::<BuckOutTestPath, OutputCreationBehavior>::new();
 
@@ -1478,6 +1746,7 @@ mod tests {
                     name: "First - test".to_owned(),
                     duration: Some(Duration::from_micros(1)),
                     details: "1".to_owned(),
+                    artifacts: Vec::new(),
                 })
                 .await?;
 
@@ -1489,6 +1758,7 @@ mod tests {
                     name: "Second - test".to_owned(),
                     duration: Some(Duration::from_micros(2)),
                     details: "2".to_owned(),
+                    artifacts: Vec::new(),
                 })
                 .await?;
 
@@ -1510,6 +1780,7 @@ mod tests {
                     name: "First - test".to_owned(),
                     duration: Some(Duration::from_micros(1)),
                     details: "1".to_owned(),
+                    artifacts: Vec::new(),
                 }),
                 ExecutorMessage::TestResult(TestResult {
                     target,
@@ -1519,6 +1790,7 @@ mod tests {
                     name: "Second - test".to_owned(),
                     duration: Some(Duration::from_micros(2)),
                     details: "2".to_owned(),
+                    artifacts: Vec::new(),
                 }),
                 ExecutorMessage::ExitCode(0),
             ]
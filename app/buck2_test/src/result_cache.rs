@@ -0,0 +1,154 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! The test result cache, enabled by `test.cache_results`.
+//!
+//! Passing test executions are keyed by the digest of their action, which covers the command,
+//! the environment and the inputs of the test. They are kept in memory by the daemon, see
+//! `TestResultCache`, and, if the executor of the test uploads to the RE action cache, written
+//! there too.
+
+use buck2_core::fs::paths::forward_rel_path::ForwardRelativePathBuf;
+use buck2_core::provider::label::ConfiguredProvidersLabel;
+use buck2_test_api::data::ArgValue;
+use buck2_test_api::data::ArgValueContent;
+use buck2_test_api::data::DeclaredOutput;
+use buck2_test_api::data::ExternalRunnerSpecValue;
+use sorted_vector_map::SortedVectorMap;
+
+/// Tests with this label are never served from the cache.
+const NO_CACHE_LABEL: &str = "no_test_cache";
+
+pub(crate) fn is_cacheable<'a>(mut labels: impl Iterator<Item = &'a str>) -> bool {
+    !labels.any(|label| label == NO_CACHE_LABEL)
+}
+
+/// The directory of the outputs of a cacheable test execution. Other executions write their
+/// outputs to a new directory every time, but the output paths are part of the action digest, so
+/// this one only depends on the test target and on the request of the test runner. Executions
+/// using it are serialized with `TestResultCache::lock_output_root`.
+pub(crate) fn output_root(
+    test_target: &ConfiguredProvidersLabel,
+    cmd: &[ArgValue],
+    env: &SortedVectorMap<String, ArgValue>,
+    pre_create_dirs: &[DeclaredOutput],
+) -> ForwardRelativePathBuf {
+    let mut hasher = RequestHasher(blake3::Hasher::new());
+    hasher.str(&test_target.to_string());
+    hasher.len(cmd.len());
+    for arg in cmd {
+        hasher.arg(arg);
+    }
+    hasher.len(env.len());
+    for (name, value) in env {
+        hasher.str(name);
+        hasher.arg(value);
+    }
+    hasher.len(pre_create_dirs.len());
+    for dir in pre_create_dirs {
+        hasher.str(dir.name.as_str());
+    }
+    ForwardRelativePathBuf::unchecked_new(format!("cacheable/{}", hasher.0.finalize().to_hex()))
+}
+
+/// Hashes a request field by field, prefixing strings and lists with their length and variants
+/// with a tag, so that different requests can't hash the same.
+struct RequestHasher(blake3::Hasher);
+
+impl RequestHasher {
+    fn len(&mut self, len: usize) {
+        self.0.update(&(len as u64).to_le_bytes());
+    }
+
+    fn tag(&mut self, tag: u8) {
+        self.0.update(&[tag]);
+    }
+
+    fn str(&mut self, s: &str) {
+        self.len(s.len());
+        self.0.update(s.as_bytes());
+    }
+
+    fn arg(&mut self, arg: &ArgValue) {
+        match &arg.content {
+            ArgValueContent::ExternalRunnerSpecValue(ExternalRunnerSpecValue::Verbatim(s)) => {
+                self.tag(0);
+                self.str(s);
+            }
+            ArgValueContent::ExternalRunnerSpecValue(ExternalRunnerSpecValue::ArgHandle(h)) => {
+                self.tag(1);
+                self.len(h.0);
+            }
+            ArgValueContent::ExternalRunnerSpecValue(ExternalRunnerSpecValue::EnvHandle(h)) => {
+                self.tag(2);
+                self.str(&h.0);
+            }
+            ArgValueContent::DeclaredOutput(output) => {
+                self.tag(3);
+                self.str(output.name.as_str());
+            }
+        }
+        match &arg.format {
+            Some(format) => {
+                self.tag(1);
+                self.str(format);
+            }
+            None => self.tag(0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use buck2_core::configuration::data::ConfigurationData;
+    use buck2_core::target::configured_target_label::ConfiguredTargetLabel;
+
+    use super::*;
+
+    fn verbatim(s: &str) -> ArgValue {
+        ArgValue {
+            content: ArgValueContent::ExternalRunnerSpecValue(ExternalRunnerSpecValue::Verbatim(
+                s.to_owned(),
+            )),
+            format: None,
+        }
+    }
+
+    fn root(
+        target: &str,
+        cmd: &[&str],
+        env: &SortedVectorMap<String, ArgValue>,
+    ) -> ForwardRelativePathBuf {
+        let target = ConfiguredProvidersLabel::new(
+            ConfiguredTargetLabel::testing_parse(target, ConfigurationData::testing_new()),
+            Default::default(),
+        );
+        let cmd = cmd.iter().map(|arg| verbatim(arg)).collect::<Vec<_>>();
+        output_root(&target, &cmd, env, &[])
+    }
+
+    #[test]
+    fn test_output_root() {
+        let a = "cell//pkg:a";
+        let env = SortedVectorMap::new();
+        assert_eq!(root(a, &["cmd"], &env), root(a, &["cmd"], &env));
+        assert_ne!(root(a, &["cmd"], &env), root(a, &["other"], &env));
+        assert_ne!(root(a, &["cmd"], &env), root("cell//pkg:b", &["cmd"], &env));
+        // Arguments are delimited.
+        assert_ne!(root(a, &["ab", "c"], &env), root(a, &["a", "bc"], &env));
+        let env_a = SortedVectorMap::from_iter([("A".to_owned(), verbatim("1"))]);
+        assert_ne!(root(a, &["cmd"], &env), root(a, &["cmd"], &env_a));
+    }
+
+    #[test]
+    fn test_is_cacheable() {
+        assert!(is_cacheable(["slow"].into_iter()));
+        assert!(!is_cacheable(["slow", "no_test_cache"].into_iter()));
+    }
+}
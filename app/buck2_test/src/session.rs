@@ -18,6 +18,9 @@ use buck2_test_api::data::ConfiguredTargetHandle;
 use chrono::Local;
 use dashmap::DashMap;
 use dupe::Dupe;
+use parking_lot::Mutex;
+
+use crate::coverage::CoverageCollector;
 
 #[derive(Debug, Clone, Copy, Dupe, Default)]
 pub struct TestSessionOptions {
@@ -25,6 +28,12 @@ pub struct TestSessionOptions {
     pub allow_re: bool,
     pub force_use_project_relative_paths: bool,
     pub force_run_from_project_root: bool,
+    /// Whether passing test results are stored to the test result cache.
+    pub cache_results: bool,
+    /// Whether tests with a result in the test result cache are served from it.
+    pub use_cached_results: bool,
+    /// Whether the coverage of the tests is collected.
+    pub coverage: bool,
 }
 
 /// The state of a buck2 test command.
@@ -41,6 +50,8 @@ pub struct TestSession {
     /// Options overriding the behavior of tests executed in this session. This is primarily
     /// intended for unstable or debugging features.
     options: TestSessionOptions,
+    /// The coverage collected so far, when `options.coverage` is set.
+    coverage: Mutex<CoverageCollector>,
 }
 
 impl TestSession {
@@ -57,6 +68,7 @@ impl TestSession {
             labels: DashMap::new(),
             prefix,
             options,
+            coverage: Mutex::new(CoverageCollector::default()),
         }
     }
 
@@ -68,6 +80,10 @@ impl TestSession {
         self.prefix.as_ref()
     }
 
+    pub(crate) fn coverage(&self) -> &Mutex<CoverageCollector> {
+        &self.coverage
+    }
+
     /// Insert a new provider and retrieve the matching handle.
     pub fn register(&self, label: ConfiguredProvidersLabel) -> ConfiguredTargetHandle {
         let id = self.next_id.fetch_add(1, Ordering::Relaxed).into();
@@ -0,0 +1,216 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Partitioning of the test targets of `buck2 test` across shards, for example CI machines.
+//!
+//! Every shard resolves and configures all the test targets, then only runs its own. The
+//! partition only depends on the targets and on the durations provided, so that shards running
+//! the same command agree on it.
+
+use std::collections::HashMap;
+
+use buck2_core::provider::label::ConfiguredProvidersLabel;
+
+#[derive(Debug, thiserror::Error)]
+enum ShardingError {
+    #[error("Shard index {0} is out of range for {1} shards")]
+    IndexOutOfRange(u32, u32),
+}
+
+pub(crate) struct Sharding {
+    index: u32,
+    count: u32,
+    /// Historical durations of unconfigured test targets, in milliseconds.
+    durations_ms: HashMap<String, u64>,
+    /// Only compute the partition, don't run the tests.
+    pub(crate) dry_run: bool,
+}
+
+/// The shard of a test target.
+pub(crate) struct ShardAssignment {
+    pub(crate) label: ConfiguredProvidersLabel,
+    pub(crate) shard: u32,
+    /// The duration the partition assumed, when durations were provided.
+    pub(crate) duration_ms: Option<u64>,
+}
+
+impl Sharding {
+    pub(crate) fn from_proto(
+        shard: &buck2_cli_proto::test_request::TestShard,
+    ) -> anyhow::Result<Self> {
+        if shard.index >= shard.count {
+            return Err(ShardingError::IndexOutOfRange(shard.index, shard.count).into());
+        }
+        Ok(Self {
+            index: shard.index,
+            count: shard.count,
+            durations_ms: shard.durations_ms.clone().into_iter().collect(),
+            dry_run: shard.dry_run,
+        })
+    }
+
+    pub(crate) fn is_own(&self, assignment: &ShardAssignment) -> bool {
+        assignment.shard == self.index
+    }
+
+    /// Assigns every test target to a shard: by a stable hash of the label, or, if durations were
+    /// provided, greedily to the least loaded shard, longest tests first.
+    pub(crate) fn assign(&self, labels: Vec<ConfiguredProvidersLabel>) -> Vec<ShardAssignment> {
+        let keyed = labels
+            .into_iter()
+            .map(|label| (label.to_string(), label))
+            .collect::<Vec<_>>();
+
+        if self.durations_ms.is_empty() {
+            let mut assignment = keyed
+                .into_iter()
+                .map(|(key, label)| ShardAssignment {
+                    shard: (stable_hash(&key) % u64::from(self.count)) as u32,
+                    label,
+                    duration_ms: None,
+                })
+                .collect::<Vec<_>>();
+            assignment.sort_by(|a, b| a.label.cmp(&b.label));
+            return assignment;
+        }
+
+        // Targets without history are assumed to take as long as the average known target.
+        let known = keyed
+            .iter()
+            .filter_map(|(_, label)| self.duration(label))
+            .collect::<Vec<_>>();
+        let default_ms = if known.is_empty() {
+            1
+        } else {
+            known.iter().sum::<u64>() / known.len() as u64
+        };
+
+        let mut weighted = keyed
+            .into_iter()
+            .map(|(key, label)| {
+                let duration_ms = self.duration(&label).unwrap_or(default_ms);
+                (duration_ms, key, label)
+            })
+            .collect::<Vec<_>>();
+        weighted.sort_by(|(a_ms, a_key, _), (b_ms, b_key, _)| {
+            b_ms.cmp(a_ms).then_with(|| a_key.cmp(b_key))
+        });
+
+        let mut loads = vec![0u64; self.count as usize];
+        let mut assignment = weighted
+            .into_iter()
+            .map(|(duration_ms, _, label)| {
+                let (shard, load) = loads
+                    .iter_mut()
+                    .enumerate()
+                    .min_by_key(|(shard, load)| (**load, *shard))
+                    .expect("At least one shard");
+                *load += duration_ms;
+                ShardAssignment {
+                    label,
+                    shard: shard as u32,
+                    duration_ms: Some(duration_ms),
+                }
+            })
+            .collect::<Vec<_>>();
+        assignment.sort_by(|a, b| a.label.cmp(&b.label));
+        assignment
+    }
+
+    fn duration(&self, label: &ConfiguredProvidersLabel) -> Option<u64> {
+        self.durations_ms
+            .get(&label.target().unconfigured().to_string())
+            .copied()
+    }
+}
+
+/// FNV-1a, which unlike the hashers of `std` is stable across releases and platforms.
+fn stable_hash(s: &str) -> u64 {
+    s.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use buck2_core::configuration::data::ConfigurationData;
+    use buck2_core::target::configured_target_label::ConfiguredTargetLabel;
+
+    use super::*;
+
+    fn label(name: &str) -> ConfiguredProvidersLabel {
+        let target = ConfiguredTargetLabel::testing_parse(
+            &format!("cell//pkg:{}", name),
+            ConfigurationData::testing_new(),
+        );
+        ConfiguredProvidersLabel::new(target, Default::default())
+    }
+
+    fn sharding(count: u32, durations_ms: &[(&str, u64)]) -> Sharding {
+        Sharding {
+            index: 0,
+            count,
+            durations_ms: durations_ms
+                .iter()
+                .map(|(name, ms)| (format!("cell//pkg:{}", name), *ms))
+                .collect(),
+            dry_run: false,
+        }
+    }
+
+    fn shards(assignment: &[ShardAssignment]) -> Vec<(String, u32)> {
+        assignment
+            .iter()
+            .map(|a| (a.label.target().name().to_string(), a.shard))
+            .collect()
+    }
+
+    #[test]
+    fn test_assign_by_hash() {
+        let labels = || ["a", "b", "c", "d", "e"].map(label).to_vec();
+        let assignment = sharding(3, &[]).assign(labels());
+        assert_eq!(5, assignment.len());
+        assert!(
+            assignment
+                .iter()
+                .all(|a| a.shard < 3 && a.duration_ms.is_none())
+        );
+
+        let mut reversed = labels();
+        reversed.reverse();
+        assert_eq!(
+            shards(&assignment),
+            shards(&sharding(3, &[]).assign(reversed))
+        );
+    }
+
+    #[test]
+    fn test_assign_by_duration() {
+        let assignment = sharding(2, &[("a", 10), ("b", 6), ("c", 4), ("d", 2)])
+            .assign(["a", "b", "c", "d", "e"].map(label).to_vec());
+        // The unknown `e` is assumed to take the average, 5.
+        assert_eq!(
+            vec![
+                ("a".to_owned(), 0),
+                ("b".to_owned(), 1),
+                ("c".to_owned(), 0),
+                ("d".to_owned(), 1),
+                ("e".to_owned(), 1),
+            ],
+            shards(&assignment)
+        );
+        assert_eq!(Some(5), assignment[4].duration_ms);
+    }
+
+    #[test]
+    fn test_stable_hash() {
+        assert_eq!(0xcbf29ce484222325, stable_hash(""));
+        assert_eq!(0xaf63dc4c8601ec8c, stable_hash("a"));
+    }
+}
@@ -213,6 +213,7 @@ impl TryFrom<i32> for TestStatus {
             buck2_test_proto::TestStatus::Rerun => TestStatus::RERUN,
             buck2_test_proto::TestStatus::ListingSuccess => TestStatus::LISTING_SUCCESS,
             buck2_test_proto::TestStatus::ListingFailed => TestStatus::LISTING_FAILED,
+            buck2_test_proto::TestStatus::Flaky => TestStatus::FLAKY,
         })
     }
 }
@@ -232,6 +233,7 @@ impl TryInto<i32> for TestStatus {
             TestStatus::RERUN => buck2_test_proto::TestStatus::Rerun,
             TestStatus::LISTING_SUCCESS => buck2_test_proto::TestStatus::ListingSuccess,
             TestStatus::LISTING_FAILED => buck2_test_proto::TestStatus::ListingFailed,
+            TestStatus::FLAKY => buck2_test_proto::TestStatus::Flaky,
         } as i32)
     }
 }
@@ -247,6 +249,7 @@ impl TryFrom<buck2_test_proto::TestResult> for TestResult {
             msg,
             duration,
             details,
+            artifacts,
         } = s;
 
         let duration = duration
@@ -264,6 +267,7 @@ impl TryFrom<buck2_test_proto::TestResult> for TestResult {
             msg: msg.map(|m| m.msg),
             duration,
             details,
+            artifacts,
         })
     }
 }
@@ -281,6 +285,7 @@ impl TryInto<buck2_test_proto::TestResult> for TestResult {
             details: self.details,
             msg: self.msg.map(|msg| OptionalMsg { msg }),
             duration: self.duration.try_map(|d| d.try_into())?,
+            artifacts: self.artifacts,
         })
     }
 }
@@ -298,6 +303,8 @@ impl TryFrom<buck2_test_proto::ExternalRunnerSpec> for ExternalRunnerSpec {
             contacts,
             oncall,
             working_dir_cell,
+            local_resources,
+            timeout,
         } = s;
 
         Ok(Self {
@@ -317,6 +324,11 @@ impl TryFrom<buck2_test_proto::ExternalRunnerSpec> for ExternalRunnerSpec {
             contacts,
             oncall,
             working_dir_cell: CellName::unchecked_new(&working_dir_cell)?,
+            local_resources: local_resources.into_map(|r| r.into()),
+            timeout: timeout
+                .map(convert::to_std_duration)
+                .transpose()
+                .context("Invalid `timeout`")?,
         })
     }
 }
@@ -334,6 +346,8 @@ impl TryInto<buck2_test_proto::ExternalRunnerSpec> for ExternalRunnerSpec {
             contacts,
             oncall,
             working_dir_cell,
+            local_resources,
+            timeout,
         } = self;
         Ok(buck2_test_proto::ExternalRunnerSpec {
             target: Some(target.try_into().context("Invalid `target`")?),
@@ -349,6 +363,8 @@ impl TryInto<buck2_test_proto::ExternalRunnerSpec> for ExternalRunnerSpec {
             contacts,
             oncall,
             working_dir_cell: working_dir_cell.as_str().to_owned(),
+            local_resources: local_resources.into_map(|r| r.into()),
+            timeout: timeout.map(|t| t.try_into()).transpose()?,
         })
     }
 }
@@ -873,6 +889,10 @@ mod tests {
             contacts: vec!["contact1".to_owned(), "contact2".to_owned()],
             oncall: Some("contact1".to_owned()),
             working_dir_cell: CellName::testing_new("qux"),
+            local_resources: vec![LocalResourceType {
+                name: "database".to_owned(),
+            }],
+            timeout: Some(Duration::from_secs(60)),
         };
         assert_roundtrips::<buck2_test_proto::ExternalRunnerSpec, ExternalRunnerSpec>(&test_spec);
     }
@@ -93,6 +93,8 @@ pub struct TestResult {
     pub duration: Option<Duration>,
     // the output of the test execution (combining stdout and stderr)
     pub details: String,
+    // paths of files written by the test, for example its outputs directory
+    pub artifacts: Vec<String>,
 }
 
 /// different possible test results
@@ -112,6 +114,8 @@ pub enum TestStatus {
     RERUN,
     LISTING_SUCCESS,
     LISTING_FAILED,
+    // Failed, then passed when run again.
+    FLAKY,
 }
 
 /// The set of information about a test rule that is passed to the test executor
@@ -135,6 +139,10 @@ pub struct ExternalRunnerSpec {
     pub oncall: Option<String>,
     /// Cell of current working directory for test command.
     pub working_dir_cell: CellName,
+    /// Types of the local resources declared by the rule.
+    pub local_resources: Vec<LocalResourceType>,
+    /// Timeout of the test set by the rule, if any.
+    pub timeout: Option<Duration>,
 }
 
 /// Command line argument or environment variable value
@@ -11,17 +11,46 @@ use std::str::FromStr;
 use std::time::Duration;
 
 use anyhow::Context;
+use buck2_test_api::data::TestStatus;
 use clap::Parser;
 
 #[derive(Debug, Parser)]
 pub struct Config {
     /// add a list of environment variables using format: --env VAR1=Value1 VAR2='Value 2'
+    /// These override the variables of the same name set by the test target.
     #[clap(long)]
     pub env: Vec<EnvValue>,
 
-    /// Max number of seconds allowed to run a test.
-    #[clap(long, default_value = "600", parse(try_from_str=try_parse_timeout_from_str))]
-    pub timeout: Duration,
+    /// Max number of seconds allowed to run a test whose target doesn't set `timeout_ms`. Tests
+    /// without either time out after 600 seconds.
+    #[clap(long, parse(try_from_str=try_parse_timeout_from_str))]
+    timeout: Option<Duration>,
+
+    /// Number of times to run again a test which failed. Tests which fail and then pass are
+    /// reported as flaky.
+    #[clap(long, default_value = "0")]
+    pub retries: u32,
+
+    /// Also run again tests which timed out, up to `--retries` times.
+    #[clap(long)]
+    pub retry_on_timeout: bool,
+
+    /// Run each test this many times, whatever the results, to find flaky tests. Tests which both
+    /// pass and fail are reported as flaky, and fail the run. Overrides `--retries`.
+    #[clap(long)]
+    pub stress_runs: Option<u32>,
+
+    /// Only run the test cases matching one of these patterns, where `*` matches any characters
+    /// and `?` a single one, for example `Suite.Case*`. Only applies to tests of frameworks whose
+    /// test cases the runner can list: `gtest`, `rust`, `pyunit` and `pytest`.
+    #[clap(long)]
+    pub filter: Vec<String>,
+
+    /// Only run this test case of this target, given by their exact names, for example
+    /// `--rerun-case root//foo:bar Suite.Case`. Targets without any such test case run all the
+    /// test cases selected by `--filter`. Used by `buck2 test --rerun-failed`.
+    #[clap(long, number_of_values = 2, value_names = &["TARGET", "CASE"])]
+    pub rerun_case: Vec<String>,
 
     /// Ignored arg included for backwards compatibility.
     #[clap(long, hidden = true)]
@@ -66,7 +95,122 @@ pub enum EnvValueParseError {
     IncorrectSyntax(String),
 }
 
+/// The timeout of tests which neither the test target nor `--timeout` set.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(600);
+
+impl Config {
+    /// The timeout of a test whose target set this one, which takes precedence over `--timeout`.
+    pub fn timeout(&self, target_timeout: Option<Duration>) -> Duration {
+        target_timeout.or(self.timeout).unwrap_or(DEFAULT_TIMEOUT)
+    }
+
+    /// Whether a test which finished with this status should run again, after `runs` runs.
+    pub fn should_run_again(&self, status: &TestStatus, runs: u32) -> bool {
+        match self.stress_runs {
+            Some(stress_runs) => runs < stress_runs,
+            None => {
+                runs <= self.retries
+                    && match status {
+                        TestStatus::FAIL => true,
+                        TestStatus::TIMEOUT => self.retry_on_timeout,
+                        _ => false,
+                    }
+            }
+        }
+    }
+
+    /// Whether the test case of the target is selected by `--filter` and `--rerun-case`.
+    pub fn is_selected(&self, target: &str, case: &str) -> bool {
+        let mut rerun_cases = self
+            .rerun_case
+            .chunks_exact(2)
+            .filter(|target_case| target_case[0] == target)
+            .peekable();
+        if rerun_cases.peek().is_some() && !rerun_cases.any(|target_case| target_case[1] == case) {
+            return false;
+        }
+        self.filter.is_empty() || {
+            let case = case.chars().collect::<Vec<_>>();
+            self.filter
+                .iter()
+                .any(|pattern| wildcard_match(&pattern.chars().collect::<Vec<_>>(), &case))
+        }
+    }
+}
+
+fn wildcard_match(pattern: &[char], text: &[char]) -> bool {
+    match pattern.split_first() {
+        None => text.is_empty(),
+        Some(('*', rest)) => (0..=text.len()).any(|i| wildcard_match(rest, &text[i..])),
+        Some(('?', rest)) => !text.is_empty() && wildcard_match(rest, &text[1..]),
+        Some((c, rest)) => text.first() == Some(c) && wildcard_match(rest, &text[1..]),
+    }
+}
+
 fn try_parse_timeout_from_str(input: &str) -> anyhow::Result<Duration> {
     let seconds = input.parse().context("Could not parse provided timeout")?;
     Ok(Duration::from_secs(seconds))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_selected() {
+        let config = Config::try_parse_from([
+            "runner",
+            "--buck-test-info",
+            "",
+            "--filter",
+            "Suite.Case*",
+            "--filter",
+            "tests::?",
+        ])
+        .unwrap();
+        assert!(config.is_selected("root//:t", "Suite.Case"));
+        assert!(config.is_selected("root//:t", "Suite.CaseTwo"));
+        assert!(config.is_selected("root//:t", "tests::a"));
+        assert!(!config.is_selected("root//:t", "tests::ab"));
+        assert!(!config.is_selected("root//:t", "Other.Case"));
+
+        let config = Config::try_parse_from(["runner", "--buck-test-info", ""]).unwrap();
+        assert!(config.is_selected("root//:t", "anything"));
+    }
+
+    #[test]
+    fn test_is_selected_rerun_case() {
+        let config = Config::try_parse_from([
+            "runner",
+            "--buck-test-info",
+            "",
+            "--rerun-case",
+            "root//:a",
+            "Suite.Case*",
+            "--rerun-case",
+            "root//:a",
+            "Suite.Other",
+        ])
+        .unwrap();
+        // Test case names are matched exactly, and only for their target.
+        assert!(config.is_selected("root//:a", "Suite.Case*"));
+        assert!(config.is_selected("root//:a", "Suite.Other"));
+        assert!(!config.is_selected("root//:a", "Suite.CaseTwo"));
+        assert!(config.is_selected("root//:b", "Suite.CaseTwo"));
+    }
+
+    #[test]
+    fn test_timeout() {
+        let minute = Some(Duration::from_secs(60));
+
+        let config = Config::try_parse_from(["runner", "--buck-test-info", ""]).unwrap();
+        assert_eq!(Duration::from_secs(600), config.timeout(None));
+        assert_eq!(Duration::from_secs(60), config.timeout(minute));
+
+        let config =
+            Config::try_parse_from(["runner", "--buck-test-info", "", "--timeout", "5"]).unwrap();
+        assert_eq!(Duration::from_secs(5), config.timeout(None));
+        // The timeout of the target wins.
+        assert_eq!(Duration::from_secs(60), config.timeout(minute));
+    }
+}
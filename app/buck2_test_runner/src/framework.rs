@@ -0,0 +1,180 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! The conventions of common test frameworks to list the test cases of a test binary, and to
+//! run a single one of them.
+
+/// A test framework, chosen by the `type` of `ExternalRunnerTestInfo`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum TestFramework {
+    /// GoogleTest, used by `cxx_test`.
+    Gtest,
+    /// The libtest harness, used by `rust_test`.
+    Rust,
+    /// The unittest main of the prelude, used by `python_test`.
+    Pyunit,
+    Pytest,
+}
+
+impl TestFramework {
+    pub(crate) fn from_test_type(test_type: &str) -> Option<Self> {
+        match test_type {
+            "gtest" => Some(Self::Gtest),
+            "rust" => Some(Self::Rust),
+            "pyunit" => Some(Self::Pyunit),
+            "pytest" => Some(Self::Pytest),
+            _ => None,
+        }
+    }
+
+    /// Arguments making the test binary print its test cases instead of running them.
+    pub(crate) fn list_args(self) -> Vec<String> {
+        let args: &[&str] = match self {
+            Self::Gtest => &["--gtest_list_tests"],
+            Self::Rust => &["--list"],
+            Self::Pyunit => &["--list-tests", "--list-format=buck"],
+            Self::Pytest => &["--collect-only", "-q"],
+        };
+        args.iter().map(|arg| (*arg).to_owned()).collect()
+    }
+
+    /// Arguments making the test binary only run this test case.
+    pub(crate) fn run_args(self, case: &str) -> Vec<String> {
+        match self {
+            Self::Gtest => vec![format!("--gtest_filter={}", case)],
+            Self::Rust => vec![case.to_owned(), "--exact".to_owned()],
+            // Listed as `module.Class#method`, but selected as `module.Class.method`.
+            Self::Pyunit => vec![case.replace('#', ".")],
+            Self::Pytest => vec![case.to_owned()],
+        }
+    }
+
+    /// Parses the output of the test binary run with `list_args` into test case names.
+    pub(crate) fn parse_list(self, stdout: &str) -> Vec<String> {
+        match self {
+            Self::Gtest => parse_gtest_list(stdout),
+            // `name: test`, or `name: benchmark` which we don't run.
+            Self::Rust => stdout
+                .lines()
+                .filter_map(|line| line.strip_suffix(": test"))
+                .map(str::to_owned)
+                .collect(),
+            Self::Pyunit => stdout
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty())
+                .map(str::to_owned)
+                .collect(),
+            // Node ids, then a summary after an empty line.
+            Self::Pytest => stdout
+                .lines()
+                .map(str::trim)
+                .take_while(|line| !line.is_empty())
+                .filter(|line| line.contains("::"))
+                .map(str::to_owned)
+                .collect(),
+        }
+    }
+}
+
+/// GoogleTest prints each suite followed by its indented tests, for example:
+///
+/// ```text
+/// Suite.
+///   Case
+/// Typed/0.  # TypeParam = int
+///   Case
+/// ```
+fn parse_gtest_list(stdout: &str) -> Vec<String> {
+    // Type and value parameters are printed in comments.
+    fn strip_comment(line: &str) -> &str {
+        line.split_once('#').map_or(line, |(name, _)| name).trim()
+    }
+
+    let mut suite = None;
+    let mut cases = Vec::new();
+    for line in stdout.lines() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        if line.starts_with(char::is_whitespace) {
+            if let Some(suite) = suite {
+                cases.push(format!("{}{}", suite, strip_comment(line)));
+            }
+        } else {
+            // Anything else GoogleTest or the test prints before the list.
+            suite = Some(strip_comment(line)).filter(|suite| suite.ends_with('.'));
+        }
+    }
+    cases
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_gtest_list() {
+        let stdout = "Running main() from gtest_main.cc\n\
+            Suite.\n  First\n  Second\n\
+            Typed/0.  # TypeParam = int\n  Case\n\
+            Values/Suite.\n  Case/0  # GetParam() = 1\n";
+        assert_eq!(
+            vec![
+                "Suite.First",
+                "Suite.Second",
+                "Typed/0.Case",
+                "Values/Suite.Case/0"
+            ],
+            TestFramework::Gtest.parse_list(stdout)
+        );
+    }
+
+    #[test]
+    fn test_parse_rust_list() {
+        let stdout = "tests::first: test\ntests::bench: benchmark\nsrc/lib.rs - f (line 3): test\n";
+        assert_eq!(
+            vec!["tests::first", "src/lib.rs - f (line 3)"],
+            TestFramework::Rust.parse_list(stdout)
+        );
+    }
+
+    #[test]
+    fn test_run_args() {
+        assert_eq!(
+            vec!["--gtest_filter=Suite.Case"],
+            TestFramework::Gtest.run_args("Suite.Case")
+        );
+        assert_eq!(
+            vec!["tests::first", "--exact"],
+            TestFramework::Rust.run_args("tests::first")
+        );
+        assert_eq!(
+            vec!["tests.test_a.Class.test_one"],
+            TestFramework::Pyunit.run_args("tests.test_a.Class#test_one")
+        );
+        assert_eq!(
+            vec!["tests/test_a.py::Class::test_two"],
+            TestFramework::Pytest.run_args("tests/test_a.py::Class::test_two")
+        );
+    }
+
+    #[test]
+    fn test_parse_pytest_list() {
+        let stdout = "tests/test_a.py::test_one\ntests/test_a.py::Class::test_two\n\n\
+            2 tests collected in 0.01s\n";
+        assert_eq!(
+            vec![
+                "tests/test_a.py::test_one",
+                "tests/test_a.py::Class::test_two"
+            ],
+            TestFramework::Pytest.parse_list(stdout)
+        );
+    }
+}
@@ -11,6 +11,7 @@
 
 mod config;
 mod executor;
+mod framework;
 mod runner;
 mod service;
 pub mod tcp;
@@ -7,16 +7,23 @@
  * of this source tree.
  */
 
+use std::collections::HashMap;
+use std::fs;
+
 use anyhow::Context;
+use buck2_core::fs::paths::forward_rel_path::ForwardRelativePathBuf;
 use buck2_test_api::data::ArgValue;
 use buck2_test_api::data::ArgValueContent;
 use buck2_test_api::data::ConfiguredTargetHandle;
+use buck2_test_api::data::DeclaredOutput;
 use buck2_test_api::data::DisplayMetadata;
 use buck2_test_api::data::ExecuteResponse;
 use buck2_test_api::data::ExecutionResult2;
 use buck2_test_api::data::ExecutionStatus;
+use buck2_test_api::data::ExecutionStream;
 use buck2_test_api::data::ExternalRunnerSpec;
 use buck2_test_api::data::ExternalRunnerSpecValue;
+use buck2_test_api::data::Output;
 use buck2_test_api::data::RequiredLocalResources;
 use buck2_test_api::data::TestResult;
 use buck2_test_api::data::TestStatus;
@@ -24,11 +31,15 @@ use buck2_test_api::grpc::TestOrchestratorClient;
 use clap::Parser;
 use futures::channel::mpsc::UnboundedReceiver;
 use futures::StreamExt;
+use futures::TryStreamExt;
 use host_sharing::HostSharingRequirements;
+use host_sharing::WeightClass;
 use parking_lot::Mutex;
+use sorted_vector_map::SortedVectorMap;
 
 use crate::config::Config;
 use crate::config::EnvValue;
+use crate::framework::TestFramework;
 
 pub type SpecReceiver = UnboundedReceiver<ExternalRunnerSpec>;
 
@@ -70,30 +81,9 @@ impl Buck2TestRunner {
         }
         let run_verdict = receiver
             .map(async move |spec| {
-                let name = format!(
-                    "{}//{}:{}",
-                    spec.target.cell, spec.target.package, spec.target.target
-                );
-                let target_handle = spec.target.handle.to_owned();
-
-                let execution_response = self
-                    .execute_test_from_spec(spec)
+                self.run_spec(spec)
                     .await
-                    .expect("Test execution request failed");
-
-                let execution_result = match execution_response {
-                    ExecuteResponse::Result(r) => r,
-                    ExecuteResponse::Cancelled => return TestStatus::OMITTED,
-                };
-
-                let test_result = get_test_result(name, target_handle, execution_result);
-                let test_status = test_result.status.clone();
-
-                self.report_test_result(test_result)
-                    .await
-                    .expect("Test result reporting failed");
-
-                test_status
+                    .expect("Test execution request failed")
             })
             // Use an arbitrarily large buffer -- execution throttling will be handled by the Buck2
             // executor, so no need to hold back on requests here.
@@ -101,9 +91,17 @@ impl Buck2TestRunner {
             // If any individual test failed, consider the entire run to have failed.
             .fold(
                 RunVerdict::Pass,
-                async move |mut run_verdict, test_status| {
-                    if test_status != TestStatus::PASS {
-                        run_verdict = RunVerdict::Fail;
+                async move |mut run_verdict, test_statuses| {
+                    for test_status in test_statuses {
+                        let passed = match test_status {
+                            TestStatus::PASS => true,
+                            // Flaky tests found by stress runs are what the user was looking for.
+                            TestStatus::FLAKY => self.config.stress_runs.is_none(),
+                            _ => false,
+                        };
+                        if !passed {
+                            run_verdict = RunVerdict::Fail;
+                        }
                     }
                     run_verdict
                 },
@@ -115,21 +113,152 @@ impl Buck2TestRunner {
             .await
     }
 
+    /// Runs the tests of a target, reports their results and returns their statuses. When the
+    /// runner knows the test framework, each selected test case is run on its own, otherwise the
+    /// whole target is run as a single test.
+    async fn run_spec(&self, spec: ExternalRunnerSpec) -> anyhow::Result<Vec<TestStatus>> {
+        let framework = match TestFramework::from_test_type(&spec.test_type) {
+            Some(framework) => framework,
+            None => return Ok(vec![self.run_and_report(spec, None).await?]),
+        };
+
+        let execution_result = match self
+            .execute_test_from_spec(spec.clone(), framework.list_args(), None)
+            .await?
+        {
+            ExecuteResponse::Result(r) => r,
+            ExecuteResponse::Cancelled => return Ok(vec![TestStatus::OMITTED]),
+        };
+        let ExecutionStream::Inline(stdout) = &execution_result.stdout;
+        let listed = framework.parse_list(&String::from_utf8_lossy(stdout));
+        let listing = get_test_result(target_name(&spec), spec.target.handle, execution_result);
+        // A test binary which lists no test cases most likely doesn't follow the conventions of
+        // its framework, rather than have no tests.
+        let listing_error = if listing.status != TestStatus::PASS {
+            Some("Listing the test cases failed")
+        } else if listed.is_empty() {
+            Some("Listing the test cases found none")
+        } else {
+            None
+        };
+        if let Some(listing_error) = listing_error {
+            self.report_test_result(TestResult {
+                status: TestStatus::LISTING_FAILED,
+                msg: Some(listing_error.to_owned()),
+                ..listing
+            })
+            .await?;
+            return Ok(vec![TestStatus::LISTING_FAILED]);
+        }
+
+        let cases = listed
+            .into_iter()
+            .filter(|case| self.config.is_selected(&target_name(&spec), case))
+            .collect::<Vec<_>>();
+        self.orchestrator_client
+            .report_tests_discovered(
+                spec.target.handle,
+                spec.target.target.clone(),
+                cases.clone(),
+            )
+            .await?;
+
+        futures::stream::iter(cases)
+            .map(|name| self.run_and_report(spec.clone(), Some(TestCase { framework, name })))
+            .buffer_unordered(10000)
+            .try_collect()
+            .await
+    }
+
+    /// Runs a test target or a test case, reports its result and returns its status.
+    async fn run_and_report(
+        &self,
+        spec: ExternalRunnerSpec,
+        case: Option<TestCase>,
+    ) -> anyhow::Result<TestStatus> {
+        Ok(match self.run_test(spec, case).await? {
+            Some(test_result) => {
+                let test_status = test_result.status.clone();
+                self.report_test_result(test_result).await?;
+                test_status
+            }
+            None => TestStatus::OMITTED,
+        })
+    }
+
+    /// Runs a test as many times as configured, and returns the combined result, or `None` if
+    /// the execution was cancelled.
+    async fn run_test(
+        &self,
+        spec: ExternalRunnerSpec,
+        case: Option<TestCase>,
+    ) -> anyhow::Result<Option<TestResult>> {
+        let target_handle = spec.target.handle.to_owned();
+        let (name, args, testcases) = match case {
+            Some(TestCase { framework, name }) => {
+                (name.clone(), framework.run_args(&name), vec![name])
+            }
+            None => (target_name(&spec), Vec::new(), Vec::new()),
+        };
+
+        let mut runs = Vec::new();
+        loop {
+            let execution_result = match self
+                .execute_test_from_spec(spec.clone(), args.clone(), Some(testcases.clone()))
+                .await?
+            {
+                ExecuteResponse::Result(r) => r,
+                ExecuteResponse::Cancelled => return Ok(None),
+            };
+            let test_result = get_test_result(name.clone(), target_handle, execution_result);
+            let run_again = self
+                .config
+                .should_run_again(&test_result.status, runs.len() as u32 + 1);
+            runs.push(test_result);
+            if !run_again {
+                break;
+            }
+        }
+        Ok(Some(combine_runs(runs)))
+    }
+
+    /// Executes the test binary of the spec, with `args` after the configured arguments, to run
+    /// `testcases`, or to list the test cases if `None`.
     async fn execute_test_from_spec(
         &self,
         spec: ExternalRunnerSpec,
+        args: Vec<String>,
+        testcases: Option<Vec<String>>,
     ) -> anyhow::Result<ExecuteResponse> {
-        let display_metadata = DisplayMetadata::Testing {
-            suite: spec.target.target,
-            testcases: Vec::new(),
+        let timeout = self.config.timeout(spec.timeout);
+        let host_sharing_requirements = host_sharing_requirements(&spec);
+        // Listings don't use the local resources of the test.
+        let required_local_resources = RequiredLocalResources {
+            resources: match testcases {
+                Some(_) => spec.local_resources.clone(),
+                None => Vec::new(),
+            },
+        };
+        let display_metadata = match testcases {
+            Some(testcases) => DisplayMetadata::Testing {
+                suite: spec.target.target,
+                testcases,
+            },
+            None => DisplayMetadata::Listing(spec.target.target),
         };
 
-        let config_args = self.config.test_arg.iter().map(|arg| ArgValue {
-            content: ArgValueContent::ExternalRunnerSpecValue(ExternalRunnerSpecValue::Verbatim(
-                arg.to_owned(),
-            )),
-            format: None,
-        });
+        let config_args = self
+            .config
+            .test_arg
+            .iter()
+            .cloned()
+            .chain(args)
+            .map(|arg| ArgValue {
+                content: ArgValueContent::ExternalRunnerSpecValue(
+                    ExternalRunnerSpecValue::Verbatim(arg),
+                ),
+                format: None,
+            });
 
         let command = spec
             .command
@@ -141,36 +270,14 @@ impl Buck2TestRunner {
             .chain(config_args)
             .collect();
 
-        let config_env = self.config.env.iter().map(|EnvValue { name, value }| {
-            (
-                name.to_owned(),
-                ArgValue {
-                    content: ArgValueContent::ExternalRunnerSpecValue(
-                        ExternalRunnerSpecValue::Verbatim(value.to_owned()),
-                    ),
-                    format: None,
-                },
-            )
-        });
-
-        let env = spec
-            .env
-            .into_iter()
-            .map(|(key, value)| {
-                (
-                    key,
-                    ArgValue {
-                        content: ArgValueContent::ExternalRunnerSpecValue(value),
-                        format: None,
-                    },
-                )
-            })
-            .chain(config_env)
-            .collect();
+        // Tests can write files to be kept, such as screenshots or logs, to this directory.
+        let outputs_dir = DeclaredOutput {
+            name: ForwardRelativePathBuf::unchecked_new("test_outputs".to_owned()),
+        };
+        let env = test_env(&outputs_dir, spec.env, &self.config.env);
 
         let target_handle = spec.target.handle;
-        let host_sharing_requirements = HostSharingRequirements::default();
-        let pre_create_dirs = Vec::new();
+        let pre_create_dirs = vec![outputs_dir];
         let executor_override = None;
 
         self.orchestrator_client
@@ -179,11 +286,11 @@ impl Buck2TestRunner {
                 target_handle,
                 command,
                 env,
-                self.config.timeout,
+                timeout,
                 host_sharing_requirements,
                 pre_create_dirs,
                 executor_override,
-                RequiredLocalResources { resources: vec![] },
+                required_local_resources,
             )
             .await
     }
@@ -195,6 +302,77 @@ impl Buck2TestRunner {
     }
 }
 
+/// A test case of a test target, run on its own.
+struct TestCase {
+    framework: TestFramework,
+    name: String,
+}
+
+/// The environment of a test: `TEST_OUTPUTS_DIR`, then the `env` of the target, then the variables
+/// passed with `--env`, each overriding the variables of the same name set before.
+fn test_env(
+    outputs_dir: &DeclaredOutput,
+    spec_env: HashMap<String, ExternalRunnerSpecValue>,
+    config_env: &[EnvValue],
+) -> SortedVectorMap<String, ArgValue> {
+    let mut env = SortedVectorMap::new();
+    env.insert(
+        "TEST_OUTPUTS_DIR".to_owned(),
+        ArgValue {
+            content: ArgValueContent::DeclaredOutput(outputs_dir.clone()),
+            format: None,
+        },
+    );
+    for (key, value) in spec_env {
+        env.insert(
+            key,
+            ArgValue {
+                content: ArgValueContent::ExternalRunnerSpecValue(value),
+                format: None,
+            },
+        );
+    }
+    for EnvValue { name, value } in config_env {
+        env.insert(
+            name.to_owned(),
+            ArgValue {
+                content: ArgValueContent::ExternalRunnerSpecValue(
+                    ExternalRunnerSpecValue::Verbatim(value.to_owned()),
+                ),
+                format: None,
+            },
+        );
+    }
+    env
+}
+
+fn target_name(spec: &ExternalRunnerSpec) -> String {
+    format!(
+        "{}//{}:{}",
+        spec.target.cell, spec.target.package, spec.target.target
+    )
+}
+
+/// The host sharing requirements of a test, from its labels: tests labelled `exclusive` run alone,
+/// `heavyweight` ones count as four tests, and the executions of a `serialize` test never overlap,
+/// for example when its test cases run in parallel.
+fn host_sharing_requirements(spec: &ExternalRunnerSpec) -> HostSharingRequirements {
+    let has_label = |label: &str| spec.labels.iter().any(|l| l == label);
+    if has_label("exclusive") {
+        return HostSharingRequirements::ExclusiveAccess;
+    }
+    let weight = if has_label("heavyweight") {
+        WeightClass::Permits(4)
+    } else {
+        WeightClass::Permits(1)
+    };
+    if has_label("serialize") {
+        HostSharingRequirements::OnePerToken(target_name(spec), weight)
+    } else {
+        HostSharingRequirements::Shared(weight)
+    }
+}
+
 fn get_test_result(
     name: String,
     target: ConfiguredTargetHandle,
@@ -207,16 +385,82 @@ fn get_test_result(
         },
         ExecutionStatus::TimedOut { .. } => TestStatus::TIMEOUT,
     };
+    // Only report the outputs the test actually wrote to.
+    let artifacts = execution_result
+        .outputs
+        .values()
+        .filter_map(|output| match output {
+            Output::LocalPath(path) => {
+                let written = fs::read_dir(path).map_or(false, |mut d| d.next().is_some());
+                written.then(|| path.to_string())
+            }
+        })
+        .collect();
     TestResult {
         target,
         name,
         status,
-        msg: None,
+        msg: execution_result
+            .execution_details
+            .cached
+            .then(|| "Cached".to_owned()),
         duration: Some(execution_result.execution_time),
         details: format!(
             "---- STDOUT ----\n{:?}\n---- STDERR ----\n{:?}\n",
             execution_result.stdout, execution_result.stderr
         ),
+        artifacts,
+    }
+}
+
+/// Combines the results of running a test several times: a test which both passed and failed is
+/// flaky, otherwise the result is that of the last run.
+fn combine_runs(mut runs: Vec<TestResult>) -> TestResult {
+    let passed = runs.iter().filter(|r| r.status == TestStatus::PASS).count();
+    let mut last = runs.pop().expect("A test runs at least once");
+    if runs.is_empty() {
+        return last;
+    }
+    let total = runs.len() + 1;
+    let artifacts = runs
+        .iter()
+        .flat_map(|r| r.artifacts.iter().cloned())
+        .chain(last.artifacts.iter().cloned())
+        .collect();
+    let duration = runs
+        .iter()
+        .filter_map(|r| r.duration)
+        .chain(last.duration)
+        .sum();
+    if passed == 0 || passed == total {
+        let verb = if passed == 0 { "Failed" } else { "Passed" };
+        last.msg = Some(format!("{} all {} runs", verb, total));
+        last.duration = Some(duration);
+        last.artifacts = artifacts;
+        return last;
+    }
+
+    runs.push(last);
+    let mut details = String::new();
+    for (i, run) in runs.iter().enumerate() {
+        if run.status != TestStatus::PASS {
+            details.push_str(&format!(
+                "==== RUN {} OF {}: {:?} ====\n",
+                i + 1,
+                total,
+                run.status
+            ));
+            details.push_str(&run.details);
+        }
+    }
+    let first = runs.swap_remove(0);
+    TestResult {
+        status: TestStatus::FLAKY,
+        msg: Some(format!("Passed {} of {} runs", passed, total)),
+        duration: Some(duration),
+        details,
+        artifacts,
+        ..first
     }
 }
 
@@ -234,3 +478,130 @@ impl RunVerdict {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use buck2_core::cells::name::CellName;
+    use buck2_test_api::data::ConfiguredTarget;
+
+    use super::*;
+
+    fn run(status: TestStatus) -> TestResult {
+        TestResult {
+            target: ConfiguredTargetHandle::from(0),
+            name: "root//foo:bar".to_owned(),
+            status,
+            msg: None,
+            duration: Some(Duration::from_secs(1)),
+            details: "output\n".to_owned(),
+            artifacts: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_combine_runs() {
+        let result = combine_runs(vec![run(TestStatus::PASS)]);
+        assert_eq!(TestStatus::PASS, result.status);
+        assert_eq!(None, result.msg);
+
+        let result = combine_runs(vec![run(TestStatus::FAIL), run(TestStatus::FAIL)]);
+        assert_eq!(TestStatus::FAIL, result.status);
+        assert_eq!(Some("Failed all 2 runs"), result.msg.as_deref());
+        assert_eq!(Some(Duration::from_secs(2)), result.duration);
+
+        let result = combine_runs(vec![
+            run(TestStatus::FAIL),
+            run(TestStatus::TIMEOUT),
+            run(TestStatus::PASS),
+        ]);
+        assert_eq!(TestStatus::FLAKY, result.status);
+        assert_eq!(Some("Passed 1 of 3 runs"), result.msg.as_deref());
+        assert_eq!(
+            "==== RUN 1 OF 3: FAIL ====\noutput\n==== RUN 2 OF 3: TIMEOUT ====\noutput\n",
+            result.details
+        );
+    }
+
+    #[test]
+    fn test_env_precedence() {
+        let verbatim = |value: &str| ArgValue {
+            content: ArgValueContent::ExternalRunnerSpecValue(ExternalRunnerSpecValue::Verbatim(
+                value.to_owned(),
+            )),
+            format: None,
+        };
+        let outputs_dir = DeclaredOutput {
+            name: ForwardRelativePathBuf::unchecked_new("test_outputs".to_owned()),
+        };
+        let spec_env = HashMap::from_iter([
+            (
+                "LOG_LEVEL".to_owned(),
+                ExternalRunnerSpecValue::Verbatim("info".to_owned()),
+            ),
+            (
+                "HOME".to_owned(),
+                ExternalRunnerSpecValue::Verbatim("/home".to_owned()),
+            ),
+        ]);
+        let config_env = [EnvValue::new("LOG_LEVEL", "debug")];
+
+        let env = test_env(&outputs_dir, spec_env, &config_env);
+        assert_eq!(
+            vec!["HOME", "LOG_LEVEL", "TEST_OUTPUTS_DIR"],
+            env.keys().map(|k| k.as_str()).collect::<Vec<_>>()
+        );
+        // `--env` overrides the `env` of the target.
+        assert_eq!(Some(&verbatim("debug")), env.get("LOG_LEVEL"));
+        assert_eq!(Some(&verbatim("/home")), env.get("HOME"));
+        assert_eq!(
+            Some(&ArgValue {
+                content: ArgValueContent::DeclaredOutput(outputs_dir),
+                format: None,
+            }),
+            env.get("TEST_OUTPUTS_DIR")
+        );
+    }
+
+    #[test]
+    fn test_host_sharing_requirements() {
+        let spec = |labels: &[&str]| ExternalRunnerSpec {
+            target: ConfiguredTarget {
+                handle: ConfiguredTargetHandle::from(0),
+                cell: "root".to_owned(),
+                package: "foo".to_owned(),
+                target: "bar".to_owned(),
+                configuration: "cfg".to_owned(),
+                package_project_relative_path: ForwardRelativePathBuf::unchecked_new(
+                    "foo".to_owned(),
+                ),
+            },
+            test_type: "custom".to_owned(),
+            command: Vec::new(),
+            env: Default::default(),
+            labels: labels.iter().map(|l| (*l).to_owned()).collect(),
+            contacts: Vec::new(),
+            oncall: None,
+            working_dir_cell: CellName::testing_new("root"),
+            local_resources: Vec::new(),
+            timeout: None,
+        };
+
+        assert_eq!(
+            HostSharingRequirements::default(),
+            host_sharing_requirements(&spec(&[]))
+        );
+        assert_eq!(
+            HostSharingRequirements::ExclusiveAccess,
+            host_sharing_requirements(&spec(&["heavyweight", "exclusive"]))
+        );
+        assert_eq!(
+            HostSharingRequirements::OnePerToken(
+                "root//foo:bar".to_owned(),
+                WeightClass::Permits(4)
+            ),
+            host_sharing_requirements(&spec(&["serialize", "heavyweight"]))
+        );
+    }
+}
@@ -0,0 +1,32 @@
+# Copyright (c) Meta Platforms, Inc. and affiliates.
+#
+# This source code is licensed under both the MIT license found in the
+# LICENSE-MIT file in the root directory of this source tree and the Apache
+# License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+# of this source tree.
+
+"""A test using the database instance reserved for it in `DATABASE_ADDRESS`."""
+
+import os
+import socket
+import sys
+
+
+def main():
+    host, port = os.environ["DATABASE_ADDRESS"].rsplit(":", 1)
+    with socket.create_connection((host, int(port))) as conn, conn.makefile("rw") as f:
+
+        def request(line):
+            f.write(line + "\n")
+            f.flush()
+            return f.readline().strip()
+
+        value = str(os.getpid())
+        if request("SET owner " + value) != "OK":
+            sys.exit("SET failed")
+        if request("GET owner") != value:
+            sys.exit("GET returned the value of another test")
+
+
+if __name__ == "__main__":
+    main()
@@ -0,0 +1,46 @@
+# Copyright (c) Meta Platforms, Inc. and affiliates.
+#
+# This source code is licensed under both the MIT license found in the
+# LICENSE-MIT file in the root directory of this source tree and the Apache
+# License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+# of this source tree.
+
+def _fake_database_impl(ctx: AnalysisContext) -> list[Provider]:
+    return [
+        DefaultInfo(),
+        LocalResourceInfo(
+            setup = cmd_args(["python3", ctx.attrs.server, "--instances", str(ctx.attrs.instances)]),
+            resource_env_vars = {"DATABASE_ADDRESS": "address"},
+        ),
+    ]
+
+# A stand-in for a database server: a pool of key-value stores listening on localhost.
+fake_database = rule(
+    impl = _fake_database_impl,
+    attrs = {
+        "instances": attrs.int(default = 2),
+        "server": attrs.source(),
+    },
+)
+
+def _database_test_impl(ctx: AnalysisContext) -> list[Provider]:
+    return [
+        DefaultInfo(),
+        ExternalRunnerTestInfo(
+            type = "custom",
+            command = ["python3", ctx.attrs.test],
+            labels = ctx.attrs.labels,
+            local_resources = {
+                "database": ctx.attrs.database.label,
+            },
+        ),
+    ]
+
+database_test = rule(
+    impl = _database_test_impl,
+    attrs = {
+        "database": attrs.dep(providers = [LocalResourceInfo]),
+        "labels": attrs.list(attrs.string(), default = []),
+        "test": attrs.source(),
+    },
+)
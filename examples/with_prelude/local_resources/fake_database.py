@@ -0,0 +1,91 @@
+# Copyright (c) Meta Platforms, Inc. and affiliates.
+#
+# This source code is licensed under both the MIT license found in the
+# LICENSE-MIT file in the root directory of this source tree and the Apache
+# License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+# of this source tree.
+
+"""
+The setup command of a `LocalResourceInfo`: starts a server in the background
+and prints the pool of resources it provides as JSON.
+
+The server runs one in-memory key-value store per instance, each listening on
+its own port. The protocol is a line per request, `SET <key> <value>` or
+`GET <key>`, answered by `OK` or the value.
+"""
+
+import argparse
+import json
+import selectors
+import signal
+import socket
+import subprocess
+import sys
+
+
+def serve(fds):
+    listeners = [socket.socket(fileno=fd) for fd in fds]
+    stores = {listener.fileno(): {} for listener in listeners}
+    selector = selectors.DefaultSelector()
+    for listener in listeners:
+        selector.register(listener, selectors.EVENT_READ, listener.fileno())
+
+    # Buck2 sends SIGTERM once the tests using the resources are done.
+    signal.signal(signal.SIGTERM, lambda *_: sys.exit(0))
+
+    while True:
+        for key, _ in selector.select():
+            conn, _ = key.fileobj.accept()
+            with conn, conn.makefile("rw") as f:
+                handle(stores[key.data], f)
+
+
+def handle(store, f):
+    for line in f:
+        request = line.split()
+        if request[:1] == ["SET"] and len(request) == 3:
+            store[request[1]] = request[2]
+            f.write("OK\n")
+        elif request[:1] == ["GET"] and len(request) == 2:
+            f.write(store.get(request[1], "") + "\n")
+        else:
+            f.write("ERROR\n")
+        f.flush()
+
+
+def main():
+    parser = argparse.ArgumentParser()
+    parser.add_argument("--instances", type=int, default=1)
+    parser.add_argument("--serve", type=int, nargs="*", help=argparse.SUPPRESS)
+    args = parser.parse_args()
+
+    if args.serve is not None:
+        serve(args.serve)
+        return
+
+    listeners = []
+    for _ in range(args.instances):
+        listener = socket.socket()
+        listener.bind(("127.0.0.1", 0))
+        listener.listen()
+        listeners.append(listener)
+    fds = [listener.fileno() for listener in listeners]
+
+    # The server outlives this command, so it must not hold on to its output.
+    server = subprocess.Popen(
+        [sys.executable, __file__, "--serve", *map(str, fds)],
+        pass_fds=fds,
+        stdin=subprocess.DEVNULL,
+        stdout=subprocess.DEVNULL,
+        stderr=subprocess.DEVNULL,
+        start_new_session=True,
+    )
+
+    resources = [
+        {"address": "{}:{}".format(*listener.getsockname())} for listener in listeners
+    ]
+    print(json.dumps({"pid": server.pid, "resources": resources}))
+
+
+if __name__ == "__main__":
+    main()
@@ -64,6 +64,7 @@ use crate::values::layout::value_not_special::FrozenValueNotSpecial;
 use crate::values::list::ListRef;
 use crate::values::string::interpolation::parse_percent_s_one;
 use crate::values::types::bool::StarlarkBool;
+use crate::values::types::bytes::StarlarkBytes;
 use crate::values::types::dict::Dict;
 use crate::values::types::ellipsis::Ellipsis;
 use crate::values::types::float::StarlarkFloat;
@@ -1033,6 +1034,7 @@ impl AstLiteralCompile for AstLiteral {
             AstLiteral::Int(i) => heap.alloc(StarlarkInt::from(i.node.clone())),
             AstLiteral::Float(f) => heap.alloc(f.node),
             AstLiteral::String(x) => heap.alloc(x.node.as_str()),
+            AstLiteral::Bytes(x) => heap.alloc(StarlarkBytes::new(&x.node)),
             AstLiteral::Ellipsis => heap.alloc(Ellipsis),
         }
     }
@@ -62,6 +62,9 @@ use crate::eval::runtime::slots::LocalSlotId;
 use crate::values::dict::Dict;
 use crate::values::dict::DictMut;
 use crate::values::dict::DictRef;
+use crate::values::set::Set;
+use crate::values::set::SetMut;
+use crate::values::set::SetRef;
 use crate::values::types::list::value::ListData;
 use crate::values::typing::type_compiled::compiled::TypeCompiled;
 use crate::values::FrozenHeap;
@@ -556,6 +559,7 @@ pub(crate) fn bit_or_assign<'v>(
     heap: &'v Heap,
 ) -> anyhow::Result<Value<'v>> {
     // The Starlark spec says dict |= mutates, while nothing else does.
+    // Sets, which are an extension, mutate too, like in Python.
     // When mutating, be careful if they alias, so we don't have `lhs`
     // mutably borrowed when we iterate over `rhs`, as they might alias.
 
@@ -582,6 +586,26 @@ pub(crate) fn bit_or_assign<'v>(
             }
         }
         Ok(lhs)
+    } else if Set::is_set_type(lhs_ty) {
+        let mut set = SetMut::from_value(lhs)?;
+        if lhs.ptr_eq(rhs) {
+            // Nothing to do as union is idempotent
+        } else {
+            let rhs = SetRef::from_value(rhs).map_or_else(
+                || {
+                    ValueError::unsupported_owned(
+                        lhs_aref.vtable().type_name,
+                        "|=",
+                        Some(rhs.get_type()),
+                    )
+                },
+                Ok,
+            )?;
+            for x in rhs.iter_hashed() {
+                set.insert_hashed(x);
+            }
+        }
+        Ok(lhs)
     } else {
         lhs_aref.bit_or(rhs, heap)
     }
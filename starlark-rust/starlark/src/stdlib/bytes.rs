@@ -0,0 +1,80 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! The `bytes()` constructor and the methods of the `bytes` type.
+
+use starlark_derive::starlark_module;
+
+use crate as starlark;
+use crate::environment::GlobalsBuilder;
+use crate::environment::MethodsBuilder;
+use crate::values::bytes::StarlarkBytes;
+use crate::values::list::AllocList;
+use crate::values::Heap;
+use crate::values::UnpackValue;
+use crate::values::Value;
+
+#[starlark_module]
+pub(crate) fn register_bytes(builder: &mut GlobalsBuilder) {
+    /// [bytes](
+    /// https://github.com/bazelbuild/starlark/blob/master/spec.md#bytes
+    /// ): converts its argument to bytes.
+    ///
+    /// A string is encoded as UTF-8, bytes are returned unchanged,
+    /// and an iterable of ints in the range 0..256 gives the bytes with those values.
+    ///
+    /// ```
+    /// # starlark::assert::all_true(r#"
+    /// len(bytes("hé")) == 3
+    /// bytes([104, 105]) == bytes("hi")
+    /// bytes(bytes("hi")) == bytes("hi")
+    /// # "#);
+    /// ```
+    #[starlark(as_type = StarlarkBytes, speculative_exec_safe)]
+    fn bytes<'v>(
+        #[starlark(require = pos)] x: Value<'v>,
+        heap: &'v Heap,
+    ) -> anyhow::Result<StarlarkBytes> {
+        if let Some(s) = x.unpack_str() {
+            Ok(StarlarkBytes::new(s.as_bytes()))
+        } else if let Some(b) = StarlarkBytes::from_value(x) {
+            Ok(b.clone())
+        } else {
+            let mut res = Vec::new();
+            for v in x.iterate(heap)? {
+                res.push(StarlarkBytes::byte(i32::unpack_param(v)?)?);
+            }
+            Ok(StarlarkBytes::from(res))
+        }
+    }
+}
+
+#[starlark_module]
+pub(crate) fn bytes_methods(registry: &mut MethodsBuilder) {
+    /// `b.elems()` returns a list of the bytes of `b` as ints in the range 0..256.
+    ///
+    /// ```
+    /// # starlark::assert::is_true(r#"
+    /// bytes("hi").elems() == [104, 105]
+    /// # "#);
+    /// ```
+    fn elems(this: &StarlarkBytes) -> anyhow::Result<AllocList<Vec<i32>>> {
+        Ok(AllocList(
+            this.as_bytes().iter().map(|b| *b as i32).collect(),
+        ))
+    }
+}
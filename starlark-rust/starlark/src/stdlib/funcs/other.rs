@@ -29,6 +29,7 @@ use crate as starlark;
 use crate::environment::GlobalsBuilder;
 use crate::eval::Evaluator;
 use crate::values::bool::StarlarkBool;
+use crate::values::bytes::StarlarkBytes;
 use crate::values::float::StarlarkFloat;
 use crate::values::function::SpecialBuiltinFunction;
 use crate::values::int::PointerI32;
@@ -393,13 +394,24 @@ pub(crate) fn register_other(builder: &mut GlobalsBuilder) {
     /// # "#);
     /// ```
     #[starlark(speculative_exec_safe)]
-    fn hash(#[starlark(require = pos)] a: &str) -> anyhow::Result<i32> {
+    fn hash(#[starlark(require = pos)] a: Either<&str, &StarlarkBytes>) -> anyhow::Result<i64> {
         // From the starlark spec:
         // > the hash function for strings is the same as that implemented by java.lang.String.hashCode,
         // > a simple polynomial accumulator over the UTF-16 transcoding of the string:
         // > `s[0]*31^(n-1) + s[1]*31^(n-2) + ... + s[n-1]`
         // As per spec the function should only support string and bytes types.
-        // We don't have support for bytes, so parameter is forced to be a string.
+        // Bytes are hashed with 32-bit FNV-1a like starlark-go, which returns it unsigned.
+        let a = match a {
+            Either::Left(a) => a,
+            Either::Right(b) => {
+                return Ok(b
+                    .as_bytes()
+                    .iter()
+                    .fold(2166136261u32, |hash: u32, b: &u8| {
+                        (hash ^ *b as u32).wrapping_mul(16777619)
+                    }) as i64);
+            }
+        };
 
         // Most strings are ASCII strings, try them first.
         #[allow(clippy::never_loop)]
@@ -411,12 +423,12 @@ pub(crate) fn register_other(builder: &mut GlobalsBuilder) {
                 }
                 hash = hash.wrapping_mul(31i32).wrapping_add(b as i32);
             }
-            return Ok(hash);
+            return Ok(hash as i64);
         }
 
         Ok(a.encode_utf16().fold(0i32, |hash: i32, c: u16| {
             31i32.wrapping_mul(hash).wrapping_add(c as i32)
-        }))
+        }) as i64)
     }
 
     /// [int](
@@ -791,6 +803,8 @@ pub(crate) fn register_other(builder: &mut GlobalsBuilder) {
     /// ): formats its argument as a string.
     ///
     /// If x is a string, the result is x (without quotation).
+    /// If x is bytes, the result is x decoded as UTF-8,
+    /// with invalid sequences replaced by U+FFFD.
     /// All other strings, such as elements of a list of strings, are
     /// double-quoted.
     ///
@@ -809,6 +823,11 @@ pub(crate) fn register_other(builder: &mut GlobalsBuilder) {
         if let Some(a) = StringValue::new(a) {
             // Special case that can avoid reallocating, but is equivalent.
             Ok(a)
+        } else if let Some(b) = StarlarkBytes::from_value(a) {
+            // Like Go Starlark, invalid UTF-8 is replaced with U+FFFD.
+            Ok(eval
+                .heap()
+                .alloc_str(&String::from_utf8_lossy(b.as_bytes())))
         } else {
             let mut s = eval.string_pool.alloc();
             a.collect_repr(&mut s);
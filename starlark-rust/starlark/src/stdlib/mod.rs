@@ -23,6 +23,7 @@ use dupe::Dupe;
 use crate::environment::GlobalsBuilder;
 
 pub(crate) mod breakpoint;
+pub(crate) mod bytes;
 pub(crate) mod call_stack;
 pub(crate) mod dict;
 pub(crate) mod extra;
@@ -31,6 +32,7 @@ pub(crate) mod internal;
 pub(crate) mod json;
 pub(crate) mod list;
 pub(crate) mod partial;
+pub(crate) mod set;
 pub(crate) mod string;
 pub(crate) mod structs;
 
@@ -60,6 +62,12 @@ pub enum LibraryExtension {
     RecordType,
     /// Definitions to support the `enum` type, the `enum()` constructor.
     EnumType,
+    /// Definitions to support the `set` type, the `set()` constructor.
+    SetType,
+    /// Definitions to support the `bytes` type, the `bytes()` constructor.
+    /// Usually used in conjunction with
+    /// [`Dialect::enable_bytes`](crate::syntax::Dialect::enable_bytes).
+    Bytes,
     /// A function `map(f, xs)` which applies `f` to each element of `xs` and returns the result.
     Map,
     /// A function `filter(f, xs)` which applies `f` to each element of `xs` and returns those for which `f` returns `True`.
@@ -97,8 +105,8 @@ impl LibraryExtension {
     pub(crate) fn all() -> &'static [Self] {
         use LibraryExtension::*;
         &[
-            StructType, RecordType, EnumType, Map, Filter, Partial, Debug, Print, Pprint,
-            Breakpoint, Json, Typing, Internal, CallStack,
+            StructType, RecordType, EnumType, SetType, Bytes, Map, Filter, Partial, Debug, Print,
+            Pprint, Breakpoint, Json, Typing, Internal, CallStack,
         ]
     }
 
@@ -109,6 +117,8 @@ impl LibraryExtension {
             StructType => structs::global(builder),
             RecordType => register_record(builder),
             EnumType => register_enum(builder),
+            SetType => set::register_set(builder),
+            Bytes => bytes::register_bytes(builder),
             Map => extra::map(builder),
             Filter => extra::filter(builder),
             Partial => partial::partial(builder),
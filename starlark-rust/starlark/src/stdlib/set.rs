@@ -0,0 +1,623 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! The `set()` constructor and the methods of the `set` type.
+
+use allocative::Allocative;
+use once_cell::sync::Lazy;
+use starlark_derive::starlark_module;
+
+use crate as starlark;
+use crate::codemap::Span;
+use crate::codemap::Spanned;
+use crate::collections::Hashed;
+use crate::environment::GlobalsBuilder;
+use crate::environment::MethodsBuilder;
+use crate::typing::error::TypingOrInternalError;
+use crate::typing::function::TyCustomFunctionImpl;
+use crate::typing::Arg;
+use crate::typing::Param;
+use crate::typing::Ty;
+use crate::typing::TyFunction;
+use crate::typing::TypingOracleCtx;
+use crate::values::error::ValueError;
+use crate::values::function::SpecialBuiltinFunction;
+use crate::values::none::NoneType;
+use crate::values::set::value::FrozenSet;
+use crate::values::set::Set;
+use crate::values::set::SetMut;
+use crate::values::set::SetRef;
+use crate::values::tuple::UnpackTuple;
+use crate::values::typing::StarlarkIter;
+use crate::values::Heap;
+use crate::values::Value;
+use crate::values::ValueOfUnchecked;
+
+#[derive(Allocative, Hash, Eq, PartialEq, Ord, PartialOrd, Clone, Debug)]
+struct SetType;
+
+impl TyCustomFunctionImpl for SetType {
+    fn has_type_attr(&self) -> bool {
+        true
+    }
+
+    fn validate_call(
+        &self,
+        span: Span,
+        args: &[Spanned<Arg>],
+        oracle: TypingOracleCtx,
+    ) -> Result<Ty, TypingOrInternalError> {
+        static SET: Lazy<TyFunction> = Lazy::new(|| {
+            TyFunction::new_with_type_attr(
+                vec![Param::pos_only(Ty::iter(Ty::any())).optional()],
+                Ty::any_set(),
+                Ty::any_set(),
+            )
+        });
+
+        oracle.validate_fn_call(span, &SET, args)?;
+
+        if let Some(arg) = args.get(0) {
+            // This is infallible after the check above.
+            if let Arg::Pos(arg_ty) = &arg.node {
+                // This is also infallible.
+                let item = oracle.iter_item(Spanned { span, node: arg_ty })?;
+                return Ok(Ty::set(item));
+            }
+        }
+
+        Ok(Ty::any_set())
+    }
+}
+
+/// The elements of an iterable as a set.
+fn to_set<'v>(iterable: Value<'v>, heap: &'v Heap) -> anyhow::Result<Set<'v>> {
+    let mut set = Set::default();
+    for x in iterable.iterate(heap)? {
+        set.insert_hashed(x.get_hashed()?);
+    }
+    Ok(set)
+}
+
+/// The elements of the iterables as sets.
+///
+/// Methods updating a set collect their arguments before borrowing the set mutably,
+/// because the arguments can be the set itself.
+fn to_sets<'v>(iterables: UnpackTuple<Value<'v>>, heap: &'v Heap) -> anyhow::Result<Vec<Set<'v>>> {
+    iterables
+        .items
+        .into_iter()
+        .map(|x| to_set(x, heap))
+        .collect()
+}
+
+#[starlark_module]
+pub(crate) fn register_set(globals: &mut GlobalsBuilder) {
+    /// `set(x)` returns a new set containing the elements of the iterable `x`,
+    /// in the order of their first occurrence. The elements must be hashable.
+    ///
+    /// With no argument, `set()` returns a new empty set.
+    ///
+    /// ```
+    /// # starlark::assert::all_true(r#"
+    /// len(set()) == 0
+    /// set([1, 2, 1]) == set([2, 1])
+    /// list(set([3, 1, 3, 2])) == [3, 1, 2]
+    /// str(set(["a"])) == 'set(["a"])'
+    /// # "#);
+    /// # starlark::assert::fail(r#"
+    /// set([[]]) # error: not hashable
+    /// # "#, r#"not hashable"#);
+    /// ```
+    #[starlark(
+    as_type = FrozenSet,
+    speculative_exec_safe,
+    special_builtin_function = SpecialBuiltinFunction::Set,
+    ty_custom_function = SetType,
+    )]
+    fn set<'v>(
+        #[starlark(require = pos)] a: Option<ValueOfUnchecked<'v, StarlarkIter<Value<'v>>>>,
+        heap: &'v Heap,
+    ) -> anyhow::Result<Set<'v>> {
+        match a {
+            Some(a) => to_set(a.get(), heap),
+            None => Ok(Set::default()),
+        }
+    }
+}
+
+#[starlark_module]
+pub(crate) fn set_methods(registry: &mut MethodsBuilder) {
+    /// `S.add(x)` adds `x` to the set S, and returns `None`.
+    /// Adding an element which is already in the set does nothing.
+    ///
+    /// `add` fails if `x` is unhashable, or if the set is frozen or has active iterators.
+    ///
+    /// ```
+    /// # starlark::assert::is_true(r#"
+    /// x = set([1])
+    /// x.add(2)
+    /// x.add(1)
+    /// x == set([1, 2])
+    /// # "#);
+    /// ```
+    fn add<'v>(
+        this: Value<'v>,
+        #[starlark(require = pos)] value: Value<'v>,
+    ) -> anyhow::Result<NoneType> {
+        let value = value.get_hashed()?;
+        SetMut::from_value(this)?.insert_hashed(value);
+        Ok(NoneType)
+    }
+
+    /// `S.clear()` removes all the elements of the set S and returns `None`.
+    /// It fails if the set is frozen or if there are active iterators.
+    ///
+    /// ```
+    /// # starlark::assert::is_true(r#"
+    /// x = set([1, 2])
+    /// x.clear()
+    /// x == set()
+    /// # "#);
+    /// ```
+    fn clear(this: Value) -> anyhow::Result<NoneType> {
+        SetMut::from_value(this)?.clear();
+        Ok(NoneType)
+    }
+
+    /// `S.difference(*others)` returns a new set with the elements of the set S
+    /// which are in none of the iterables `others`.
+    ///
+    /// ```
+    /// # starlark::assert::is_true(r#"
+    /// set([1, 2, 3, 4]).difference([1], (3, 5)) == set([2, 4])
+    /// # "#);
+    /// ```
+    #[starlark(speculative_exec_safe)]
+    fn difference<'v>(
+        this: SetRef<'v>,
+        #[starlark(args)] others: UnpackTuple<Value<'v>>,
+        heap: &'v Heap,
+    ) -> anyhow::Result<Set<'v>> {
+        let others = to_sets(others, heap)?;
+        let mut result = Set::clone(&this);
+        result.retain(|x| others.iter().all(|other| !other.contains_hashed(x)));
+        Ok(result)
+    }
+
+    /// `S.difference_update(*others)` removes from the set S the elements
+    /// of the iterables `others`, and returns `None`.
+    ///
+    /// ```
+    /// # starlark::assert::is_true(r#"
+    /// x = set([1, 2, 3, 4])
+    /// x.difference_update([1], (3, 5))
+    /// x == set([2, 4])
+    /// # "#);
+    /// ```
+    fn difference_update<'v>(
+        this: Value<'v>,
+        #[starlark(args)] others: UnpackTuple<Value<'v>>,
+        heap: &'v Heap,
+    ) -> anyhow::Result<NoneType> {
+        let others = to_sets(others, heap)?;
+        SetMut::from_value(this)?.retain(|x| others.iter().all(|other| !other.contains_hashed(x)));
+        Ok(NoneType)
+    }
+
+    /// `S.discard(x)` removes `x` from the set S if it is present, and returns `None`.
+    ///
+    /// `discard` fails if `x` is unhashable, or if the set is frozen or has active iterators.
+    ///
+    /// ```
+    /// # starlark::assert::is_true(r#"
+    /// x = set([1, 2])
+    /// x.discard(2)
+    /// x.discard(3)
+    /// x == set([1])
+    /// # "#);
+    /// ```
+    fn discard<'v>(
+        this: Value<'v>,
+        #[starlark(require = pos)] value: Value<'v>,
+    ) -> anyhow::Result<NoneType> {
+        let value = value.get_hashed()?;
+        SetMut::from_value(this)?.remove_hashed(value);
+        Ok(NoneType)
+    }
+
+    /// `S.intersection(*others)` returns a new set with the elements of the set S
+    /// which are in all of the iterables `others`.
+    ///
+    /// ```
+    /// # starlark::assert::is_true(r#"
+    /// set([1, 2, 3, 4]).intersection([1, 2, 3], (2, 3, 5)) == set([2, 3])
+    /// # "#);
+    /// ```
+    #[starlark(speculative_exec_safe)]
+    fn intersection<'v>(
+        this: SetRef<'v>,
+        #[starlark(args)] others: UnpackTuple<Value<'v>>,
+        heap: &'v Heap,
+    ) -> anyhow::Result<Set<'v>> {
+        let others = to_sets(others, heap)?;
+        let mut result = Set::clone(&this);
+        result.retain(|x| others.iter().all(|other| other.contains_hashed(x)));
+        Ok(result)
+    }
+
+    /// `S.intersection_update(*others)` removes from the set S the elements
+    /// which are not in all of the iterables `others`, and returns `None`.
+    ///
+    /// ```
+    /// # starlark::assert::is_true(r#"
+    /// x = set([1, 2, 3, 4])
+    /// x.intersection_update([1, 2, 3], (2, 3, 5))
+    /// x == set([2, 3])
+    /// # "#);
+    /// ```
+    fn intersection_update<'v>(
+        this: Value<'v>,
+        #[starlark(args)] others: UnpackTuple<Value<'v>>,
+        heap: &'v Heap,
+    ) -> anyhow::Result<NoneType> {
+        let others = to_sets(others, heap)?;
+        SetMut::from_value(this)?.retain(|x| others.iter().all(|other| other.contains_hashed(x)));
+        Ok(NoneType)
+    }
+
+    /// `S.isdisjoint(x)` returns `True` if the set S has no element in common
+    /// with the iterable `x`.
+    ///
+    /// ```
+    /// # starlark::assert::all_true(r#"
+    /// set([1, 2]).isdisjoint([3, 4])
+    /// not set([1, 2]).isdisjoint((2, 3))
+    /// # "#);
+    /// ```
+    #[starlark(speculative_exec_safe)]
+    fn isdisjoint<'v>(
+        this: SetRef<'v>,
+        #[starlark(require = pos)] other: Value<'v>,
+        heap: &'v Heap,
+    ) -> anyhow::Result<bool> {
+        for x in other.iterate(heap)? {
+            if this.contains_hashed(x.get_hashed()?) {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    /// `S.issubset(x)` returns `True` if all the elements of the set S
+    /// are in the iterable `x`.
+    ///
+    /// ```
+    /// # starlark::assert::all_true(r#"
+    /// set([1, 2]).issubset([3, 2, 1])
+    /// not set([1, 2]).issubset((2, 3))
+    /// # "#);
+    /// ```
+    #[starlark(speculative_exec_safe)]
+    fn issubset<'v>(
+        this: SetRef<'v>,
+        #[starlark(require = pos)] other: Value<'v>,
+        heap: &'v Heap,
+    ) -> anyhow::Result<bool> {
+        let other = to_set(other, heap)?;
+        Ok(this.iter_hashed().all(|x| other.contains_hashed(x)))
+    }
+
+    /// `S.issuperset(x)` returns `True` if all the elements of the iterable `x`
+    /// are in the set S.
+    ///
+    /// ```
+    /// # starlark::assert::all_true(r#"
+    /// set([1, 2, 3]).issuperset([2, 1])
+    /// not set([1, 2]).issuperset((2, 3))
+    /// # "#);
+    /// ```
+    #[starlark(speculative_exec_safe)]
+    fn issuperset<'v>(
+        this: SetRef<'v>,
+        #[starlark(require = pos)] other: Value<'v>,
+        heap: &'v Heap,
+    ) -> anyhow::Result<bool> {
+        for x in other.iterate(heap)? {
+            if !this.contains_hashed(x.get_hashed()?) {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    /// `S.pop()` removes the first element of the set S, in iteration order, and returns it.
+    ///
+    /// `pop` fails if the set is empty, frozen, or has active iterators.
+    ///
+    /// ```
+    /// # starlark::assert::is_true(r#"
+    /// x = set([3, 1])
+    /// # (
+    /// x.pop() == 3
+    /// # and
+    /// x.pop() == 1
+    /// # and
+    /// x == set()
+    /// # )"#);
+    /// ```
+    ///
+    /// Failure:
+    ///
+    /// ```
+    /// # starlark::assert::fail(r#"
+    /// set().pop()   # error: empty set
+    /// # "#, "empty set");
+    /// ```
+    fn pop<'v>(this: Value<'v>) -> anyhow::Result<Value<'v>> {
+        let mut this = SetMut::from_value(this)?;
+        match this.iter_hashed().next() {
+            Some(x) => {
+                this.remove_hashed(x);
+                Ok(*x.key())
+            }
+            None => Err(anyhow::anyhow!("Cannot .pop() on an empty set")),
+        }
+    }
+
+    /// `S.remove(x)` removes `x` from the set S, and returns `None`.
+    ///
+    /// `remove` fails if `x` is not in the set, unhashable, or if the set is frozen
+    /// or has active iterators.
+    ///
+    /// ```
+    /// # starlark::assert::is_true(r#"
+    /// x = set([1, 2])
+    /// x.remove(2)
+    /// x == set([1])
+    /// # "#);
+    /// ```
+    ///
+    /// Failure:
+    ///
+    /// ```
+    /// # starlark::assert::fail(r#"
+    /// set([1]).remove(2)   # error: not found
+    /// # "#, "not found");
+    /// ```
+    fn remove<'v>(
+        this: Value<'v>,
+        #[starlark(require = pos)] value: Value<'v>,
+    ) -> anyhow::Result<NoneType> {
+        let hashed = value.get_hashed()?;
+        if SetMut::from_value(this)?.remove_hashed(hashed) {
+            Ok(NoneType)
+        } else {
+            Err(ValueError::KeyNotFound(value.to_repr()).into())
+        }
+    }
+
+    /// `S.symmetric_difference(x)` returns a new set with the elements which are
+    /// either in the set S or in the iterable `x`, but not in both.
+    ///
+    /// ```
+    /// # starlark::assert::is_true(r#"
+    /// set([1, 2, 3]).symmetric_difference([4, 3]) == set([1, 2, 4])
+    /// # "#);
+    /// ```
+    #[starlark(speculative_exec_safe)]
+    fn symmetric_difference<'v>(
+        this: SetRef<'v>,
+        #[starlark(require = pos)] other: Value<'v>,
+        heap: &'v Heap,
+    ) -> anyhow::Result<Set<'v>> {
+        let other = to_set(other, heap)?;
+        let mut result = Set::clone(&this);
+        result.retain(|x| !other.contains_hashed(x));
+        for x in other.iter_hashed() {
+            if !this.contains_hashed(x) {
+                result.insert_hashed(x);
+            }
+        }
+        Ok(result)
+    }
+
+    /// `S.symmetric_difference_update(x)` updates the set S to contain the elements
+    /// which are either in S or in the iterable `x`, but not in both, and returns `None`.
+    ///
+    /// ```
+    /// # starlark::assert::is_true(r#"
+    /// x = set([1, 2, 3])
+    /// x.symmetric_difference_update([4, 3])
+    /// x == set([1, 2, 4])
+    /// # "#);
+    /// ```
+    fn symmetric_difference_update<'v>(
+        this: Value<'v>,
+        #[starlark(require = pos)] other: Value<'v>,
+        heap: &'v Heap,
+    ) -> anyhow::Result<NoneType> {
+        let other = to_set(other, heap)?;
+        let mut this = SetMut::from_value(this)?;
+        for x in other.iter_hashed() {
+            if !this.remove_hashed(x) {
+                this.insert_hashed(x);
+            }
+        }
+        Ok(NoneType)
+    }
+
+    /// `S.union(*others)` returns a new set with the elements of the set S
+    /// and of the iterables `others`.
+    ///
+    /// ```
+    /// # starlark::assert::is_true(r#"
+    /// set([1, 2]).union([2, 3], (4,)) == set([1, 2, 3, 4])
+    /// # "#);
+    /// ```
+    #[starlark(speculative_exec_safe)]
+    fn union<'v>(
+        this: SetRef<'v>,
+        #[starlark(args)] others: UnpackTuple<Value<'v>>,
+        heap: &'v Heap,
+    ) -> anyhow::Result<Set<'v>> {
+        let mut result = Set::clone(&this);
+        for other in others.items {
+            for x in other.iterate(heap)? {
+                result.insert_hashed(x.get_hashed()?);
+            }
+        }
+        Ok(result)
+    }
+
+    /// `S.update(*others)` adds the elements of the iterables `others` to the set S,
+    /// and returns `None`.
+    ///
+    /// ```
+    /// # starlark::assert::is_true(r#"
+    /// x = set([1, 2])
+    /// x.update([2, 3], (4,))
+    /// x == set([1, 2, 3, 4])
+    /// # "#);
+    /// ```
+    fn update<'v>(
+        this: Value<'v>,
+        #[starlark(args)] others: UnpackTuple<Value<'v>>,
+        heap: &'v Heap,
+    ) -> anyhow::Result<NoneType> {
+        let mut elements: Vec<Hashed<Value<'v>>> = Vec::new();
+        for other in others.items {
+            for x in other.iterate(heap)? {
+                elements.push(x.get_hashed()?);
+            }
+        }
+        let mut this = SetMut::from_value(this)?;
+        for x in elements {
+            this.insert_hashed(x);
+        }
+        Ok(NoneType)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assert;
+
+    #[test]
+    fn test_operators() {
+        assert::all_true(
+            r#"
+set([1, 2]) | set([2, 3]) == set([1, 2, 3])
+set([1, 2]) & set([2, 3]) == set([2])
+set([1, 2]) - set([2, 3]) == set([1])
+set([1, 2]) ^ set([2, 3]) == set([1, 3])
+list(set([3, 1]) | set([2, 1])) == [3, 1, 2]
+2 in set([1, 2])
+3 not in set([1, 2])
+not set()
+"#,
+        );
+        assert::fail_skip_typecheck("set([1]) | [2]", "not supported");
+        assert::fail_skip_typecheck("[] in set([1])", "not hashable");
+    }
+
+    #[test]
+    fn test_bit_or_assign_mutates() {
+        assert::is_true(
+            r#"
+x = set([1])
+y = x
+x |= set([2])
+y == set([1, 2])
+"#,
+        );
+    }
+
+    #[test]
+    fn test_freeze() {
+        let mut a = assert::Assert::new();
+        a.module("m", "s = set([1, 2])");
+        a.is_true(
+            r#"
+load("m", "s")
+s == set([2, 1]) and 1 in s
+"#,
+        );
+        a.fail(
+            r#"
+load("m", "s")
+s.add(3)
+"#,
+            "Immutable",
+        );
+    }
+
+    #[test]
+    fn test_mutation_during_iteration() {
+        assert::fail(
+            r#"
+x = set([1, 2])
+for i in x:
+    x.add(3)
+"#,
+            "mutate an iterable for an iterator while iterating",
+        );
+    }
+
+    #[test]
+    fn test_update_self() {
+        assert::is_true(
+            r#"
+x = set([1, 2])
+x.update(x)
+x.difference_update(x)
+x == set()
+"#,
+        );
+    }
+
+    #[test]
+    fn test_repr() {
+        assert::eq("repr(set())", "'set()'");
+        assert::eq("repr(set([1, 'a']))", "'set([1, \"a\"])'");
+        assert::eq("type(set())", "'set'");
+    }
+
+    #[test]
+    fn test_type_annotations() {
+        assert::pass(
+            r#"
+def f(x: set[int]) -> set[int]:
+    return x | set([1])
+f(set([2]))
+"#,
+        );
+        assert::fail_skip_typecheck(
+            r#"
+def f(x: set[int]):
+    pass
+f(set(["a"]))
+"#,
+            "Value `set([\"a\"])` of type `set` does not match the type annotation `set[int]`",
+        );
+    }
+
+    #[test]
+    fn test_not_hashable() {
+        assert::fail("{set(): 1}", "not hashable");
+    }
+}
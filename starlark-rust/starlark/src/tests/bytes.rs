@@ -0,0 +1,115 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use crate::assert;
+use crate::assert::Assert;
+use crate::environment::Globals;
+use crate::syntax::Dialect;
+
+fn assert() -> Assert<'static> {
+    let mut a = Assert::new();
+    a.dialect(&Dialect {
+        enable_bytes: true,
+        ..Dialect::Extended
+    });
+    a
+}
+
+#[test]
+fn test_literals() {
+    assert().all_true(
+        r#"
+b"abc" == bytes("abc")
+b'abc' == b"abc"
+b"\x00\xff" == bytes([0, 255])
+b"\377" == bytes([255])
+br"\n" == bytes("\\n")
+rb"\n" == bytes("\\n")
+b"é" == bytes([0xc3, 0xa9])
+type(b"") == "bytes"
+"#,
+    );
+}
+
+#[test]
+fn test_operations() {
+    assert().all_true(
+        r#"
+len(b"abc") == 3
+b"abc"[1] == 98
+b"abc"[-1] == 99
+b"abcd"[1:3] == b"bc"
+b"abcd"[::-1] == b"dcba"
+[x for x in b"ab"] == [97, 98]
+b"ab" + b"cd" == b"abcd"
+b"ab" * 2 == b"abab"
+2 * b"ab" == b"abab"
+b"bc" in b"abcd"
+98 in b"abc"
+not (100 in b"abc")
+b"a" < b"b"
+not b""
+{b"a": 1}[b"a"] == 1
+b"ab".elems() == [97, 98]
+"#,
+    );
+    assert().fail("256 in b'abc'", "out of range");
+}
+
+#[test]
+fn test_hash() {
+    // 32-bit FNV-1a, like starlark-go.
+    assert().eq("2166136261", "hash(b'')");
+    assert().eq("3826002220", "hash(b'a')");
+    assert().eq("440920331", "hash(b'abc')");
+    assert().eq("2047574606", "hash(b'\\xff')");
+}
+
+#[test]
+fn test_str_repr() {
+    assert().all_true(
+        r#"
+repr(b"a\"\n") == 'b"a\\"\\n"'
+repr(b"\xff") == 'b"\\xff"'
+str(b"abc") == "abc"
+str(b"\xff") == "�"
+"#,
+    );
+}
+
+#[test]
+fn test_bytes_function() {
+    // The `bytes` function is not a standard global.
+    let mut a = Assert::new();
+    a.globals(Globals::standard());
+    a.fail("bytes('x')", "Variable `bytes` not found");
+
+    // With the library extension, it is available even without the literal syntax.
+    assert::all_true(
+        r#"
+bytes("hé") == bytes([104, 0xc3, 0xa9])
+bytes(bytes("x")) == bytes("x")
+str(bytes("hé")) == "hé"
+"#,
+    );
+    assert::fail("bytes([256])", "out of range");
+}
+
+#[test]
+fn test_not_enabled() {
+    assert::fail("b'abc'", "must enable bytes");
+}
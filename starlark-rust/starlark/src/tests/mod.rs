@@ -18,6 +18,7 @@
 mod basic;
 mod bc;
 mod before_stmt;
+mod bytes;
 mod call;
 mod comprehension;
 mod def;
@@ -39,6 +39,7 @@ use crate::typing::TypingBinOp;
 use crate::typing::TypingUnOp;
 use crate::values::dict::value::MutableDict;
 use crate::values::list::value::List;
+use crate::values::set::value::MutableSet;
 use crate::values::tuple::value::Tuple;
 
 #[derive(Debug, thiserror::Error)]
@@ -287,7 +288,7 @@ impl<'a> TypingOracleCtx<'a> {
             TyBasic::Any => Ok(Ty::any()),
             TyBasic::Name(n) => self.validate_call_for_type_name(span, n, args),
             TyBasic::StarlarkValue(t) => Ok(t.validate_call(span, *self)?),
-            TyBasic::List(_) | TyBasic::Dict(..) | TyBasic::Tuple(_) => Err(self
+            TyBasic::List(_) | TyBasic::Dict(..) | TyBasic::Tuple(_) | TyBasic::Set(_) => Err(self
                 .mk_error_as_maybe_internal(
                     span,
                     TypingOracleCtxError::CallToNonCallable {
@@ -345,6 +346,7 @@ impl<'a> TypingOracleCtx<'a> {
             TyBasic::StarlarkValue(ty) => ty.iter_item(),
             TyBasic::List(item) => Ok((**item).dupe()),
             TyBasic::Dict(k, _v) => Ok((**k).dupe()),
+            TyBasic::Set(item) => Ok((**item).dupe()),
             TyBasic::Tuple(tuple) => Ok(tuple.item_ty()),
             TyBasic::Callable => Ok(Ty::any()),
             TyBasic::Type => Ok(Ty::any()),
@@ -394,6 +396,7 @@ impl<'a> TypingOracleCtx<'a> {
                 }
                 Ok(Ok((**v).dupe()))
             }
+            TyBasic::Set(_) => Ok(Err(())),
             TyBasic::StarlarkValue(array) => Ok(array.index(index.node)),
             TyBasic::Custom(c) => Ok(c.0.index_dyn(index.node, self)),
             TyBasic::Name(_) => Ok(Ok(Ty::any())),
@@ -515,6 +518,10 @@ impl<'a> TypingOracleCtx<'a> {
                     attr => TyStarlarkValue::new::<MutableDict>().attr(attr),
                 }
             }
+            TyBasic::Set(elem) => match attr {
+                "pop" => Ok(Ty::function(vec![], (**elem).dupe())),
+                attr => TyStarlarkValue::new::<MutableSet>().attr(attr),
+            },
             TyBasic::Custom(custom) => custom.0.attribute_dyn(attr),
             TyBasic::Name(_) => Ok(Ty::any()),
         }
@@ -625,6 +632,33 @@ impl<'a> TypingOracleCtx<'a> {
                 }
                 bin_op => TyStarlarkValue::new::<MutableDict>().bin_op(bin_op, rhs.node),
             },
+            TyBasic::Set(elem) => match bin_op {
+                TypingBinOp::BitOr | TypingBinOp::BitXor => {
+                    if self.intersects_basic(rhs.node, &TyBasic::any_set()) {
+                        Ok(Ty::set(Ty::union2(
+                            elem.to_ty(),
+                            self.iter_item_basic(rhs.node)?,
+                        )))
+                    } else {
+                        Err(())
+                    }
+                }
+                TypingBinOp::BitAnd | TypingBinOp::Sub => {
+                    if self.intersects_basic(rhs.node, &TyBasic::any_set()) {
+                        Ok(Ty::set(elem.to_ty()))
+                    } else {
+                        Err(())
+                    }
+                }
+                TypingBinOp::In => {
+                    if self.intersects(elem, &Ty::basic(rhs.node.dupe())) {
+                        Ok(Ty::bool())
+                    } else {
+                        Err(())
+                    }
+                }
+                bin_op => TyStarlarkValue::new::<MutableSet>().bin_op(bin_op, rhs.node),
+            },
             TyBasic::Custom(lhs) => lhs.0.bin_op_dyn(bin_op, rhs.node, self),
             TyBasic::Name(_) => Ok(Ty::any()),
         }
@@ -836,6 +870,8 @@ impl<'a> TypingOracleCtx<'a> {
                 self.intersects(x_k, y_k) && self.intersects(x_v, y_v)
             }
             (TyBasic::Dict(..), TyBasic::StarlarkValue(y)) => y.is_dict(),
+            (TyBasic::Set(x), TyBasic::Set(y)) => self.intersects(x, y),
+            (TyBasic::Set(_), TyBasic::StarlarkValue(y)) => y.is_set(),
             (TyBasic::Tuple(x), TyBasic::Tuple(y)) => TyTuple::intersects(x, y, self),
             (TyBasic::Tuple(_), TyBasic::StarlarkValue(y)) => y.is_tuple(),
             (TyBasic::Iter(x), TyBasic::Iter(y)) => self.intersects(x, y),
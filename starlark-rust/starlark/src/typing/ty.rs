@@ -272,6 +272,15 @@ impl Ty {
         Ty::basic(TyBasic::dict(key, value))
     }
 
+    /// Create a set type.
+    pub fn set(item: Ty) -> Self {
+        Ty::basic(TyBasic::set(item))
+    }
+
+    pub(crate) fn any_set() -> Self {
+        Self::set(Ty::any())
+    }
+
     pub(crate) fn any_dict() -> Self {
         Self::dict(Ty::any(), Ty::any())
     }
@@ -398,6 +407,7 @@ impl Ty {
                 ArcTy::union2(x_k, y_k),
                 ArcTy::union2(x_v, y_v),
             )),
+            (TyBasic::Set(x), TyBasic::Set(y)) => Either::Left(TyBasic::Set(ArcTy::union2(x, y))),
             (TyBasic::Custom(x), TyBasic::Custom(y)) => match TyCustom::union2(x, y) {
                 Ok(u) => Either::Left(TyBasic::Custom(u)),
                 Err((x, y)) => Either::Right((TyBasic::Custom(x), TyBasic::Custom(y))),
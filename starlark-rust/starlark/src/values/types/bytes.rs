@@ -0,0 +1,254 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! The bytes type, an immutable sequence of bytes, written `b"..."`.
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::fmt::Display;
+use std::fmt::Write;
+use std::hash::Hasher;
+use std::str;
+
+use allocative::Allocative;
+use starlark_derive::starlark_value;
+use starlark_derive::NoSerialize;
+use starlark_derive::StarlarkDocs;
+
+use crate as starlark;
+use crate::any::ProvidesStaticType;
+use crate::collections::StarlarkHasher;
+use crate::environment::Methods;
+use crate::environment::MethodsStatic;
+use crate::starlark_simple_value;
+use crate::typing::Ty;
+use crate::values::index::apply_slice;
+use crate::values::index::convert_index;
+use crate::values::Heap;
+use crate::values::StarlarkValue;
+use crate::values::UnpackValue;
+use crate::values::Value;
+use crate::values::ValueError;
+
+#[derive(Debug, thiserror::Error)]
+enum BytesError {
+    #[error("Byte value `{0}` is out of range, must be in 0..256")]
+    ByteOutOfRange(i32),
+}
+
+/// Representation of the bytes type.
+#[derive(
+    Clone,
+    Default,
+    Debug,
+    PartialEq,
+    Eq,
+    Hash,
+    ProvidesStaticType,
+    NoSerialize,
+    StarlarkDocs,
+    Allocative
+)]
+#[starlark_docs(builtin = "standard")]
+pub struct StarlarkBytes(Box<[u8]>);
+
+starlark_simple_value!(StarlarkBytes);
+
+impl StarlarkBytes {
+    /// The result of calling `type()` on bytes.
+    pub const TYPE: &'static str = "bytes";
+
+    /// Create a new [`StarlarkBytes`].
+    pub fn new(bytes: &[u8]) -> StarlarkBytes {
+        StarlarkBytes(bytes.into())
+    }
+
+    /// The bytes.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// Convert a Starlark int to a byte.
+    pub(crate) fn byte(x: i32) -> anyhow::Result<u8> {
+        u8::try_from(x).map_err(|_| BytesError::ByteOutOfRange(x).into())
+    }
+}
+
+impl From<Vec<u8>> for StarlarkBytes {
+    fn from(bytes: Vec<u8>) -> StarlarkBytes {
+        StarlarkBytes(bytes.into_boxed_slice())
+    }
+}
+
+/// Write a character of a bytes literal, escaping it if it is not printable.
+fn fmt_char(f: &mut fmt::Formatter<'_>, c: char) -> fmt::Result {
+    match c {
+        '"' => f.write_str("\\\""),
+        '\\' => f.write_str("\\\\"),
+        '\n' => f.write_str("\\n"),
+        '\r' => f.write_str("\\r"),
+        '\t' => f.write_str("\\t"),
+        c if c == ' ' || !(c.is_control() || c.is_whitespace()) => f.write_char(c),
+        c if c.is_ascii() => write!(f, "\\x{:02x}", c as u32),
+        c if (c as u32) < 0x10000 => write!(f, "\\u{:04x}", c as u32),
+        c => write!(f, "\\U{:08x}", c as u32),
+    }
+}
+
+/// Like Go Starlark, bytes are shown as text where they are valid UTF-8,
+/// and as `\x` escapes where they are not.
+impl Display for StarlarkBytes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("b\"")?;
+        let mut bytes = self.as_bytes();
+        while !bytes.is_empty() {
+            let (valid, invalid) = match str::from_utf8(bytes) {
+                Ok(valid) => (valid, &[][..]),
+                Err(e) => {
+                    let (valid, rest) = bytes.split_at(e.valid_up_to());
+                    let invalid = &rest[..e.error_len().unwrap_or(rest.len())];
+                    // Valid up to this point, as checked above.
+                    (str::from_utf8(valid).unwrap(), invalid)
+                }
+            };
+            for c in valid.chars() {
+                fmt_char(f, c)?;
+            }
+            for b in invalid {
+                write!(f, "\\x{:02x}", b)?;
+            }
+            bytes = &bytes[valid.len() + invalid.len()..];
+        }
+        f.write_str("\"")
+    }
+}
+
+#[starlark_value(type = StarlarkBytes::TYPE)]
+impl<'v> StarlarkValue<'v> for StarlarkBytes {
+    fn get_methods() -> Option<&'static Methods> {
+        static RES: MethodsStatic = MethodsStatic::new();
+        RES.methods(crate::stdlib::bytes::bytes_methods)
+    }
+
+    fn to_bool(&self) -> bool {
+        !self.0.is_empty()
+    }
+
+    fn write_hash(&self, hasher: &mut StarlarkHasher) -> anyhow::Result<()> {
+        hasher.write(&self.0);
+        Ok(())
+    }
+
+    fn equals(&self, other: Value<'v>) -> anyhow::Result<bool> {
+        match StarlarkBytes::from_value(other) {
+            Some(other) => Ok(self == other),
+            None => Ok(false),
+        }
+    }
+
+    fn compare(&self, other: Value<'v>) -> anyhow::Result<Ordering> {
+        match StarlarkBytes::from_value(other) {
+            Some(other) => Ok(self.0.cmp(&other.0)),
+            None => ValueError::unsupported_with(self, "cmp()", other),
+        }
+    }
+
+    fn length(&self) -> anyhow::Result<i32> {
+        Ok(self.0.len() as i32)
+    }
+
+    fn at(&self, index: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        let i = convert_index(index, self.0.len() as i32)?;
+        Ok(heap.alloc(self.0[i as usize] as i32))
+    }
+
+    fn slice(
+        &self,
+        start: Option<Value<'v>>,
+        stop: Option<Value<'v>>,
+        stride: Option<Value<'v>>,
+        heap: &'v Heap,
+    ) -> anyhow::Result<Value<'v>> {
+        let bytes = apply_slice(self.as_bytes(), start, stop, stride)?;
+        Ok(heap.alloc(StarlarkBytes::from(bytes)))
+    }
+
+    unsafe fn iterate(&self, me: Value<'v>, _heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        Ok(me)
+    }
+
+    unsafe fn iter_next(&self, index: usize, heap: &'v Heap) -> Option<Value<'v>> {
+        self.0.get(index).map(|b| heap.alloc(*b as i32))
+    }
+
+    unsafe fn iter_size_hint(&self, index: usize) -> (usize, Option<usize>) {
+        let rem = self.0.len().saturating_sub(index);
+        (rem, Some(rem))
+    }
+
+    unsafe fn iter_stop(&self) {}
+
+    fn is_in(&self, other: Value<'v>) -> anyhow::Result<bool> {
+        if let Some(needle) = StarlarkBytes::from_value(other) {
+            Ok(needle.0.is_empty() || self.0.windows(needle.0.len()).any(|w| w == &*needle.0))
+        } else if let Some(x) = i32::unpack_value(other) {
+            Ok(self.0.contains(&StarlarkBytes::byte(x)?))
+        } else {
+            ValueError::unsupported_owned(other.get_type(), "in", Some(Self::TYPE))
+        }
+    }
+
+    fn add(&self, other: Value<'v>, heap: &'v Heap) -> Option<anyhow::Result<Value<'v>>> {
+        let other = StarlarkBytes::from_value(other)?;
+        Some(Ok(
+            heap.alloc(StarlarkBytes::from([&*self.0, &*other.0].concat()))
+        ))
+    }
+
+    fn mul(&self, other: Value<'v>, heap: &'v Heap) -> Option<anyhow::Result<Value<'v>>> {
+        let n = i32::unpack_value(other)?;
+        Some(Ok(heap.alloc(StarlarkBytes::from(
+            self.0.repeat(n.max(0) as usize),
+        ))))
+    }
+
+    fn rmul(&self, lhs: Value<'v>, heap: &'v Heap) -> Option<anyhow::Result<Value<'v>>> {
+        self.mul(lhs, heap)
+    }
+
+    fn typechecker_ty(&self) -> Option<Ty> {
+        Some(Ty::starlark_value::<Self>())
+    }
+
+    fn get_type_starlark_repr() -> Ty {
+        Ty::starlark_value::<Self>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::values::bytes::StarlarkBytes;
+
+    #[test]
+    fn test_display() {
+        assert_eq!(r#"b"""#, StarlarkBytes::new(b"").to_string());
+        assert_eq!(
+            r#"b"a\"\\\n\x00\xffé""#,
+            StarlarkBytes::new(b"a\"\\\n\x00\xff\xc3\xa9").to_string()
+        );
+    }
+}
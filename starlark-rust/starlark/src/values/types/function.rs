@@ -85,6 +85,7 @@ pub enum SpecialBuiltinFunction {
     List,
     Dict,
     Tuple,
+    Set,
 }
 
 /// A native function that can be evaluated.
@@ -315,6 +316,10 @@ impl<'v> StarlarkValue<'v> for NativeFunction {
                 let index = TypeCompiled::new(index, heap)?;
                 Ok(TypeCompiled::type_list_of(index, heap).to_inner())
             }
+            Some(SpecialBuiltinFunction::Set) => {
+                let index = TypeCompiled::new(index, heap)?;
+                Ok(TypeCompiled::from_ty(&Ty::set(index.as_ty().clone()), heap).to_inner())
+            }
             _ => ValueError::unsupported(self, "[]"),
         }
     }
@@ -20,6 +20,7 @@ pub mod any_array;
 pub mod array;
 pub mod bigint;
 pub mod bool;
+pub mod bytes;
 pub mod dict;
 pub(crate) mod ellipsis;
 pub mod enumeration;
@@ -35,6 +36,7 @@ pub mod list_or_tuple;
 pub mod none;
 pub mod range;
 pub mod record;
+pub mod set;
 pub mod starlark_value_as_type;
 pub mod string;
 pub mod structs;
@@ -0,0 +1,442 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::any::TypeId;
+use std::cell::Ref;
+use std::cell::RefCell;
+use std::cell::RefMut;
+use std::fmt;
+use std::fmt::Debug;
+use std::fmt::Display;
+use std::mem;
+use std::ops::Deref;
+
+use allocative::Allocative;
+use display_container::fmt_container;
+use serde::Serialize;
+use starlark_derive::starlark_value;
+use starlark_derive::StarlarkDocs;
+
+use crate as starlark;
+use crate::any::ProvidesStaticType;
+use crate::coerce::coerce;
+use crate::coerce::Coerce;
+use crate::collections::Hashed;
+use crate::collections::SmallSet;
+use crate::environment::Methods;
+use crate::environment::MethodsStatic;
+use crate::typing::Ty;
+use crate::values::dict::refcell::unleak_borrow;
+use crate::values::error::ValueError;
+use crate::values::set::SetRef;
+use crate::values::type_repr::StarlarkTypeRepr;
+use crate::values::AllocFrozenValue;
+use crate::values::AllocValue;
+use crate::values::Freeze;
+use crate::values::Freezer;
+use crate::values::FrozenHeap;
+use crate::values::FrozenStringValue;
+use crate::values::FrozenValue;
+use crate::values::Heap;
+use crate::values::StarlarkValue;
+use crate::values::Trace;
+use crate::values::Value;
+
+#[derive(
+    Clone,
+    Default,
+    Trace,
+    Debug,
+    ProvidesStaticType,
+    StarlarkDocs,
+    Allocative
+)]
+#[starlark_docs(builtin = "extension")]
+pub(crate) struct SetGen<T>(pub(crate) T);
+
+/// `set([1, 2])`, or `set()` for the empty set, which has no literal syntax.
+fn fmt_set<T: Display>(
+    f: &mut fmt::Formatter<'_>,
+    items: impl ExactSizeIterator<Item = T>,
+) -> fmt::Result {
+    if items.len() == 0 {
+        write!(f, "set()")
+    } else {
+        fmt_container(f, "set([", "])", items)
+    }
+}
+
+impl<'v, T: SetLike<'v>> Display for SetGen<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt_set(f, self.0.content().iter())
+    }
+}
+
+impl<'v> Display for Set<'v> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt_set(f, self.iter())
+    }
+}
+
+/// Define the set type.
+#[derive(Clone, Default, Trace, Debug, ProvidesStaticType, Allocative)]
+#[repr(transparent)]
+pub struct Set<'v> {
+    /// The elements of the set. They must all be hashable values.
+    content: SmallSet<Value<'v>>,
+}
+
+impl<'v> StarlarkTypeRepr for Set<'v> {
+    fn starlark_type_repr() -> Ty {
+        Ty::any_set()
+    }
+}
+
+#[derive(Clone, Default, Debug, ProvidesStaticType, Allocative)]
+#[repr(transparent)]
+pub(crate) struct FrozenSetData {
+    /// The elements of the set. They must all be hashable values.
+    pub(crate) content: SmallSet<FrozenValue>,
+}
+
+/// Alias is used in `StarlarkDocs` derive.
+pub(crate) type FrozenSet = SetGen<FrozenSetData>;
+
+pub(crate) type MutableSet<'v> = SetGen<RefCell<Set<'v>>>;
+
+unsafe impl<'v> Coerce<Set<'v>> for FrozenSetData {}
+
+impl<'v> AllocValue<'v> for Set<'v> {
+    fn alloc_value(self, heap: &'v Heap) -> Value<'v> {
+        heap.alloc_complex(SetGen(RefCell::new(self)))
+    }
+}
+
+impl StarlarkTypeRepr for FrozenSetData {
+    fn starlark_type_repr() -> Ty {
+        Ty::any_set()
+    }
+}
+
+impl AllocFrozenValue for FrozenSetData {
+    fn alloc_frozen_value(self, heap: &FrozenHeap) -> FrozenValue {
+        heap.alloc_simple(SetGen(self))
+    }
+}
+
+impl<'v> Set<'v> {
+    /// The result of calling `type()` on sets.
+    pub const TYPE: &'static str = "set";
+
+    /// Set type string as Starlark frozen string value.
+    pub fn get_type_value_static() -> FrozenStringValue {
+        SetGen::<FrozenSetData>::get_type_value_static()
+    }
+
+    pub(crate) fn is_set_type(x: TypeId) -> bool {
+        x == TypeId::of::<SetGen<FrozenSetData>>()
+            || x == TypeId::of::<SetGen<RefCell<Set<'static>>>>()
+    }
+
+    /// Create a set with the given elements.
+    pub fn new(content: SmallSet<Value<'v>>) -> Self {
+        Self { content }
+    }
+
+    /// Number of elements in the set.
+    pub fn len(&self) -> usize {
+        self.content.len()
+    }
+
+    /// Is the set empty?
+    pub fn is_empty(&self) -> bool {
+        self.content.is_empty()
+    }
+
+    /// Iterate through the elements of the set, in insertion order.
+    pub fn iter<'a>(&'a self) -> impl ExactSizeIterator<Item = Value<'v>> + 'a {
+        self.content.iter().copied()
+    }
+
+    /// Iterate through the elements of the set, retaining their hashes.
+    pub fn iter_hashed<'a>(&'a self) -> impl ExactSizeIterator<Item = Hashed<Value<'v>>> + 'a
+    where
+        'v: 'a,
+    {
+        self.content.iter_hashed().map(|x| x.copied())
+    }
+
+    /// Check if the set contains a value. Will be [`Err`] if the value is not hashable.
+    pub fn contains(&self, value: Value<'v>) -> anyhow::Result<bool> {
+        Ok(self.contains_hashed(value.get_hashed()?))
+    }
+
+    /// Check if the set contains a prehashed value.
+    pub fn contains_hashed(&self, value: Hashed<Value<'v>>) -> bool {
+        self.content.contains_hashed(value.as_ref())
+    }
+
+    /// Insert a value into the set, returning whether it was not already present.
+    pub fn insert_hashed(&mut self, value: Hashed<Value<'v>>) -> bool {
+        self.content.insert_hashed(value)
+    }
+
+    /// Remove a value from the set, returning whether it was present.
+    pub fn remove_hashed(&mut self, value: Hashed<Value<'v>>) -> bool {
+        self.content.remove_hashed(value.as_ref())
+    }
+
+    /// Remove all elements from the set.
+    pub fn clear(&mut self) {
+        self.content.clear();
+    }
+
+    /// Keep only the elements for which the predicate returns `true`, in their order.
+    pub(crate) fn retain(&mut self, f: impl FnMut(Hashed<Value<'v>>) -> bool) {
+        self.content = filter_hashed(&self.content, f);
+    }
+}
+
+/// The elements of the set for which the predicate returns `true`, in their order.
+fn filter_hashed<'v>(
+    set: &SmallSet<Value<'v>>,
+    mut f: impl FnMut(Hashed<Value<'v>>) -> bool,
+) -> SmallSet<Value<'v>> {
+    let mut result = SmallSet::new();
+    for x in set.iter_hashed() {
+        let x = x.copied();
+        if f(x) {
+            // The elements come from a set, so they are unique.
+            result.insert_hashed_unique_unchecked(x);
+        }
+    }
+    result
+}
+
+impl<'v> Freeze for SetGen<RefCell<Set<'v>>> {
+    type Frozen = SetGen<FrozenSetData>;
+    fn freeze(self, freezer: &Freezer) -> anyhow::Result<Self::Frozen> {
+        let content = self.0.into_inner().content.freeze(freezer)?;
+        Ok(SetGen(FrozenSetData { content }))
+    }
+}
+
+trait SetLike<'v>: Debug + Allocative {
+    type ContentRef<'a>: Deref<Target = SmallSet<Value<'v>>>
+    where
+        Self: 'a,
+        'v: 'a;
+    fn content<'a>(&'a self) -> Self::ContentRef<'a>;
+    // These functions are unsafe for the same reason
+    // `StarlarkValue` iterator functions are unsafe.
+    unsafe fn iter_start(&self);
+    unsafe fn content_unchecked(&self) -> &SmallSet<Value<'v>>;
+    unsafe fn iter_stop(&self);
+}
+
+impl<'v> SetLike<'v> for RefCell<Set<'v>> {
+    type ContentRef<'a> = Ref<'a, SmallSet<Value<'v>>> where Self: 'a, 'v: 'a;
+
+    fn content<'a>(&'a self) -> Ref<'a, SmallSet<Value<'v>>> {
+        Ref::map(self.borrow(), |x| &x.content)
+    }
+
+    #[inline]
+    unsafe fn iter_start(&self) {
+        mem::forget(self.borrow());
+    }
+
+    #[inline]
+    unsafe fn iter_stop(&self) {
+        unleak_borrow(self);
+    }
+
+    #[inline]
+    unsafe fn content_unchecked(&self) -> &SmallSet<Value<'v>> {
+        // SAFETY: this function contract is, caller must ensure that the value is borrowed.
+        &self.try_borrow_unguarded().ok().unwrap_unchecked().content
+    }
+}
+
+impl<'v> SetLike<'v> for FrozenSetData {
+    type ContentRef<'a> = &'a SmallSet<Value<'v>> where Self: 'a, 'v: 'a;
+
+    fn content<'a>(&'a self) -> &'a SmallSet<Value<'v>> {
+        coerce(&self.content)
+    }
+
+    unsafe fn iter_start(&self) {}
+
+    unsafe fn iter_stop(&self) {}
+
+    unsafe fn content_unchecked(&self) -> &SmallSet<Value<'v>> {
+        coerce(&self.content)
+    }
+}
+
+pub(crate) fn set_methods() -> Option<&'static Methods> {
+    static RES: MethodsStatic = MethodsStatic::new();
+    RES.methods(crate::stdlib::set::set_methods)
+}
+
+impl<'v, T: SetLike<'v>> SetGen<T> {
+    /// Apply a set operator, which requires a set on both sides, unlike the methods.
+    fn bin_op(
+        &self,
+        op: &str,
+        rhs: Value<'v>,
+        heap: &'v Heap,
+        f: impl FnOnce(&SmallSet<Value<'v>>, &Set<'v>) -> SmallSet<Value<'v>>,
+    ) -> anyhow::Result<Value<'v>> {
+        let rhs = SetRef::from_value(rhs).map_or_else(
+            || ValueError::unsupported_owned(Set::TYPE, op, Some(rhs.get_type())),
+            Ok,
+        )?;
+        let content = f(&self.0.content(), &rhs);
+        Ok(heap.alloc(Set::new(content)))
+    }
+}
+
+#[starlark_value(type = Set::TYPE)]
+impl<'v, T: SetLike<'v> + 'v> StarlarkValue<'v> for SetGen<T>
+where
+    Self: ProvidesStaticType<'v>,
+{
+    type Canonical = FrozenSet;
+
+    fn get_methods() -> Option<&'static Methods> {
+        set_methods()
+    }
+
+    fn collect_repr(&self, r: &mut String) {
+        let content = self.0.content();
+        if content.is_empty() {
+            r.push_str("set()");
+            return;
+        }
+        r.push_str("set([");
+        for (i, value) in content.iter().enumerate() {
+            if i != 0 {
+                r.push_str(", ");
+            }
+            value.collect_repr(r);
+        }
+        r.push_str("])");
+    }
+
+    fn collect_repr_cycle(&self, collector: &mut String) {
+        collector.push_str("set(...)");
+    }
+
+    fn to_bool(&self) -> bool {
+        !self.0.content().is_empty()
+    }
+
+    fn equals(&self, other: Value<'v>) -> anyhow::Result<bool> {
+        match SetRef::from_value(other) {
+            None => Ok(false),
+            Some(other) => {
+                let content = self.0.content();
+                Ok(content.len() == other.len()
+                    && content
+                        .iter_hashed()
+                        .all(|x| other.contains_hashed(x.copied())))
+            }
+        }
+    }
+
+    fn length(&self) -> anyhow::Result<i32> {
+        Ok(self.0.content().len() as i32)
+    }
+
+    fn is_in(&self, other: Value<'v>) -> anyhow::Result<bool> {
+        Ok(self
+            .0
+            .content()
+            .contains_hashed(other.get_hashed()?.as_ref()))
+    }
+
+    unsafe fn iterate(&self, me: Value<'v>, _heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        self.0.iter_start();
+        Ok(me)
+    }
+
+    unsafe fn iter_size_hint(&self, index: usize) -> (usize, Option<usize>) {
+        debug_assert!(index <= self.0.content().len());
+        let rem = self.0.content().len() - index;
+        (rem, Some(rem))
+    }
+
+    unsafe fn iter_next(&self, index: usize, _heap: &'v Heap) -> Option<Value<'v>> {
+        self.0.content_unchecked().get_index(index).copied()
+    }
+
+    unsafe fn iter_stop(&self) {
+        self.0.iter_stop();
+    }
+
+    fn bit_or(&self, rhs: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        self.bin_op("|", rhs, heap, |lhs, rhs| {
+            let mut content = lhs.clone();
+            for x in rhs.iter_hashed() {
+                content.insert_hashed(x);
+            }
+            content
+        })
+    }
+
+    fn bit_and(&self, rhs: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        self.bin_op("&", rhs, heap, |lhs, rhs| {
+            filter_hashed(lhs, |x| rhs.contains_hashed(x))
+        })
+    }
+
+    fn sub(&self, rhs: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        self.bin_op("-", rhs, heap, |lhs, rhs| {
+            filter_hashed(lhs, |x| !rhs.contains_hashed(x))
+        })
+    }
+
+    fn bit_xor(&self, rhs: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        self.bin_op("^", rhs, heap, |lhs, rhs| {
+            let mut content = filter_hashed(lhs, |x| !rhs.contains_hashed(x));
+            for x in rhs.iter_hashed() {
+                if !lhs.contains_hashed(x.as_ref()) {
+                    content.insert_hashed(x);
+                }
+            }
+            content
+        })
+    }
+
+    fn typechecker_ty(&self) -> Option<Ty> {
+        Some(Ty::any_set())
+    }
+
+    fn get_type_starlark_repr() -> Ty {
+        Ty::any_set()
+    }
+}
+
+impl<'v, T: SetLike<'v>> Serialize for SetGen<T> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_seq(self.0.content().iter())
+    }
+}
@@ -26,6 +26,8 @@ use crate::values::dict::value::FrozenDict;
 use crate::values::dict::DictRef;
 use crate::values::list::value::FrozenList;
 use crate::values::list::ListRef;
+use crate::values::set::value::FrozenSet;
+use crate::values::set::SetRef;
 use crate::values::starlark_type_id::StarlarkTypeId;
 use crate::values::starlark_type_id::StarlarkTypeIdAligned;
 use crate::values::tuple::value::Tuple;
@@ -172,6 +174,27 @@ impl<K: TypeMatcher, V: TypeMatcher> TypeMatcher for IsDictOf<K, V> {
     }
 }
 
+#[derive(Clone, Allocative, Debug)]
+pub(crate) struct IsSet;
+
+impl TypeMatcher for IsSet {
+    fn matches(&self, value: Value) -> bool {
+        value.starlark_type_id() == StarlarkTypeId::of::<FrozenSet>()
+    }
+}
+
+#[derive(Clone, Allocative, Debug)]
+pub(crate) struct IsSetOf<I: TypeMatcher>(pub(crate) I);
+
+impl<I: TypeMatcher> TypeMatcher for IsSetOf<I> {
+    fn matches(&self, value: Value) -> bool {
+        match SetRef::from_value(value) {
+            None => false,
+            Some(set) => set.iter().all(|v| self.0.matches(v)),
+        }
+    }
+}
+
 #[derive(Clone, Allocative, Debug)]
 pub(crate) struct IsAnyOfTwo<A: TypeMatcher, B: TypeMatcher>(pub(crate) A, pub(crate) B);
 
@@ -40,6 +40,7 @@ pub use crate::small_set::iter::IterMutUnchecked;
 
 /// An memory-efficient set with deterministic order, based on [`SmallMap`].
 #[derive(Clone, Allocative)]
+#[repr(transparent)]
 pub struct SmallSet<T>(SmallMap<T, ()>);
 
 impl<T> Default for SmallSet<T> {
@@ -244,6 +245,18 @@ impl<T> SmallSet<T> {
         self.0.remove(key).is_some()
     }
 
+    /// Remove the element from the set if it is present, by a prehashed value.
+    ///
+    /// Time complexity of this operation is *O(N)* where *N* is the number of entries in the set.
+    #[inline]
+    pub fn remove_hashed<Q>(&mut self, key: Hashed<&Q>) -> bool
+    where
+        Q: ?Sized + Equivalent<T>,
+        T: Eq,
+    {
+        self.0.remove_hashed(key).is_some()
+    }
+
     /// Insert entry if it doesn't exist.
     ///
     /// Return the resulting entry in the map.
@@ -707,6 +720,14 @@ mod tests {
         assert!(!s.remove(&17));
     }
 
+    #[test]
+    fn test_remove_hashed() {
+        let mut s: SmallSet<u32> = SmallSet::from_iter([17, 19, 23]);
+        assert!(s.remove_hashed(Hashed::new(&19)));
+        assert!(!s.remove_hashed(Hashed::new(&19)));
+        assert_eq!(vec![17, 23], Vec::from_iter(s.iter().copied()));
+    }
+
     #[test]
     fn test_difference() {
         let a = SmallSet::from_iter([1, 2, 3]);
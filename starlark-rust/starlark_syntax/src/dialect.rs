@@ -59,6 +59,10 @@ pub struct Dialect {
     /// Are `f"{expression}"` strings supported?
     /// Disabled in all dialects by default.
     pub enable_f_strings: bool,
+    /// Are `b"..."` bytes literals supported?
+    /// Disabled in all dialects by default.
+    /// The `bytes()` function is added by the `Bytes` library extension.
+    pub enable_bytes: bool,
     /// Like `#[non_exhaustive]`, but allows struct expression.
     ///
     /// [Explanation](https://github.com/rust-lang/rust-clippy/issues/6559).
@@ -87,6 +91,7 @@ impl Dialect {
         enable_load_reexport: true, // But they plan to change it
         enable_top_level_stmt: false,
         enable_f_strings: false,
+        enable_bytes: false,
         _non_exhaustive: (),
     };
 
@@ -100,6 +105,7 @@ impl Dialect {
         enable_load_reexport: true,
         enable_top_level_stmt: true,
         enable_f_strings: false,
+        enable_bytes: false,
         _non_exhaustive: (),
     };
 }
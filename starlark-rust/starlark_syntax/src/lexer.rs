@@ -66,6 +66,81 @@ fn map_lexeme_t<T1, T2>(lexeme: LexemeT<T1>, f: impl FnOnce(T1) -> T2) -> Lexeme
     lexeme.map(|(l, t, r)| (l, f(t), r))
 }
 
+/// The contents of a string or bytes literal, accumulated while lexing.
+trait LiteralBuf {
+    fn from_str(s: &str) -> Self;
+    fn with_capacity(capacity: usize) -> Self;
+    fn len(&self) -> usize;
+    fn truncate(&mut self, len: usize);
+    fn push_str(&mut self, s: &str);
+    fn push_char(&mut self, c: char);
+    /// Push the value of a `\x` or octal escape, which is a code point in strings,
+    /// but a byte in bytes.
+    fn push_escaped(&mut self, value: u32) -> Result<(), ()>;
+}
+
+impl LiteralBuf for String {
+    fn from_str(s: &str) -> Self {
+        s.to_owned()
+    }
+
+    fn with_capacity(capacity: usize) -> Self {
+        String::with_capacity(capacity)
+    }
+
+    fn len(&self) -> usize {
+        self.len()
+    }
+
+    fn truncate(&mut self, len: usize) {
+        self.truncate(len)
+    }
+
+    fn push_str(&mut self, s: &str) {
+        self.push_str(s)
+    }
+
+    fn push_char(&mut self, c: char) {
+        self.push(c)
+    }
+
+    fn push_escaped(&mut self, value: u32) -> Result<(), ()> {
+        self.push(char::from_u32(value).ok_or(())?);
+        Ok(())
+    }
+}
+
+impl LiteralBuf for Vec<u8> {
+    fn from_str(s: &str) -> Self {
+        s.as_bytes().to_vec()
+    }
+
+    fn with_capacity(capacity: usize) -> Self {
+        Vec::with_capacity(capacity)
+    }
+
+    fn len(&self) -> usize {
+        self.len()
+    }
+
+    fn truncate(&mut self, len: usize) {
+        self.truncate(len)
+    }
+
+    fn push_str(&mut self, s: &str) {
+        self.extend_from_slice(s.as_bytes())
+    }
+
+    fn push_char(&mut self, c: char) {
+        self.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes())
+    }
+
+    fn push_escaped(&mut self, value: u32) -> Result<(), ()> {
+        self.push(u8::try_from(value).map_err(|_| ())?);
+        Ok(())
+    }
+}
+
 pub struct Lexer<'a> {
     // Information for spans
     codemap: CodeMap,
@@ -235,7 +310,7 @@ impl<'a> Lexer<'a> {
 
     // We've potentially seen one character, now consume between min and max elements of iterator
     // and treat it as an int in base radix
-    fn escape_char(it: &mut CursorChars, min: usize, max: usize, radix: u32) -> Result<char, ()> {
+    fn escape_char(it: &mut CursorChars, min: usize, max: usize, radix: u32) -> Result<u32, ()> {
         let mut value = 0u32;
         let mut count = 0;
         while count < max {
@@ -263,19 +338,19 @@ impl<'a> Lexer<'a> {
                 },
             }
         }
-        char::from_u32(value).ok_or(())
+        Ok(value)
     }
 
     // We have seen a '\' character, now parse what comes next
-    fn escape(it: &mut CursorChars, res: &mut String) -> Result<(), ()> {
+    fn escape(it: &mut CursorChars, res: &mut impl LiteralBuf) -> Result<(), ()> {
         match it.next() {
-            Some('n') => res.push('\n'),
-            Some('r') => res.push('\r'),
-            Some('t') => res.push('\t'),
-            Some('a') => res.push('\x07'),
-            Some('b') => res.push('\x08'),
-            Some('f') => res.push('\x0C'),
-            Some('v') => res.push('\x0B'),
+            Some('n') => res.push_char('\n'),
+            Some('r') => res.push_char('\r'),
+            Some('t') => res.push_char('\t'),
+            Some('a') => res.push_char('\x07'),
+            Some('b') => res.push_char('\x08'),
+            Some('f') => res.push_char('\x0C'),
+            Some('v') => res.push_char('\x0B'),
             Some('\n') => {}
             Some('\r') => {
                 // Windows newline incoming, we expect a \n next, which we can ignore
@@ -284,18 +359,18 @@ impl<'a> Lexer<'a> {
                     return Err(());
                 }
             }
-            Some('x') => res.push(Self::escape_char(it, 2, 2, 16)?),
-            Some('u') => res.push(Self::escape_char(it, 4, 4, 16)?),
-            Some('U') => res.push(Self::escape_char(it, 8, 8, 16)?),
+            Some('x') => res.push_escaped(Self::escape_char(it, 2, 2, 16)?)?,
+            Some('u') => res.push_char(char::from_u32(Self::escape_char(it, 4, 4, 16)?).ok_or(())?),
+            Some('U') => res.push_char(char::from_u32(Self::escape_char(it, 8, 8, 16)?).ok_or(())?),
             Some(c) => match c {
                 '0'..='7' => {
                     it.unnext(c);
-                    res.push(Self::escape_char(it, 1, 3, 8)?)
+                    res.push_escaped(Self::escape_char(it, 1, 3, 8)?)?
                 }
-                '"' | '\'' | '\\' => res.push(c),
+                '"' | '\'' | '\\' => res.push_char(c),
                 _ => {
-                    res.push('\\');
-                    res.push(c);
+                    res.push_char('\\');
+                    res.push_char(c);
                 }
             },
             None => {
@@ -305,15 +380,15 @@ impl<'a> Lexer<'a> {
         Ok(())
     }
 
-    /// Parse a String. Return the String, and the offset where it starts.
+    /// Parse a String (or bytes). Return the contents, and the offset where it starts.
     // String parsing is a hot-spot, so parameterise by a `stop` function which gets
     // specialised for each variant
-    fn string(
+    fn string<B: LiteralBuf>(
         &mut self,
         triple: bool,
         raw: bool,
         mut stop: impl FnMut(char) -> bool,
-    ) -> LexemeT<(String, usize)> {
+    ) -> LexemeT<(B, usize)> {
         // We have seen an opening quote, which is either ' or "
         // If triple is true, it was a triple quote
         // stop lets us know when a string ends.
@@ -350,11 +425,11 @@ impl<'a> Lexer<'a> {
                         self.lexer.bump(it.pos());
                         return Ok((
                             string_start,
-                            (contents.to_owned(), contents_start),
+                            (B::from_str(contents), contents_start),
                             string_end + it.pos(),
                         ));
                     } else if c == '\\' || c == '\r' || (c == '\n' && !triple) {
-                        res = String::with_capacity(it.pos() + 10);
+                        res = B::with_capacity(it.pos() + 10);
                         res.push_str(&self.lexer.remainder()[contents_start..it.pos() - 1]);
                         it2 = CursorChars::new_offset(self.lexer.remainder(), it.pos() - 1);
                         break;
@@ -389,9 +464,9 @@ impl<'a> Lexer<'a> {
                         match it.next() {
                             Some(c) => {
                                 if c != '\'' && c != '"' {
-                                    res.push('\\');
+                                    res.push_char('\\');
                                 }
-                                res.push(c);
+                                res.push_char(c);
                             }
                             _ => break, // Out of chars
                         }
@@ -411,7 +486,7 @@ impl<'a> Lexer<'a> {
                         }
                     }
                 }
-                c => res.push(c),
+                c => res.push_char(c),
             }
         }
 
@@ -533,6 +608,19 @@ impl<'a> Lexer<'a> {
                         Token::FString(_) => {
                             unreachable!("The lexer does not produce FString")
                         }
+                        Token::RawBytesDoubleQuote => {
+                            let raw = self.lexer.span().len() == 3;
+                            self.parse_double_quoted_string(raw)
+                                .map(|lex| map_lexeme_t(lex, |(s, _offset)| Token::Bytes(s)))
+                        }
+                        Token::RawBytesSingleQuote => {
+                            let raw = self.lexer.span().len() == 3;
+                            self.parse_single_quoted_string(raw)
+                                .map(|lex| map_lexeme_t(lex, |(s, _offset)| Token::Bytes(s)))
+                        }
+                        Token::Bytes(_) => {
+                            unreachable!("The lexer does not produce Bytes")
+                        }
                         Token::OpeningCurly | Token::OpeningRound | Token::OpeningSquare => {
                             self.parens += 1;
                             self.wrap(token)
@@ -548,7 +636,10 @@ impl<'a> Lexer<'a> {
         }
     }
 
-    fn parse_double_quoted_string(&mut self, raw: bool) -> Option<LexemeT<(String, usize)>> {
+    fn parse_double_quoted_string<B: LiteralBuf>(
+        &mut self,
+        raw: bool,
+    ) -> Option<LexemeT<(B, usize)>> {
         if self.lexer.remainder().starts_with("\"\"") {
             let mut qs = 0;
             Some(self.string(true, raw, |c| {
@@ -565,7 +656,10 @@ impl<'a> Lexer<'a> {
         }
     }
 
-    fn parse_single_quoted_string(&mut self, raw: bool) -> Option<LexemeT<(String, usize)>> {
+    fn parse_single_quoted_string<B: LiteralBuf>(
+        &mut self,
+        raw: bool,
+    ) -> Option<LexemeT<(B, usize)>> {
         if self.lexer.remainder().starts_with("''") {
             let mut qs = 0;
             Some(self.string(true, raw, |c| {
@@ -652,6 +746,17 @@ pub enum Token {
     #[token("fr\"")]
     RawFStringDoubleQuote,
 
+    /// The start of a single-quoted bytes literal.
+    #[token("b'")]
+    #[token("br'")]
+    #[token("rb'")]
+    RawBytesSingleQuote,
+    /// The start of a double-quoted bytes literal.
+    #[token("b\"")]
+    #[token("br\"")]
+    #[token("rb\"")]
+    RawBytesDoubleQuote,
+
     #[regex(
         "as|\
         assert|\
@@ -698,6 +803,8 @@ pub enum Token {
     String(String), // A string literal
     /// The raw text of a f-string
     FString(TokenFString),
+    /// A bytes literal
+    Bytes(Vec<u8>),
 
     // Keywords
     #[token("and")]
@@ -842,6 +949,12 @@ impl Token {
                 serde_json::to_writer(&mut buff, &x.content).unwrap();
                 String::from_utf8(buff).unwrap()
             }
+            Token::Bytes(x) => {
+                let mut buff = Vec::new();
+                write!(&mut buff, "b").unwrap();
+                serde_json::to_writer(&mut buff, &String::from_utf8_lossy(x)).unwrap();
+                String::from_utf8(buff).unwrap()
+            }
             _ => {
                 let s = self.to_string();
                 // Out display is often: keyword 'lambda'
@@ -937,6 +1050,9 @@ impl Display for Token {
             Token::RawFStringDoubleQuote => write!(f, "starting f'"),
             Token::RawFStringSingleQuote => write!(f, "starting f\""),
             Token::FString(s) => write!(f, "f-string {:?}", &s.content),
+            Token::RawBytesDoubleQuote => write!(f, "starting b\""),
+            Token::RawBytesSingleQuote => write!(f, "starting b'"),
+            Token::Bytes(s) => write!(f, "bytes literal {:?}", String::from_utf8_lossy(s)),
             Token::Comment(c) => write!(f, "comment '{}'", c),
             Token::Tabs => Ok(()),
         }
@@ -251,6 +251,35 @@ fn test_string_lit() {
     );
 }
 
+#[test]
+fn test_bytes_lit() {
+    assert_eq!(
+        vec![
+            Token::Bytes(b"abc".to_vec()),
+            Token::Bytes(vec![0xff, 0, 0o377]),
+            Token::Bytes(b"\\n".to_vec()),
+            Token::Bytes(b"\"".to_vec()),
+            Token::Bytes("\u{e9}".as_bytes().to_vec()),
+            Token::Bytes("\u{e9}".as_bytes().to_vec()),
+            Token::Newline,
+        ],
+        lex_tokens("b'abc' b\"\\xff\\0\\377\" br'\\n' rb\"\\\"\" b'\\u00e9' b'\u{e9}'")
+            .into_map(|(_, token, _)| token)
+    );
+
+    // Hex and octal escapes denote bytes, so must be less than 256.
+    let program = r"b'\400'";
+    assert!(
+        Lexer::new(
+            program,
+            &Dialect::Extended,
+            CodeMap::new("x".to_owned(), program.to_owned()),
+        )
+        .collect::<Result<Vec<_>, _>>()
+        .is_err()
+    );
+}
+
 #[test]
 fn test_string_escape() {
     lexer_golden_test(
@@ -87,6 +87,7 @@ pub type AstAssignIdent = AstAssignIdentP<AstNoPayload>;
 pub type AstIdent = AstIdentP<AstNoPayload>;
 pub type AstArgument = AstArgumentP<AstNoPayload>;
 pub type AstString = Spanned<String>;
+pub type AstBytes = Spanned<Vec<u8>>;
 pub type AstParameter = AstParameterP<AstNoPayload>;
 pub type AstInt = Spanned<TokenInt>;
 pub type AstFloat = Spanned<f64>;
@@ -143,6 +144,7 @@ pub enum AstLiteral {
     Int(AstInt),
     Float(AstFloat),
     String(AstString),
+    Bytes(AstBytes),
     Ellipsis,
 }
 
@@ -489,12 +491,29 @@ fn fmt_string_literal(f: &mut Formatter<'_>, s: &str) -> fmt::Result {
     f.write_str("\"")
 }
 
+fn fmt_bytes_literal(f: &mut Formatter<'_>, s: &[u8]) -> fmt::Result {
+    f.write_str("b\"")?;
+    for b in s {
+        match b {
+            b'\n' => f.write_str("\\n")?,
+            b'\t' => f.write_str("\\t")?,
+            b'\r' => f.write_str("\\r")?,
+            b'"' => f.write_str("\\\"")?,
+            b'\\' => f.write_str("\\\\")?,
+            b' '..=b'~' => write!(f, "{}", *b as char)?,
+            b => write!(f, "\\x{:02x}", b)?,
+        }
+    }
+    f.write_str("\"")
+}
+
 impl Display for AstLiteral {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match self {
             AstLiteral::Int(i) => write!(f, "{}", &i.node),
             AstLiteral::Float(n) => write!(f, "{}", &n.node),
             AstLiteral::String(s) => fmt_string_literal(f, &s.node),
+            AstLiteral::Bytes(s) => fmt_bytes_literal(f, &s.node),
             AstLiteral::Ellipsis => f.write_str("..."),
         }
     }
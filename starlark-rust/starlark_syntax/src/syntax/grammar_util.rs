@@ -34,6 +34,7 @@ use crate::syntax::ast::AssignTarget;
 use crate::syntax::ast::AssignTargetP;
 use crate::syntax::ast::AstAssignIdent;
 use crate::syntax::ast::AstAssignTarget;
+use crate::syntax::ast::AstBytes;
 use crate::syntax::ast::AstExpr;
 use crate::syntax::ast::AstFString;
 use crate::syntax::ast::AstParameter;
@@ -322,6 +323,22 @@ pub fn fstring(
     .ast(begin, end)
 }
 
+#[derive(thiserror::Error, Debug)]
+enum BytesError {
+    #[error("Your Starlark dialect must enable bytes to use them")]
+    NotEnabled,
+}
+
+pub fn bytes(bytes: Vec<u8>, begin: usize, end: usize, parser_state: &mut ParserState) -> AstBytes {
+    if !parser_state.dialect.enable_bytes {
+        parser_state.error(
+            Span::new(Pos::new(begin as _), Pos::new(end as _)),
+            BytesError::NotEnabled,
+        );
+    }
+    bytes.ast(begin, end)
+}
+
 #[derive(thiserror::Error, Debug)]
 enum DialectError {
     #[error("`def` is not allowed in this dialect")]
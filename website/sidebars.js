@@ -97,6 +97,8 @@ const manualSidebar = [
           'users/build_observability/interactive_console',
           'users/build_observability/logging',
           'users/build_observability/build_report',
+          'users/build_observability/execution_log',
+          'users/build_observability/test_report',
           isInternal() ? 'users/build_observability/observability' : [],
           isInternal() ? 'users/build_observability/scuba' : [],
           isInternal() ? 'users/build_observability/ods' : [],